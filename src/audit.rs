@@ -0,0 +1,163 @@
+//! Config audit log
+//!
+//! While `audit_log_path` is set, every attempted config mutation is appended
+//! to that file as one JSON line: who made it, what it changed, when, the old
+//! and new values, and whether it succeeded. Unlike the change journal the
+//! log is never compacted or encrypted; rotating it is left to the operator.
+//! Changes to `audit_log_path` itself are written to both the old and the
+//! new file, so switching the log off is recorded too.
+
+use crate::expand::expand_path;
+use crate::system_info::user_info;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Key of the audit log file.
+pub const AUDIT_LOG_PATH_KEY: &str = "audit_log_path";
+
+/// What kind of mutation a record describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOperation {
+    /// `set_value`, `set_tool_settings`, or `config_set`.
+    Set,
+    Reset,
+    Undo,
+    Redo,
+    /// Restored from a backup file.
+    Restore,
+    /// Pulled from the remote config source.
+    Remote,
+}
+
+impl AuditOperation {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Set => "set",
+            Self::Reset => "reset",
+            Self::Undo => "undo",
+            Self::Redo => "redo",
+            Self::Restore => "restore",
+            Self::Remote => "remote",
+        }
+    }
+}
+
+impl fmt::Display for AuditOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Outcome of an audited mutation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum AuditResult {
+    Success,
+    Failure { error: String },
+}
+
+/// One line of the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp: DateTime<Utc>,
+    /// User the server runs as; `None` if it can't be determined.
+    pub user: Option<String>,
+    pub pid: u32,
+    pub operation: AuditOperation,
+    pub key: String,
+    pub old: serde_json::Value,
+    /// The value written, or for a failure the value that was attempted.
+    pub new: serde_json::Value,
+    /// Why the change was made, as given by the caller.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(flatten)]
+    pub result: AuditResult,
+}
+
+impl AuditRecord {
+    #[must_use]
+    pub fn new(
+        operation: AuditOperation,
+        key: impl Into<String>,
+        old: serde_json::Value,
+        new: serde_json::Value,
+        result: AuditResult,
+    ) -> Self {
+        static USER: OnceLock<Option<String>> = OnceLock::new();
+        Self {
+            timestamp: Utc::now(),
+            user: USER.get_or_init(|| user_info().username).clone(),
+            pid: std::process::id(),
+            operation,
+            key: key.into(),
+            old,
+            new,
+            reason: None,
+            result,
+        }
+    }
+
+    /// Attach the caller's reason for the change.
+    #[must_use]
+    pub fn with_reason(mut self, reason: Option<String>) -> Self {
+        self.reason = reason;
+        self
+    }
+}
+
+/// Serializes appends so concurrent records don't interleave.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Append `record` to the log at `path`, creating the file if needed.
+pub fn append_record(path: &Path, record: &AuditRecord) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    let _guard = WRITE_LOCK.lock();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    file.write_all(line.as_bytes())
+}
+
+/// Every readable record in the log at `path`, oldest first.
+#[must_use]
+pub fn read_records(path: &Path) -> Vec<AuditRecord> {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Expand an `audit_log_path` entry and check that the file can be opened
+/// for appending, creating it and its directory if missing.
+pub(crate) fn prepare_log_file(entry: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(expand_path(entry.trim())?);
+    if !path.is_absolute() {
+        return Err(format!("'{}' is not an absolute path", entry));
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("cannot create '{}': {}", parent.display(), e))?;
+    }
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("cannot open '{}' for appending: {}", path.display(), e))?;
+    Ok(path)
+}
@@ -0,0 +1,115 @@
+//! Authentication of config changes
+//!
+//! When an auth token is configured, tools that change the config require it
+//! as their `auth_token` argument, bare or as `Bearer <token>`, and so does
+//! `config_get` to reveal secrets; other reads stay open. Without a token
+//! every caller may write, as before. Only a SHA-256 digest of the token is
+//! kept in memory.
+
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Environment variable holding the token required for config changes.
+pub const AUTH_TOKEN_ENV: &str = "KODEGEN_CONFIG_AUTH_TOKEN";
+
+/// Keyring entry, under `KEYRING_SERVICE`, read when `AUTH_TOKEN_ENV` is
+/// unset. The `:` keeps it apart from secrets set with `config_set_secret`.
+#[cfg(feature = "keyring")]
+pub const AUTH_TOKEN_KEYRING_ENTRY: &str = "auth:config";
+
+/// Code every auth failure message starts with, so clients can tell it
+/// apart from other refusals.
+pub const AUTH_ERROR_CODE: &str = "unauthenticated";
+
+/// Why a config change was refused for lack of authentication.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum AuthError {
+    #[error(
+        "{}: authentication required; pass the server's auth token as auth_token",
+        AUTH_ERROR_CODE
+    )]
+    Missing,
+    #[error("{}: authentication failed; auth_token does not match", AUTH_ERROR_CODE)]
+    Invalid,
+}
+
+/// Reported as `McpError::Other` wrapping the `AuthError`, rather than
+/// `PermissionDenied`, which read-only configs, rate limits, and missing
+/// confirmations already use. Embedders can downcast to `AuthError`.
+impl From<AuthError> for kodegen_mcp_tool::error::McpError {
+    fn from(error: AuthError) -> Self {
+        Self::Other(anyhow::Error::new(error))
+    }
+}
+
+/// Token a caller must present to change the config.
+#[derive(Clone)]
+pub struct AuthToken([u8; 32]);
+
+impl AuthToken {
+    /// Fails for an empty token, which would be trivially guessed.
+    pub fn new(token: &str) -> Result<Self, String> {
+        let token = token.trim();
+        if token.is_empty() {
+            return Err("auth token is empty".to_string());
+        }
+        Ok(Self(Sha256::digest(token.as_bytes()).into()))
+    }
+
+    /// Token from `KODEGEN_CONFIG_AUTH_TOKEN`, else from the OS keyring in
+    /// builds with the `keyring` feature; `None` if neither is set.
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        let token = match std::env::var(AUTH_TOKEN_ENV) {
+            Ok(token) => token,
+            Err(_) => from_keyring()?,
+        };
+        match Self::new(&token) {
+            Ok(token) => Some(token),
+            Err(e) => {
+                log::error!("Ignoring {}: {}", AUTH_TOKEN_ENV, e);
+                None
+            }
+        }
+    }
+
+    /// Check `given`, which may carry a `Bearer ` prefix, in constant time.
+    pub fn verify(&self, given: Option<&str>) -> Result<(), AuthError> {
+        let given = given.map(str::trim).filter(|t| !t.is_empty());
+        let Some(given) = given else {
+            return Err(AuthError::Missing);
+        };
+        let given = given
+            .strip_prefix("Bearer ")
+            .or_else(|| given.strip_prefix("bearer "))
+            .unwrap_or(given)
+            .trim();
+        let digest: [u8; 32] = Sha256::digest(given.as_bytes()).into();
+        let diff = digest.iter().zip(&self.0).fold(0u8, |acc, (a, b)| acc | (a ^ b));
+        if diff == 0 { Ok(()) } else { Err(AuthError::Invalid) }
+    }
+}
+
+impl fmt::Debug for AuthToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AuthToken(<redacted>)")
+    }
+}
+
+#[cfg(feature = "keyring")]
+fn from_keyring() -> Option<String> {
+    let entry = keyring::Entry::new(crate::secrets::KEYRING_SERVICE, AUTH_TOKEN_KEYRING_ENTRY);
+    match entry.and_then(|entry| entry.get_password()) {
+        Ok(token) => Some(token),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            log::error!("Failed to read the auth token from the keyring: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+fn from_keyring() -> Option<String> {
+    None
+}
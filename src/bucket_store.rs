@@ -0,0 +1,277 @@
+//! S3/GCS object storage backend
+//!
+//! The extended config is stored as one JSON object at an `s3://bucket/key`
+//! or `gs://bucket/key` URL, for deployments without a persistent volume.
+//! Saves are conditional on the ETag seen at the last load or save, so a
+//! concurrent writer is detected rather than silently overwritten. A local
+//! `FileStore` copy lets the server start with its last config while the
+//! bucket is unreachable. With an encryption key the object is encrypted
+//! before upload.
+
+use crate::encryption::{self, EncryptionKey};
+use crate::extended_config::ExtendedConfig;
+use crate::store::{ConfigStore, FileStore, StoreFuture};
+use chrono::Utc;
+use object_store::aws::{AmazonS3Builder, S3ConditionalPut};
+use object_store::gcp::GoogleCloudStorageBuilder;
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectStore, PutMode, PutPayload, UpdateVersion};
+use parking_lot::Mutex;
+use reqwest::Url;
+
+/// Environment variable holding the object URL; the store is used only when set.
+pub const OBJECT_URL_ENV: &str = "KODEGEN_CONFIG_OBJECT_URL";
+
+/// File name of the local copy, stored alongside `config.json`.
+pub const OBJECT_CACHE_FILE: &str = "config.object.json";
+
+pub struct BucketStore {
+    url: String,
+    store: Box<dyn ObjectStore>,
+    path: ObjectPath,
+    cache: FileStore,
+    encryption: Option<EncryptionKey>,
+    /// Version of the object as of our last load or save.
+    version: Mutex<Option<UpdateVersion>>,
+}
+
+impl BucketStore {
+    /// Store the config at `url` (`s3://` or `gs://`), keeping a local copy
+    /// in `cache`. Credentials and region come from the usual `AWS_*` and
+    /// `GOOGLE_*` environment variables.
+    pub fn new(url: &str, cache: FileStore) -> std::io::Result<Self> {
+        let parsed = Url::parse(url).map_err(invalid_input)?;
+        let store: Box<dyn ObjectStore> = match parsed.scheme() {
+            "s3" => Box::new(
+                AmazonS3Builder::from_env()
+                    .with_url(url)
+                    .with_conditional_put(S3ConditionalPut::ETagMatch)
+                    .build()
+                    .map_err(to_io)?,
+            ),
+            "gs" => Box::new(
+                GoogleCloudStorageBuilder::from_env()
+                    .with_url(url)
+                    .build()
+                    .map_err(to_io)?,
+            ),
+            scheme => {
+                return Err(invalid_input(format!(
+                    "unsupported object store scheme '{}', expected s3 or gs",
+                    scheme
+                )));
+            }
+        };
+        let path = ObjectPath::from_url_path(parsed.path()).map_err(invalid_input)?;
+        Ok(Self {
+            url: url.to_string(),
+            store,
+            path,
+            cache,
+            encryption: None,
+            version: Mutex::new(None),
+        })
+    }
+
+    /// Encrypt the stored object with `key`.
+    #[must_use]
+    pub fn encryption_key(mut self, key: Option<EncryptionKey>) -> Self {
+        self.encryption = key;
+        self
+    }
+
+    /// Store configured from `KODEGEN_CONFIG_OBJECT_URL`, or `None` if unset.
+    /// The local copy is `~/.kodegen/config.object.json`. Both are encrypted
+    /// if `KODEGEN_CONFIG_KEY` is set.
+    pub fn from_env() -> Option<std::io::Result<Self>> {
+        let url = std::env::var(OBJECT_URL_ENV).ok().filter(|url| !url.is_empty())?;
+        let encryption = EncryptionKey::from_env();
+        let cache = FileStore::new(crate::persistence::config_dir().join(OBJECT_CACHE_FILE))
+            .encryption_key(encryption.clone());
+        Some(Self::new(&url, cache).map(|store| store.encryption_key(encryption)))
+    }
+
+    /// `config` as the stored object, encrypted if a key is set.
+    fn encode(&self, config: &ExtendedConfig) -> std::io::Result<Vec<u8>> {
+        encryption::seal_json(config, self.encryption.as_ref())
+    }
+
+    /// Config from an object stored by `encode`.
+    fn decode(&self, object: &[u8]) -> std::io::Result<ExtendedConfig> {
+        encryption::open_json(object, self.encryption.as_ref())
+    }
+
+    /// The stored config and its version, or `None` if there is no object.
+    async fn fetch(&self) -> std::io::Result<Option<(ExtendedConfig, UpdateVersion)>> {
+        let result = match self.store.get(&self.path).await {
+            Ok(result) => result,
+            Err(object_store::Error::NotFound { .. }) => return Ok(None),
+            Err(e) => return Err(to_io(e)),
+        };
+        let version = UpdateVersion {
+            e_tag: result.meta.e_tag.clone(),
+            version: result.meta.version.clone(),
+        };
+        let bytes = result.bytes().await.map_err(to_io)?;
+        Ok(Some((self.decode(&bytes)?, version)))
+    }
+
+    /// Copy the object written by someone else aside before overwriting it.
+    ///
+    /// The copy is `<cache file>.external-<timestamp>`, like an external edit
+    /// of `config.tools.*`.
+    async fn preserve_remote(&self) {
+        let mut name = self.cache.path().file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".external-{}", Utc::now().format("%Y%m%dT%H%M%S")));
+        let backup = self.cache.path().with_file_name(name);
+        let result = match self.store.get(&self.path).await {
+            Ok(result) => result.bytes().await.map_err(to_io),
+            Err(e) => Err(to_io(e)),
+        };
+        match result {
+            Ok(bytes) => match tokio::fs::write(&backup, bytes).await {
+                Ok(()) => log::warn!(
+                    "{} was modified by another writer; previous contents saved to {}",
+                    self.url,
+                    backup.display()
+                ),
+                Err(e) => log::error!("Failed to back up {}: {}", self.url, e),
+            },
+            Err(e) => log::error!("Failed to back up {}: {}", self.url, e),
+        }
+    }
+}
+
+impl ConfigStore for BucketStore {
+    fn location(&self) -> String {
+        self.url.clone()
+    }
+
+    fn load(&self) -> StoreFuture<'_, Option<ExtendedConfig>> {
+        Box::pin(async move {
+            match self.fetch().await {
+                Ok(Some((config, version))) => {
+                    *self.version.lock() = Some(version);
+                    if let Err(e) = self.cache.save(&config).await {
+                        log::warn!("Failed to update {}: {}", self.cache.location(), e);
+                    }
+                    Ok(Some(config))
+                }
+                Ok(None) => Ok(None),
+                Err(e) => {
+                    log::warn!(
+                        "{} unreachable ({}); starting from local copy {}",
+                        self.url,
+                        e,
+                        self.cache.location()
+                    );
+                    self.cache.load().await
+                }
+            }
+        })
+    }
+
+    fn save<'a>(&'a self, config: &'a ExtendedConfig) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            let payload = PutPayload::from(self.encode(config)?);
+            if let Err(e) = self.cache.save(config).await {
+                log::warn!("Failed to update {}: {}", self.cache.location(), e);
+            }
+            let version = self.version.lock().clone();
+            let mode = match version {
+                Some(version) => PutMode::Update(version),
+                None => PutMode::Create,
+            };
+            let result = match self
+                .store
+                .put_opts(&self.path, payload.clone(), mode.into())
+                .await
+            {
+                Err(
+                    object_store::Error::Precondition { .. }
+                    | object_store::Error::AlreadyExists { .. },
+                ) => {
+                    self.preserve_remote().await;
+                    self.store.put(&self.path, payload).await
+                }
+                result => result,
+            }
+            .map_err(to_io)?;
+            *self.version.lock() = Some(UpdateVersion {
+                e_tag: result.e_tag,
+                version: result.version,
+            });
+            Ok(())
+        })
+    }
+}
+
+fn to_io(e: object_store::Error) -> std::io::Error {
+    std::io::Error::other(e)
+}
+
+fn invalid_input(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_store::memory::InMemory;
+
+    fn store(dir: &std::path::Path, key: Option<EncryptionKey>) -> BucketStore {
+        BucketStore {
+            url: "s3://bucket/config.json".to_string(),
+            store: Box::new(InMemory::new()),
+            path: ObjectPath::from("config.json"),
+            cache: FileStore::new(dir.join(OBJECT_CACHE_FILE)),
+            encryption: key,
+            version: Mutex::new(None),
+        }
+    }
+
+    fn config() -> ExtendedConfig {
+        ExtendedConfig {
+            max_search_results: Some(10),
+            ..ExtendedConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn objects_are_uploaded_encrypted_and_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let bucket = store(dir.path(), Some(EncryptionKey::new([3; 32])));
+        bucket.save(&config()).await.unwrap();
+
+        let object = bucket
+            .store
+            .get(&bucket.path)
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        assert!(encryption::is_encrypted(&object));
+        assert!(store(dir.path(), None).decode(&object).is_err());
+        let loaded = bucket.load().await.unwrap().unwrap();
+        assert_eq!(loaded.max_search_results, Some(10));
+    }
+
+    #[tokio::test]
+    async fn objects_are_plain_json_without_a_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let bucket = store(dir.path(), None);
+        bucket.save(&config()).await.unwrap();
+
+        let object = bucket
+            .store
+            .get(&bucket.path)
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&object).unwrap();
+        assert_eq!(json["max_search_results"], 10);
+    }
+}
@@ -0,0 +1,191 @@
+//! ConfigManagerBuilder
+//!
+//! Collects optional settings for [`ConfigManager`] so new options don't each
+//! need another constructor.
+
+use crate::ServerConfig;
+use crate::auth::AuthToken;
+use crate::encryption::EncryptionKey;
+use crate::env_loader::DEFAULT_ENV_PREFIX;
+use crate::extended_config::ExtendedConfig;
+use crate::history::DEFAULT_UNDO_LIMIT;
+use crate::manager::{ConfigManager, UpstreamConfigManager};
+use crate::persistence::{self, Durability, RetryPolicy};
+use crate::remote::RemoteSource;
+use crate::saver::{DEFAULT_DEBOUNCE, DEFAULT_MAX_SAVE_DELAY};
+use crate::secrets;
+use crate::store::ConfigStore;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[must_use = "call build() to create the ConfigManager"]
+pub struct ConfigManagerBuilder {
+    pub(crate) upstream: UpstreamConfigManager,
+    pub(crate) extended_path: PathBuf,
+    pub(crate) read_only: bool,
+    pub(crate) undo_limit: usize,
+    pub(crate) backup_count: usize,
+    pub(crate) durability: Durability,
+    pub(crate) encryption_key: Option<EncryptionKey>,
+    pub(crate) retry_policy: RetryPolicy,
+    pub(crate) debounce: Duration,
+    pub(crate) max_save_delay: Duration,
+    pub(crate) defaults: Option<ServerConfig>,
+    pub(crate) extended_defaults: ExtendedConfig,
+    pub(crate) store: Option<Arc<dyn ConfigStore>>,
+    pub(crate) journal: bool,
+    pub(crate) remote: Option<RemoteSource>,
+    pub(crate) env_prefix: String,
+    pub(crate) allow_secret_reveal: bool,
+    pub(crate) auth_token: Option<AuthToken>,
+}
+
+impl ConfigManagerBuilder {
+    pub(crate) fn new(upstream: UpstreamConfigManager) -> Self {
+        Self {
+            upstream,
+            extended_path: persistence::default_extended_config_path(),
+            read_only: false,
+            undo_limit: DEFAULT_UNDO_LIMIT,
+            backup_count: persistence::DEFAULT_BACKUP_COUNT,
+            durability: Durability::default(),
+            encryption_key: EncryptionKey::from_env(),
+            retry_policy: RetryPolicy::default(),
+            debounce: DEFAULT_DEBOUNCE,
+            max_save_delay: DEFAULT_MAX_SAVE_DELAY,
+            defaults: None,
+            extended_defaults: ExtendedConfig::default(),
+            store: None,
+            journal: true,
+            remote: RemoteSource::from_env(),
+            env_prefix: DEFAULT_ENV_PREFIX.to_string(),
+            allow_secret_reveal: secrets::reveal_allowed_by_env(),
+            auth_token: AuthToken::from_env(),
+        }
+    }
+
+    /// Location of the extended config file (default: `KODEGEN_CONFIG_PATH`,
+    /// if set, else `~/.kodegen/config.tools.json`).
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.extended_path = path.into();
+        self
+    }
+
+    /// Reject every mutation with a permission error.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Number of mutations kept for `undo()`; `0` disables history.
+    pub fn undo_limit(mut self, limit: usize) -> Self {
+        self.undo_limit = limit;
+        self
+    }
+
+    /// Number of rotated backups (`config.json.1`, `.2`, ...) kept per file; `0` disables backups.
+    pub fn backup_count(mut self, count: usize) -> Self {
+        self.backup_count = count;
+        self
+    }
+
+    /// How saves reach the disk (default `Fsync`). `None` or `Flush` skip the
+    /// fsync to save power at the cost of losing recent changes on a crash.
+    pub fn durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Retries for failed saves (default: 4 attempts, 100ms doubling to at most 2s).
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Quiet period the background saver waits for before writing (default 300ms).
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Longest the background saver lets a change go unsaved while changes
+    /// keep arriving (default 5s).
+    pub fn max_save_delay(mut self, delay: Duration) -> Self {
+        self.max_save_delay = delay;
+        self
+    }
+
+    /// Encrypt the extended config file and its backups with `key`
+    /// (default: `KODEGEN_CONFIG_KEY`, if set). `None` writes plaintext but
+    /// can no longer read previously encrypted files.
+    pub fn encryption_key(mut self, key: Option<EncryptionKey>) -> Self {
+        self.encryption_key = key;
+        self
+    }
+
+    /// Product-specific `ServerConfig` defaults, applied by `init()` when no
+    /// `config.json` exists yet and restored by `reset()`.
+    pub fn defaults(mut self, defaults: ServerConfig) -> Self {
+        self.defaults = Some(defaults);
+        self
+    }
+
+    /// `ExtendedConfig` used when no extended config file exists and restored by `reset()`.
+    pub fn extended_defaults(mut self, defaults: ExtendedConfig) -> Self {
+        self.extended_defaults = defaults;
+        self
+    }
+
+    /// Persist the extended config and change/client history in `store`
+    /// instead of `config.tools.*`. The store is loaded by `init()`; until
+    /// then the extended defaults are in effect. `path`, `backup_count`, and
+    /// `durability` only apply to the file backend.
+    pub fn store(mut self, store: impl ConfigStore + 'static) -> Self {
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    /// Append every change to `config.journal` next to the extended config
+    /// (default `true`), so unsaved changes survive a crash.
+    pub fn journal(mut self, enabled: bool) -> Self {
+        self.journal = enabled;
+        self
+    }
+
+    /// Prefix of the variables overriding config keys (default `KODEGEN_`),
+    /// e.g. `MYPRODUCT_` to read `MYPRODUCT_ALLOWED_DIRS` and
+    /// `MYPRODUCT_CONFIG_DEFAULT_SHELL`.
+    pub fn env_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.env_prefix = prefix.into();
+        self
+    }
+
+    /// Remote config merged in by `init()` and polled afterwards (default:
+    /// `KODEGEN_CONFIG_URL`, if set). `None` disables it.
+    pub fn remote_source(mut self, source: Option<RemoteSource>) -> Self {
+        self.remote = source;
+        self
+    }
+
+    /// Let `config_get` reveal secret values when asked with
+    /// `include_secrets` (default: `KODEGEN_ALLOW_SECRET_REVEAL`). Values are
+    /// redacted otherwise, and always in read-only mode.
+    pub fn allow_secret_reveal(mut self, allow: bool) -> Self {
+        self.allow_secret_reveal = allow;
+        self
+    }
+
+    /// Token config-changing tools require as `auth_token` (default:
+    /// `KODEGEN_CONFIG_AUTH_TOKEN`, else the OS keyring with the `keyring`
+    /// feature). `None` lets every caller change the config.
+    pub fn auth_token(mut self, token: Option<AuthToken>) -> Self {
+        self.auth_token = token;
+        self
+    }
+
+    #[must_use]
+    pub fn build(self) -> ConfigManager {
+        ConfigManager::from_builder(self)
+    }
+}
@@ -0,0 +1,69 @@
+//! Per-key change callbacks
+
+use crate::history::ConfigChange;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Callback invoked with each applied change to a watched key.
+pub type ChangeCallback = Arc<dyn Fn(&ConfigChange) + Send + Sync>;
+
+/// Handle returned by `ConfigManager::on_change`, used to unregister the callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CallbackId(u64);
+
+/// Callbacks grouped by the key they watch.
+///
+/// A callback registered on a parent key (e.g. `tool_settings`) also fires for
+/// its nested keys (`tool_settings.terminal`).
+#[derive(Default)]
+pub struct CallbackRegistry {
+    by_key: HashMap<String, Vec<(CallbackId, ChangeCallback)>>,
+    next_id: u64,
+}
+
+impl CallbackRegistry {
+    pub fn register(&mut self, key: &str, callback: ChangeCallback) -> CallbackId {
+        let id = CallbackId(self.next_id);
+        self.next_id += 1;
+        self.by_key
+            .entry(key.to_string())
+            .or_default()
+            .push((id, callback));
+        id
+    }
+
+    /// Remove a callback; returns whether it was registered.
+    pub fn unregister(&mut self, id: CallbackId) -> bool {
+        let mut removed = false;
+        self.by_key.retain(|_, callbacks| {
+            let before = callbacks.len();
+            callbacks.retain(|(cb_id, _)| *cb_id != id);
+            removed |= callbacks.len() != before;
+            !callbacks.is_empty()
+        });
+        removed
+    }
+
+    /// Callbacks interested in `key`, cloned so they can run without holding a lock.
+    #[must_use]
+    pub fn matching(&self, key: &str) -> Vec<ChangeCallback> {
+        let parent = key.split('.').next().unwrap_or(key);
+        let mut callbacks: Vec<ChangeCallback> = self
+            .by_key
+            .get(key)
+            .into_iter()
+            .flatten()
+            .map(|(_, cb)| Arc::clone(cb))
+            .collect();
+        if parent != key {
+            callbacks.extend(
+                self.by_key
+                    .get(parent)
+                    .into_iter()
+                    .flatten()
+                    .map(|(_, cb)| Arc::clone(cb)),
+            );
+        }
+        callbacks
+    }
+}
@@ -0,0 +1,176 @@
+//! Client history retention
+//!
+//! The infrastructure crate appends a record to `client_history` for every
+//! client that connects and never drops one. This crate keeps its own copy in
+//! `clients.json`, merging the infrastructure crate's records into it and
+//! leaving `client_history` empty in the `config.json` it writes. Before the
+//! history is served or persisted, records older than
+//! `client_history_max_age_days` are removed, then the least recently seen
+//! ones beyond `client_history_max_entries`.
+
+use crate::encryption::{self, EncryptionKey};
+use chrono::{DateTime, Duration, Utc};
+use std::path::Path;
+
+/// Key of the most client records kept.
+pub const CLIENT_HISTORY_MAX_ENTRIES_KEY: &str = "client_history_max_entries";
+
+/// Key of the age in days after which a client record is dropped.
+pub const CLIENT_HISTORY_MAX_AGE_DAYS_KEY: &str = "client_history_max_age_days";
+
+/// Client records kept while `client_history_max_entries` is unset.
+pub const DEFAULT_CLIENT_HISTORY_MAX_ENTRIES: u32 = 100;
+
+/// Drop records from `clients` last seen more than `max_age` before `now`,
+/// then the least recently seen beyond `max_entries`, keeping the order of
+/// the rest. Records without a readable `last_seen` or `connected_at` are
+/// never too old and are the first to go when over the limit.
+pub fn prune_client_history(
+    clients: &mut Vec<serde_json::Value>,
+    max_entries: usize,
+    max_age: Option<Duration>,
+    now: DateTime<Utc>,
+) {
+    if let Some(cutoff) = max_age.and_then(|age| now.checked_sub_signed(age)) {
+        clients.retain(|client| last_seen(client).is_none_or(|seen| seen >= cutoff));
+    }
+    if clients.len() <= max_entries {
+        return;
+    }
+    let mut by_recency: Vec<(usize, Option<DateTime<Utc>>)> =
+        clients.iter().map(last_seen).enumerate().collect();
+    by_recency.sort_by(|a, b| b.1.cmp(&a.1));
+    let mut kept: Vec<usize> = by_recency.into_iter().take(max_entries).map(|(i, _)| i).collect();
+    kept.sort_unstable();
+    let mut index = 0;
+    clients.retain(|_| {
+        let keep = kept.binary_search(&index).is_ok();
+        index += 1;
+        keep
+    });
+}
+
+/// When `client` was last seen, falling back to when it connected.
+fn last_seen(client: &serde_json::Value) -> Option<DateTime<Utc>> {
+    ["last_seen", "connected_at"].iter().find_map(|field| {
+        let value = client.get(field)?.as_str()?;
+        DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|t| t.with_timezone(&Utc))
+    })
+}
+
+/// Add `record` to `clients`, replacing the record of the same client
+/// (matched on `client_info.name`) if `record` was seen no earlier. The
+/// replaced record's `connected_at` is kept.
+pub(crate) fn merge_client(clients: &mut Vec<serde_json::Value>, record: serde_json::Value) {
+    let name = client_name(&record);
+    let existing = name.and_then(|name| {
+        clients
+            .iter_mut()
+            .find(|client| client_name(client) == Some(name))
+    });
+    let Some(existing) = existing else {
+        clients.push(record);
+        return;
+    };
+    if last_seen(&record) < last_seen(existing) {
+        return;
+    }
+    let connected_at = existing.get("connected_at").cloned();
+    *existing = record;
+    if let (Some(connected_at), Some(fields)) = (connected_at, existing.as_object_mut()) {
+        fields.insert("connected_at".to_string(), connected_at);
+    }
+}
+
+/// Client records saved at `path`, decrypted with `key` when encrypted.
+pub(crate) fn load(
+    path: &Path,
+    key: Option<&EncryptionKey>,
+) -> std::io::Result<Vec<serde_json::Value>> {
+    let contents = encryption::decode(std::fs::read(path)?, key)?;
+    serde_json::from_str(&contents)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+fn client_name(client: &serde_json::Value) -> Option<&str> {
+    client.pointer("/client_info/name")?.as_str()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn client(name: &str, connected_at: &str, last_seen: &str) -> serde_json::Value {
+        json!({
+            "client_info": { "name": name, "version": "1.0.0" },
+            "connected_at": connected_at,
+            "last_seen": last_seen,
+        })
+    }
+
+    fn names(clients: &[serde_json::Value]) -> Vec<&str> {
+        clients.iter().filter_map(client_name).collect()
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn prune_drops_old_records_then_the_least_recently_seen() {
+        let mut clients = vec![
+            client("old", "2025-01-01T00:00:00Z", "2025-01-02T00:00:00Z"),
+            client("a", "2025-03-01T00:00:00Z", "2025-03-03T00:00:00Z"),
+            client("b", "2025-03-01T00:00:00Z", "2025-03-01T00:00:00Z"),
+            client("c", "2025-03-01T00:00:00Z", "2025-03-02T00:00:00Z"),
+        ];
+        prune_client_history(
+            &mut clients,
+            2,
+            Some(Duration::days(30)),
+            at("2025-03-10T00:00:00Z"),
+        );
+        assert_eq!(names(&clients), ["a", "c"]);
+    }
+
+    #[test]
+    fn prune_drops_unreadable_records_first() {
+        let mut clients = vec![
+            json!({ "client_info": { "name": "undated" } }),
+            client("dated", "2025-03-01T00:00:00Z", "2025-03-01T00:00:00Z"),
+        ];
+        prune_client_history(&mut clients, 1, None, at("2025-03-10T00:00:00Z"));
+        assert_eq!(names(&clients), ["dated"]);
+    }
+
+    #[test]
+    fn merge_updates_a_known_client_and_keeps_when_it_connected() {
+        let mut clients = vec![client("a", "2025-03-01T00:00:00Z", "2025-03-01T00:00:00Z")];
+        merge_client(
+            &mut clients,
+            client("a", "2025-03-05T00:00:00Z", "2025-03-05T00:00:00Z"),
+        );
+        merge_client(
+            &mut clients,
+            client("b", "2025-03-02T00:00:00Z", "2025-03-02T00:00:00Z"),
+        );
+        assert_eq!(names(&clients), ["a", "b"]);
+        assert_eq!(clients[0]["connected_at"], "2025-03-01T00:00:00Z");
+        assert_eq!(clients[0]["last_seen"], "2025-03-05T00:00:00Z");
+    }
+
+    #[test]
+    fn merge_ignores_an_older_record() {
+        let mut clients = vec![client("a", "2025-03-01T00:00:00Z", "2025-03-05T00:00:00Z")];
+        merge_client(
+            &mut clients,
+            client("a", "2025-03-01T00:00:00Z", "2025-03-02T00:00:00Z"),
+        );
+        assert_eq!(clients[0]["last_seen"], "2025-03-05T00:00:00Z");
+    }
+}
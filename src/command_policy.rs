@@ -0,0 +1,226 @@
+//! Command blocking policy
+//!
+//! `blocked_commands` matches command names; `blocked_command_patterns`
+//! matches whole command lines, so dangerous argument combinations such as
+//! `git push --force` can be refused while `git` itself stays allowed.
+//! Chained commands (`;`, `&&`, `||`, `|`) are checked one by one, so
+//! blocked commands can't be hidden behind an allowed one.
+
+use regex::Regex;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+/// Marks a `blocked_command_patterns` entry as a glob rather than a regex.
+pub const GLOB_PREFIX: &str = "glob:";
+
+/// Outcome of checking a command line against the blocking rules.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum CommandDecision {
+    Allowed,
+    /// `command` is listed in `blocked_commands`.
+    BlockedCommand { command: String },
+    /// The command line, or one of its chained commands, matches `pattern`.
+    BlockedPattern { pattern: String },
+}
+
+impl CommandDecision {
+    #[must_use]
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, Self::Allowed)
+    }
+
+    /// Why the command was refused, for error messages.
+    #[must_use]
+    pub fn reason(&self) -> String {
+        match self {
+            Self::Allowed => "no blocking rule matches".to_string(),
+            Self::BlockedCommand { command } => format!("'{}' is a blocked command", command),
+            Self::BlockedPattern { pattern } => format!("matches blocked pattern '{}'", pattern),
+        }
+    }
+}
+
+impl fmt::Display for CommandDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.is_allowed() { "allowed" } else { "blocked" };
+        write!(f, "{}: {}", verdict, self.reason())
+    }
+}
+
+/// Compile a `blocked_command_patterns` entry: a regex searched for anywhere
+/// in the command line, or with `glob:` a glob that must match all of it.
+pub fn compile_pattern(pattern: &str) -> Result<Regex, regex::Error> {
+    match pattern.strip_prefix(GLOB_PREFIX) {
+        Some(glob) => Regex::new(&glob_to_regex(glob.trim())),
+        None => Regex::new(pattern),
+    }
+}
+
+/// Check `cmdline` against `blocked` command names, then `patterns`.
+///
+/// Patterns that don't compile are logged and skipped; `set_value` refuses
+/// them, so they can only come from a hand-edited file.
+#[must_use]
+pub fn evaluate_command(cmdline: &str, blocked: &[String], patterns: &[String]) -> CommandDecision {
+    let line = normalize(cmdline);
+    let segments = segments(&line);
+    for segment in &segments {
+        if let Some(command) = command_name(segment)
+            && blocked.iter().any(|b| b == command)
+        {
+            return CommandDecision::BlockedCommand {
+                command: command.to_string(),
+            };
+        }
+    }
+    for pattern in patterns {
+        let re = match compile_pattern(pattern) {
+            Ok(re) => re,
+            Err(e) => {
+                log::warn!("Skipping invalid blocked command pattern '{}': {}", pattern, e);
+                continue;
+            }
+        };
+        let whole = std::iter::once(line.as_str());
+        if whole.chain(segments.iter().copied()).any(|s| re.is_match(s)) {
+            return CommandDecision::BlockedPattern {
+                pattern: pattern.clone(),
+            };
+        }
+    }
+    CommandDecision::Allowed
+}
+
+/// Collapse whitespace runs so spacing can't dodge a pattern.
+fn normalize(cmdline: &str) -> String {
+    cmdline.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The commands of a chain or pipeline, trimmed.
+fn segments(line: &str) -> Vec<&str> {
+    line.split([';', '|', '&', '\n'])
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .collect()
+}
+
+/// File name of the first word that isn't an environment assignment, so
+/// `/usr/bin/sudo` and `FOO=1 sudo` both name `sudo`.
+fn command_name(segment: &str) -> Option<&str> {
+    let word = segment
+        .split_whitespace()
+        .find(|word| !word.contains('=') || word.starts_with('='))?;
+    let word = word.trim_matches(['"', '\'']);
+    Path::new(word).file_name()?.to_str()
+}
+
+/// Anchored regex for a glob: `*` matches any run of characters, `?` one.
+fn glob_to_regex(glob: &str) -> String {
+    let mut re = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            c => re.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    re.push('$');
+    re
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn blocks_listed_commands_by_file_name() {
+        let blocked = list(&["sudo"]);
+        for line in [
+            "sudo ls",
+            "/usr/bin/sudo ls",
+            "FOO=1 sudo ls",
+            "\"sudo\" ls",
+        ] {
+            let decision = evaluate_command(line, &blocked, &[]);
+            assert_eq!(
+                decision,
+                CommandDecision::BlockedCommand {
+                    command: "sudo".to_string()
+                },
+                "{}",
+                line
+            );
+        }
+        assert!(evaluate_command("ls -la", &blocked, &[]).is_allowed());
+    }
+
+    #[test]
+    fn checks_every_command_of_a_chain() {
+        let blocked = list(&["rm"]);
+        for line in [
+            "ls; rm -rf /",
+            "true && rm x",
+            "false || rm x",
+            "cat x | rm y",
+            "ls & rm x",
+        ] {
+            assert!(
+                !evaluate_command(line, &blocked, &[]).is_allowed(),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn glob_patterns_match_whole_commands() {
+        let patterns = list(&["glob:git push *--force*"]);
+        assert!(!evaluate_command("git push origin --force", &[], &patterns).is_allowed());
+        assert!(!evaluate_command("git   push  origin  --force", &[], &patterns).is_allowed());
+        assert!(
+            !evaluate_command("cd repo && git push origin --force", &[], &patterns).is_allowed()
+        );
+        assert!(evaluate_command("git push origin", &[], &patterns).is_allowed());
+        assert!(evaluate_command("echo git push --force", &[], &patterns).is_allowed());
+    }
+
+    #[test]
+    fn glob_question_mark_matches_one_character() {
+        let patterns = list(&["glob:rm -?f *"]);
+        assert!(!evaluate_command("rm -rf /", &[], &patterns).is_allowed());
+        assert!(evaluate_command("rm -f /", &[], &patterns).is_allowed());
+    }
+
+    #[test]
+    fn glob_escapes_regex_metacharacters() {
+        let patterns = list(&["glob:curl * | sh"]);
+        assert!(!evaluate_command("curl x.sh | sh", &[], &patterns).is_allowed());
+        let patterns = list(&["glob:a.b"]);
+        assert!(evaluate_command("axb", &[], &patterns).is_allowed());
+    }
+
+    #[test]
+    fn regex_patterns_match_anywhere() {
+        let patterns = list(&[r"--no-verify\b"]);
+        let decision = evaluate_command("git commit --no-verify -m x", &[], &patterns);
+        assert!(
+            matches!(decision, CommandDecision::BlockedPattern { .. }),
+            "{}",
+            decision
+        );
+    }
+
+    #[test]
+    fn invalid_patterns_are_skipped() {
+        assert!(compile_pattern("(unclosed").is_err());
+        let patterns = list(&["(unclosed", "glob:shutdown*"]);
+        assert!(evaluate_command("ls", &[], &patterns).is_allowed());
+        assert!(!evaluate_command("shutdown -h now", &[], &patterns).is_allowed());
+    }
+}
@@ -0,0 +1,186 @@
+use crate::ConfigManager;
+use crate::metadata::is_sensitive;
+use crate::schema::{BackupAction, CONFIG_BACKUPS, ConfigBackupsArgs, ConfigBackupsPromptArgs};
+use crate::set_config_value::confirmation_required;
+use kodegen_mcp_tool::Tool;
+use kodegen_mcp_tool::error::McpError;
+use rmcp::model::{Content, PromptArgument, PromptMessage, PromptMessageContent, PromptMessageRole};
+use serde_json::json;
+
+// ============================================================================
+// TOOL STRUCT
+// ============================================================================
+
+#[derive(Clone)]
+pub struct ConfigBackupsTool {
+    config_manager: ConfigManager,
+}
+
+impl ConfigBackupsTool {
+    #[must_use]
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self { config_manager }
+    }
+}
+
+// ============================================================================
+// TOOL IMPLEMENTATION
+// ============================================================================
+
+impl Tool for ConfigBackupsTool {
+    type Args = ConfigBackupsArgs;
+    type PromptArgs = ConfigBackupsPromptArgs;
+
+    fn name() -> &'static str {
+        CONFIG_BACKUPS
+    }
+
+    fn description() -> &'static str {
+        "List or restore rotated backups of the configuration files. A backup is taken \
+         before every change (config.json.1 is the most recent). \n\n\
+         Actions:\n\
+         - list: show available backups (default)\n\
+         - restore: restore the backup given by index\n\n\
+         file selects config.json (\"config\", default) or config.tools.json (\"tools\"). \
+         Restored keys can be reverted with config_undo. A restore that changes sensitive \
+         keys needs confirmation as with config_set: the first call returns a \
+         confirmation_token, and repeating the call with it applies the restore. Servers \
+         that require authentication also need their token as auth_token to restore."
+    }
+
+    fn read_only() -> bool {
+        false
+    }
+
+    fn destructive() -> bool {
+        false
+    }
+
+    fn idempotent() -> bool {
+        false
+    }
+
+    fn prompt_arguments() -> Vec<PromptArgument> {
+        vec![] // No prompt arguments needed
+    }
+
+    #[tracing::instrument(name = "config.tool", skip_all, fields(tool = Self::name()), err)]
+    async fn execute(&self, args: Self::Args) -> Result<Vec<Content>, McpError> {
+        let mut contents = Vec::new();
+
+        match args.action {
+            BackupAction::List => {
+                let backups = self.config_manager.list_backups(args.file).await;
+
+                // ========================================
+                // Content[0]: Human-Readable Summary
+                // ========================================
+                let mut summary = format!("🗂️  Configuration Backups ({:?})\n", args.file);
+                if backups.is_empty() {
+                    summary.push_str("\nNo backups yet.");
+                }
+                for backup in &backups {
+                    summary.push_str(&format!(
+                        "\n• #{} - {} ({} bytes)",
+                        backup.index,
+                        backup
+                            .modified
+                            .map_or_else(|| "unknown time".to_string(), |t| t.to_rfc3339()),
+                        backup.size_bytes
+                    ));
+                }
+                contents.push(Content::text(summary));
+
+                // ========================================
+                // Content[1]: Machine-Parseable JSON
+                // ========================================
+                let metadata = json!({
+                    "success": true,
+                    "file": args.file,
+                    "backups": backups
+                });
+                let json_str = serde_json::to_string_pretty(&metadata)
+                    .unwrap_or_else(|_| "{}".to_string());
+                contents.push(Content::text(json_str));
+            }
+            BackupAction::Restore => {
+                self.config_manager.authorize(args.auth_token.as_deref())?;
+                let index = args.index.ok_or_else(|| {
+                    McpError::InvalidArguments("index is required for restore".to_string())
+                })?;
+                let changes = self.config_manager.backup_changes(args.file, index).await?;
+
+                // Changes to sensitive keys need a second, confirming call, as
+                // with config_set; the token covers all of them at once
+                let sensitive: serde_json::Map<String, serde_json::Value> = changes
+                    .iter()
+                    .filter(|delta| is_sensitive(&delta.key))
+                    .map(|delta| (delta.key.clone(), delta.new.clone()))
+                    .collect();
+                if !sensitive.is_empty() {
+                    let keys = sensitive.keys().cloned().collect::<Vec<_>>().join(", ");
+                    let value = serde_json::Value::Object(sensitive);
+                    match args.confirmation_token.as_deref() {
+                        Some(token) => self.config_manager.confirm_change(token, &keys, &value)?,
+                        None => {
+                            let token = self.config_manager.request_confirmation(&keys, &value);
+                            return Ok(confirmation_required(&keys, &value, &token));
+                        }
+                    }
+                }
+                let restored = self
+                    .config_manager
+                    .restore_backup_expected(args.file, index, &changes)
+                    .await?;
+
+                // ========================================
+                // Content[0]: Human-Readable Summary
+                // ========================================
+                let mut summary = format!(
+                    "✅ Restored backup #{} ({:?})\n",
+                    index, args.file
+                );
+                if restored.is_empty() {
+                    summary.push_str("\nBackup matches the current configuration; nothing changed.");
+                }
+                for delta in &restored {
+                    summary.push_str(&format!("\n• {}: {} → {}", delta.key, delta.old, delta.new));
+                }
+                contents.push(Content::text(summary));
+
+                // ========================================
+                // Content[1]: Machine-Parseable JSON
+                // ========================================
+                let metadata = json!({
+                    "success": true,
+                    "file": args.file,
+                    "index": index,
+                    "restored": restored
+                });
+                let json_str = serde_json::to_string_pretty(&metadata)
+                    .unwrap_or_else(|_| "{}".to_string());
+                contents.push(Content::text(json_str));
+            }
+        }
+
+        Ok(contents)
+    }
+
+    async fn prompt(&self, _args: Self::PromptArgs) -> Result<Vec<PromptMessage>, McpError> {
+        Ok(vec![
+            PromptMessage {
+                role: PromptMessageRole::User,
+                content: PromptMessageContent::text("How do I recover a previous configuration?"),
+            },
+            PromptMessage {
+                role: PromptMessageRole::Assistant,
+                content: PromptMessageContent::text(
+                    "Use config_backups to list backups, then restore one by index:\n\n\
+                     {\"action\": \"list\"}\n\
+                     {\"action\": \"restore\", \"index\": 1}\n\n\
+                     Pass \"file\": \"tools\" to work with config.tools.json instead of config.json.",
+                ),
+            },
+        ])
+    }
+}
@@ -0,0 +1,113 @@
+use crate::ConfigManager;
+use crate::schema::{CONFIG_CONVERT, ConfigConvertArgs, ConfigConvertPromptArgs};
+use kodegen_mcp_tool::Tool;
+use kodegen_mcp_tool::error::McpError;
+use rmcp::model::{Content, PromptArgument, PromptMessage, PromptMessageContent, PromptMessageRole};
+use serde_json::json;
+
+// ============================================================================
+// TOOL STRUCT
+// ============================================================================
+
+#[derive(Clone)]
+pub struct ConfigConvertTool {
+    config_manager: ConfigManager,
+}
+
+impl ConfigConvertTool {
+    #[must_use]
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self { config_manager }
+    }
+}
+
+// ============================================================================
+// TOOL IMPLEMENTATION
+// ============================================================================
+
+impl Tool for ConfigConvertTool {
+    type Args = ConfigConvertArgs;
+    type PromptArgs = ConfigConvertPromptArgs;
+
+    fn name() -> &'static str {
+        CONFIG_CONVERT
+    }
+
+    fn description() -> &'static str {
+        "Rewrite the extended config file (config.tools.*) in another format: json, toml, \
+         or yaml. The file is renamed to the matching extension and the old file is removed; \
+         its contents are unchanged. config.json is managed by the server and stays JSON."
+    }
+
+    fn read_only() -> bool {
+        false
+    }
+
+    fn destructive() -> bool {
+        false
+    }
+
+    fn idempotent() -> bool {
+        true
+    }
+
+    fn prompt_arguments() -> Vec<PromptArgument> {
+        vec![] // No prompt arguments needed
+    }
+
+    #[tracing::instrument(name = "config.tool", skip_all, fields(tool = Self::name()), err)]
+    async fn execute(&self, args: Self::Args) -> Result<Vec<Content>, McpError> {
+        self.config_manager.authorize(args.auth_token.as_deref())?;
+        let old_path = self.config_manager.extended_config_path();
+        let new_path = self.config_manager.convert(args.format).await?;
+
+        let mut contents = Vec::new();
+
+        // ========================================
+        // Content[0]: Human-Readable Summary
+        // ========================================
+        let summary = format!(
+            "🔄 Configuration Converted to {}\n\
+             \n\
+             From: {}\n\
+             To:   {}",
+            args.format.extension().to_uppercase(),
+            old_path.display(),
+            new_path.display()
+        );
+        contents.push(Content::text(summary));
+
+        // ========================================
+        // Content[1]: Machine-Parseable JSON
+        // ========================================
+        let metadata = json!({
+            "success": true,
+            "format": args.format,
+            "old_path": old_path,
+            "new_path": new_path
+        });
+        let json_str = serde_json::to_string_pretty(&metadata)
+            .unwrap_or_else(|_| "{}".to_string());
+        contents.push(Content::text(json_str));
+
+        Ok(contents)
+    }
+
+    async fn prompt(&self, _args: Self::PromptArgs) -> Result<Vec<PromptMessage>, McpError> {
+        Ok(vec![
+            PromptMessage {
+                role: PromptMessageRole::User,
+                content: PromptMessageContent::text("Can I edit the tool settings as TOML?"),
+            },
+            PromptMessage {
+                role: PromptMessageRole::Assistant,
+                content: PromptMessageContent::text(
+                    "Yes. Convert the extended config once and edit config.tools.toml from then on:\n\n\
+                     {\"format\": \"toml\"}\n\n\
+                     The format is detected from the file extension on every load, so you can \
+                     switch back to json or yaml at any time.",
+                ),
+            },
+        ])
+    }
+}
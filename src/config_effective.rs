@@ -0,0 +1,111 @@
+use crate::ConfigManager;
+use crate::keys::resolve_key;
+use crate::schema::{CONFIG_EFFECTIVE, ConfigEffectiveArgs, ConfigEffectivePromptArgs};
+use kodegen_mcp_tool::Tool;
+use kodegen_mcp_tool::error::McpError;
+use rmcp::model::{Content, PromptArgument, PromptMessage, PromptMessageContent, PromptMessageRole};
+use serde_json::json;
+
+// ============================================================================
+// TOOL STRUCT
+// ============================================================================
+
+#[derive(Clone)]
+pub struct ConfigEffectiveTool {
+    config_manager: ConfigManager,
+}
+
+impl ConfigEffectiveTool {
+    #[must_use]
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self { config_manager }
+    }
+}
+
+// ============================================================================
+// TOOL IMPLEMENTATION
+// ============================================================================
+
+impl Tool for ConfigEffectiveTool {
+    type Args = ConfigEffectiveArgs;
+    type PromptArgs = ConfigEffectivePromptArgs;
+
+    fn name() -> &'static str {
+        CONFIG_EFFECTIVE
+    }
+
+    fn description() -> &'static str {
+        "Show the effective value of every configuration key and where it came from: \
+         default, file (config.json, the tool settings file, or the config store), env \
+         (an environment variable), runtime (config_set since startup), or remote \
+         (KODEGEN_CONFIG_URL). Pass key to report a single key."
+    }
+
+    fn read_only() -> bool {
+        true
+    }
+
+    fn prompt_arguments() -> Vec<PromptArgument> {
+        vec![] // No prompt arguments needed
+    }
+
+    #[tracing::instrument(name = "config.tool", skip_all, fields(tool = Self::name()), err)]
+    async fn execute(&self, args: Self::Args) -> Result<Vec<Content>, McpError> {
+        let mut keys = self.config_manager.provenance();
+        if let Some(key) = &args.key {
+            let key = resolve_key(key).key;
+            keys.retain(|p| p.key == key);
+            if keys.is_empty() {
+                return Err(McpError::InvalidArguments(format!("Unknown config key: {}", key)));
+            }
+        }
+
+        let mut contents = Vec::new();
+
+        // ========================================
+        // Content[0]: Human-Readable Summary
+        // ========================================
+        let mut summary = format!("🧭 Effective Configuration ({} keys)\n", keys.len());
+        for p in &keys {
+            let origin = p.origin.as_ref().map_or_else(String::new, |o| format!(": {}", o));
+            summary.push_str(&format!(
+                "\n• {} = {}\n  from {}{}",
+                p.key, p.value, p.source, origin
+            ));
+        }
+        contents.push(Content::text(summary));
+
+        // ========================================
+        // Content[1]: Machine-Parseable JSON
+        // ========================================
+        let metadata = json!({
+            "success": true,
+            "keys": keys
+        });
+        let json_str = serde_json::to_string_pretty(&metadata)
+            .unwrap_or_else(|_| "{}".to_string());
+        contents.push(Content::text(json_str));
+
+        Ok(contents)
+    }
+
+    async fn prompt(&self, _args: Self::PromptArgs) -> Result<Vec<PromptMessage>, McpError> {
+        Ok(vec![
+            PromptMessage {
+                role: PromptMessageRole::User,
+                content: PromptMessageContent::text(
+                    "Why is allowed_directories not what I set in config.json?",
+                ),
+            },
+            PromptMessage {
+                role: PromptMessageRole::Assistant,
+                content: PromptMessageContent::text(
+                    "Use config_effective to see where the value comes from:\n\n\
+                     {\"key\": \"allowed_directories\"}\n\n\
+                     A source of env means an environment variable such as \
+                     KODEGEN_ALLOWED_DIRS overrides config.json; the origin names the variable.",
+                ),
+            },
+        ])
+    }
+}
@@ -0,0 +1,103 @@
+//! Typed access to `ConfigValue`
+//!
+//! `ConfigValue` is defined by `kodegen_config_manager`, so conversions are
+//! provided by the `ConfigValueExt` extension trait. They apply the same
+//! coercions `config_set` does, so a tool reading a value gets the answer
+//! the user meant when they wrote `"true"` or `1`.
+
+use crate::ConfigValue;
+use crate::normalize::coerce_bool;
+use std::fmt;
+
+/// A `ConfigValue` that can't be converted to the requested type.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("expected {expected}, got {found}")]
+pub struct ConfigValueError {
+    pub expected: &'static str,
+    /// The value as JSON.
+    pub found: String,
+}
+
+/// Shapes a `ConfigValue` can hold.
+const CONFIG_VALUE_SHAPES: &str = "a string, an integer, a boolean, or an array of strings";
+
+/// Conversions to and from `ConfigValue`.
+///
+/// `to_json` and `from_json` round-trip exactly, as do `display` and
+/// `parse_str`, so values can be built from `json!` literals:
+///
+/// ```ignore
+/// let value = ConfigValue::from_json(json!(["rm", "sudo"]))?;
+/// assert_eq!(value.to_json(), json!(["rm", "sudo"]));
+/// assert_eq!(ConfigValue::parse_str(&value.display().to_string())?.to_json(), value.to_json());
+/// ```
+pub trait ConfigValueExt: Sized {
+    /// The value as a boolean. Accepts `true`/`false`, the numbers `1` and
+    /// `0`, and the strings `true`, `false`, `yes`, `no`, `on`, `off`, `1`,
+    /// and `0` in any case.
+    fn into_bool(self) -> Result<bool, ConfigValueError>;
+
+    /// The value as JSON: a string, integer, boolean, or array of strings.
+    fn to_json(&self) -> serde_json::Value;
+
+    /// Build a value from JSON. Anything `ConfigValue` can't hold exactly,
+    /// such as `null`, `0.5`, objects, and arrays of non-strings, is an error
+    /// rather than an approximation.
+    fn from_json(value: serde_json::Value) -> Result<Self, ConfigValueError>;
+
+    /// Parse `text` as JSON, such as `42`, `true`, `"a"`, or `["a", "b"]`,
+    /// falling back to the whole text as a string. The inverse of `display`.
+    fn parse_str(text: &str) -> Result<Self, ConfigValueError>;
+
+    /// Display the value as compact JSON.
+    fn display(&self) -> DisplayConfigValue<'_>;
+}
+
+impl ConfigValueExt for ConfigValue {
+    fn into_bool(self) -> Result<bool, ConfigValueError> {
+        let json = self.to_json();
+        coerce_bool(&json).ok_or_else(|| ConfigValueError {
+            expected: "true or false",
+            found: json.to_string(),
+        })
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    fn from_json(value: serde_json::Value) -> Result<Self, ConfigValueError> {
+        let error = || ConfigValueError {
+            expected: CONFIG_VALUE_SHAPES,
+            found: value.to_string(),
+        };
+        let converted: ConfigValue = serde_json::from_value(value.clone()).map_err(|_| error())?;
+        // Rejects anything deserialization would have approximated
+        if converted.to_json() != value {
+            return Err(error());
+        }
+        Ok(converted)
+    }
+
+    fn parse_str(text: &str) -> Result<Self, ConfigValueError> {
+        match serde_json::from_str::<serde_json::Value>(text) {
+            Ok(json) => Self::from_json(json),
+            Err(_) => Self::from_json(serde_json::Value::String(text.to_string())),
+        }
+    }
+
+    fn display(&self) -> DisplayConfigValue<'_> {
+        DisplayConfigValue(self)
+    }
+}
+
+/// Displays a `ConfigValue` as compact JSON; returned by
+/// [`ConfigValueExt::display`].
+#[derive(Clone, Copy)]
+pub struct DisplayConfigValue<'a>(&'a ConfigValue);
+
+impl fmt::Display for DisplayConfigValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.to_json())
+    }
+}
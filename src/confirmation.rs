@@ -0,0 +1,73 @@
+//! Confirmation of sensitive changes
+//!
+//! `config_set` on a key marked `SecurityLevel::Sensitive` doesn't apply the
+//! value right away. It issues a single-use token bound to the key and value,
+//! and the change is applied by a second call repeating them with the token.
+//! `config_unset`, `config_undo`, `config_redo`, and `config_backups` restore
+//! do the same. An agent steered by injected instructions can't quietly widen
+//! the sandbox in one step.
+
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+
+/// How long a confirmation token stays valid.
+pub const CONFIRMATION_TTL: Duration = Duration::from_secs(300);
+
+/// A change waiting for its second call.
+#[derive(Debug)]
+struct Pending {
+    key: String,
+    value: serde_json::Value,
+    issued: Instant,
+}
+
+/// Outstanding confirmation tokens.
+#[derive(Debug, Default)]
+pub struct PendingConfirmations {
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+impl PendingConfirmations {
+    /// Issue a token confirming that `key` may be set to `value`.
+    pub fn issue(&self, key: &str, value: &serde_json::Value) -> String {
+        let mut bytes = [0u8; 16];
+        OsRng.fill_bytes(&mut bytes);
+        let token = bytes.iter().fold(String::new(), |mut token, b| {
+            let _ = write!(token, "{:02x}", b);
+            token
+        });
+        let mut pending = self.pending.lock();
+        pending.retain(|_, p| p.issued.elapsed() < CONFIRMATION_TTL);
+        pending.insert(
+            token.clone(),
+            Pending {
+                key: key.to_string(),
+                value: value.clone(),
+                issued: Instant::now(),
+            },
+        );
+        token
+    }
+
+    /// Use up `token`, failing unless it is unexpired and was issued for
+    /// setting `key` to `value`. A token is spent even when it doesn't match.
+    pub fn redeem(&self, token: &str, key: &str, value: &serde_json::Value) -> Result<(), String> {
+        let pending = self
+            .pending
+            .lock()
+            .remove(token.trim())
+            .filter(|p| p.issued.elapsed() < CONFIRMATION_TTL)
+            .ok_or("confirmation token is unknown, expired, or already used")?;
+        if pending.key != key || &pending.value != value {
+            return Err(format!(
+                "confirmation token was issued for a different change to '{}'",
+                pending.key
+            ));
+        }
+        Ok(())
+    }
+}
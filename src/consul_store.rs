@@ -0,0 +1,338 @@
+//! Consul KV persistence backend
+//!
+//! The extended config is stored as JSON under a single KV key. Each save
+//! holds a Consul session lock on `<key>.lock`, so servers sharing the key
+//! never interleave writes, and a server that dies mid-save releases the lock
+//! when its session TTL expires. Changes made elsewhere arrive through
+//! blocking queries on the key. With an encryption key the value is
+//! encrypted before it is sent to Consul.
+
+use crate::encryption::{self, EncryptionKey};
+use crate::extended_config::ExtendedConfig;
+use crate::store::{ConfigStore, StoreFuture};
+use reqwest::{Method, RequestBuilder, StatusCode};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+/// Environment variable naming the KV key; the store is used only when set.
+pub const CONSUL_KEY_ENV: &str = "KODEGEN_CONSUL_KEY";
+
+/// Consul's own variable for the agent address.
+pub const CONSUL_ADDR_ENV: &str = "CONSUL_HTTP_ADDR";
+
+/// Consul's own variable for the ACL token.
+pub const CONSUL_TOKEN_ENV: &str = "CONSUL_HTTP_TOKEN";
+
+/// Agent address used when `CONSUL_HTTP_ADDR` is unset.
+pub const DEFAULT_CONSUL_ADDR: &str = "http://127.0.0.1:8500";
+
+/// Timeout for ordinary requests.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest a blocking query waits for a change.
+const BLOCKING_WAIT: Duration = Duration::from_secs(300);
+
+/// Longest a save waits for another server to release the lock.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pause between lock attempts.
+const LOCK_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// TTL of the session holding the lock; Consul's minimum.
+const SESSION_TTL: &str = "10s";
+
+/// Wait before retrying a failed blocking query.
+const WATCH_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
+pub struct ConsulStore {
+    http: reqwest::Client,
+    address: String,
+    key: String,
+    token: Option<String>,
+    encryption: Option<EncryptionKey>,
+}
+
+#[derive(Deserialize)]
+struct SessionCreated {
+    #[serde(rename = "ID")]
+    id: String,
+}
+
+impl ConsulStore {
+    /// Store the config under `key` via the Consul agent at `address`.
+    #[must_use]
+    pub fn new(address: impl Into<String>, key: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            address: address.into().trim_end_matches('/').to_string(),
+            key: key.into().trim_start_matches('/').to_string(),
+            token: None,
+            encryption: None,
+        }
+    }
+
+    /// ACL token sent with every request.
+    #[must_use]
+    pub fn token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    /// Encrypt the stored value with `key`.
+    #[must_use]
+    pub fn encryption_key(mut self, key: Option<EncryptionKey>) -> Self {
+        self.encryption = key;
+        self
+    }
+
+    /// Store configured from `KODEGEN_CONSUL_KEY`, `CONSUL_HTTP_ADDR`, and
+    /// `CONSUL_HTTP_TOKEN`, or `None` if no key is set. The value is
+    /// encrypted if `KODEGEN_CONFIG_KEY` is set.
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        let key = std::env::var(CONSUL_KEY_ENV).ok().filter(|key| !key.is_empty())?;
+        let address = std::env::var(CONSUL_ADDR_ENV)
+            .ok()
+            .filter(|address| !address.is_empty())
+            .unwrap_or_else(|| DEFAULT_CONSUL_ADDR.to_string());
+        let token = std::env::var(CONSUL_TOKEN_ENV)
+            .ok()
+            .filter(|token| !token.is_empty());
+        Some(
+            Self::new(address, key)
+                .token(token)
+                .encryption_key(EncryptionKey::from_env()),
+        )
+    }
+
+    #[must_use]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}/v1/{}", self.address, path))
+            .timeout(REQUEST_TIMEOUT);
+        match &self.token {
+            Some(token) => request.header("X-Consul-Token", token),
+            None => request,
+        }
+    }
+
+    /// `config` as the value stored in Consul, encrypted if a key is set.
+    fn encode(&self, config: &ExtendedConfig) -> std::io::Result<Vec<u8>> {
+        encryption::seal_json(config, self.encryption.as_ref())
+    }
+
+    /// Config from a value stored in Consul by `encode`.
+    fn decode(&self, value: &[u8]) -> std::io::Result<ExtendedConfig> {
+        encryption::open_json(value, self.encryption.as_ref())
+    }
+
+    /// The stored config and its Consul index. With `index`, blocks until
+    /// the key changes past it or `BLOCKING_WAIT` elapses.
+    async fn get(&self, index: Option<u64>) -> std::io::Result<(Option<ExtendedConfig>, u64)> {
+        let mut request = self
+            .request(Method::GET, &format!("kv/{}", self.key))
+            .query(&[("raw", "")]);
+        if let Some(index) = index {
+            request = request
+                .query(&[("index", index.to_string())])
+                .query(&[("wait", format!("{}s", BLOCKING_WAIT.as_secs()))])
+                .timeout(BLOCKING_WAIT + REQUEST_TIMEOUT);
+        }
+        let response = request.send().await.map_err(to_io)?;
+        let index = response
+            .headers()
+            .get("X-Consul-Index")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0);
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok((None, index));
+        }
+        let body = response
+            .error_for_status()
+            .map_err(to_io)?
+            .bytes()
+            .await
+            .map_err(to_io)?;
+        Ok((Some(self.decode(&body)?), index))
+    }
+
+    async fn create_session(&self) -> std::io::Result<String> {
+        let body = serde_json::json!({
+            "Name": "kodegen-config",
+            "TTL": SESSION_TTL,
+            "Behavior": "release",
+            "LockDelay": "1s",
+        });
+        let created: SessionCreated = self
+            .request(Method::PUT, "session/create")
+            .json(&body)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(to_io)?
+            .json()
+            .await
+            .map_err(to_io)?;
+        Ok(created.id)
+    }
+
+    async fn destroy_session(&self, session: &str) {
+        let result = self
+            .request(Method::PUT, &format!("session/destroy/{}", session))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+        if let Err(e) = result {
+            log::warn!("Failed to destroy Consul session {}: {}", session, e);
+        }
+    }
+
+    /// Acquire (`acquire`) or release the save lock for `session`.
+    async fn lock(&self, session: &str, acquire: bool) -> std::io::Result<bool> {
+        let operation = if acquire { "acquire" } else { "release" };
+        self.request(Method::PUT, &format!("kv/{}.lock", self.key))
+            .query(&[(operation, session)])
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(to_io)?
+            .json()
+            .await
+            .map_err(to_io)
+    }
+
+    /// Write `data` while holding the save lock under `session`.
+    async fn put_locked(&self, session: &str, data: Vec<u8>) -> std::io::Result<()> {
+        let deadline = Instant::now() + LOCK_TIMEOUT;
+        while !self.lock(session, true).await? {
+            if Instant::now() >= deadline {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("{}.lock is held by another server", self.key),
+                ));
+            }
+            tokio::time::sleep(LOCK_RETRY_DELAY).await;
+        }
+        let result = self
+            .request(Method::PUT, &format!("kv/{}", self.key))
+            .body(data)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map(|_| ())
+            .map_err(to_io);
+        if let Err(e) = self.lock(session, false).await {
+            log::warn!("Failed to release {}.lock: {}", self.key, e);
+        }
+        result
+    }
+
+    /// Forward configs saved to Consul until `updates` closes.
+    async fn follow(&self, updates: mpsc::Sender<ExtendedConfig>) {
+        let mut index = None;
+        while !updates.is_closed() {
+            match self.get(index).await {
+                Ok((config, new_index)) => {
+                    // Consul may reset the index; start over rather than block forever
+                    let changed = index.is_some_and(|index| new_index > index);
+                    index = Some(if index.is_some_and(|index| new_index < index) {
+                        0
+                    } else {
+                        new_index.max(1)
+                    });
+                    if let (true, Some(config)) = (changed, config)
+                        && updates.send(config).await.is_err()
+                    {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    log::warn!("Consul watch on {} failed: {}; retrying", self.key, e);
+                    tokio::time::sleep(WATCH_RETRY_DELAY).await;
+                }
+            }
+        }
+    }
+}
+
+impl ConfigStore for ConsulStore {
+    fn location(&self) -> String {
+        format!("{}/v1/kv/{}", self.address, self.key)
+    }
+
+    fn load(&self) -> StoreFuture<'_, Option<ExtendedConfig>> {
+        Box::pin(async move { self.get(None).await.map(|(config, _)| config) })
+    }
+
+    fn save<'a>(&'a self, config: &'a ExtendedConfig) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            let data = self.encode(config)?;
+            let session = self.create_session().await?;
+            let result = self.put_locked(&session, data).await;
+            self.destroy_session(&session).await;
+            result
+        })
+    }
+
+    fn watch(&self) -> Option<mpsc::Receiver<ExtendedConfig>> {
+        let (tx, rx) = mpsc::channel(16);
+        let store = self.clone();
+        tokio::spawn(async move { store.follow(tx).await });
+        Some(rx)
+    }
+}
+
+fn to_io(e: reqwest::Error) -> std::io::Error {
+    std::io::Error::other(e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(key: Option<EncryptionKey>) -> ConsulStore {
+        ConsulStore::new(DEFAULT_CONSUL_ADDR, "kodegen/config").encryption_key(key)
+    }
+
+    fn config() -> ExtendedConfig {
+        ExtendedConfig {
+            max_search_results: Some(10),
+            ..ExtendedConfig::default()
+        }
+    }
+
+    #[test]
+    fn values_are_encrypted_with_the_key() {
+        let encrypted = store(Some(EncryptionKey::new([3; 32])));
+        let value = encrypted.encode(&config()).unwrap();
+        assert!(encryption::is_encrypted(&value));
+        assert_eq!(
+            encrypted.decode(&value).unwrap().max_search_results,
+            Some(10)
+        );
+        assert!(store(None).decode(&value).is_err());
+    }
+
+    #[test]
+    fn values_are_plain_json_without_a_key() {
+        let value = store(None).encode(&config()).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&value).unwrap();
+        assert_eq!(json["max_search_results"], 10);
+    }
+
+    #[test]
+    fn keys_and_addresses_are_normalized() {
+        let store = ConsulStore::new("http://consul:8500/", "/kodegen/config");
+        assert_eq!(store.key(), "kodegen/config");
+        assert_eq!(store.location(), "http://consul:8500/v1/kv/kodegen/config");
+    }
+}
@@ -0,0 +1,51 @@
+//! Structured comparison of two configurations
+
+use crate::ServerConfig;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// Fields that describe the running host rather than configuration.
+const VOLATILE_FIELDS: &[&str] = &["system_info", "save_error_count"];
+
+/// One key whose value differs between two configurations.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigDelta {
+    pub key: String,
+    /// Value in the base configuration; `null` if absent.
+    pub old: serde_json::Value,
+    /// Value in the compared configuration; `null` if absent.
+    pub new: serde_json::Value,
+}
+
+/// Compare two configurations key by key, ignoring volatile system fields.
+///
+/// Deltas are ordered by key.
+#[must_use]
+pub fn diff_configs(old: &ServerConfig, new: &ServerConfig) -> Vec<ConfigDelta> {
+    diff_values(
+        &serde_json::to_value(old).unwrap_or_default(),
+        &serde_json::to_value(new).unwrap_or_default(),
+    )
+}
+
+/// Compare the top-level fields of two serialized configurations.
+#[must_use]
+pub fn diff_values(old: &serde_json::Value, new: &serde_json::Value) -> Vec<ConfigDelta> {
+    let empty = serde_json::Map::new();
+    let old = old.as_object().unwrap_or(&empty);
+    let new = new.as_object().unwrap_or(&empty);
+
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    keys.into_iter()
+        .filter(|key| !VOLATILE_FIELDS.contains(&key.as_str()))
+        .filter_map(|key| {
+            let old_value = old.get(key).cloned().unwrap_or_default();
+            let new_value = new.get(key).cloned().unwrap_or_default();
+            (old_value != new_value).then(|| ConfigDelta {
+                key: key.clone(),
+                old: old_value,
+                new: new_value,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,207 @@
+//! Encryption of the extended config at rest
+//!
+//! Encrypted files start with `MAGIC`, followed by a random 96-bit nonce and
+//! the AES-256-GCM ciphertext of the serialized config.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+/// Environment variable holding the base64-encoded 32-byte key.
+pub const ENCRYPTION_KEY_ENV: &str = "KODEGEN_CONFIG_KEY";
+
+/// Marks an encrypted config file.
+const MAGIC: &[u8] = b"KODEGEN-ENC1\n";
+
+const NONCE_LEN: usize = 12;
+
+/// AES-256-GCM key for the extended config.
+#[derive(Clone)]
+pub struct EncryptionKey(Key<Aes256Gcm>);
+
+impl EncryptionKey {
+    #[must_use]
+    pub fn new(bytes: [u8; 32]) -> Self {
+        Self(bytes.into())
+    }
+
+    /// Decode a base64-encoded 32-byte key.
+    pub fn from_base64(encoded: &str) -> Result<Self, String> {
+        let bytes = STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| format!("key is not valid base64: {}", e))?;
+        let bytes: [u8; 32] = bytes
+            .try_into()
+            .map_err(|b: Vec<u8>| format!("key must be 32 bytes, got {}", b.len()))?;
+        Ok(Self::new(bytes))
+    }
+
+    /// Key from `KODEGEN_CONFIG_KEY`, or `None` if unset or invalid.
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        let encoded = std::env::var(ENCRYPTION_KEY_ENV).ok()?;
+        match Self::from_base64(&encoded) {
+            Ok(key) => Some(key),
+            Err(e) => {
+                log::error!("Ignoring {}: {}", ENCRYPTION_KEY_ENV, e);
+                None
+            }
+        }
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> std::io::Result<Vec<u8>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = Aes256Gcm::new(&self.0)
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| std::io::Error::other("config encryption failed"))?;
+        let mut out = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+        Ok(out)
+    }
+
+    /// Decrypt a file produced by `encrypt`; fails on a wrong key or tampering.
+    pub fn decrypt(&self, contents: &[u8]) -> std::io::Result<Vec<u8>> {
+        let invalid = |msg: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+        let body = contents
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| invalid("config is not encrypted"))?;
+        if body.len() < NONCE_LEN {
+            return Err(invalid("encrypted config is truncated"));
+        }
+        let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+        Aes256Gcm::new(&self.0)
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| invalid("cannot decrypt config: wrong key or corrupted file"))
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(<redacted>)")
+    }
+}
+
+/// Whether `contents` were written by `EncryptionKey::encrypt`.
+#[must_use]
+pub fn is_encrypted(contents: &[u8]) -> bool {
+    contents.starts_with(MAGIC)
+}
+
+/// Plaintext of a config file read from disk, decrypting it if needed.
+pub fn decode(contents: Vec<u8>, key: Option<&EncryptionKey>) -> std::io::Result<String> {
+    let plaintext = match (is_encrypted(&contents), key) {
+        (false, _) => contents,
+        (true, Some(key)) => key.decrypt(&contents)?,
+        (true, None) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("config is encrypted; set {} to read it", ENCRYPTION_KEY_ENV),
+            ));
+        }
+    };
+    String::from_utf8(plaintext)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// `value` as JSON, encrypted with `key` if one is given. Used by the
+/// remote stores, whose payloads never pass through `save_to_disk`.
+pub fn seal_json<T: Serialize>(
+    value: &T,
+    key: Option<&EncryptionKey>,
+) -> std::io::Result<Vec<u8>> {
+    let json = serde_json::to_vec(value)?;
+    match key {
+        Some(key) => key.encrypt(&json),
+        None => Ok(json),
+    }
+}
+
+/// Parse a payload written by `seal_json`. Plaintext JSON is read as is, so
+/// data stored before a key was configured stays readable.
+pub fn open_json<T: DeserializeOwned>(
+    payload: &[u8],
+    key: Option<&EncryptionKey>,
+) -> std::io::Result<T> {
+    Ok(serde_json::from_str(&decode(payload.to_vec(), key)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> EncryptionKey {
+        EncryptionKey::new([byte; 32])
+    }
+
+    #[test]
+    fn round_trips_with_a_fresh_nonce_each_time() {
+        let key = key(7);
+        let first = key.encrypt(b"{\"a\":1}").unwrap();
+        let second = key.encrypt(b"{\"a\":1}").unwrap();
+        assert!(is_encrypted(&first));
+        assert_ne!(first, second);
+        assert_eq!(key.decrypt(&first).unwrap(), b"{\"a\":1}");
+    }
+
+    #[test]
+    fn rejects_the_wrong_key() {
+        let sealed = key(1).encrypt(b"secret").unwrap();
+        let error = key(2).decrypt(&sealed).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_tampered_truncated_and_plaintext_input() {
+        let key = key(3);
+        let mut sealed = key.encrypt(b"secret").unwrap();
+        *sealed.last_mut().unwrap() ^= 1;
+        assert!(key.decrypt(&sealed).is_err());
+        assert!(key.decrypt(&sealed[..MAGIC.len() + 4]).is_err());
+        assert!(key.decrypt(b"{\"plain\":true}").is_err());
+    }
+
+    #[test]
+    fn decode_passes_plaintext_through_and_needs_a_key_for_ciphertext() {
+        assert_eq!(decode(b"plain".to_vec(), None).unwrap(), "plain");
+        assert_eq!(decode(b"plain".to_vec(), Some(&key(4))).unwrap(), "plain");
+        let sealed = key(4).encrypt(b"secret").unwrap();
+        assert_eq!(decode(sealed.clone(), Some(&key(4))).unwrap(), "secret");
+        let error = decode(sealed, None).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn sealed_json_round_trips_and_reads_plaintext() {
+        let key = key(5);
+        let value = serde_json::json!({"timeout_ms": 500});
+        let sealed = seal_json(&value, Some(&key)).unwrap();
+        assert!(is_encrypted(&sealed));
+        assert_eq!(
+            open_json::<serde_json::Value>(&sealed, Some(&key)).unwrap(),
+            value
+        );
+        let plain = seal_json(&value, None).unwrap();
+        assert_eq!(
+            open_json::<serde_json::Value>(&plain, Some(&key)).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn parses_base64_keys_of_32_bytes_only() {
+        assert!(EncryptionKey::from_base64(&STANDARD.encode([9u8; 32])).is_ok());
+        assert!(EncryptionKey::from_base64(&STANDARD.encode([9u8; 16])).is_err());
+        assert!(EncryptionKey::from_base64("not base64!").is_err());
+    }
+
+    #[test]
+    fn debug_output_hides_the_key() {
+        assert_eq!(format!("{:?}", key(6)), "EncryptionKey(<redacted>)");
+    }
+}
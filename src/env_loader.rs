@@ -0,0 +1,301 @@
+//! Environment overrides for config keys
+//!
+//! Any key can be overridden with `KODEGEN_CONFIG_<KEY>`, e.g.
+//! `KODEGEN_CONFIG_FILE_READ_LINE_LIMIT=5000`, and the security lists also
+//! with the shorter `KODEGEN_ALLOWED_DIRS`, `KODEGEN_DENIED_DIRS`, and
+//! `KODEGEN_BLOCKED_COMMANDS`.
+//! `KODEGEN_CONFIG_JSON` can hold a JSON object overriding several keys at
+//! once; a per-key variable wins over its entry in that object. The
+//! `KODEGEN_` prefix can be changed with `ConfigManagerBuilder::env_prefix`.
+//! Overrides are parsed by the key's type and validated when the manager is
+//! built; invalid ones are logged and ignored. They are layered over
+//! `config.json` whenever the snapshot is refreshed and never written to disk.
+
+use crate::ServerConfig;
+use crate::expand::expand_directories;
+use crate::keys::{ConfigKey, UnknownConfigKey, resolve_key};
+use crate::metadata::ValueType;
+use crate::normalize::{duration_unit, normalize};
+use crate::validation::validate;
+use serde::Serialize;
+
+/// Prefix of every override variable unless configured otherwise.
+pub const DEFAULT_ENV_PREFIX: &str = "KODEGEN_";
+
+/// Suffix of the variable holding a JSON object of overrides.
+pub const CONFIG_JSON_ENV_SUFFIX: &str = "CONFIG_JSON";
+
+/// A key whose value comes from the environment.
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvOverride {
+    pub key: &'static str,
+    /// Variable the value was read from.
+    pub var: String,
+    /// Parsed value, in the form `config_set` accepts.
+    pub value: serde_json::Value,
+    /// Directory entries as written, if expanding or normalizing changed them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Vec<String>>,
+}
+
+/// Every valid override, read once at startup.
+#[derive(Debug, Clone, Default)]
+pub struct EnvOverrides {
+    overrides: Vec<EnvOverride>,
+}
+
+impl EnvOverrides {
+    /// Read and validate the override for every key, logging invalid ones.
+    ///
+    /// `<prefix>CONFIG_<KEY>` takes precedence over the short names, and both over `<prefix>CONFIG_JSON`.
+    #[must_use]
+    pub fn from_env(prefix: &str) -> Self {
+        let json_var = format!("{}{}", prefix, CONFIG_JSON_ENV_SUFFIX);
+        let mut json = match std::env::var(&json_var) {
+            Ok(raw) => parse_json_overrides(&json_var, &raw),
+            Err(_) => Vec::new(),
+        };
+        let mut overrides = Vec::new();
+        for &key in ConfigKey::SERVER {
+            let found = std::iter::once(env_var_name(prefix, key))
+                .chain(short_env_var_name(prefix, key))
+                .find_map(|var| std::env::var(&var).ok().map(|raw| (var, raw)));
+            let Some((var, raw)) = found else {
+                if let Some(index) = json.iter().position(|o| o.key == key.as_str()) {
+                    let found = json.swap_remove(index);
+                    log::info!("{} overridden by {}", key, found.var);
+                    overrides.push(found);
+                }
+                continue;
+            };
+            match parse_override(key, &raw) {
+                Ok((value, raw)) => {
+                    log::info!("{} overridden by {}", key, var);
+                    overrides.push(EnvOverride {
+                        key: key.as_str(),
+                        var,
+                        value,
+                        raw,
+                    });
+                }
+                Err(e) => log::error!("Ignoring {}: {}", var, e),
+            }
+        }
+        Self { overrides }
+    }
+
+    #[must_use]
+    pub fn all(&self) -> &[EnvOverride] {
+        &self.overrides
+    }
+
+    #[must_use]
+    pub fn get(&self, key: ConfigKey<'_>) -> Option<&EnvOverride> {
+        self.overrides.iter().find(|o| o.key == key.as_str())
+    }
+
+    /// The variable overriding `key`, if any.
+    #[must_use]
+    pub fn var_for(&self, key: ConfigKey<'_>) -> Option<String> {
+        self.get(key).map(|found| found.var.clone())
+    }
+
+    /// `config` with every override applied.
+    #[must_use]
+    pub fn apply(&self, config: ServerConfig) -> ServerConfig {
+        if self.overrides.is_empty() {
+            return config;
+        }
+        let mut value = serde_json::to_value(&config).unwrap_or_default();
+        for o in &self.overrides {
+            value[o.key] = stored_form(o.key, &o.value);
+        }
+        serde_json::from_value(value).unwrap_or_else(|e| {
+            log::error!("Failed to apply environment overrides: {}", e);
+            config
+        })
+    }
+}
+
+/// `<prefix>CONFIG_<KEY>` for `key`, e.g. `KODEGEN_CONFIG_DEFAULT_SHELL`.
+#[must_use]
+pub fn env_var_name(prefix: &str, key: ConfigKey<'_>) -> String {
+    format!("{}CONFIG_{}", prefix, key.as_str().to_uppercase())
+}
+
+/// Short variable name for the security lists, e.g. `KODEGEN_ALLOWED_DIRS`.
+#[must_use]
+pub fn short_env_var_name(prefix: &str, key: ConfigKey<'_>) -> Option<String> {
+    let name = match key {
+        ConfigKey::AllowedDirectories => "ALLOWED_DIRS",
+        ConfigKey::DeniedDirectories => "DENIED_DIRS",
+        ConfigKey::BlockedCommands => "BLOCKED_COMMANDS",
+        _ => return None,
+    };
+    Some(format!("{}{}", prefix, name))
+}
+
+/// Parse `raw` according to the type of `key`, expand directory entries,
+/// and validate it.
+///
+/// Lists may be a JSON array; otherwise directory lists are split like
+/// `PATH` and other lists on commas.
+fn parse_override(
+    key: ConfigKey<'_>,
+    raw: &str,
+) -> Result<(serde_json::Value, Option<Vec<String>>), String> {
+    let Some(metadata) = key.metadata() else {
+        return Err(format!("{} can't be set from the environment", key));
+    };
+    let raw = raw.trim();
+    let value = match metadata.value_type {
+        ValueType::String => serde_json::Value::String(raw.to_string()),
+        ValueType::Number | ValueType::Float => match raw.parse::<serde_json::Number>() {
+            Ok(number) => serde_json::Value::Number(number),
+            // A duration string such as 30s, converted by normalize
+            Err(_) if duration_unit(key.as_str()).is_some() => {
+                serde_json::Value::String(raw.to_string())
+            }
+            Err(_) => return Err(format!("expected a number, got '{}'", raw)),
+        },
+        ValueType::Boolean => match raw.to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => serde_json::Value::Bool(true),
+            "false" | "0" | "no" | "off" => serde_json::Value::Bool(false),
+            _ => return Err(format!("expected true or false, got '{}'", raw)),
+        },
+        ValueType::StringArray if raw.starts_with('[') => {
+            serde_json::from_str::<Vec<String>>(raw)
+                .map(Into::into)
+                .map_err(|e| format!("invalid JSON array: {}", e))?
+        }
+        ValueType::StringArray => {
+            let items: Vec<String> = match key {
+                ConfigKey::AllowedDirectories | ConfigKey::DeniedDirectories => {
+                    std::env::split_paths(raw)
+                        .map(|path| path.to_string_lossy().into_owned())
+                        .collect()
+                }
+                _ => raw.split(',').map(|item| item.trim().to_string()).collect(),
+            };
+            items.into_iter().filter(|item| !item.is_empty()).collect()
+        }
+    };
+    let (value, unexpanded) = expand_directories(key, normalize(key.as_str(), value)?)?;
+    validate(key, &value).map_err(|e| e.to_string())?;
+    Ok((value, unexpanded))
+}
+
+/// Validate each entry of the JSON object in `var`, logging and skipping
+/// invalid ones.
+fn parse_json_overrides(var: &str, raw: &str) -> Vec<EnvOverride> {
+    let object: serde_json::Map<String, serde_json::Value> = match serde_json::from_str(raw) {
+        Ok(object) => object,
+        Err(e) => {
+            log::error!("Ignoring {}: expected a JSON object: {}", var, e);
+            return Vec::new();
+        }
+    };
+    let mut overrides = Vec::new();
+    for (name, value) in object {
+        let key = match ConfigKey::server(resolve_key(&name).key) {
+            Ok(key) => key,
+            Err(UnknownConfigKey(name)) => {
+                log::error!("Ignoring '{}' in {}: unknown config key", name, var);
+                continue;
+            }
+        };
+        let checked = normalize(key.as_str(), value)
+            .and_then(|value| expand_directories(key, value))
+            .and_then(|(value, raw)| {
+                validate(key, &value).map_err(|e| e.to_string())?;
+                Ok((value, raw))
+            });
+        match checked {
+            Ok((value, raw)) => overrides.push(EnvOverride {
+                key: key.as_str(),
+                var: var.to_string(),
+                value,
+                raw,
+            }),
+            Err(e) => log::error!("Ignoring '{}' in {}: {}", name, var, e),
+        }
+    }
+    overrides
+}
+
+/// `value` as `ServerConfig` serializes it.
+fn stored_form(key: &str, value: &serde_json::Value) -> serde_json::Value {
+    // Set as a 0-100 percentage but stored as a 0.0-1.0 fraction
+    match value.as_f64() {
+        Some(percent) if key == ConfigKey::FuzzySearchThreshold.as_str() => {
+            serde_json::json!(percent / 100.0)
+        }
+        _ => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_variables_after_the_prefix_and_key() {
+        assert_eq!(
+            env_var_name("KODEGEN_", ConfigKey::FileReadLineLimit),
+            "KODEGEN_CONFIG_FILE_READ_LINE_LIMIT"
+        );
+        assert_eq!(
+            short_env_var_name("ACME_", ConfigKey::BlockedCommands).as_deref(),
+            Some("ACME_BLOCKED_COMMANDS")
+        );
+        assert_eq!(
+            short_env_var_name("KODEGEN_", ConfigKey::DefaultShell),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_numbers_and_durations() {
+        let (value, raw) = parse_override(ConfigKey::FileReadLineLimit, " 5000 ").unwrap();
+        assert_eq!(value, serde_json::json!(5000));
+        assert_eq!(raw, None);
+        let (value, _) = parse_override(ConfigKey::HttpConnectionTimeoutSecs, "2m").unwrap();
+        assert_eq!(value, serde_json::json!(120));
+    }
+
+    #[test]
+    fn rejects_malformed_and_invalid_values() {
+        assert!(parse_override(ConfigKey::FileReadLineLimit, "lots").is_err());
+        assert!(parse_override(ConfigKey::FileReadLineLimit, "0").is_err());
+        assert!(parse_override(ConfigKey::HttpConnectionTimeoutSecs, "1500ms").is_err());
+        assert!(parse_override(ConfigKey::BlockedCommands, "[\"rm\",").is_err());
+    }
+
+    #[test]
+    fn splits_lists_on_commas_or_reads_json_arrays() {
+        let (value, _) = parse_override(ConfigKey::BlockedCommands, "rm, sudo ,,").unwrap();
+        assert_eq!(value, serde_json::json!(["rm", "sudo"]));
+        let (value, _) = parse_override(ConfigKey::BlockedCommands, r#"["rm","dd"]"#).unwrap();
+        assert_eq!(value, serde_json::json!(["rm", "dd"]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn splits_directory_lists_like_path_and_keeps_the_raw_entries() {
+        let (value, raw) = parse_override(ConfigKey::DeniedDirectories, "/etc:/var/log/").unwrap();
+        assert_eq!(value, serde_json::json!(["/etc", "/var/log"]));
+        assert_eq!(raw, Some(vec!["/etc".to_string(), "/var/log/".to_string()]));
+    }
+
+    #[test]
+    fn json_overrides_resolve_aliases_and_skip_bad_entries() {
+        let raw = r#"{"fileReadLineLimit": 10, "bogus": 1, "file_write_line_limit": "x"}"#;
+        let overrides = parse_json_overrides("KODEGEN_CONFIG_JSON", raw);
+        assert_eq!(overrides.len(), 1);
+        assert_eq!(overrides[0].key, "file_read_line_limit");
+        assert_eq!(overrides[0].var, "KODEGEN_CONFIG_JSON");
+        assert_eq!(overrides[0].value, serde_json::json!(10));
+        assert!(parse_json_overrides("KODEGEN_CONFIG_JSON", "[1, 2]").is_empty());
+        assert!(parse_json_overrides("KODEGEN_CONFIG_JSON", "{").is_empty());
+    }
+}
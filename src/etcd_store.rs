@@ -0,0 +1,343 @@
+//! etcd persistence backend
+//!
+//! The extended config is stored as JSON under a single key, so every server
+//! pointed at the same cluster shares one configuration, and saves made by
+//! one server reach the others through an etcd watch. A local `FileStore`
+//! copy is kept current and takes over while etcd is unreachable; saves made
+//! during an outage are pushed to etcd once it is reachable again. With an
+//! encryption key the value is encrypted before it leaves the process.
+
+use crate::encryption::{self, EncryptionKey};
+use crate::extended_config::ExtendedConfig;
+use crate::store::{ConfigStore, FileStore, StoreFuture};
+use etcd_client::{Client, ConnectOptions, EventType, WatchOptions};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::{Mutex, mpsc};
+
+/// Environment variable holding comma-separated etcd endpoints.
+pub const ETCD_ENDPOINTS_ENV: &str = "KODEGEN_ETCD_ENDPOINTS";
+
+/// Environment variable overriding the key the config is stored under.
+pub const ETCD_KEY_ENV: &str = "KODEGEN_ETCD_KEY";
+
+/// Key the config is stored under unless configured otherwise.
+pub const DEFAULT_ETCD_KEY: &str = "/kodegen/config/extended";
+
+/// File name of the local copy, stored alongside `config.json`.
+pub const ETCD_CACHE_FILE: &str = "config.etcd.json";
+
+/// Timeout for connecting and for each request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Wait before re-establishing a broken watch.
+const WATCH_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+pub struct EtcdStore {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    endpoints: Vec<String>,
+    key: String,
+    client: Mutex<Option<Client>>,
+    fallback: FileStore,
+    encryption: Option<EncryptionKey>,
+    /// A save reached only the local copy and must still be pushed to etcd.
+    unsynced: AtomicBool,
+}
+
+impl EtcdStore {
+    /// Store the config under `key` on the cluster at `endpoints`, keeping a
+    /// local copy in `fallback`. Connects lazily, so an unreachable cluster
+    /// doesn't prevent startup.
+    #[must_use]
+    pub fn new(endpoints: Vec<String>, key: impl Into<String>, fallback: FileStore) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                endpoints,
+                key: key.into(),
+                client: Mutex::new(None),
+                fallback,
+                encryption: None,
+                unsynced: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Encrypt the value stored in etcd with `key`. Takes effect only before
+    /// `watch` is called.
+    #[must_use]
+    pub fn encryption_key(mut self, key: Option<EncryptionKey>) -> Self {
+        if let Some(inner) = Arc::get_mut(&mut self.inner) {
+            inner.encryption = key;
+        }
+        self
+    }
+
+    /// Store configured from `KODEGEN_ETCD_ENDPOINTS` and `KODEGEN_ETCD_KEY`,
+    /// or `None` if no endpoints are set. The local copy is
+    /// `~/.kodegen/config.etcd.json`. Both are encrypted if
+    /// `KODEGEN_CONFIG_KEY` is set.
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        let endpoints: Vec<String> = std::env::var(ETCD_ENDPOINTS_ENV)
+            .ok()?
+            .split(',')
+            .map(str::trim)
+            .filter(|endpoint| !endpoint.is_empty())
+            .map(str::to_string)
+            .collect();
+        if endpoints.is_empty() {
+            return None;
+        }
+        let key = std::env::var(ETCD_KEY_ENV)
+            .ok()
+            .filter(|key| !key.is_empty())
+            .unwrap_or_else(|| DEFAULT_ETCD_KEY.to_string());
+        let encryption = EncryptionKey::from_env();
+        let fallback = FileStore::new(crate::persistence::config_dir().join(ETCD_CACHE_FILE))
+            .encryption_key(encryption.clone());
+        Some(Self::new(endpoints, key, fallback).encryption_key(encryption))
+    }
+
+    #[must_use]
+    pub fn key(&self) -> &str {
+        &self.inner.key
+    }
+
+    /// Whether a save made while etcd was unreachable hasn't been pushed yet.
+    #[must_use]
+    pub fn is_unsynced(&self) -> bool {
+        self.inner.unsynced.load(Ordering::Relaxed)
+    }
+}
+
+impl Inner {
+    async fn client(&self) -> std::io::Result<Client> {
+        let mut client = self.client.lock().await;
+        if let Some(client) = &*client {
+            return Ok(client.clone());
+        }
+        let options = ConnectOptions::new()
+            .with_connect_timeout(REQUEST_TIMEOUT)
+            .with_timeout(REQUEST_TIMEOUT);
+        let connected = Client::connect(&self.endpoints, Some(options))
+            .await
+            .map_err(to_io)?;
+        Ok(client.insert(connected).clone())
+    }
+
+    /// `config` as the value stored in etcd, encrypted if a key is set.
+    fn encode(&self, config: &ExtendedConfig) -> std::io::Result<Vec<u8>> {
+        encryption::seal_json(config, self.encryption.as_ref())
+    }
+
+    /// Config from a value stored in etcd by `encode`.
+    fn decode(&self, value: &[u8]) -> std::io::Result<ExtendedConfig> {
+        encryption::open_json(value, self.encryption.as_ref())
+    }
+
+    /// The stored config and the cluster revision it was read at.
+    async fn get(&self) -> std::io::Result<(Option<ExtendedConfig>, i64)> {
+        let response = self
+            .client()
+            .await?
+            .get(self.key.as_str(), None)
+            .await
+            .map_err(to_io)?;
+        let revision = response.header().map_or(0, |header| header.revision());
+        let config = response
+            .kvs()
+            .first()
+            .map(|kv| self.decode(kv.value()))
+            .transpose()?;
+        Ok((config, revision))
+    }
+
+    async fn put(&self, config: &ExtendedConfig) -> std::io::Result<()> {
+        let data = self.encode(config)?;
+        self.client()
+            .await?
+            .put(self.key.as_str(), data, None)
+            .await
+            .map(|_| ())
+            .map_err(to_io)
+    }
+
+    /// Push the local copy if a save during an outage never reached etcd.
+    async fn push_unsynced(&self) -> std::io::Result<()> {
+        if !self.unsynced.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        if let Some(config) = self.fallback.load().await? {
+            self.put(&config).await?;
+            log::info!("Pushed config saved during etcd outage to {}", self.key);
+        }
+        self.unsynced.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Refresh the local copy with a config read from etcd.
+    async fn cache(&self, config: &ExtendedConfig) {
+        if let Err(e) = self.fallback.save(config).await {
+            log::warn!("Failed to update {}: {}", self.fallback.location(), e);
+        }
+    }
+
+    /// Forward configs saved to etcd until the watch breaks or `updates` closes.
+    async fn follow(&self, updates: &mpsc::Sender<ExtendedConfig>) -> std::io::Result<()> {
+        self.push_unsynced().await?;
+        let (current, revision) = self.get().await?;
+        if let Some(config) = current {
+            self.cache(&config).await;
+            if updates.send(config).await.is_err() {
+                return Ok(());
+            }
+        }
+        let options = WatchOptions::new().with_start_revision(revision + 1);
+        let (_watcher, mut stream) = self
+            .client()
+            .await?
+            .watch(self.key.as_str(), Some(options))
+            .await
+            .map_err(to_io)?;
+        while let Some(response) = stream.message().await.map_err(to_io)? {
+            for event in response.events() {
+                if event.event_type() != EventType::Put {
+                    continue;
+                }
+                let Some(kv) = event.kv() else {
+                    continue;
+                };
+                match self.decode(kv.value()) {
+                    Ok(config) => {
+                        self.cache(&config).await;
+                        if updates.send(config).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                    Err(e) => log::warn!("Ignoring unreadable config at {}: {}", self.key, e),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ConfigStore for EtcdStore {
+    fn location(&self) -> String {
+        format!("etcd://{}{}", self.inner.endpoints.join(","), self.inner.key)
+    }
+
+    fn load(&self) -> StoreFuture<'_, Option<ExtendedConfig>> {
+        let inner = &self.inner;
+        Box::pin(async move {
+            match inner.get().await {
+                Ok((Some(config), _)) => {
+                    inner.cache(&config).await;
+                    Ok(Some(config))
+                }
+                // Seed an empty cluster from the local copy
+                Ok((None, _)) => {
+                    let local = inner.fallback.load().await?;
+                    if let Some(config) = &local
+                        && let Err(e) = inner.put(config).await
+                    {
+                        log::warn!("Failed to seed {} from local copy: {}", inner.key, e);
+                    }
+                    Ok(local)
+                }
+                Err(e) => {
+                    log::warn!(
+                        "etcd unreachable ({}); loading local copy {}",
+                        e,
+                        inner.fallback.location()
+                    );
+                    inner.fallback.load().await
+                }
+            }
+        })
+    }
+
+    fn save<'a>(&'a self, config: &'a ExtendedConfig) -> StoreFuture<'a, ()> {
+        let inner = &self.inner;
+        Box::pin(async move {
+            inner.fallback.save(config).await?;
+            match inner.put(config).await {
+                Ok(()) => inner.unsynced.store(false, Ordering::Relaxed),
+                Err(e) => {
+                    log::warn!(
+                        "etcd unreachable ({}); saved to {} until it is back",
+                        e,
+                        inner.fallback.location()
+                    );
+                    inner.unsynced.store(true, Ordering::Relaxed);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn watch(&self) -> Option<mpsc::Receiver<ExtendedConfig>> {
+        let (tx, rx) = mpsc::channel(16);
+        let inner = Arc::clone(&self.inner);
+        tokio::spawn(async move {
+            while !tx.is_closed() {
+                if let Err(e) = inner.follow(&tx).await {
+                    log::warn!("etcd watch on {} failed: {}; retrying", inner.key, e);
+                }
+                tokio::time::sleep(WATCH_RETRY_DELAY).await;
+            }
+        });
+        Some(rx)
+    }
+}
+
+fn to_io(e: etcd_client::Error) -> std::io::Error {
+    std::io::Error::other(e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(dir: &std::path::Path, key: Option<EncryptionKey>) -> EtcdStore {
+        let fallback = FileStore::new(dir.join(ETCD_CACHE_FILE));
+        EtcdStore::new(
+            vec!["http://127.0.0.1:2379".to_string()],
+            DEFAULT_ETCD_KEY,
+            fallback,
+        )
+        .encryption_key(key)
+    }
+
+    fn config() -> ExtendedConfig {
+        ExtendedConfig {
+            max_search_results: Some(10),
+            ..ExtendedConfig::default()
+        }
+    }
+
+    #[test]
+    fn values_are_encrypted_with_the_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let encrypted = store(dir.path(), Some(EncryptionKey::new([3; 32])));
+        let value = encrypted.inner.encode(&config()).unwrap();
+        assert!(encryption::is_encrypted(&value));
+        assert_eq!(
+            encrypted.inner.decode(&value).unwrap().max_search_results,
+            Some(10)
+        );
+        assert!(store(dir.path(), None).inner.decode(&value).is_err());
+    }
+
+    #[test]
+    fn values_are_plain_json_without_a_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let value = store(dir.path(), None).inner.encode(&config()).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&value).unwrap();
+        assert_eq!(json["max_search_results"], 10);
+    }
+}
@@ -0,0 +1,227 @@
+//! Expansion and normalization of directory entries
+//!
+//! `~/projects`, `$HOME/src`, and `${PROJECT_DIR}/build` are expanded, and
+//! separators normalized, when allowed/denied directories are loaded from the
+//! environment or set through `config_set`. Entries set through `config_set`
+//! may also start with a directory alias such as `@work/api`. The resulting
+//! paths are what the sandbox checks; the entries as written are kept in
+//! `ExtendedConfig::raw_directories`.
+
+use crate::keys::ConfigKey;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Key of the named directory aliases, set one at a time as
+/// `directory_aliases.<name>`.
+pub const DIRECTORY_ALIASES_KEY: &str = "directory_aliases";
+
+/// Marks a directory alias at the start of an entry.
+pub const ALIAS_PREFIX: char = '@';
+
+/// Replace a leading `@name` in `entry` with the directory `aliases` maps
+/// `name` to. Entries without an alias are returned unchanged.
+pub fn expand_alias(entry: &str, aliases: &BTreeMap<String, String>) -> Result<String, String> {
+    let Some(rest) = entry.strip_prefix(ALIAS_PREFIX) else {
+        return Ok(entry.to_string());
+    };
+    let (name, tail) = rest.split_at(rest.find(['/', '\\']).unwrap_or(rest.len()));
+    let target = aliases
+        .get(name)
+        .ok_or_else(|| format!("unknown directory alias '{}{}'", ALIAS_PREFIX, name))?;
+    if tail.is_empty() {
+        return Ok(target.clone());
+    }
+    Ok(format!("{}{}", target.trim_end_matches(['/', '\\']), tail))
+}
+
+/// Parse the value of `directory_aliases.<name>`; `null` removes the alias.
+pub(crate) fn parse_alias(name: &str, value: &serde_json::Value) -> Result<Option<String>, String> {
+    match value {
+        serde_json::Value::Null => Ok(None),
+        serde_json::Value::String(target) => prepare_alias(name, target).map(Some),
+        other => Err(format!(
+            "Invalid value for '{}.{}': expected a directory, got {}",
+            DIRECTORY_ALIASES_KEY, name, other
+        )),
+    }
+}
+
+/// Check an alias name and expand and normalize its directory, which must
+/// be absolute. Returns the directory to store.
+pub(crate) fn prepare_alias(name: &str, target: &str) -> Result<String, String> {
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'));
+    if !valid_name {
+        return Err(format!(
+            "'{}' is not a valid alias name; use letters, digits, '_', and '-'",
+            name
+        ));
+    }
+    let dir = normalize_directory(&expand_path(target.trim())?)?;
+    if !Path::new(&dir).is_absolute() {
+        return Err(format!("'{}' is not an absolute path", target));
+    }
+    Ok(dir)
+}
+
+/// Expand a leading `~` and every `$NAME` / `${NAME}` in `entry`.
+///
+/// Fails if a referenced variable (or the home directory) is unset.
+pub fn expand_path(entry: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(entry.len());
+    let rest = match entry.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            let home = dirs::home_dir().ok_or("cannot expand '~': no home directory")?;
+            out.push_str(&home.to_string_lossy());
+            rest
+        }
+        _ => entry,
+    };
+
+    let mut chars = rest.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            out.push(c);
+            continue;
+        }
+        let name: String = if chars.next_if_eq(&'{').is_some() {
+            let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+            if name.is_empty() {
+                return Err(format!("empty variable name in '{}'", entry));
+            }
+            name
+        } else {
+            let mut name = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                name.push(c);
+            }
+            if name.is_empty() {
+                out.push('$');
+                continue;
+            }
+            name
+        };
+        let value = std::env::var(&name)
+            .map_err(|_| format!("cannot expand '{}': {} is not set", entry, name))?;
+        out.push_str(&value);
+    }
+    Ok(out)
+}
+
+/// Whether `entry` contains anything `expand_path` would replace.
+#[must_use]
+pub fn needs_expansion(entry: &str) -> bool {
+    entry.starts_with('~') || entry.contains('$')
+}
+
+/// Expand every entry of a directory list.
+pub fn expand_list(entries: &[String]) -> Result<Vec<String>, String> {
+    entries.iter().map(|entry| expand_path(entry)).collect()
+}
+
+/// Normalize a directory entry so that equal paths compare equal.
+///
+/// Trailing separators are dropped. On Windows `/` also becomes `\`,
+/// repeated separators are collapsed, the drive letter is upper-cased, and
+/// `\\?\` prefixes are removed, so `c:/Users/me/` and `C:\Users\me` agree.
+/// Drive-relative entries such as `C:foo` are rejected.
+pub fn normalize_directory(entry: &str) -> Result<String, String> {
+    #[cfg(windows)]
+    let mut entry = normalize_windows(entry)?;
+    #[cfg(not(windows))]
+    let mut entry = entry.to_string();
+    while entry.ends_with(std::path::is_separator) && Path::new(&entry).parent().is_some() {
+        entry.pop();
+    }
+    Ok(entry)
+}
+
+/// Expand and normalize a scratch directory entry, then create it if it is
+/// missing and check that files can be written to it.
+pub fn prepare_writable_directory(entry: &str) -> Result<PathBuf, String> {
+    let dir = PathBuf::from(normalize_directory(&expand_path(entry.trim())?)?);
+    if !dir.is_absolute() {
+        return Err(format!("'{}' is not an absolute path", entry));
+    }
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("cannot create '{}': {}", dir.display(), e))?;
+    let probe = dir.join(format!(".kodegen-write-test-{}", std::process::id()));
+    std::fs::write(&probe, b"")
+        .map_err(|e| format!("'{}' is not writable: {}", dir.display(), e))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(dir)
+}
+
+#[cfg(windows)]
+fn normalize_windows(entry: &str) -> Result<String, String> {
+    let path = entry.replace('/', "\\");
+    let path = match path.strip_prefix(r"\\?\") {
+        Some(rest) => match rest.strip_prefix(r"UNC\") {
+            Some(share) => format!(r"\\{}", share),
+            None => rest.to_string(),
+        },
+        None => path,
+    };
+    let (mut out, body) = match path.strip_prefix(r"\\") {
+        Some(share) => (r"\\".to_string(), share),
+        None => match path.strip_prefix('\\') {
+            Some(rooted) => ("\\".to_string(), rooted),
+            None => (String::new(), path.as_str()),
+        },
+    };
+    let bytes = body.as_bytes();
+    let drive =
+        out.is_empty() && bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    if drive && bytes.get(2) != Some(&b'\\') {
+        return Err(format!(
+            "'{}' is relative to the current directory of drive {}",
+            entry,
+            &body[..2]
+        ));
+    }
+    let parts: Vec<&str> = body.split('\\').filter(|part| !part.is_empty()).collect();
+    out.push_str(&parts.join("\\"));
+    if drive {
+        out[..1].make_ascii_uppercase();
+        if parts.len() == 1 {
+            out.push('\\');
+        }
+    }
+    Ok(out)
+}
+
+/// Expand and normalize `value` if `key` is a directory list.
+///
+/// Returns the value to store and, if any entry changed, the entries as
+/// written.
+pub fn expand_directories(
+    key: ConfigKey<'_>,
+    value: serde_json::Value,
+) -> Result<(serde_json::Value, Option<Vec<String>>), String> {
+    expand_directories_with(key, value, &BTreeMap::new())
+}
+
+/// `expand_directories`, also replacing leading directory `aliases`.
+pub fn expand_directories_with(
+    key: ConfigKey<'_>,
+    value: serde_json::Value,
+    aliases: &BTreeMap<String, String>,
+) -> Result<(serde_json::Value, Option<Vec<String>>), String> {
+    if !matches!(key, ConfigKey::AllowedDirectories | ConfigKey::DeniedDirectories) {
+        return Ok((value, None));
+    }
+    let Ok(raw) = serde_json::from_value::<Vec<String>>(value.clone()) else {
+        // Not a list of strings; left for validation to reject
+        return Ok((value, None));
+    };
+    let prepared = raw
+        .iter()
+        .map(|entry| normalize_directory(&expand_path(&expand_alias(entry, aliases)?)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    if prepared == raw {
+        return Ok((value, None));
+    }
+    Ok((prepared.into(), Some(raw)))
+}
@@ -0,0 +1,448 @@
+//! Settings owned by this crate
+//!
+//! `ServerConfig` lives in `kodegen_config_manager`; everything this crate adds
+//! on top of it is modelled here and persisted next to `config.json`.
+
+use crate::ServerConfig;
+use crate::audit::{AUDIT_LOG_PATH_KEY, prepare_log_file};
+use crate::client_history::{CLIENT_HISTORY_MAX_AGE_DAYS_KEY, CLIENT_HISTORY_MAX_ENTRIES_KEY};
+use crate::command_policy::compile_pattern;
+use crate::expand::{parse_alias, prepare_alias, prepare_writable_directory};
+use crate::file_encoding::{DEFAULT_ENCODING_KEY, FileEncoding};
+use crate::host_policy::{self, ALLOWED_HOSTS_KEY, DENIED_HOSTS_KEY};
+use crate::keys::ConfigKey;
+use crate::log_level::{LOG_LEVEL_KEY, LogLevel};
+use crate::normalize::normalize;
+use crate::otlp::{self, OTLP_ENDPOINT_KEY};
+use crate::path_policy::FOLLOW_SYMLINKS_KEY;
+use crate::rate_limit::parse_limit;
+use crate::schema_version::SchemaVersion;
+use crate::shell::{SHELL_ARGS_KEY, apply_shell_args};
+use crate::subprocess_env::{ENV_PASSTHROUGH_KEY, validate_entry};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Key of the usage reporting switch.
+pub const TELEMETRY_ENABLED_KEY: &str = "telemetry_enabled";
+
+/// Key of the command line patterns refused by `check_command`.
+pub const BLOCKED_COMMAND_PATTERNS_KEY: &str = "blocked_command_patterns";
+
+/// Key of the default timeout for command execution.
+pub const COMMAND_TIMEOUT_SECS_KEY: &str = "command_timeout_secs";
+
+/// Command timeout used while `command_timeout_secs` is unset.
+pub const DEFAULT_COMMAND_TIMEOUT_SECS: u64 = 120;
+
+/// Key of the directory tools put scratch files in.
+pub const TEMP_DIRECTORY_KEY: &str = "temp_directory";
+
+/// Key of the directory tools cache downloads and build artifacts in.
+pub const CACHE_DIRECTORY_KEY: &str = "cache_directory";
+
+/// Key of the cap on results returned by search tools.
+pub const MAX_SEARCH_RESULTS_KEY: &str = "max_search_results";
+
+/// Search result cap used while `max_search_results` is unset.
+pub const DEFAULT_MAX_SEARCH_RESULTS: u32 = 1000;
+
+/// Largest accepted `max_search_results`.
+pub const MAX_SEARCH_RESULTS_LIMIT: u32 = 100_000;
+
+/// Top-level settings of `ExtendedConfig` that `set_value` accepts like the
+/// server keys.
+pub const EXTENDED_KEYS: &[&str] = &[
+    LOG_LEVEL_KEY,
+    TELEMETRY_ENABLED_KEY,
+    BLOCKED_COMMAND_PATTERNS_KEY,
+    COMMAND_TIMEOUT_SECS_KEY,
+    TEMP_DIRECTORY_KEY,
+    CACHE_DIRECTORY_KEY,
+    ENV_PASSTHROUGH_KEY,
+    DEFAULT_ENCODING_KEY,
+    FOLLOW_SYMLINKS_KEY,
+    MAX_SEARCH_RESULTS_KEY,
+    SHELL_ARGS_KEY,
+    AUDIT_LOG_PATH_KEY,
+    OTLP_ENDPOINT_KEY,
+    CLIENT_HISTORY_MAX_ENTRIES_KEY,
+    CLIENT_HISTORY_MAX_AGE_DAYS_KEY,
+    ALLOWED_HOSTS_KEY,
+    DENIED_HOSTS_KEY,
+];
+
+// ============================================================================
+// EXTENDED CONFIG
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExtendedConfig {
+    /// Layout the config was written with; see `schema_version::migrate`.
+    #[serde(default)]
+    pub schema_version: SchemaVersion,
+    /// Per-tool-category settings, keyed by category (`terminal`, `fs`, `search`, ...).
+    #[serde(default)]
+    pub tool_settings: HashMap<String, serde_json::Value>,
+    /// Third-party settings addressed as `ext.<namespace>.<name>`, keyed by namespace then name.
+    #[serde(default)]
+    pub extensions: HashMap<String, HashMap<String, serde_json::Value>>,
+    /// Directory lists as entered, before `~` and `$VAR` expansion, keyed by
+    /// `allowed_directories` / `denied_directories`. Only lists changed by
+    /// expansion or normalization are recorded.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub raw_directories: HashMap<String, Vec<String>>,
+    /// Most verbose level logged; `None` leaves the host's level alone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_level: Option<LogLevel>,
+    /// Whether downstream crates may report usage; off unless opted in.
+    #[serde(default)]
+    pub telemetry_enabled: bool,
+    /// Regexes, or globs prefixed with `glob:`, matched against whole
+    /// command lines in addition to `blocked_commands`.
+    #[serde(default)]
+    pub blocked_command_patterns: Vec<String>,
+    /// Default timeout for command execution; `None` means
+    /// `DEFAULT_COMMAND_TIMEOUT_SECS`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_timeout_secs: Option<u64>,
+    /// Scratch directory shared by all tools; `None` means
+    /// `default_temp_directory()`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temp_directory: Option<PathBuf>,
+    /// Cache directory shared by all tools; `None` means
+    /// `default_cache_directory()`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_directory: Option<PathBuf>,
+    /// Variables forwarded to spawned commands; `None` means
+    /// `DEFAULT_ENV_PASSTHROUGH`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_passthrough: Option<Vec<String>>,
+    /// Encoding file tools use when a call doesn't name one; `None` means
+    /// UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_encoding: Option<FileEncoding>,
+    /// Hosts network tools may reach; empty allows every host not denied.
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    /// Hosts network tools must not reach, checked before `allowed_hosts`.
+    #[serde(default)]
+    pub denied_hosts: Vec<String>,
+    /// Whether symlinks inside `allowed_directories` may lead outside them.
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    /// Most results search tools return per call; `None` means
+    /// `DEFAULT_MAX_SEARCH_RESULTS`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_search_results: Option<u32>,
+    /// Arguments passed to `default_shell` ahead of the command line, keyed
+    /// by platform (`linux`, `macos`, `windows`, `unix`, ...).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub shell_args: HashMap<String, Vec<String>>,
+    /// JSONL file every config mutation is appended to; `None` disables
+    /// auditing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit_log_path: Option<PathBuf>,
+    /// OTLP/HTTP collector config spans are exported to; `None` disables
+    /// export.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub otlp_endpoint: Option<String>,
+    /// Most client records kept; `None` means
+    /// `DEFAULT_CLIENT_HISTORY_MAX_ENTRIES`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_history_max_entries: Option<u32>,
+    /// Days after which a client record is dropped; `None` keeps them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_history_max_age_days: Option<u32>,
+    /// Requests per minute each tool may serve, keyed by tool name.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub rate_limits: HashMap<String, u32>,
+    /// Absolute directories that `@name` at the start of a directory entry
+    /// or checked path stands for, keyed by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub directory_aliases: BTreeMap<String, String>,
+}
+
+impl ExtendedConfig {
+    /// Value of `key` as this config serializes it, without serializing the
+    /// rest; `None` for unset settings, empty maps that are skipped when
+    /// saving, missing entries, and `config.json` keys.
+    pub(crate) fn get_key(&self, key: ConfigKey<'_>) -> Option<serde_json::Value> {
+        match key {
+            ConfigKey::ToolSettings => to_json(&self.tool_settings),
+            ConfigKey::Extensions => to_json(&self.extensions),
+            ConfigKey::RateLimits => non_empty(&self.rate_limits, HashMap::is_empty),
+            ConfigKey::DirectoryAliases => non_empty(&self.directory_aliases, BTreeMap::is_empty),
+            ConfigKey::ToolCategory(path) => {
+                let mut parts = path.split('.');
+                let settings = self.tool_settings.get(parts.next()?)?;
+                parts
+                    .try_fold(settings, |current, part| current.get(part))
+                    .cloned()
+            }
+            ConfigKey::RateLimit(tool) => self.rate_limits.get(tool).map(|&n| n.into()),
+            ConfigKey::DirectoryAlias(name) => {
+                self.directory_aliases.get(name).cloned().map(Into::into)
+            }
+            ConfigKey::Extension(ext) => self.extensions.get(ext.namespace)?.get(ext.name).cloned(),
+            ConfigKey::LogLevel => self.log_level.as_ref().and_then(to_json),
+            ConfigKey::TelemetryEnabled => Some(self.telemetry_enabled.into()),
+            ConfigKey::BlockedCommandPatterns => to_json(&self.blocked_command_patterns),
+            ConfigKey::CommandTimeoutSecs => self.command_timeout_secs.map(Into::into),
+            ConfigKey::TempDirectory => self.temp_directory.as_ref().and_then(to_json),
+            ConfigKey::CacheDirectory => self.cache_directory.as_ref().and_then(to_json),
+            ConfigKey::EnvPassthrough => self.env_passthrough.as_ref().and_then(to_json),
+            ConfigKey::DefaultEncoding => self.default_encoding.as_ref().and_then(to_json),
+            ConfigKey::AllowedHosts => to_json(&self.allowed_hosts),
+            ConfigKey::DeniedHosts => to_json(&self.denied_hosts),
+            ConfigKey::FollowSymlinks => Some(self.follow_symlinks.into()),
+            ConfigKey::MaxSearchResults => self.max_search_results.map(Into::into),
+            ConfigKey::ShellArgs => non_empty(&self.shell_args, HashMap::is_empty),
+            ConfigKey::AuditLogPath => self.audit_log_path.as_ref().and_then(to_json),
+            ConfigKey::OtlpEndpoint => self.otlp_endpoint.clone().map(Into::into),
+            ConfigKey::ClientHistoryMaxEntries => self.client_history_max_entries.map(Into::into),
+            ConfigKey::ClientHistoryMaxAgeDays => self.client_history_max_age_days.map(Into::into),
+            _ => None,
+        }
+    }
+
+    /// Set `key` from JSON; `null` clears a setting, empties a section, or
+    /// removes an entry. `config.json` keys are refused.
+    pub(crate) fn set_key(
+        &mut self,
+        key: ConfigKey<'_>,
+        value: &serde_json::Value,
+    ) -> Result<(), String> {
+        match key {
+            ConfigKey::ToolSettings => {
+                self.tool_settings = parse_setting(key, value)?.unwrap_or_default();
+            }
+            ConfigKey::Extensions => {
+                self.extensions = parse_setting(key, value)?.unwrap_or_default();
+            }
+            ConfigKey::RateLimits => {
+                let limits: HashMap<String, u32> = parse_setting(key, value)?.unwrap_or_default();
+                if let Some(tool) = limits
+                    .iter()
+                    .find_map(|(tool, &n)| (n == 0).then_some(tool))
+                {
+                    return Err(format!(
+                        "Invalid value for '{}.{}': value must be positive (got 0)",
+                        key, tool
+                    ));
+                }
+                self.rate_limits = limits;
+            }
+            ConfigKey::DirectoryAliases => {
+                let aliases: BTreeMap<String, String> =
+                    parse_setting(key, value)?.unwrap_or_default();
+                self.directory_aliases = aliases
+                    .into_iter()
+                    .map(|(name, target)| prepare_alias(&name, &target).map(|dir| (name, dir)))
+                    .collect::<Result<_, _>>()?;
+            }
+            ConfigKey::ToolCategory(category) => {
+                if value.is_null() {
+                    self.tool_settings.remove(category);
+                } else {
+                    self.tool_settings
+                        .insert(category.to_string(), value.clone());
+                }
+            }
+            ConfigKey::RateLimit("") => {
+                return Err("Rate limit keys must have the form rate_limits.<tool>".to_string());
+            }
+            ConfigKey::RateLimit(tool) => match parse_limit(&key.to_string(), value)? {
+                Some(limit) => {
+                    self.rate_limits.insert(tool.to_string(), limit);
+                }
+                None => {
+                    self.rate_limits.remove(tool);
+                }
+            },
+            ConfigKey::DirectoryAlias(name) => match parse_alias(name, value)? {
+                Some(target) => {
+                    self.directory_aliases.insert(name.to_string(), target);
+                }
+                None => {
+                    self.directory_aliases.remove(name);
+                }
+            },
+            ConfigKey::Extension(ext) => {
+                if value.is_null() {
+                    if let Some(namespace) = self.extensions.get_mut(ext.namespace) {
+                        namespace.remove(ext.name);
+                        if namespace.is_empty() {
+                            self.extensions.remove(ext.namespace);
+                        }
+                    }
+                } else {
+                    self.extensions
+                        .entry(ext.namespace.to_string())
+                        .or_default()
+                        .insert(ext.name.to_string(), value.clone());
+                }
+            }
+            key => self.set_setting(key, &normalize(key.as_str(), value.clone())?)?,
+        }
+        Ok(())
+    }
+
+    /// Set one of the top-level settings in `ConfigKey::EXTENDED`.
+    fn set_setting(&mut self, key: ConfigKey<'_>, value: &serde_json::Value) -> Result<(), String> {
+        match key {
+            ConfigKey::LogLevel => self.log_level = parse_setting(key, value)?,
+            ConfigKey::TelemetryEnabled => {
+                self.telemetry_enabled = parse_setting(key, value)?.unwrap_or_default();
+            }
+            ConfigKey::BlockedCommandPatterns => {
+                let patterns: Vec<String> = parse_setting(key, value)?.unwrap_or_default();
+                let invalid: Vec<String> = patterns
+                    .iter()
+                    .filter_map(|p| compile_pattern(p).err().map(|e| format!("'{}': {}", p, e)))
+                    .collect();
+                if !invalid.is_empty() {
+                    return Err(format!("Invalid value for '{}': {}", key, invalid.join("; ")));
+                }
+                self.blocked_command_patterns = patterns;
+            }
+            ConfigKey::CommandTimeoutSecs => {
+                let secs: Option<u64> = parse_setting(key, value)?;
+                if secs == Some(0) {
+                    return Err(format!(
+                        "Invalid value for '{}': value must be positive (got 0)",
+                        key
+                    ));
+                }
+                self.command_timeout_secs = secs;
+            }
+            ConfigKey::ClientHistoryMaxEntries | ConfigKey::ClientHistoryMaxAgeDays => {
+                let limit: Option<u32> = parse_setting(key, value)?;
+                if limit == Some(0) {
+                    return Err(format!(
+                        "Invalid value for '{}': value must be positive (got 0)",
+                        key
+                    ));
+                }
+                match key {
+                    ConfigKey::ClientHistoryMaxEntries => self.client_history_max_entries = limit,
+                    _ => self.client_history_max_age_days = limit,
+                }
+            }
+            ConfigKey::MaxSearchResults => {
+                let max: Option<u32> = parse_setting(key, value)?;
+                if let Some(n) = max
+                    && !(1..=MAX_SEARCH_RESULTS_LIMIT).contains(&n)
+                {
+                    return Err(format!(
+                        "Invalid value for '{}': value must be between 1 and {} (got {})",
+                        key, MAX_SEARCH_RESULTS_LIMIT, n
+                    ));
+                }
+                self.max_search_results = max;
+            }
+            ConfigKey::AllowedHosts | ConfigKey::DeniedHosts => {
+                let hosts: Vec<String> = parse_setting(key, value)?.unwrap_or_default();
+                let invalid: Vec<String> = hosts
+                    .iter()
+                    .filter_map(|entry| host_policy::validate_entry(entry).err())
+                    .collect();
+                if !invalid.is_empty() {
+                    return Err(format!("Invalid value for '{}': {}", key, invalid.join("; ")));
+                }
+                match key {
+                    ConfigKey::AllowedHosts => self.allowed_hosts = hosts,
+                    _ => self.denied_hosts = hosts,
+                }
+            }
+            ConfigKey::EnvPassthrough => {
+                let entries: Option<Vec<String>> = parse_setting(key, value)?;
+                let invalid: Vec<String> = entries
+                    .iter()
+                    .flatten()
+                    .filter_map(|entry| validate_entry(entry).err())
+                    .collect();
+                if !invalid.is_empty() {
+                    return Err(format!("Invalid value for '{}': {}", key, invalid.join("; ")));
+                }
+                self.env_passthrough = entries;
+            }
+            ConfigKey::DefaultEncoding => self.default_encoding = parse_setting(key, value)?,
+            ConfigKey::ShellArgs => apply_shell_args(&mut self.shell_args, value)?,
+            ConfigKey::FollowSymlinks => {
+                self.follow_symlinks = parse_setting(key, value)?.unwrap_or_default();
+            }
+            ConfigKey::AuditLogPath => {
+                self.audit_log_path = parse_setting::<String>(key, value)?
+                    .map(|entry| prepare_log_file(&entry))
+                    .transpose()
+                    .map_err(|e| format!("Invalid value for '{}': {}", key, e))?;
+            }
+            ConfigKey::OtlpEndpoint => {
+                self.otlp_endpoint = parse_setting::<String>(key, value)?
+                    .map(|entry| otlp::validate_endpoint(&entry))
+                    .transpose()
+                    .map_err(|e| format!("Invalid value for '{}': {}", key, e))?;
+            }
+            ConfigKey::TempDirectory | ConfigKey::CacheDirectory => {
+                let dir = parse_setting::<String>(key, value)?
+                    .map(|entry| prepare_writable_directory(&entry))
+                    .transpose()
+                    .map_err(|e| format!("Invalid value for '{}': {}", key, e))?;
+                match key {
+                    ConfigKey::TempDirectory => self.temp_directory = dir,
+                    _ => self.cache_directory = dir,
+                }
+            }
+            _ => return Err(format!("Unknown config key: {}", key)),
+        }
+        Ok(())
+    }
+}
+
+/// `kodegen` under the system temp directory.
+#[must_use]
+pub fn default_temp_directory() -> PathBuf {
+    std::env::temp_dir().join("kodegen")
+}
+
+/// `kodegen` under the user's cache directory, or the temp directory where
+/// there is none.
+#[must_use]
+pub fn default_cache_directory() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("kodegen")
+}
+
+fn to_json<T: Serialize>(value: &T) -> Option<serde_json::Value> {
+    serde_json::to_value(value).ok()
+}
+
+/// `map` as JSON unless `is_empty`, mirroring `skip_serializing_if`.
+fn non_empty<T: Serialize>(map: &T, is_empty: impl Fn(&T) -> bool) -> Option<serde_json::Value> {
+    if is_empty(map) { None } else { to_json(map) }
+}
+
+fn parse_setting<T: DeserializeOwned>(
+    key: ConfigKey<'_>,
+    value: &serde_json::Value,
+) -> Result<Option<T>, String> {
+    serde_json::from_value(value.clone())
+        .map_err(|e| format!("Invalid value for '{}': {}", key, e))
+}
+
+// ============================================================================
+// COMBINED VIEW
+// ============================================================================
+
+/// `ServerConfig` and `ExtendedConfig` snapshots serialized as a single flat object.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigView {
+    #[serde(flatten)]
+    pub server: Arc<ServerConfig>,
+    #[serde(flatten)]
+    pub extended: Arc<ExtendedConfig>,
+}
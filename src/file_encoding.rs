@@ -0,0 +1,93 @@
+//! Default text encoding for file tools
+//!
+//! `default_encoding` tells file read and write tools how to decode and
+//! encode files when a call doesn't name an encoding, for codebases that
+//! predate UTF-8. Names are parsed case-insensitively with common aliases.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Key of the default file encoding.
+pub const DEFAULT_ENCODING_KEY: &str = "default_encoding";
+
+/// Text encoding of files read and written by tools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", try_from = "String")]
+pub enum FileEncoding {
+    #[default]
+    Utf8,
+    Utf16le,
+    Utf16be,
+    /// ISO-8859-1.
+    Latin1,
+    Windows1252,
+    /// Detect from the byte order mark or content; new files are UTF-8.
+    Auto,
+}
+
+impl FileEncoding {
+    pub const ALL: [FileEncoding; 6] = [
+        FileEncoding::Utf8,
+        FileEncoding::Utf16le,
+        FileEncoding::Utf16be,
+        FileEncoding::Latin1,
+        FileEncoding::Windows1252,
+        FileEncoding::Auto,
+    ];
+
+    /// Canonical name, as stored and reported.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            FileEncoding::Utf8 => "utf-8",
+            FileEncoding::Utf16le => "utf-16le",
+            FileEncoding::Utf16be => "utf-16be",
+            FileEncoding::Latin1 => "latin-1",
+            FileEncoding::Windows1252 => "windows-1252",
+            FileEncoding::Auto => "auto",
+        }
+    }
+
+    /// Other accepted spellings.
+    const fn aliases(self) -> &'static [&'static str] {
+        match self {
+            FileEncoding::Utf8 => &["utf8"],
+            FileEncoding::Utf16le => &["utf16le", "utf-16"],
+            FileEncoding::Utf16be => &["utf16be"],
+            FileEncoding::Latin1 => &["latin1", "iso-8859-1", "iso8859-1"],
+            FileEncoding::Windows1252 => &["cp1252"],
+            FileEncoding::Auto => &["auto-detect", "detect"],
+        }
+    }
+}
+
+impl fmt::Display for FileEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for FileEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        FileEncoding::ALL
+            .into_iter()
+            .find(|encoding| encoding.as_str() == s || encoding.aliases().contains(&s.as_str()))
+            .ok_or_else(|| {
+                let names: Vec<&str> =
+                    FileEncoding::ALL.into_iter().map(FileEncoding::as_str).collect();
+                format!("unknown encoding '{}', expected one of {}", s, names.join(", "))
+            })
+    }
+}
+
+impl TryFrom<String> for FileEncoding {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
@@ -0,0 +1,104 @@
+//! Config file formats
+//!
+//! The extended config may be stored as JSON, TOML, or YAML. The format is
+//! taken from the file extension, falling back to sniffing the contents.
+
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigFormat {
+    #[default]
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    pub const ALL: [ConfigFormat; 3] = [ConfigFormat::Json, ConfigFormat::Toml, ConfigFormat::Yaml];
+
+    /// File extension written for this format.
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            ConfigFormat::Json => "json",
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Yaml => "yaml",
+        }
+    }
+
+    /// Format implied by the extension of `path`, if recognised.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "json" => Some(ConfigFormat::Json),
+            "toml" => Some(ConfigFormat::Toml),
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Format of `contents` read from `path`: the extension wins, otherwise
+    /// the first format that parses.
+    #[must_use]
+    pub fn detect(path: &Path, contents: &str) -> Self {
+        if let Some(format) = Self::from_path(path) {
+            return format;
+        }
+        let trimmed = contents.trim_start();
+        if trimmed.starts_with('{') {
+            ConfigFormat::Json
+        } else if toml::from_str::<toml::Table>(contents).is_ok() {
+            ConfigFormat::Toml
+        } else {
+            ConfigFormat::Yaml
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(self, contents: &str) -> Result<T, String> {
+        match self {
+            ConfigFormat::Json => serde_json::from_str(contents).map_err(|e| e.to_string()),
+            ConfigFormat::Toml => toml::from_str(contents).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_yaml::from_str(contents).map_err(|e| e.to_string()),
+        }
+    }
+
+    pub fn serialize<T: Serialize>(self, value: &T) -> Result<String, String> {
+        match self {
+            ConfigFormat::Json => serde_json::to_string_pretty(value).map_err(|e| e.to_string()),
+            ConfigFormat::Toml => toml::to_string_pretty(value).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_yaml::to_string(value).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+/// `path` with its extension replaced by the one for `format`.
+#[must_use]
+pub fn path_for_format(path: &Path, format: ConfigFormat) -> PathBuf {
+    path.with_extension(format.extension())
+}
+
+/// The first existing variant of `path` in any supported format, or `path`
+/// itself if none exists.
+#[must_use]
+pub fn find_existing(path: &Path) -> PathBuf {
+    if path.exists() {
+        return path.to_path_buf();
+    }
+    ConfigFormat::ALL
+        .iter()
+        .map(|format| path_for_format(path, *format))
+        .chain(std::iter::once(path.with_extension("yml")))
+        .find(|candidate| candidate.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
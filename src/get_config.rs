@@ -1,10 +1,21 @@
-use crate::{ConfigManager, get_system_info};
+use crate::system_info::{
+    SYSTEM_INFO_SETTINGS_CATEGORY, SystemInfoSettings, battery_info, cached_cpu_info,
+    cached_memory_bytes, cached_os_release, cached_system_info, installed_shells, locale_info,
+    network_interfaces, process_info, resource_limits, toolchains, user_info,
+};
+use crate::format::ConfigFormat;
+use crate::schema::{ConfigGetArgs, ConfigSection, SummaryFormat};
+use crate::secrets::{SECRETS_KEY, redact};
+use crate::{ConfigManager, IntegrityState, LogLevel, UpstreamConfigManager, ValueSource};
 use kodegen_mcp_tool::Tool;
 use kodegen_mcp_tool::error::McpError;
-use kodegen_mcp_schema::config::{GetConfigArgs, GetConfigPromptArgs, CONFIG_GET};
+use kodegen_mcp_schema::config::{GetConfigPromptArgs, CONFIG_GET};
 use rmcp::model::{Content, PromptArgument, PromptMessage, PromptMessageContent, PromptMessageRole};
 use serde_json::json;
 
+/// Changes included with `include_history`.
+const HISTORY_LIMIT: usize = 20;
+
 // ============================================================================
 // TOOL STRUCT
 // ============================================================================
@@ -19,6 +30,147 @@ impl GetConfigTool {
     pub fn new(config_manager: ConfigManager) -> Self {
         Self { config_manager }
     }
+
+    /// The `System:` summary section and the `system_info` JSON.
+    async fn system_info(&self, refresh: bool) -> (String, serde_json::Value) {
+        // Static details are collected once; memory is refreshed every few seconds
+        let system_info = cached_system_info(refresh);
+        let settings: SystemInfoSettings = self
+            .config_manager
+            .get_tool_settings(SYSTEM_INFO_SETTINGS_CATEGORY)
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let network = network_interfaces(settings.include_mac);
+        let os = cached_os_release(refresh);
+        let cpu = cached_cpu_info(refresh);
+        let memory = cached_memory_bytes(refresh);
+        let process = process_info();
+        let battery = battery_info();
+        let locale = locale_info();
+        let user = user_info();
+        let limits = resource_limits();
+        let shells = installed_shells();
+        let toolchains = toolchains(refresh).await;
+        let found: Vec<String> = toolchains
+            .iter()
+            .filter_map(|t| Some(format!("{} {}", t.name, t.version.as_ref()?)))
+            .collect();
+        let unlimited =
+            |value: Option<u64>| value.map_or_else(|| "unlimited".to_string(), |v| v.to_string());
+        let limit_list: Vec<String> = limits
+            .iter()
+            .map(|l| format!("{} {} (hard {})", l.name, unlimited(l.soft), unlimited(l.hard)))
+            .collect();
+        let shell_list: Vec<String> = shells.iter().map(|s| s.path.display().to_string()).collect();
+        let addresses: Vec<String> = network
+            .iter()
+            .filter_map(|interface| {
+                let addr = interface.ipv4.first().or(interface.ipv6.first())?;
+                Some(format!("{} ({})", interface.name, addr))
+            })
+            .collect();
+
+        let summary = format!(
+            "System:\n\
+             • Platform: {} ({})\n\
+             • OS: {}\n\
+             • Distribution: {}\n\
+             • Kernel: {}\n\
+             • CPU cores: {}\n\
+             • CPU: {} ({} physical / {} logical cores, {} MHz)\n\
+             • Memory: {} used, {} available of {} total\n\
+             • Network: {}\n\
+             • Server process: {}\n\
+             • Battery: {}\n\
+             • Toolchains: {}\n\
+             • Time zone: {} (UTC{}), locale {}\n\
+             • User: {}{}\n\
+             • Limits: {}\n\
+             • Shells: {}\n\
+             \n",
+            system_info.platform,
+            system_info.arch,
+            system_info.os_version,
+            [Some(&os.id), os.version_id.as_ref(), os.codename.as_ref()]
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(" "),
+            system_info.kernel_version,
+            system_info.cpu_count,
+            if cpu.brand.is_empty() { "unknown" } else { cpu.brand.as_str() },
+            cpu.physical_cores.map_or_else(|| "?".to_string(), |cores| cores.to_string()),
+            cpu.logical_cores,
+            cpu.frequency_mhz,
+            system_info.memory.used_mb,
+            system_info.memory.available_mb,
+            system_info.memory.total_mb,
+            if addresses.is_empty() {
+                "no addresses".to_string()
+            } else {
+                addresses.join(", ")
+            },
+            process.as_ref().map_or_else(
+                || "unavailable".to_string(),
+                |p| format!(
+                    "pid {}, {} MB resident, {} threads, {} open files",
+                    p.pid,
+                    p.rss_bytes / (1024 * 1024),
+                    p.threads.map_or_else(|| "?".to_string(), |n| n.to_string()),
+                    p.open_files.map_or_else(|| "?".to_string(), |n| n.to_string())
+                )
+            ),
+            match (&battery.charge_percent, battery.on_ac) {
+                _ if !battery.present => "none".to_string(),
+                (Some(charge), Some(true)) => format!("{:.0}% (on AC)", charge),
+                (Some(charge), Some(false)) => format!("{:.0}% (on battery)", charge),
+                (Some(charge), None) => format!("{:.0}%", charge),
+                (None, _) => "present".to_string(),
+            },
+            if found.is_empty() {
+                "none found".to_string()
+            } else {
+                found.join(", ")
+            },
+            locale.timezone.as_deref().unwrap_or("unknown"),
+            locale.utc_offset,
+            locale.locale.as_deref().unwrap_or("unknown"),
+            user.username.as_deref().unwrap_or("unknown"),
+            if user.privileged { " (privileged)" } else { "" },
+            if limit_list.is_empty() {
+                "not reported".to_string()
+            } else {
+                limit_list.join(", ")
+            },
+            if shell_list.is_empty() {
+                "none found".to_string()
+            } else {
+                shell_list.join(", ")
+            }
+        );
+
+        let mut json = json!(system_info);
+        // Holds this crate's version, not the compiler's; see `toolchains`
+        if let Some(info) = json.as_object_mut() {
+            info.remove("rust_version");
+        }
+        json["toolchains"] = json!(toolchains);
+        json["memory"]["total_bytes"] = json!(memory.total_bytes);
+        json["memory"]["available_bytes"] = json!(memory.available_bytes);
+        json["memory"]["used_bytes"] = json!(memory.used_bytes);
+        json["os_release"] = json!(os);
+        json["cpu"] = json!(cpu);
+        json["process"] = json!(process);
+        json["battery"] = json!(battery);
+        json["network"] = json!(network);
+        json["locale"] = json!(locale);
+        json["user"] = json!(user);
+        json["limits"] = json!(limits);
+        json["shells"] = json!(shells);
+        (summary, json)
+    }
 }
 
 // ============================================================================
@@ -26,7 +178,7 @@ impl GetConfigTool {
 // ============================================================================
 
 impl Tool for GetConfigTool {
-    type Args = GetConfigArgs;
+    type Args = ConfigGetArgs;
     type PromptArgs = GetConfigPromptArgs;
 
     fn name() -> &'static str {
@@ -36,7 +188,14 @@ impl Tool for GetConfigTool {
     fn description() -> &'static str {
         "Get complete server configuration including security settings (blocked commands, \
          allowed directories), shell preferences, resource limits, and live system diagnostics \
-         (platform, architecture, OS version, kernel version, hostname, CPU count, memory usage)."
+         (platform, architecture, OS version, kernel version, hostname, CPU count, memory usage). \
+         System details are cached; pass refresh: true to re-collect them, or \
+         include_system_info: false for a minimal, fast response. Pass sections (security, \
+         shell, limits, tools, logging, clients, secrets, system, persistence) to report only \
+         those groups and keep the response small. format (text, json, toml, table) sets how \
+         the first content item is rendered. include_secrets: true shows secret values where \
+         the server allows it; servers that require authentication also need their token as \
+         auth_token."
     }
 
     fn read_only() -> bool {
@@ -47,70 +206,239 @@ impl Tool for GetConfigTool {
         vec![] // No arguments needed
     }
 
-    async fn execute(&self, _args: Self::Args) -> Result<Vec<Content>, McpError> {
-        let mut config = self.config_manager.get_config();
+    #[tracing::instrument(name = "config.tool", skip_all, fields(tool = Self::name()), err)]
+    async fn execute(&self, args: Self::Args) -> Result<Vec<Content>, McpError> {
+        // Pick up client tracking changes made outside this crate, and check
+        // that no setting was edited behind our back
+        self.config_manager.verify_integrity();
+        let view = self.config_manager.get_config_view();
+        let config = &view.server;
         
-        // Refresh system info with current values
-        config.system_info = get_system_info();
-        config.save_error_count = ConfigManager::get_save_error_count();
+        let save_error_count = UpstreamConfigManager::get_save_error_count();
+        let persistence = self.config_manager.persistence_status();
+        let secret_names = self.config_manager.secret_names();
+        // Refused before any work unless the server allows revealing secrets
+        // and the caller is authenticated
+        let revealed = if args.include_secrets {
+            Some(self.config_manager.reveal_secrets(args.auth_token.as_deref())?)
+        } else {
+            None
+        };
         
+        let sections: Vec<ConfigSection> = ConfigSection::ALL
+            .into_iter()
+            .filter(|s| args.sections.is_empty() || args.sections.contains(s))
+            .collect();
+        let wants = |section: ConfigSection| sections.contains(&section);
         let mut contents = Vec::new();
         
         // ========================================
         // Content[0]: Human-Readable Summary
         // ========================================
-        let system_info = &config.system_info;
-        let summary = format!(
-            "⚙️  Server Configuration\n\
-             \n\
-             Security:\n\
-             • Blocked commands: {}\n\
-             • Allowed directories: {}\n\
-             \n\
-             Shell:\n\
-             • Default: {}\n\
-             \n\
-             Limits:\n\
-             • Read limit: {} lines\n\
-             • Write limit: {} lines\n\
-             \n\
-             System:\n\
-             • Platform: {} ({})\n\
-             • OS: {}\n\
-             • Kernel: {}\n\
-             • CPU cores: {}\n\
-             • Memory: {} used, {} available of {} total",
-            if config.blocked_commands.is_empty() {
-                "none".to_string()
-            } else {
-                config.blocked_commands.join(", ")
-            },
-            if config.allowed_directories.is_empty() {
-                "all (unrestricted)".to_string()
-            } else {
-                format!("{} paths", config.allowed_directories.len())
-            },
-            config.default_shell,
-            config.file_read_line_limit,
-            config.file_write_line_limit,
-            system_info.platform,
-            system_info.arch,
-            system_info.os_version,
-            system_info.kernel_version,
-            system_info.cpu_count,
-            system_info.memory.used_mb,
-            system_info.memory.available_mb,
-            system_info.memory.total_mb
-        );
-        contents.push(Content::text(summary));
-        
+        let mut summary = "⚙️  Server Configuration\n\n".to_string();
+        if wants(ConfigSection::Security) {
+            summary.push_str(&format!(
+                "Security:\n\
+                 • Blocked commands: {}\n\
+                 • Allowed directories: {}\n\
+                 \n",
+                if config.blocked_commands.is_empty() {
+                    "none".to_string()
+                } else {
+                    config.blocked_commands.join(", ")
+                },
+                if config.allowed_directories.is_empty() {
+                    "all (unrestricted)".to_string()
+                } else {
+                    format!("{} paths", config.allowed_directories.len())
+                }
+            ));
+        }
+        if wants(ConfigSection::Shell) {
+            summary.push_str(&format!("Shell:\n• Default: {}\n\n", config.default_shell));
+        }
+        if wants(ConfigSection::Limits) {
+            summary.push_str(&format!(
+                "Limits:\n\
+                 • Read limit: {} lines\n\
+                 • Write limit: {} lines\n\
+                 \n",
+                config.file_read_line_limit, config.file_write_line_limit
+            ));
+        }
+        if wants(ConfigSection::Logging) {
+            summary.push_str(&format!(
+                "Logging:\n\
+                 • Level: {}\n\
+                 • Telemetry: {}\n\
+                 • Trace export: {}\n\
+                 \n",
+                view.extended.log_level.map_or("host default", LogLevel::as_str),
+                if view.extended.telemetry_enabled { "enabled" } else { "disabled" },
+                view.extended.otlp_endpoint.as_deref().unwrap_or("disabled")
+            ));
+        }
+        if wants(ConfigSection::Secrets) {
+            summary.push_str(&format!(
+                "Secrets: {}{}\n\n",
+                if secret_names.is_empty() {
+                    "none".to_string()
+                } else {
+                    secret_names.join(", ")
+                },
+                if revealed.is_some() { " (values in JSON)" } else { "" }
+            ));
+        }
+        let system = if args.include_system_info && wants(ConfigSection::System) {
+            let (system_summary, system_json) = self.system_info(args.refresh).await;
+            summary.push_str(&system_summary);
+            Some(system_json)
+        } else {
+            None
+        };
+        if wants(ConfigSection::Persistence) {
+            summary.push_str(&format!(
+                "Persistence:\n\
+                 • Last save: {}{}\n\
+                 • Save errors: {}{}\n\
+                 • Save retries: {}\n\
+                 • Integrity: {}\n\
+                 • External edits overwritten: {}\n\
+                 \n",
+                persistence
+                    .last_save_at
+                    .map_or_else(|| "never".to_string(), |t| t.to_rfc3339()),
+                if persistence.save_pending { " (save pending)" } else { "" },
+                persistence.error_count + persistence.upstream_error_count,
+                persistence
+                    .last_error
+                    .as_ref()
+                    .map_or_else(String::new, |e| format!(" (last: {} - {})", e.kind, e.message)),
+                persistence.retry_count,
+                match persistence.integrity.as_ref().map(|report| report.state) {
+                    Some(IntegrityState::Verified) => "verified",
+                    Some(IntegrityState::Unsigned) => "unsigned",
+                    Some(IntegrityState::Mismatch) => {
+                        "⚠️ MISMATCH - settings were edited outside the config tools"
+                    }
+                    Some(IntegrityState::AlgorithmChanged) => {
+                        "unchecked (signature algorithm changed)"
+                    }
+                    None => "unchecked",
+                },
+                match &persistence.last_conflict {
+                    Some(conflict) => format!(
+                        "{} (last backed up to {})",
+                        persistence.conflict_count,
+                        conflict
+                            .backup_path
+                            .as_ref()
+                            .map_or_else(|| "nowhere".to_string(), |p| p.display().to_string())
+                    ),
+                    None => "0".to_string(),
+                }
+            ));
+        }
+        // Provenance of the keys in the requested sections only
+        let in_sections = |key: &str| {
+            let top = key.split('.').next().unwrap_or(key);
+            let top = if top == "ext" { "extensions" } else { top };
+            sections.iter().any(|section| section.keys().contains(&top))
+        };
+        let provenance: Vec<_> = self
+            .config_manager
+            .provenance()
+            .into_iter()
+            .filter(|p| in_sections(&p.key))
+            .collect();
+        let non_default: Vec<String> = provenance
+            .iter()
+            .filter(|p| p.source != ValueSource::Default)
+            .map(|p| match &p.origin {
+                Some(origin) => format!("\n• {}: {} ({})", p.key, p.source, origin),
+                None => format!("\n• {}: {}", p.key, p.source),
+            })
+            .collect();
+        if !non_default.is_empty() {
+            summary.push_str("Non-default values:");
+            summary.push_str(&non_default.concat());
+        }
+        // Startup problems are reported whatever the sections
+        if let Some(e) = &persistence.schema_error {
+            summary.push_str(&format!("\n\n⛔ Config not loaded and read-only: {}", e));
+        }
+        if let Some(recovery) = &persistence.load_recovery {
+            summary.push_str(&format!(
+                "\n\n⚠️  {} was corrupt at startup ({})\n\
+                 • Corrupt file moved to: {}\n\
+                 • Recovered from: {}",
+                recovery.path.display(),
+                recovery.error,
+                recovery
+                    .corrupt_path
+                    .as_ref()
+                    .map_or_else(|| "nowhere (move failed)".to_string(), |p| p.display().to_string()),
+                recovery
+                    .recovered_from
+                    .as_ref()
+                    .map_or_else(|| "defaults".to_string(), |p| p.display().to_string())
+            ));
+        }
         // ========================================
         // Content[1]: Machine-Parseable JSON
         // ========================================
-        let metadata = json!({
+        let mut config_json = serde_json::to_value(&view).unwrap_or_else(|_| json!({}));
+        if let Some(object) = config_json.as_object_mut() {
+            // The view carries the infrastructure crate's copy either way
+            match system {
+                Some(system) => {
+                    object.insert("system_info".to_string(), system);
+                }
+                None => {
+                    object.remove("system_info");
+                }
+            }
+            if args.include_client_history {
+                object.insert("client_history".to_string(), self.config_manager.client_history());
+            } else {
+                object.remove("client_history");
+            }
+        }
+        config_json[SECRETS_KEY] = match &revealed {
+            Some(values) => json!(values),
+            None => redact(&secret_names),
+        };
+        config_json["save_error_count"] = json!(save_error_count);
+        if let Some(object) = config_json.as_object_mut()
+            && !args.sections.is_empty()
+        {
+            object.retain(|key, _| sections.iter().any(|s| s.keys().contains(&key.as_str())));
+        }
+        // Content[0] in the requested format
+        let summary = match args.format {
+            SummaryFormat::Text => summary.trim_end().to_string(),
+            SummaryFormat::Json => serde_json::to_string_pretty(&config_json)
+                .unwrap_or_else(|_| "{}".to_string()),
+            SummaryFormat::Toml => ConfigFormat::Toml
+                .serialize(&without_nulls(config_json.clone()))
+                .map_err(|e| McpError::Other(anyhow::anyhow!("Cannot render TOML: {}", e)))?,
+            SummaryFormat::Table => markdown_table(&config_json),
+        };
+        contents.push(Content::text(summary));
+        let mut metadata = json!({
             "success": true,
-            "config": config
+            "sections": sections,
+            "config": config_json,
+            "provenance": provenance
         });
+        if wants(ConfigSection::Persistence) {
+            metadata["persistence"] = json!(persistence);
+            metadata["save_errors"] = json!(self.config_manager.get_save_errors());
+        }
+        if args.include_history {
+            metadata["change_history"] =
+                json!(self.config_manager.change_history(None, HISTORY_LIMIT).await?);
+        }
         let json_str = serde_json::to_string_pretty(&metadata)
             .unwrap_or_else(|_| "{}".to_string());
         contents.push(Content::text(json_str));
@@ -135,3 +463,74 @@ impl Tool for GetConfigTool {
         ])
     }
 }
+
+/// `value` with null members removed, which TOML can't represent.
+fn without_nulls(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(object) => object
+            .into_iter()
+            .filter(|(_, v)| !v.is_null())
+            .map(|(k, v)| (k, without_nulls(v)))
+            .collect::<serde_json::Map<_, _>>()
+            .into(),
+        serde_json::Value::Array(items) => items
+            .into_iter()
+            .filter(|v| !v.is_null())
+            .map(without_nulls)
+            .collect::<Vec<_>>()
+            .into(),
+        other => other,
+    }
+}
+
+/// One `| key | value |` row per top-level member of `config`, values as
+/// compact JSON.
+fn markdown_table(config: &serde_json::Value) -> String {
+    let mut table = "| Key | Value |\n|-----|-------|\n".to_string();
+    for (key, value) in config.as_object().into_iter().flatten() {
+        let value = match value {
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        table.push_str(&format!("| {} | {} |\n", key, value.replace('|', "\\|")));
+    }
+    table.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn config_json(args: ConfigGetArgs) -> serde_json::Value {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ConfigManager::builder(UpstreamConfigManager::new())
+            .path(dir.path().join("config.tools.json"))
+            .encryption_key(None)
+            .build();
+        let contents = GetConfigTool::new(config).execute(args).await.unwrap();
+        serde_json::from_str(&contents[1].as_text().unwrap().text).unwrap()
+    }
+
+    #[tokio::test]
+    async fn system_info_is_left_out_unless_included() {
+        let without = config_json(ConfigGetArgs {
+            include_system_info: false,
+            ..ConfigGetArgs::default()
+        })
+        .await;
+        assert!(without.get("system_info").is_none());
+
+        let with = config_json(ConfigGetArgs::default()).await;
+        assert!(with.get("system_info").is_some());
+    }
+
+    #[tokio::test]
+    async fn client_history_is_left_out_unless_included() {
+        let without = config_json(ConfigGetArgs {
+            include_client_history: false,
+            ..ConfigGetArgs::default()
+        })
+        .await;
+        assert!(without.get("client_history").is_none());
+    }
+}
@@ -0,0 +1,172 @@
+//! Health and readiness probes
+//!
+//! `/healthz` answers `200` while the process can serve requests, and
+//! `/readyz` answers `503` until `init()` has finished and whenever the
+//! config can't be trusted: it fell back to defaults because the file is
+//! from a newer build, or the latest save failed. Both return the same JSON
+//! report so an operator can see why a probe failed.
+//!
+//! The probes are served on a listener of their own, at
+//! `KODEGEN_CONFIG_HEALTH_ADDR`, so they answer in stdio mode too and can be
+//! exposed to an orchestrator without exposing the MCP endpoint.
+
+use crate::ConfigManager;
+use crate::persistence::RecoveryRecord;
+use crate::schema_version::SchemaTooNew;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Address the probes listen on, e.g. `127.0.0.1:9090`; unset disables them.
+pub const HEALTH_ADDR_ENV: &str = "KODEGEN_CONFIG_HEALTH_ADDR";
+
+/// Largest request head read before answering.
+const MAX_REQUEST_BYTES: usize = 8192;
+
+// ============================================================================
+// REPORT
+// ============================================================================
+
+/// State of the config reported by both probes.
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    /// Whether `/readyz` succeeds.
+    pub ready: bool,
+    /// Why the server isn't ready; empty when it is.
+    pub reasons: Vec<String>,
+    /// Whether `init()` has finished.
+    pub initialized: bool,
+    pub read_only: bool,
+    /// Set if the extended config was corrupt at startup and recovered.
+    pub load_recovery: Option<RecoveryRecord>,
+    /// Set if the extended config is from a newer build.
+    pub schema_error: Option<SchemaTooNew>,
+    pub last_save_at: Option<DateTime<Utc>>,
+    /// Whether the latest save succeeded; `None` before the first save.
+    pub last_save_ok: Option<bool>,
+    pub save_pending: bool,
+    /// Failed saves of both config files since startup.
+    pub save_error_count: u64,
+}
+
+impl ConfigManager {
+    /// Load and save status for health and readiness probes.
+    #[must_use]
+    pub fn health(&self) -> HealthReport {
+        let status = self.persistence_status();
+        let initialized = self.is_initialized();
+        let mut reasons = Vec::new();
+        if !initialized {
+            reasons.push("config is still initializing".to_string());
+        }
+        if let Some(error) = &status.schema_error {
+            reasons.push(error.to_string());
+        }
+        if let Some(error) = &status.last_error {
+            reasons.push(format!("latest save failed: {}", error.message));
+        }
+        let last_save_ok = match (&status.last_error, status.last_save_at) {
+            (Some(_), _) => Some(false),
+            (None, Some(_)) => Some(true),
+            (None, None) => None,
+        };
+        HealthReport {
+            ready: reasons.is_empty(),
+            reasons,
+            initialized,
+            read_only: self.is_read_only(),
+            load_recovery: status.load_recovery,
+            schema_error: status.schema_error,
+            last_save_at: status.last_save_at,
+            last_save_ok,
+            save_pending: status.save_pending,
+            save_error_count: status.error_count + status.upstream_error_count,
+        }
+    }
+}
+
+// ============================================================================
+// LISTENER
+// ============================================================================
+
+/// The probe address from `KODEGEN_CONFIG_HEALTH_ADDR`, if set and valid.
+#[must_use]
+pub fn health_addr_from_env() -> Option<SocketAddr> {
+    let raw = std::env::var(HEALTH_ADDR_ENV).ok()?;
+    match raw.trim().parse() {
+        Ok(addr) => Some(addr),
+        Err(e) => {
+            log::error!("Ignoring {}='{}': {}", HEALTH_ADDR_ENV, raw, e);
+            None
+        }
+    }
+}
+
+/// Serve `/healthz` and `/readyz` on `addr` until the task is dropped.
+/// Fails only if `addr` can't be bound.
+pub async fn serve_health(config: ConfigManager, addr: SocketAddr) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    log::info!("Serving health probes on http://{}", listener.local_addr()?);
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                // Usually out of file descriptors; back off instead of spinning
+                log::warn!("Failed to accept health probe connection: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let config = config.clone();
+        tokio::spawn(async move {
+            if let Err(e) = answer(stream, &config).await {
+                log::debug!("Health probe connection failed: {}", e);
+            }
+        });
+    }
+}
+
+/// Read one request head from `stream` and answer it.
+async fn answer(mut stream: TcpStream, config: &ConfigManager) -> std::io::Result<()> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() < MAX_REQUEST_BYTES {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default();
+
+    let (status, body) = match (method, path) {
+        ("GET" | "HEAD", "/healthz") => {
+            ("200 OK", serde_json::to_string(&config.health()).unwrap_or_default())
+        }
+        ("GET" | "HEAD", "/readyz") => {
+            let report = config.health();
+            let status = if report.ready { "200 OK" } else { "503 Service Unavailable" };
+            (status, serde_json::to_string(&report).unwrap_or_default())
+        }
+        ("GET" | "HEAD", _) => ("404 Not Found", r#"{"error":"not found"}"#.to_string()),
+        _ => ("405 Method Not Allowed", r#"{"error":"method not allowed"}"#.to_string()),
+    };
+    let mut response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    if method != "HEAD" {
+        response.push_str(&body);
+    }
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
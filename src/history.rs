@@ -0,0 +1,151 @@
+//! Undo/redo history of config mutations
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Default number of mutations kept on the undo stack.
+pub const DEFAULT_UNDO_LIMIT: usize = 50;
+
+// ============================================================================
+// CONFIG CHANGE
+// ============================================================================
+
+/// A single applied mutation, with the effective values before and after.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigChange {
+    pub key: String,
+    /// Value before the change; `null` if the key was unset.
+    pub old: serde_json::Value,
+    /// Value after the change; `null` if the key was removed.
+    pub new: serde_json::Value,
+    pub timestamp: DateTime<Utc>,
+    /// Why the change was made, as given by the caller.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl ConfigChange {
+    #[must_use]
+    pub fn new(key: impl Into<String>, old: serde_json::Value, new: serde_json::Value) -> Self {
+        Self {
+            key: key.into(),
+            old,
+            new,
+            timestamp: Utc::now(),
+            reason: None,
+        }
+    }
+
+    /// Attach the caller's reason for the change.
+    #[must_use]
+    pub fn with_reason(mut self, reason: Option<String>) -> Self {
+        self.reason = reason;
+        self
+    }
+
+    /// The change that reverts this one.
+    #[must_use]
+    pub fn inverse(&self) -> Self {
+        Self::new(self.key.clone(), self.new.clone(), self.old.clone())
+    }
+}
+
+// ============================================================================
+// UNDO STACK
+// ============================================================================
+
+/// Bounded undo stack with a redo stack that is cleared by any new mutation.
+#[derive(Debug, Clone)]
+pub struct UndoStack {
+    undo: VecDeque<ConfigChange>,
+    redo: Vec<ConfigChange>,
+    limit: usize,
+}
+
+impl Default for UndoStack {
+    fn default() -> Self {
+        Self::new(DEFAULT_UNDO_LIMIT)
+    }
+}
+
+impl UndoStack {
+    #[must_use]
+    pub fn new(limit: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            limit,
+        }
+    }
+
+    /// Record a new mutation, discarding the redo stack.
+    pub fn record(&mut self, change: ConfigChange) {
+        if self.limit == 0 {
+            return;
+        }
+        if self.undo.len() == self.limit {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(change);
+        self.redo.clear();
+    }
+
+    pub fn pop_undo(&mut self) -> Option<ConfigChange> {
+        self.undo.pop_back()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<ConfigChange> {
+        self.redo.pop()
+    }
+
+    /// The change `pop_undo` would return.
+    #[must_use]
+    pub fn peek_undo(&self) -> Option<&ConfigChange> {
+        self.undo.back()
+    }
+
+    /// The change `pop_redo` would return.
+    #[must_use]
+    pub fn peek_redo(&self) -> Option<&ConfigChange> {
+        self.redo.last()
+    }
+
+    /// Push an undone change so it can be redone.
+    pub fn push_redo(&mut self, change: ConfigChange) {
+        self.redo.push(change);
+    }
+
+    /// Push a redone change back onto the undo stack without clearing redo.
+    pub fn push_undo(&mut self, change: ConfigChange) {
+        if self.limit == 0 {
+            return;
+        }
+        if self.undo.len() == self.limit {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(change);
+    }
+
+    /// Undoable changes, most recent first, optionally only those to `key`.
+    #[must_use]
+    pub fn recent(&self, key: Option<&str>, limit: usize) -> Vec<ConfigChange> {
+        self.undo
+            .iter()
+            .rev()
+            .filter(|change| key.is_none_or(|key| change.key == key))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    #[must_use]
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    #[must_use]
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+}
@@ -0,0 +1,232 @@
+//! Network host policy
+//!
+//! The outbound counterpart of the directory policy: fetch and browser tools
+//! check hosts against `allowed_hosts` / `denied_hosts` instead of each
+//! implementing their own list. Entries are host names, IP addresses, or
+//! wildcards: `*.example.com` matches every subdomain of `example.com` but
+//! not `example.com` itself, and `*` matches any host. Hosts compare
+//! case-insensitively, ignoring a trailing dot.
+
+use serde::Serialize;
+use std::fmt;
+
+/// Key of the hosts network tools may reach.
+pub const ALLOWED_HOSTS_KEY: &str = "allowed_hosts";
+
+/// Key of the hosts network tools must not reach.
+pub const DENIED_HOSTS_KEY: &str = "denied_hosts";
+
+/// Outcome of checking a host against the host lists.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum HostDecision {
+    /// Matches `pattern`, an entry of `allowed_hosts`.
+    Allowed { host: String, pattern: String },
+    /// `allowed_hosts` is empty, so every host not denied is allowed.
+    Unrestricted { host: String },
+    /// Matches `pattern`, an entry of `denied_hosts`.
+    Denied { host: String, pattern: String },
+    /// Matches no entry of `allowed_hosts`.
+    NotAllowed { host: String },
+}
+
+impl HostDecision {
+    #[must_use]
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, Self::Allowed { .. } | Self::Unrestricted { .. })
+    }
+
+    /// The host as normalized for the check.
+    #[must_use]
+    pub fn host(&self) -> &str {
+        match self {
+            Self::Allowed { host, .. }
+            | Self::Unrestricted { host }
+            | Self::Denied { host, .. }
+            | Self::NotAllowed { host } => host,
+        }
+    }
+
+    /// Why the host was allowed or refused, for error messages.
+    #[must_use]
+    pub fn reason(&self) -> String {
+        match self {
+            Self::Allowed { pattern, .. } => format!("matches allowed host '{}'", pattern),
+            Self::Unrestricted { .. } => "no allowed hosts configured".to_string(),
+            Self::Denied { pattern, .. } => format!("matches denied host '{}'", pattern),
+            Self::NotAllowed { .. } => "matches no allowed host".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for HostDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.is_allowed() { "allowed" } else { "denied" };
+        write!(f, "{} {}: {}", self.host(), verdict, self.reason())
+    }
+}
+
+/// Check `host` against `denied` first, then `allowed`.
+///
+/// `host` may also be a URL or `host:port`; only the host part is checked.
+#[must_use]
+pub fn evaluate_host(host: &str, allowed: &[String], denied: &[String]) -> HostDecision {
+    let host = normalize_host(host);
+    if let Some(pattern) = denied.iter().find(|p| matches_host(&host, p)) {
+        return HostDecision::Denied {
+            host,
+            pattern: pattern.clone(),
+        };
+    }
+    if allowed.is_empty() {
+        return HostDecision::Unrestricted { host };
+    }
+    match allowed.iter().find(|p| matches_host(&host, p)) {
+        Some(pattern) => HostDecision::Allowed {
+            host,
+            pattern: pattern.clone(),
+        },
+        None => HostDecision::NotAllowed { host },
+    }
+}
+
+/// Check that `entry` is a host, an IP address, `*`, or `*.` followed by a
+/// domain.
+pub(crate) fn validate_entry(entry: &str) -> Result<(), String> {
+    let domain = entry.strip_prefix("*.").unwrap_or(entry);
+    let invalid = domain.is_empty()
+        || (domain.contains('*') && entry != "*")
+        || domain.contains(['/', '@', '?', '#'])
+        || domain.chars().any(char::is_whitespace);
+    if invalid {
+        return Err(format!(
+            "'{}' is not a host name, IP address, or wildcard such as *.example.com",
+            entry
+        ));
+    }
+    Ok(())
+}
+
+/// Lowercase host of `input`, which may be a bare host, `host:port`, or a URL.
+fn normalize_host(input: &str) -> String {
+    let input = input.trim();
+    let authority = match input.split_once("://") {
+        Some((_, rest)) => rest.split(['/', '?', '#']).next().unwrap_or(rest),
+        None => input,
+    };
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    let host = match authority.strip_prefix('[') {
+        // [IPv6]:port
+        Some(rest) => rest.split_once(']').map_or(rest, |(ip, _)| ip),
+        // A single colon separates a port; more make an IPv6 address
+        None if authority.matches(':').count() == 1 => {
+            authority.split_once(':').map_or(authority, |(host, _)| host)
+        }
+        None => authority,
+    };
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+fn matches_host(host: &str, pattern: &str) -> bool {
+    let pattern = normalize_host(pattern);
+    if pattern == "*" {
+        return true;
+    }
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+        None => host == pattern,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn allows_listed_hosts_from_urls_and_host_ports() {
+        let allowed = list(&["api.example.com", "10.0.0.1"]);
+        for host in [
+            "https://API.example.com./v1?q=1",
+            "api.example.com:443",
+            "10.0.0.1",
+        ] {
+            let decision = evaluate_host(host, &allowed, &[]);
+            assert!(
+                matches!(decision, HostDecision::Allowed { .. }),
+                "{}",
+                decision
+            );
+        }
+    }
+
+    #[test]
+    fn refuses_hosts_outside_the_allow_list() {
+        let decision = evaluate_host("evil.com", &list(&["example.com"]), &[]);
+        assert_eq!(
+            decision,
+            HostDecision::NotAllowed {
+                host: "evil.com".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn allows_everything_without_allowed_hosts() {
+        let decision = evaluate_host("example.com", &[], &[]);
+        assert!(matches!(decision, HostDecision::Unrestricted { .. }));
+    }
+
+    #[test]
+    fn denied_hosts_win_over_allowed_ones() {
+        let decision = evaluate_host(
+            "internal.example.com",
+            &list(&["*"]),
+            &list(&["*.example.com"]),
+        );
+        assert!(
+            matches!(decision, HostDecision::Denied { .. }),
+            "{}",
+            decision
+        );
+    }
+
+    #[test]
+    fn wildcards_match_subdomains_only() {
+        let allowed = list(&["*.example.com"]);
+        assert!(evaluate_host("a.b.example.com", &allowed, &[]).is_allowed());
+        assert!(!evaluate_host("example.com", &allowed, &[]).is_allowed());
+        assert!(!evaluate_host("badexample.com", &allowed, &[]).is_allowed());
+    }
+
+    #[test]
+    fn userinfo_and_ipv6_are_stripped_to_the_host() {
+        let allowed = list(&["::1", "example.com"]);
+        assert!(evaluate_host("http://[::1]:8080/", &allowed, &[]).is_allowed());
+        let decision = evaluate_host("https://example.com@evil.com/", &allowed, &[]);
+        assert_eq!(decision.host(), "evil.com");
+        assert!(!decision.is_allowed());
+    }
+
+    #[test]
+    fn rejects_malformed_entries() {
+        for entry in [
+            "",
+            "*.",
+            "ex*ample.com",
+            "example.com/path",
+            "user@host",
+            "a b",
+        ] {
+            assert!(validate_entry(entry).is_err(), "{:?} accepted", entry);
+        }
+        for entry in ["*", "*.example.com", "example.com", "10.0.0.1", "::1"] {
+            assert!(validate_entry(entry).is_ok(), "{:?} rejected", entry);
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! Tamper detection for config settings
+//!
+//! After every change made through `ConfigManager`, and after changes from a
+//! config store, the remote source, or a requested reload, a digest of all
+//! settings is written to `config.sig`. At startup (and on
+//! `verify_integrity()`) the digest is recomputed; a mismatch means the
+//! settings were edited outside the config tools. With `KODEGEN_CONFIG_HMAC_KEY` set the digest is an
+//! HMAC, so it can't be recomputed by someone without the key.
+
+use crate::ServerConfig;
+use crate::extended_config::ExtendedConfig;
+use crate::keys::ConfigKey;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// File name of the signature, stored alongside the extended config.
+pub const SIGNATURE_FILE: &str = "config.sig";
+
+/// Environment variable holding the HMAC key.
+pub const HMAC_KEY_ENV: &str = "KODEGEN_CONFIG_HMAC_KEY";
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignatureAlgorithm {
+    Sha256,
+    HmacSha256,
+}
+
+/// Contents of `config.sig`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Signature {
+    pub algorithm: SignatureAlgorithm,
+    /// Base64-encoded digest.
+    pub digest: String,
+    pub signed_at: DateTime<Utc>,
+}
+
+/// Outcome of checking the settings against `config.sig`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityState {
+    /// Settings match the signature.
+    Verified,
+    /// No signature exists yet.
+    Unsigned,
+    /// Settings changed without going through the config tools.
+    Mismatch,
+    /// Signed with a different algorithm (e.g. the HMAC key was added or
+    /// removed), so the signature can't be checked.
+    AlgorithmChanged,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityReport {
+    pub state: IntegrityState,
+    pub algorithm: SignatureAlgorithm,
+    pub checked_at: DateTime<Utc>,
+    /// When the signature that was checked was written.
+    pub signed_at: Option<DateTime<Utc>>,
+}
+
+pub struct Signer {
+    hmac_key: Option<Vec<u8>>,
+}
+
+impl Signer {
+    /// Plain SHA-256, or HMAC-SHA256 when `key` is given.
+    #[must_use]
+    pub fn new(hmac_key: Option<Vec<u8>>) -> Self {
+        Self { hmac_key }
+    }
+
+    /// Signer keyed from `KODEGEN_CONFIG_HMAC_KEY`, if set.
+    #[must_use]
+    pub fn from_env() -> Self {
+        let key = std::env::var(HMAC_KEY_ENV)
+            .ok()
+            .filter(|key| !key.is_empty())
+            .map(String::into_bytes);
+        Self::new(key)
+    }
+
+    #[must_use]
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        match self.hmac_key {
+            Some(_) => SignatureAlgorithm::HmacSha256,
+            None => SignatureAlgorithm::Sha256,
+        }
+    }
+
+    #[must_use]
+    pub fn sign(&self, server: &ServerConfig, extended: &ExtendedConfig) -> Signature {
+        Signature {
+            algorithm: self.algorithm(),
+            digest: STANDARD.encode(self.digest(&signed_content(server, extended))),
+            signed_at: Utc::now(),
+        }
+    }
+
+    /// Compare the current settings with `signature`, or report them unsigned.
+    #[must_use]
+    pub fn verify(
+        &self,
+        server: &ServerConfig,
+        extended: &ExtendedConfig,
+        signature: Option<&Signature>,
+    ) -> IntegrityReport {
+        let state = match signature {
+            None => IntegrityState::Unsigned,
+            Some(signature) if signature.algorithm != self.algorithm() => {
+                IntegrityState::AlgorithmChanged
+            }
+            Some(signature) => {
+                let content = signed_content(server, extended);
+                let expected = STANDARD.decode(&signature.digest).unwrap_or_default();
+                let matches = match &self.hmac_key {
+                    Some(key) => hmac(key, &content).verify_slice(&expected).is_ok(),
+                    None => Sha256::digest(&content).as_slice() == expected.as_slice(),
+                };
+                if matches {
+                    IntegrityState::Verified
+                } else {
+                    IntegrityState::Mismatch
+                }
+            }
+        };
+        IntegrityReport {
+            state,
+            algorithm: self.algorithm(),
+            checked_at: Utc::now(),
+            signed_at: signature.map(|s| s.signed_at),
+        }
+    }
+
+    fn digest(&self, content: &[u8]) -> Vec<u8> {
+        match &self.hmac_key {
+            Some(key) => hmac(key, content).finalize().into_bytes().to_vec(),
+            None => Sha256::digest(content).to_vec(),
+        }
+    }
+}
+
+fn hmac(key: &[u8], content: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(content);
+    mac
+}
+
+/// Canonical bytes covered by the signature: every settings key plus the
+/// extended config, with object keys sorted.
+fn signed_content(server: &ServerConfig, extended: &ExtendedConfig) -> Vec<u8> {
+    let server = serde_json::to_value(server).unwrap_or_default();
+    let mut settings = serde_json::Map::new();
+    for key in ConfigKey::SERVER {
+        if let Some(value) = server.get(key.as_str()) {
+            settings.insert(key.as_str().to_string(), value.clone());
+        }
+    }
+    settings.insert(
+        "extended".to_string(),
+        serde_json::to_value(extended).unwrap_or_default(),
+    );
+    serde_json::to_vec(&settings).unwrap_or_default()
+}
+
+/// Read `config.sig`, or `None` if it is missing or unreadable.
+#[must_use]
+pub fn read_signature(path: &Path) -> Option<Signature> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents)
+        .map_err(|e| log::warn!("Ignoring unreadable {}: {}", path.display(), e))
+        .ok()
+}
+
+pub fn write_signature(path: &Path, signature: &Signature) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(signature)?;
+    let tmp = crate::persistence::temp_path(path);
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, path)
+}
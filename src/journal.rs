@@ -0,0 +1,462 @@
+//! Append-only change journal
+//!
+//! Every applied change is appended to `config.journal` as one JSON line
+//! before the extended config is saved, and a checkpoint line is appended
+//! after each successful save. Changes newer than the last checkpoint were
+//! never saved and are replayed by `ConfigManager::init()` after a crash.
+//!
+//! Several processes may share one journal. Appends and compactions hold an
+//! exclusive lock on `config.journal.lock`, and each entry's sequence number
+//! follows the last entry on disk rather than a per-process counter.
+
+use crate::encryption::EncryptionKey;
+use crate::history::ConfigChange;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// File name of the journal, stored alongside the extended config.
+pub const JOURNAL_FILE: &str = "config.journal";
+
+/// Entries the journal may hold before it is compacted.
+pub const JOURNAL_MAX_ENTRIES: usize = 1000;
+
+/// Entries kept by a compaction, leaving room for further appends before
+/// the next one.
+const COMPACTED_ENTRIES: usize = JOURNAL_MAX_ENTRIES / 2;
+
+/// One line of the journal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JournalEntry {
+    Change {
+        seq: u64,
+        key: String,
+        old: serde_json::Value,
+        new: serde_json::Value,
+        timestamp: DateTime<Utc>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+    /// Every change up to and including `through` has been saved.
+    Checkpoint {
+        seq: u64,
+        through: u64,
+        timestamp: DateTime<Utc>,
+    },
+}
+
+impl JournalEntry {
+    #[must_use]
+    pub fn seq(&self) -> u64 {
+        match self {
+            JournalEntry::Change { seq, .. } | JournalEntry::Checkpoint { seq, .. } => *seq,
+        }
+    }
+
+    #[must_use]
+    pub fn change(&self) -> Option<ConfigChange> {
+        match self {
+            JournalEntry::Change {
+                key,
+                old,
+                new,
+                timestamp,
+                reason,
+                ..
+            } => Some(ConfigChange {
+                key: key.clone(),
+                old: old.clone(),
+                new: new.clone(),
+                timestamp: *timestamp,
+                reason: reason.clone(),
+            }),
+            JournalEntry::Checkpoint { .. } => None,
+        }
+    }
+}
+
+pub struct Journal {
+    path: PathBuf,
+    /// Sequence number of the last entry written by this process.
+    last_seq: Mutex<u64>,
+    key: Option<EncryptionKey>,
+}
+
+impl Journal {
+    /// Open the journal at `path`, compacting it if it holds more than
+    /// `JOURNAL_MAX_ENTRIES`.
+    ///
+    /// Lines are encrypted with `key` when given, since they carry config values.
+    #[must_use]
+    pub fn open(path: PathBuf, key: Option<EncryptionKey>) -> Self {
+        let journal = Self {
+            path,
+            last_seq: Mutex::new(0),
+            key,
+        };
+        let entries = journal.entries();
+        *journal.last_seq.lock() = entries.last().map_or(0, JournalEntry::seq);
+        if entries.len() > JOURNAL_MAX_ENTRIES {
+            // Re-read under the lock; another process may be appending
+            let compacted = journal
+                .lock_exclusive()
+                .and_then(|_lock| journal.compact(&journal.entries()));
+            if let Err(e) = compacted {
+                log::warn!("Failed to compact {}: {}", journal.path.display(), e);
+            }
+        }
+        journal
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Sequence number of the last entry this process wrote, to pass to
+    /// `checkpoint` once the config as of now has been saved.
+    #[must_use]
+    pub fn last_seq(&self) -> u64 {
+        *self.last_seq.lock()
+    }
+
+    /// Append an applied change.
+    pub fn append_change(&self, change: &ConfigChange) -> std::io::Result<()> {
+        self.append(|seq| JournalEntry::Change {
+            seq,
+            key: change.key.clone(),
+            old: change.old.clone(),
+            new: change.new.clone(),
+            timestamp: change.timestamp,
+            reason: change.reason.clone(),
+        })
+    }
+
+    /// Mark every change up to and including `through` as saved.
+    pub fn checkpoint(&self, through: u64) -> std::io::Result<()> {
+        self.append(|seq| JournalEntry::Checkpoint {
+            seq,
+            through,
+            timestamp: Utc::now(),
+        })
+    }
+
+    /// Append the entry built from the sequence number after the last entry
+    /// on disk, compacting the journal once it exceeds `JOURNAL_MAX_ENTRIES`.
+    fn append(&self, entry: impl FnOnce(u64) -> JournalEntry) -> std::io::Result<()> {
+        let mut last_seq = self.last_seq.lock();
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let _lock = self.lock_exclusive()?;
+        let mut entries = self.entries();
+        let entry = entry(entries.last().map_or(0, JournalEntry::seq) + 1);
+        let line = self.encode(&entry)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        *last_seq = entry.seq();
+        entries.push(entry);
+        if let Err(e) = self.compact(&entries) {
+            log::warn!("Failed to compact {}: {}", self.path.display(), e);
+        }
+        Ok(())
+    }
+
+    /// Rewrite the journal with the newest `COMPACTED_ENTRIES` of `entries`
+    /// if there are more than `JOURNAL_MAX_ENTRIES`. Call with the lock held.
+    fn compact(&self, entries: &[JournalEntry]) -> std::io::Result<()> {
+        if entries.len() <= JOURNAL_MAX_ENTRIES {
+            return Ok(());
+        }
+        let kept = &entries[entries.len() - COMPACTED_ENTRIES..];
+        // Carry the save state forward in case its checkpoint is dropped
+        let mut compacted = vec![JournalEntry::Checkpoint {
+            seq: kept[0].seq() - 1,
+            through: saved_through(entries),
+            timestamp: Utc::now(),
+        }];
+        compacted.extend_from_slice(kept);
+        self.rewrite(&compacted)
+    }
+
+    /// Exclusive lock on `<journal>.lock`, held until the file is dropped.
+    ///
+    /// A separate file, since compaction replaces the journal itself.
+    fn lock_exclusive(&self) -> std::io::Result<File> {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.path.with_file_name(name))?;
+        file.lock()?;
+        Ok(file)
+    }
+
+    /// Every readable entry, oldest first. Unreadable lines (e.g. a torn
+    /// final write) are skipped.
+    #[must_use]
+    pub fn entries(&self) -> Vec<JournalEntry> {
+        let Ok(contents) = std::fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match self.decode(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    log::warn!("Skipping unreadable line in {}: {}", self.path.display(), e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Changes not covered by any checkpoint, oldest first.
+    #[must_use]
+    pub fn unsaved_changes(&self) -> Vec<ConfigChange> {
+        let entries = self.entries();
+        let saved = saved_through(&entries);
+        entries
+            .iter()
+            .filter(|entry| entry.seq() > saved)
+            .filter_map(JournalEntry::change)
+            .collect()
+    }
+
+    /// Most recent changes first, optionally only those to `key`.
+    #[must_use]
+    pub fn history(&self, key: Option<&str>, limit: usize) -> Vec<ConfigChange> {
+        self.entries()
+            .iter()
+            .rev()
+            .filter_map(JournalEntry::change)
+            .filter(|change| key.is_none_or(|key| change.key == key))
+            .take(limit)
+            .collect()
+    }
+
+    fn rewrite(&self, entries: &[JournalEntry]) -> std::io::Result<()> {
+        let mut contents = String::new();
+        for entry in entries {
+            contents.push_str(&self.encode(entry)?);
+        }
+        let tmp = crate::persistence::temp_path(&self.path);
+        std::fs::write(&tmp, contents)?;
+        std::fs::rename(&tmp, &self.path)
+    }
+
+    fn encode(&self, entry: &JournalEntry) -> std::io::Result<String> {
+        let json = serde_json::to_string(entry)?;
+        let line = match &self.key {
+            Some(key) => STANDARD.encode(key.encrypt(json.as_bytes())?),
+            None => json,
+        };
+        Ok(line + "\n")
+    }
+
+    fn decode(&self, line: &str) -> std::io::Result<JournalEntry> {
+        if line.starts_with('{') {
+            return Ok(serde_json::from_str(line)?);
+        }
+        let key = self.key.as_ref().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::PermissionDenied, "entry is encrypted")
+        })?;
+        let bytes = STANDARD
+            .decode(line)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(serde_json::from_slice(&key.decrypt(&bytes)?)?)
+    }
+}
+
+/// Highest change sequence number covered by a checkpoint in `entries`.
+fn saved_through(entries: &[JournalEntry]) -> u64 {
+    entries
+        .iter()
+        .filter_map(|entry| match entry {
+            JournalEntry::Checkpoint { through, .. } => Some(*through),
+            JournalEntry::Change { .. } => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(n: u64) -> ConfigChange {
+        ConfigChange::new(
+            "file_read_line_limit",
+            serde_json::json!(n),
+            serde_json::json!(n + 1),
+        )
+    }
+
+    fn old_values(changes: &[ConfigChange]) -> Vec<serde_json::Value> {
+        changes.iter().map(|c| c.old.clone()).collect()
+    }
+
+    #[test]
+    fn replays_only_changes_after_the_last_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = Journal::open(dir.path().join(JOURNAL_FILE), None);
+        for n in 1..=3 {
+            journal.append_change(&change(n)).unwrap();
+        }
+        journal.checkpoint(2).unwrap();
+        assert_eq!(
+            old_values(&journal.unsaved_changes()),
+            [serde_json::json!(3)]
+        );
+        journal.checkpoint(journal.last_seq()).unwrap();
+        assert!(journal.unsaved_changes().is_empty());
+    }
+
+    #[test]
+    fn reopening_continues_the_sequence() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(JOURNAL_FILE);
+        let journal = Journal::open(path.clone(), None);
+        journal.append_change(&change(1)).unwrap();
+        journal.append_change(&change(2)).unwrap();
+        let reopened = Journal::open(path, None);
+        assert_eq!(reopened.last_seq(), 2);
+        reopened.append_change(&change(3)).unwrap();
+        let seqs: Vec<u64> = reopened.entries().iter().map(JournalEntry::seq).collect();
+        assert_eq!(seqs, [1, 2, 3]);
+    }
+
+    #[test]
+    fn skips_torn_and_malformed_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(JOURNAL_FILE);
+        let journal = Journal::open(path.clone(), None);
+        journal.append_change(&change(1)).unwrap();
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"{\"type\":\"change\",\"seq\":2,\"ke\nnot json\n\n")
+            .unwrap();
+        assert_eq!(
+            old_values(&journal.unsaved_changes()),
+            [serde_json::json!(1)]
+        );
+    }
+
+    #[test]
+    fn encrypted_entries_need_the_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(JOURNAL_FILE);
+        let key = EncryptionKey::new([8; 32]);
+        let journal = Journal::open(path.clone(), Some(key.clone()));
+        journal.append_change(&change(1)).unwrap();
+        assert!(
+            !std::fs::read_to_string(&path)
+                .unwrap()
+                .contains("file_read_line_limit")
+        );
+        assert_eq!(journal.unsaved_changes().len(), 1);
+        assert!(Journal::open(path.clone(), None).entries().is_empty());
+        assert!(
+            Journal::open(path, Some(EncryptionKey::new([9; 32])))
+                .entries()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn history_is_newest_first_and_filtered_by_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = Journal::open(dir.path().join(JOURNAL_FILE), None);
+        journal.append_change(&change(1)).unwrap();
+        journal
+            .append_change(&ConfigChange::new(
+                "default_shell",
+                "bash".into(),
+                "zsh".into(),
+            ))
+            .unwrap();
+        journal.append_change(&change(2)).unwrap();
+        let history = journal.history(Some("file_read_line_limit"), 10);
+        assert_eq!(
+            old_values(&history),
+            [serde_json::json!(2), serde_json::json!(1)]
+        );
+        assert_eq!(journal.history(None, 1)[0].old, serde_json::json!(2));
+    }
+
+    #[test]
+    fn journals_sharing_a_file_never_reuse_a_sequence_number() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(JOURNAL_FILE);
+        let first = Journal::open(path.clone(), None);
+        let second = Journal::open(path, None);
+        first.append_change(&change(1)).unwrap();
+        second.append_change(&change(2)).unwrap();
+        first.append_change(&change(3)).unwrap();
+        let seqs: Vec<u64> = first.entries().iter().map(JournalEntry::seq).collect();
+        assert_eq!(seqs, [1, 2, 3]);
+        assert_eq!((first.last_seq(), second.last_seq()), (3, 2));
+    }
+
+    #[test]
+    fn appends_compact_the_journal_once_it_is_full() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = Journal::open(dir.path().join(JOURNAL_FILE), None);
+        let total = JOURNAL_MAX_ENTRIES as u64;
+        for n in 1..=total {
+            journal.append_change(&change(n)).unwrap();
+        }
+        assert_eq!(journal.entries().len(), JOURNAL_MAX_ENTRIES);
+        journal.checkpoint(total - 2).unwrap();
+
+        let entries = journal.entries();
+        assert_eq!(entries.len(), COMPACTED_ENTRIES + 1);
+        assert!(matches!(
+            entries[0],
+            JournalEntry::Checkpoint { through, .. } if through == total - 2
+        ));
+        assert_eq!(journal.last_seq(), total + 1);
+        let unsaved = old_values(&journal.unsaved_changes());
+        assert_eq!(
+            unsaved,
+            [serde_json::json!(total - 1), serde_json::json!(total)]
+        );
+        journal.append_change(&change(0)).unwrap();
+        assert_eq!(journal.last_seq(), total + 2);
+    }
+
+    #[test]
+    fn opening_compacts_an_oversized_journal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(JOURNAL_FILE);
+        let total = JOURNAL_MAX_ENTRIES as u64 + 5;
+        let entries: Vec<JournalEntry> = (1..=total)
+            .map(|seq| JournalEntry::Checkpoint {
+                seq,
+                through: seq,
+                timestamp: Utc::now(),
+            })
+            .collect();
+        Journal::open(path.clone(), None).rewrite(&entries).unwrap();
+
+        let journal = Journal::open(path, None);
+        let entries = journal.entries();
+        assert_eq!(entries.len(), COMPACTED_ENTRIES + 1);
+        assert_eq!(entries.last().map(JournalEntry::seq), Some(total));
+        assert_eq!(journal.last_seq(), total);
+    }
+}
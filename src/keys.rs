@@ -0,0 +1,455 @@
+//! Config key registry
+//!
+//! Names every key `ConfigManager::set_value` accepts, maps legacy and renamed
+//! key names onto their canonical form so that existing agent prompts keep
+//! working after a key is renamed, and parses namespaced extension keys.
+
+use crate::audit::AUDIT_LOG_PATH_KEY;
+use crate::client_history::{CLIENT_HISTORY_MAX_AGE_DAYS_KEY, CLIENT_HISTORY_MAX_ENTRIES_KEY};
+use crate::expand::DIRECTORY_ALIASES_KEY;
+use crate::extended_config::{
+    BLOCKED_COMMAND_PATTERNS_KEY, CACHE_DIRECTORY_KEY, COMMAND_TIMEOUT_SECS_KEY,
+    MAX_SEARCH_RESULTS_KEY, TELEMETRY_ENABLED_KEY, TEMP_DIRECTORY_KEY,
+};
+use crate::file_encoding::DEFAULT_ENCODING_KEY;
+use crate::host_policy::{ALLOWED_HOSTS_KEY, DENIED_HOSTS_KEY};
+use crate::log_level::LOG_LEVEL_KEY;
+use crate::otlp::OTLP_ENDPOINT_KEY;
+use crate::path_policy::FOLLOW_SYMLINKS_KEY;
+use crate::rate_limit::RATE_LIMITS_KEY;
+use crate::shell::SHELL_ARGS_KEY;
+use crate::subprocess_env::ENV_PASSTHROUGH_KEY;
+use std::fmt;
+
+// ============================================================================
+// KNOWN KEYS
+// ============================================================================
+
+/// A key `ConfigManager::set_value` accepts.
+///
+/// The first eight variants are stored in `config.json` by the
+/// infrastructure crate (see [`ConfigKey::SERVER`]); the rest live in the
+/// extended config. The entry variants borrow the part of the key after
+/// their section's prefix, e.g. `search` in `tool_settings.search`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ConfigKey<'a> {
+    BlockedCommands,
+    DefaultShell,
+    AllowedDirectories,
+    DeniedDirectories,
+    FileReadLineLimit,
+    FileWriteLineLimit,
+    FuzzySearchThreshold,
+    HttpConnectionTimeoutSecs,
+    LogLevel,
+    TelemetryEnabled,
+    BlockedCommandPatterns,
+    CommandTimeoutSecs,
+    TempDirectory,
+    CacheDirectory,
+    EnvPassthrough,
+    DefaultEncoding,
+    FollowSymlinks,
+    MaxSearchResults,
+    ShellArgs,
+    AuditLogPath,
+    OtlpEndpoint,
+    ClientHistoryMaxEntries,
+    ClientHistoryMaxAgeDays,
+    AllowedHosts,
+    DeniedHosts,
+    /// The whole `tool_settings` section.
+    ToolSettings,
+    /// The whole `rate_limits` section.
+    RateLimits,
+    /// The whole `directory_aliases` section.
+    DirectoryAliases,
+    /// Every `ext.*` value.
+    Extensions,
+    /// `tool_settings.<category>`.
+    ToolCategory(&'a str),
+    /// `rate_limits.<tool>`.
+    RateLimit(&'a str),
+    /// `directory_aliases.<name>`.
+    DirectoryAlias(&'a str),
+    /// `ext.<namespace>.<name>`.
+    Extension(ExtensionKey<'a>),
+}
+
+impl ConfigKey<'_> {
+    /// The keys stored in `config.json`.
+    pub const SERVER: &'static [ConfigKey<'static>] = &[
+        ConfigKey::BlockedCommands,
+        ConfigKey::DefaultShell,
+        ConfigKey::AllowedDirectories,
+        ConfigKey::DeniedDirectories,
+        ConfigKey::FileReadLineLimit,
+        ConfigKey::FileWriteLineLimit,
+        ConfigKey::FuzzySearchThreshold,
+        ConfigKey::HttpConnectionTimeoutSecs,
+    ];
+
+    /// The top-level settings of the extended config, in `EXTENDED_KEYS` order.
+    pub const EXTENDED: &'static [ConfigKey<'static>] = &[
+        ConfigKey::LogLevel,
+        ConfigKey::TelemetryEnabled,
+        ConfigKey::BlockedCommandPatterns,
+        ConfigKey::CommandTimeoutSecs,
+        ConfigKey::TempDirectory,
+        ConfigKey::CacheDirectory,
+        ConfigKey::EnvPassthrough,
+        ConfigKey::DefaultEncoding,
+        ConfigKey::FollowSymlinks,
+        ConfigKey::MaxSearchResults,
+        ConfigKey::ShellArgs,
+        ConfigKey::AuditLogPath,
+        ConfigKey::OtlpEndpoint,
+        ConfigKey::ClientHistoryMaxEntries,
+        ConfigKey::ClientHistoryMaxAgeDays,
+        ConfigKey::AllowedHosts,
+        ConfigKey::DeniedHosts,
+    ];
+
+    /// The extended-config sections that are set as a whole or entry by entry.
+    pub const SECTIONS: &'static [ConfigKey<'static>] = &[
+        ConfigKey::ToolSettings,
+        ConfigKey::RateLimits,
+        ConfigKey::DirectoryAliases,
+        ConfigKey::Extensions,
+    ];
+
+    /// Canonical snake_case name, as used in the config files and by
+    /// `config_set`. For an entry this is the name of its section; use
+    /// `Display` for the whole key.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ConfigKey::BlockedCommands => "blocked_commands",
+            ConfigKey::DefaultShell => "default_shell",
+            ConfigKey::AllowedDirectories => "allowed_directories",
+            ConfigKey::DeniedDirectories => "denied_directories",
+            ConfigKey::FileReadLineLimit => "file_read_line_limit",
+            ConfigKey::FileWriteLineLimit => "file_write_line_limit",
+            ConfigKey::FuzzySearchThreshold => "fuzzy_search_threshold",
+            ConfigKey::HttpConnectionTimeoutSecs => "http_connection_timeout_secs",
+            ConfigKey::LogLevel => LOG_LEVEL_KEY,
+            ConfigKey::TelemetryEnabled => TELEMETRY_ENABLED_KEY,
+            ConfigKey::BlockedCommandPatterns => BLOCKED_COMMAND_PATTERNS_KEY,
+            ConfigKey::CommandTimeoutSecs => COMMAND_TIMEOUT_SECS_KEY,
+            ConfigKey::TempDirectory => TEMP_DIRECTORY_KEY,
+            ConfigKey::CacheDirectory => CACHE_DIRECTORY_KEY,
+            ConfigKey::EnvPassthrough => ENV_PASSTHROUGH_KEY,
+            ConfigKey::DefaultEncoding => DEFAULT_ENCODING_KEY,
+            ConfigKey::FollowSymlinks => FOLLOW_SYMLINKS_KEY,
+            ConfigKey::MaxSearchResults => MAX_SEARCH_RESULTS_KEY,
+            ConfigKey::ShellArgs => SHELL_ARGS_KEY,
+            ConfigKey::AuditLogPath => AUDIT_LOG_PATH_KEY,
+            ConfigKey::OtlpEndpoint => OTLP_ENDPOINT_KEY,
+            ConfigKey::ClientHistoryMaxEntries => CLIENT_HISTORY_MAX_ENTRIES_KEY,
+            ConfigKey::ClientHistoryMaxAgeDays => CLIENT_HISTORY_MAX_AGE_DAYS_KEY,
+            ConfigKey::AllowedHosts => ALLOWED_HOSTS_KEY,
+            ConfigKey::DeniedHosts => DENIED_HOSTS_KEY,
+            ConfigKey::ToolSettings | ConfigKey::ToolCategory(_) => "tool_settings",
+            ConfigKey::RateLimits | ConfigKey::RateLimit(_) => RATE_LIMITS_KEY,
+            ConfigKey::DirectoryAliases | ConfigKey::DirectoryAlias(_) => DIRECTORY_ALIASES_KEY,
+            ConfigKey::Extensions | ConfigKey::Extension(_) => "extensions",
+        }
+    }
+
+    /// Whether the key is stored in `config.json` rather than the extended
+    /// config.
+    #[must_use]
+    pub fn is_server(self) -> bool {
+        ConfigKey::SERVER.contains(&self)
+    }
+}
+
+impl<'a> ConfigKey<'a> {
+    /// Parse a canonical key name. Deprecated aliases are not accepted here;
+    /// pass them through [`resolve_key`] first.
+    pub fn parse(key: &'a str) -> Result<Self, UnknownConfigKey> {
+        let unknown = || UnknownConfigKey(key.to_string());
+        let fixed = ConfigKey::SERVER
+            .iter()
+            .chain(ConfigKey::EXTENDED)
+            .chain(ConfigKey::SECTIONS)
+            .find(|k| k.as_str() == key);
+        if let Some(&fixed) = fixed {
+            return Ok(fixed);
+        }
+        if key.starts_with(EXTENSION_PREFIX) {
+            return match parse_extension_key(key) {
+                Ok(Some(ext)) => Ok(ConfigKey::Extension(ext)),
+                _ => Err(unknown()),
+            };
+        }
+        let (section, entry) = key.split_once('.').ok_or_else(unknown)?;
+        match section {
+            "tool_settings" => Ok(ConfigKey::ToolCategory(entry)),
+            RATE_LIMITS_KEY => Ok(ConfigKey::RateLimit(entry)),
+            DIRECTORY_ALIASES_KEY => Ok(ConfigKey::DirectoryAlias(entry)),
+            _ => Err(unknown()),
+        }
+    }
+
+    /// The `config.json` key named `key`.
+    pub fn server(key: &str) -> Result<ConfigKey<'static>, UnknownConfigKey> {
+        ConfigKey::SERVER
+            .iter()
+            .copied()
+            .find(|k| k.as_str() == key)
+            .ok_or_else(|| UnknownConfigKey(key.to_string()))
+    }
+}
+
+impl fmt::Display for ConfigKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ConfigKey::ToolCategory(entry)
+            | ConfigKey::RateLimit(entry)
+            | ConfigKey::DirectoryAlias(entry) => write!(f, "{}.{}", self.as_str(), entry),
+            ConfigKey::Extension(ext) => {
+                write!(f, "{}{}.{}", EXTENSION_PREFIX, ext.namespace, ext.name)
+            }
+            _ => f.write_str(self.as_str()),
+        }
+    }
+}
+
+/// Error returned when parsing a string that is not a known config key.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Unknown config key: {0}")]
+pub struct UnknownConfigKey(pub String);
+
+// ============================================================================
+// KEY ALIASES
+// ============================================================================
+
+/// A deprecated key name that is still accepted in place of its canonical key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyAlias {
+    /// The old name callers may still send.
+    pub alias: &'static str,
+    /// The key the alias resolves to.
+    pub canonical: &'static str,
+}
+
+/// Renamed keys, plus the camelCase spellings used by early client prompts.
+pub const KEY_ALIASES: &[KeyAlias] = &[
+    KeyAlias { alias: "http_timeout", canonical: "http_connection_timeout_secs" },
+    KeyAlias { alias: "httpConnectionTimeoutSecs", canonical: "http_connection_timeout_secs" },
+    KeyAlias { alias: "blockedCommands", canonical: "blocked_commands" },
+    KeyAlias { alias: "defaultShell", canonical: "default_shell" },
+    KeyAlias { alias: "allowedDirectories", canonical: "allowed_directories" },
+    KeyAlias { alias: "deniedDirectories", canonical: "denied_directories" },
+    KeyAlias { alias: "fileReadLineLimit", canonical: "file_read_line_limit" },
+    KeyAlias { alias: "fileWriteLineLimit", canonical: "file_write_line_limit" },
+    KeyAlias { alias: "fuzzySearchThreshold", canonical: "fuzzy_search_threshold" },
+];
+
+/// Result of resolving a caller-supplied key against the alias table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResolvedKey<'a> {
+    /// Canonical key to read or write.
+    pub key: &'a str,
+    /// The deprecated alias the caller used, if any.
+    pub alias: Option<&'static str>,
+}
+
+impl ResolvedKey<'_> {
+    /// Human-readable deprecation warning when the caller used an alias.
+    #[must_use]
+    pub fn deprecation_warning(&self) -> Option<String> {
+        self.alias.map(|alias| {
+            format!(
+                "Config key '{}' is deprecated; use '{}' instead.",
+                alias, self.key
+            )
+        })
+    }
+}
+
+/// Resolve `key` to its canonical name, recording whether an alias was used.
+#[must_use]
+pub fn resolve_key(key: &str) -> ResolvedKey<'_> {
+    match KEY_ALIASES.iter().find(|a| a.alias == key) {
+        Some(alias) => ResolvedKey {
+            key: alias.canonical,
+            alias: Some(alias.alias),
+        },
+        None => ResolvedKey { key, alias: None },
+    }
+}
+
+// ============================================================================
+// EXTENSION KEYS
+// ============================================================================
+
+/// Prefix of namespaced extension keys, e.g. `ext.myplugin.max_retries`.
+pub const EXTENSION_PREFIX: &str = "ext.";
+
+/// A parsed `ext.<namespace>.<name>` key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ExtensionKey<'a> {
+    pub namespace: &'a str,
+    pub name: &'a str,
+}
+
+/// Parse `key` as an extension key.
+///
+/// Returns `Ok(None)` for keys without the `ext.` prefix and an error message
+/// for malformed extension keys.
+pub fn parse_extension_key(key: &str) -> Result<Option<ExtensionKey<'_>>, String> {
+    let Some(rest) = key.strip_prefix(EXTENSION_PREFIX) else {
+        return Ok(None);
+    };
+    let Some((namespace, name)) = rest.split_once('.') else {
+        return Err(format!(
+            "Extension key '{}' must have the form ext.<namespace>.<name>",
+            key
+        ));
+    };
+    for part in [namespace, name] {
+        let valid = !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return Err(format!(
+                "Invalid extension key '{}': namespace and name may only contain \
+                 letters, digits, '_' and '-'",
+                key
+            ));
+        }
+    }
+    Ok(Some(ExtensionKey { namespace, name }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_aliases_and_warns_about_them() {
+        let resolved = resolve_key("http_timeout");
+        assert_eq!(resolved.key, "http_connection_timeout_secs");
+        assert_eq!(resolved.alias, Some("http_timeout"));
+        let warning = resolved.deprecation_warning().unwrap();
+        assert!(
+            warning.contains("'http_timeout'")
+                && warning.contains("'http_connection_timeout_secs'")
+        );
+    }
+
+    #[test]
+    fn leaves_canonical_and_unknown_keys_alone() {
+        for key in ["default_shell", "tool_settings.search", "no_such_key", ""] {
+            let resolved = resolve_key(key);
+            assert_eq!(resolved, ResolvedKey { key, alias: None });
+            assert_eq!(resolved.deprecation_warning(), None);
+        }
+    }
+
+    #[test]
+    fn every_alias_targets_a_known_key() {
+        for alias in KEY_ALIASES {
+            assert!(
+                ConfigKey::parse(alias.canonical).is_ok(),
+                "{}",
+                alias.canonical
+            );
+            assert!(ConfigKey::parse(alias.alias).is_err(), "{}", alias.alias);
+        }
+    }
+
+    #[test]
+    fn parses_extension_keys() {
+        assert_eq!(parse_extension_key("default_shell"), Ok(None));
+        assert_eq!(
+            parse_extension_key("ext.my-plugin.max_retries"),
+            Ok(Some(ExtensionKey {
+                namespace: "my-plugin",
+                name: "max_retries"
+            }))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_extension_keys() {
+        for key in [
+            "ext.",
+            "ext.plugin",
+            "ext..name",
+            "ext.plugin.",
+            "ext.plug in.x",
+            "ext.a.b.c",
+        ] {
+            assert!(parse_extension_key(key).is_err(), "{} accepted", key);
+        }
+    }
+
+    #[test]
+    fn canonical_names_round_trip() {
+        for &key in ConfigKey::SERVER
+            .iter()
+            .chain(ConfigKey::EXTENDED)
+            .chain(ConfigKey::SECTIONS)
+        {
+            assert_eq!(ConfigKey::parse(key.as_str()), Ok(key));
+            assert_eq!(key.to_string(), key.as_str());
+        }
+        assert_eq!(
+            ConfigKey::parse("blockedCommands"),
+            Err(UnknownConfigKey("blockedCommands".to_string()))
+        );
+    }
+
+    #[test]
+    fn extended_keys_match_the_extended_config() {
+        let names: Vec<&str> = ConfigKey::EXTENDED.iter().map(|k| k.as_str()).collect();
+        assert_eq!(names, crate::extended_config::EXTENDED_KEYS);
+        assert!(ConfigKey::SERVER.iter().all(|k| k.is_server()));
+        assert!(!ConfigKey::EXTENDED.iter().any(|k| k.is_server()));
+    }
+
+    #[test]
+    fn parses_entry_keys() {
+        for (name, key) in [
+            ("tool_settings.search", ConfigKey::ToolCategory("search")),
+            (
+                "rate_limits.fs_read_file",
+                ConfigKey::RateLimit("fs_read_file"),
+            ),
+            ("directory_aliases.work", ConfigKey::DirectoryAlias("work")),
+            (
+                "ext.my-plugin.max_retries",
+                ConfigKey::Extension(ExtensionKey {
+                    namespace: "my-plugin",
+                    name: "max_retries",
+                }),
+            ),
+        ] {
+            assert_eq!(ConfigKey::parse(name), Ok(key));
+            assert_eq!(key.to_string(), name);
+            assert!(!key.is_server());
+        }
+        for name in [
+            "ext.plugin",
+            "shell_args.linux",
+            "no_such.key",
+            "secrets.token",
+        ] {
+            assert!(ConfigKey::parse(name).is_err(), "{} accepted", name);
+        }
+    }
+
+    #[test]
+    fn server_only_accepts_config_json_keys() {
+        assert_eq!(
+            ConfigKey::server("default_shell"),
+            Ok(ConfigKey::DefaultShell)
+        );
+        assert!(ConfigKey::server("log_level").is_err());
+        assert!(ConfigKey::server("tool_settings.search").is_err());
+    }
+}
@@ -1,8 +1,187 @@
+mod audit;
+mod auth;
+mod builder;
+#[cfg(feature = "object-store")]
+mod bucket_store;
+mod callbacks;
+mod client_history;
+mod command_policy;
+mod config_backups;
+mod config_convert;
+mod config_effective;
+mod config_value;
+mod confirmation;
+#[cfg(feature = "consul")]
+mod consul_store;
+mod diff;
+mod encryption;
+mod env_loader;
+mod expand;
+#[cfg(feature = "etcd")]
+mod etcd_store;
+mod extended_config;
+mod file_encoding;
+mod format;
 mod get_config;
+mod health;
+mod history;
+mod host_policy;
+mod integrity;
+mod journal;
+mod keys;
+mod list_config_keys;
+mod log_level;
+mod manager;
+mod metadata;
+mod normalize;
+mod otlp;
+mod path_policy;
+mod persistence;
+mod provenance;
+mod rate_limit;
+mod redo_config;
+#[cfg(feature = "redis")]
+mod redis_store;
+mod remote;
+mod saver;
+mod schema_version;
+mod secrets;
 mod set_config_value;
+mod set_secret;
+mod shell;
+#[cfg(feature = "sqlite")]
+mod sqlite_store;
+mod store;
+mod subprocess_env;
+mod system_diagnostics;
+mod system_info;
+mod system_metrics;
+mod undo_config;
+mod unset_config;
+mod validation;
+mod watcher;
 
+pub mod schema;
+
+pub use audit::{
+    AUDIT_LOG_PATH_KEY, AuditOperation, AuditRecord, AuditResult, append_record, read_records,
+};
+#[cfg(feature = "keyring")]
+pub use auth::AUTH_TOKEN_KEYRING_ENTRY;
+pub use auth::{AUTH_ERROR_CODE, AUTH_TOKEN_ENV, AuthError, AuthToken};
+#[cfg(feature = "object-store")]
+pub use bucket_store::{BucketStore, OBJECT_CACHE_FILE, OBJECT_URL_ENV};
+pub use builder::ConfigManagerBuilder;
+pub use callbacks::{CallbackId, ChangeCallback};
+pub use client_history::{
+    CLIENT_HISTORY_MAX_AGE_DAYS_KEY, CLIENT_HISTORY_MAX_ENTRIES_KEY,
+    DEFAULT_CLIENT_HISTORY_MAX_ENTRIES, prune_client_history,
+};
+pub use command_policy::{CommandDecision, GLOB_PREFIX, compile_pattern, evaluate_command};
+pub use config_backups::ConfigBackupsTool;
+pub use config_convert::ConfigConvertTool;
+pub use config_effective::ConfigEffectiveTool;
+pub use config_value::{ConfigValueError, ConfigValueExt, DisplayConfigValue};
+pub use confirmation::{CONFIRMATION_TTL, PendingConfirmations};
+#[cfg(feature = "consul")]
+pub use consul_store::{
+    CONSUL_ADDR_ENV, CONSUL_KEY_ENV, CONSUL_TOKEN_ENV, ConsulStore, DEFAULT_CONSUL_ADDR,
+};
+pub use diff::{ConfigDelta, diff_configs, diff_values};
+pub use encryption::{ENCRYPTION_KEY_ENV, EncryptionKey};
+pub use env_loader::{
+    CONFIG_JSON_ENV_SUFFIX, DEFAULT_ENV_PREFIX, EnvOverride, env_var_name, short_env_var_name,
+};
+#[cfg(feature = "etcd")]
+pub use etcd_store::{
+    DEFAULT_ETCD_KEY, ETCD_CACHE_FILE, ETCD_ENDPOINTS_ENV, ETCD_KEY_ENV, EtcdStore,
+};
+pub use expand::{
+    ALIAS_PREFIX, DIRECTORY_ALIASES_KEY, expand_alias, expand_directories,
+    expand_directories_with, expand_list, expand_path, needs_expansion, normalize_directory,
+};
+pub use extended_config::{
+    BLOCKED_COMMAND_PATTERNS_KEY, CACHE_DIRECTORY_KEY, COMMAND_TIMEOUT_SECS_KEY, ConfigView,
+    DEFAULT_COMMAND_TIMEOUT_SECS, DEFAULT_MAX_SEARCH_RESULTS, EXTENDED_KEYS, ExtendedConfig,
+    MAX_SEARCH_RESULTS_KEY, MAX_SEARCH_RESULTS_LIMIT, TELEMETRY_ENABLED_KEY, TEMP_DIRECTORY_KEY,
+    default_cache_directory, default_temp_directory,
+};
+pub use file_encoding::{DEFAULT_ENCODING_KEY, FileEncoding};
+pub use format::ConfigFormat;
 pub use get_config::GetConfigTool;
+pub use health::{HEALTH_ADDR_ENV, HealthReport, health_addr_from_env, serve_health};
+pub use history::{ConfigChange, DEFAULT_UNDO_LIMIT};
+pub use host_policy::{ALLOWED_HOSTS_KEY, DENIED_HOSTS_KEY, HostDecision, evaluate_host};
+pub use integrity::{
+    HMAC_KEY_ENV, IntegrityReport, IntegrityState, SIGNATURE_FILE, Signature, SignatureAlgorithm,
+};
+pub use journal::{JOURNAL_FILE, JOURNAL_MAX_ENTRIES, JournalEntry};
+pub use keys::{
+    ConfigKey, EXTENSION_PREFIX, ExtensionKey, KEY_ALIASES, KeyAlias, ResolvedKey,
+    UnknownConfigKey, parse_extension_key, resolve_key,
+};
+pub use list_config_keys::ListConfigKeysTool;
+pub use log_level::{LOG_LEVEL_KEY, LogLevel};
+pub use manager::{ConfigManager, UpstreamConfigManager};
+pub use metadata::{
+    KeyMetadata, RiskLevel, SecurityLevel, ValueRange, ValueType, all_key_metadata,
+    extended_key_metadata, is_sensitive, metadata_for, risk_level,
+};
+pub use normalize::parse_duration_ms;
+pub use otlp::OTLP_ENDPOINT_KEY;
+#[cfg(feature = "otlp")]
+pub use otlp::OtlpExporter;
+pub use path_policy::{FOLLOW_SYMLINKS_KEY, PathDecision, evaluate_path};
+pub use persistence::{
+    BackupFile, BackupInfo, CONFIG_PATH_ENV, ConflictRecord, DEFAULT_BACKUP_COUNT, Durability,
+    Histogram, LATENCY_BUCKETS_MS, PROFILES_DIR, PersistenceMetrics, PersistenceStatus,
+    RecoveryRecord, RetryPolicy, SAVE_ERROR_HISTORY, SIZE_BUCKETS_BYTES, SaveErrorRecord,
+    SavedFile, profile_config_path,
+};
+pub use provenance::{Provenance, ValueSource};
+pub use rate_limit::{RATE_LIMITS_KEY, RateDecision, RateLimiter};
+pub use redo_config::RedoConfigTool;
+#[cfg(feature = "redis")]
+pub use redis_store::{DEFAULT_REDIS_KEY, REDIS_KEY_ENV, REDIS_URL_ENV, RedisStore};
+pub use remote::{
+    DEFAULT_REMOTE_POLL_INTERVAL, REMOTE_CONFIG_URL_ENV, REMOTE_POLL_INTERVAL_ENV, RemoteSource,
+};
+pub use saver::{
+    DEFAULT_DEBOUNCE, DEFAULT_MAX_SAVE_DELAY, SAVER_SETTINGS_CATEGORY, SaverHandle, SaverSettings,
+};
+pub use schema_version::{SCHEMA_VERSION, SCHEMA_VERSION_KEY, SchemaTooNew, SchemaVersion};
+#[cfg(feature = "keyring")]
+pub use secrets::KEYRING_SERVICE;
+pub use secrets::{
+    REDACTED, SECRETS_FILE, SECRETS_KEY, SECRET_REVEAL_ENV, StoredSecret, redact,
+};
 pub use set_config_value::SetConfigValueTool;
+pub use set_secret::SetSecretTool;
+pub use shell::{
+    SHELL_ARGS_KEY, SHELL_ARGS_PLATFORMS, ShellInvocation, current_platform, default_shell_args,
+    platform_shell_args,
+};
+#[cfg(feature = "sqlite")]
+pub use sqlite_store::{SQLITE_CONFIG_FILE, SqliteStore};
+pub use store::{ConfigStore, FileStore, StoreFuture};
+pub use subprocess_env::{
+    DEFAULT_ENV_PASSTHROUGH, ENV_PASSTHROUGH_KEY, filter_env, is_passed_through,
+};
+pub use system_info::{
+    BatteryInfo, CpuInfo, CpuUsage, DEFAULT_CPU_SAMPLE_INTERVAL, DiskInfo, LocaleInfo,
+    MAX_CPU_SAMPLE_INTERVAL, MemoryBytes, NetworkInterface, OsRelease, ProcessInfo, ResourceLimit,
+    SYSTEM_INFO_SETTINGS_CATEGORY, SYSTEM_INFO_TTL, Shell, SystemInfoSettings, TOOLCHAIN_PROBES,
+    TemperatureSensor, Toolchain, UserInfo, battery_info, cached_cpu_info, cached_memory_bytes,
+    cached_os_release, cached_system_info, cpu_info, disks, installed_shells, locale_info,
+    memory_bytes, network_interfaces, os_release, process_info, resolve_shell, resource_limits,
+    sample_cpu_usage, temperature_sensors, toolchains, user_info,
+};
+pub use system_diagnostics::SystemDiagnosticsTool;
+pub use system_metrics::SystemMetricsTool;
+pub use undo_config::UndoConfigTool;
+pub use unset_config::UnsetConfigTool;
+pub use validation::{Rule, ValidationErrors, validate};
+pub use watcher::FILE_WATCH_DEBOUNCE;
 
-// Re-export ConfigManager and types from infrastructure crate
-pub use kodegen_config_manager::{ConfigManager, ConfigValue, ServerConfig, get_system_info};
+// Re-export config types from infrastructure crate
+pub use kodegen_config_manager::{ConfigValue, ServerConfig, SystemInfo, get_system_info};
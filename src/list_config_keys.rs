@@ -0,0 +1,122 @@
+use crate::ConfigManager;
+use crate::metadata::SecurityLevel;
+use crate::schema::{CONFIG_LIST_KEYS, ConfigListKeysArgs, ConfigListKeysPromptArgs};
+use kodegen_mcp_tool::Tool;
+use kodegen_mcp_tool::error::McpError;
+use rmcp::model::{Content, PromptArgument, PromptMessage, PromptMessageContent, PromptMessageRole};
+use serde_json::json;
+
+// ============================================================================
+// TOOL STRUCT
+// ============================================================================
+
+#[derive(Clone)]
+pub struct ListConfigKeysTool {
+    config_manager: ConfigManager,
+}
+
+impl ListConfigKeysTool {
+    #[must_use]
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self { config_manager }
+    }
+}
+
+// ============================================================================
+// TOOL IMPLEMENTATION
+// ============================================================================
+
+impl Tool for ListConfigKeysTool {
+    type Args = ConfigListKeysArgs;
+    type PromptArgs = ConfigListKeysPromptArgs;
+
+    fn name() -> &'static str {
+        CONFIG_LIST_KEYS
+    }
+
+    fn description() -> &'static str {
+        "List every configuration key accepted by config_set with its description, \
+         value type, valid range, security level, and risk level (safe, caution, or \
+         dangerous). Security-sensitive keys control what the server may execute or \
+         access; changes to dangerous keys should be approved by a human."
+    }
+
+    fn read_only() -> bool {
+        true
+    }
+
+    fn prompt_arguments() -> Vec<PromptArgument> {
+        vec![] // No arguments needed
+    }
+
+    #[tracing::instrument(name = "config.tool", skip_all, fields(tool = Self::name()), err)]
+    async fn execute(&self, _args: Self::Args) -> Result<Vec<Content>, McpError> {
+        let keys = self.config_manager.key_metadata();
+
+        let mut contents = Vec::new();
+
+        // ========================================
+        // Content[0]: Human-Readable Summary
+        // ========================================
+        let mut summary = format!("🔑 Configuration Keys ({})\n", keys.len());
+        for meta in &keys {
+            let range = match meta.range {
+                Some(range) => match range.max {
+                    Some(max) => format!(", {}-{}", range.min, max),
+                    None => format!(", >= {}", range.min),
+                },
+                None => String::new(),
+            };
+            let unit = meta.unit.map_or_else(String::new, |u| format!(" {}", u));
+            let security = match meta.security {
+                SecurityLevel::Sensitive => " 🔒",
+                SecurityLevel::Normal => "",
+            };
+            summary.push_str(&format!(
+                "\n• {}{} ({:?}{}{}, {})\n  {}",
+                meta.key,
+                security,
+                meta.value_type,
+                range,
+                unit,
+                meta.risk.as_str(),
+                meta.description
+            ));
+            if let Some(notes) = meta.notes {
+                summary.push_str(&format!("\n  {}", notes));
+            }
+        }
+        contents.push(Content::text(summary));
+
+        // ========================================
+        // Content[1]: Machine-Parseable JSON
+        // ========================================
+        let metadata = json!({
+            "success": true,
+            "keys": keys
+        });
+        let json_str = serde_json::to_string_pretty(&metadata)
+            .unwrap_or_else(|_| "{}".to_string());
+        contents.push(Content::text(json_str));
+
+        Ok(contents)
+    }
+
+    async fn prompt(&self, _args: Self::PromptArgs) -> Result<Vec<PromptMessage>, McpError> {
+        Ok(vec![
+            PromptMessage {
+                role: PromptMessageRole::User,
+                content: PromptMessageContent::text("Which configuration keys can I change?"),
+            },
+            PromptMessage {
+                role: PromptMessageRole::Assistant,
+                content: PromptMessageContent::text(
+                    "Use config_list_keys to see every key accepted by config_set, its type, \
+                     valid range, whether it is security-sensitive, and its risk level. Keys \
+                     marked 🔒 control what the server may execute or access; ask the user \
+                     before changing a key whose risk is dangerous.",
+                ),
+            },
+        ])
+    }
+}
@@ -0,0 +1,119 @@
+//! Runtime log level
+//!
+//! `log_level` is stored in the extended config and applied to the `log`
+//! facade's max level whenever it changes, so `config_set` can switch the
+//! server to debug logging without a restart. Hosts that filter through
+//! their own tracing subscriber can follow the key with
+//! `ConfigManager::on_change` and reload their filter.
+
+use crate::ConfigManager;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Key of the log level in the config.
+pub const LOG_LEVEL_KEY: &str = "log_level";
+
+/// Most verbose level logged; parsed case-insensitively.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", try_from = "String")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 6] = [
+        LogLevel::Off,
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Off => "off",
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        }
+    }
+
+    /// The equivalent `log` filter.
+    #[must_use]
+    pub const fn filter(self) -> log::LevelFilter {
+        match self {
+            LogLevel::Off => log::LevelFilter::Off,
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        let s = if s == "warning" { "warn" } else { s.as_str() };
+        LogLevel::ALL
+            .into_iter()
+            .find(|level| level.as_str() == s)
+            .ok_or_else(|| {
+                let levels: Vec<&str> = LogLevel::ALL.into_iter().map(LogLevel::as_str).collect();
+                format!("unknown log level '{}', expected one of {}", s, levels.join(", "))
+            })
+    }
+}
+
+impl TryFrom<String> for LogLevel {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// The `log` max level in force before any `log_level` was applied, restored
+/// when the key is cleared.
+static HOST_LEVEL: OnceLock<log::LevelFilter> = OnceLock::new();
+
+/// Apply `level`, or restore the host's level for `None`.
+fn apply(level: Option<LogLevel>) {
+    let host = *HOST_LEVEL.get_or_init(log::max_level);
+    let filter = level.map_or(host, LogLevel::filter);
+    if filter != log::max_level() {
+        log::set_max_level(filter);
+        log::info!("Log level set to {}", filter);
+    }
+}
+
+/// Apply the configured level now and after every change to it.
+pub(crate) fn follow(manager: &ConfigManager) {
+    apply(manager.with_extended_config(|c| c.log_level));
+    manager.on_change(LOG_LEVEL_KEY, |change| {
+        match serde_json::from_value::<Option<LogLevel>>(change.new.clone()) {
+            Ok(level) => apply(level),
+            Err(e) => log::warn!("Ignoring invalid {}: {}", LOG_LEVEL_KEY, e),
+        }
+    });
+}
@@ -1,34 +1,405 @@
-//! Config Category HTTP Server
+//! Config Category Server
 //!
-//! Serves configuration tools via HTTP/HTTPS transport using kodegen_server_http.
+//! Serves configuration tools over HTTP/HTTPS, or over stdio with `--stdio`.
+
+mod server;
 
 use anyhow::Result;
-use kodegen_server_http::{run_http_server, Managers, RouterSet, register_tool};
+use clap::Parser;
+use kodegen_server_http::register_tool;
+use kodegen_tools_config::LogLevel;
 use rmcp::handler::server::router::{prompt::PromptRouter, tool::ToolRouter};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::server::{ConfigServer, TlsFiles};
+
+/// Port served on without `--port`.
+const DEFAULT_PORT: u16 = 3100;
+
+/// Configuration MCP server.
+///
+/// Flags take precedence over the matching environment variables and the
+/// defaults.
+#[derive(Debug, Parser)]
+#[command(name = "kodegen-config", version)]
+struct Cli {
+    /// Address to serve MCP over HTTP on
+    #[arg(long, value_name = "IP", default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST))]
+    bind: IpAddr,
+
+    /// Port to serve MCP over HTTP on
+    #[arg(long, value_name = "PORT", default_value_t = DEFAULT_PORT)]
+    port: u16,
+
+    /// Address and port at once, e.g. 127.0.0.1:3100
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["bind", "port"])]
+    http: Option<SocketAddr>,
+
+    /// TLS certificate (PEM) for serving HTTPS
+    #[arg(long, value_name = "PATH", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// TLS private key (PEM) for serving HTTPS
+    #[arg(long, value_name = "PATH", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// Extended config file, instead of KODEGEN_CONFIG_PATH or
+    /// ~/.kodegen/config.tools.json
+    #[arg(long, value_name = "PATH", conflicts_with = "profile")]
+    config_path: Option<PathBuf>,
+
+    /// Keep the extended config in ~/.kodegen/profiles/<NAME>/
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Reject every config change
+    #[arg(long)]
+    read_only: bool,
+
+    /// Log level used while the log_level key is unset: off, error, warn,
+    /// info, debug, or trace
+    #[arg(long, value_name = "LEVEL")]
+    log_level: Option<LogLevel>,
+
+    /// Serve MCP over stdin/stdout instead of HTTP
+    #[arg(long, conflicts_with_all = ["bind", "port", "http", "tls_cert", "tls_key"])]
+    stdio: bool,
+}
+
+impl Cli {
+    /// Where to serve MCP over HTTP.
+    fn http_addr(&self) -> SocketAddr {
+        self.http.unwrap_or(SocketAddr::new(self.bind, self.port))
+    }
+
+    /// Certificate and key for HTTPS, if both were given.
+    fn tls(&self) -> Option<TlsFiles> {
+        Some(TlsFiles {
+            cert: self.tls_cert.clone()?,
+            key: self.tls_key.clone()?,
+        })
+    }
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    run_http_server("config", |config, _tracker| {
-        let config = config.clone();
-        Box::pin(async move {
-            let tool_router = ToolRouter::new();
-            let prompt_router = PromptRouter::new();
-            let managers = Managers::new();
-
-            // Register config tools
-            let (tool_router, prompt_router) = register_tool(
-                tool_router,
-                prompt_router,
-                kodegen_tools_config::GetConfigTool::new(config.clone()),
-            );
-
-            let (tool_router, prompt_router) = register_tool(
-                tool_router,
-                prompt_router,
-                kodegen_tools_config::SetConfigValueTool::new(config.clone()),
-            );
-
-            Ok(RouterSet::new(tool_router, prompt_router, managers))
-        })
-    }).await
+    let cli = Cli::parse();
+    let config_path = match (&cli.config_path, &cli.profile) {
+        (Some(path), _) => Some(path.clone()),
+        (None, Some(profile)) => Some(
+            kodegen_tools_config::profile_config_path(profile).map_err(anyhow::Error::msg)?,
+        ),
+        (None, None) => None,
+    };
+
+    // stdout may carry the protocol, so logs always go to stderr. Without
+    // RUST_LOG the logger passes everything and the `log` max level filters,
+    // so --log-level and the log_level key can change it
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace"))
+        .target(env_logger::Target::Stderr)
+        .init();
+    if std::env::var_os("RUST_LOG").is_none() || cli.log_level.is_some() {
+        log::set_max_level(cli.log_level.map_or(log::LevelFilter::Info, LogLevel::filter));
+    }
+
+    // Kept so pending saves can be flushed once the server stops
+    let shutdown = Shutdown::default();
+    let upstream = kodegen_tools_config::UpstreamConfigManager::new();
+    let config = open_config(upstream, &cli, config_path, &shutdown)?;
+    start_config(&config).await;
+    let (tool_router, prompt_router) = register_tools(&config);
+    let server = ConfigServer::new(tool_router, prompt_router, config);
+
+    let result = if cli.stdio {
+        log::info!("Serving config tools over stdio");
+        server.serve_stdio().await
+    } else {
+        server.serve_http(cli.http_addr(), cli.tls()).await
+    };
+
+    shutdown.flush().await?;
+    result
+}
+
+/// State flushed once the server stops.
+#[derive(Default)]
+struct Shutdown {
+    config: OnceLock<kodegen_tools_config::ConfigManager>,
+    #[cfg(feature = "otlp")]
+    exporter: OnceLock<kodegen_tools_config::OtlpExporter>,
+}
+
+impl Shutdown {
+    /// Save pending config changes, then send any buffered spans.
+    async fn flush(&self) -> Result<()> {
+        let flushed = match self.config.get() {
+            Some(config) => config
+                .shutdown()
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to flush config on shutdown: {}", e)),
+            None => Ok(()),
+        };
+        // After the final save, so its span is exported too
+        #[cfg(feature = "otlp")]
+        if let Some(exporter) = self.exporter.get() {
+            exporter.shutdown();
+        }
+        flushed
+    }
+}
+
+/// Build the config manager from the command line, and start exporting
+/// traces if `otlp_endpoint` is set.
+fn open_config(
+    upstream: kodegen_tools_config::UpstreamConfigManager,
+    cli: &Cli,
+    config_path: Option<PathBuf>,
+    shutdown: &Shutdown,
+) -> Result<kodegen_tools_config::ConfigManager> {
+    let config = build_config(upstream, config_path, cli.read_only)?;
+    let _ = shutdown.config.set(config.clone());
+    // Installed once; a changed otlp_endpoint applies on the next start
+    #[cfg(feature = "otlp")]
+    if let Some(endpoint) = config.otlp_endpoint() {
+        match kodegen_tools_config::OtlpExporter::install(&endpoint) {
+            Ok(exporter) => {
+                let _ = shutdown.exporter.set(exporter);
+            }
+            Err(e) => log::error!("Failed to export traces to {}: {}", endpoint, e),
+        }
+    }
+    Ok(config)
+}
+
+/// Initialize the config and start its background tasks.
+async fn start_config(config: &kodegen_tools_config::ConfigManager) {
+    // Probes start first so /readyz reports the server as not ready while
+    // the config initializes
+    if let Some(addr) = kodegen_tools_config::health_addr_from_env() {
+        let health_config = config.clone();
+        tokio::spawn(async move {
+            if let Err(e) = kodegen_tools_config::serve_health(health_config, addr).await {
+                log::error!("Health probes on {} stopped: {}", addr, e);
+            }
+        });
+    }
+    // Replays changes journaled but not saved before a crash
+    if let Err(e) = config.init().await {
+        log::error!("Failed to initialize config: {}", e);
+    }
+    config.start_background_saver();
+    // Picks up changes made by the other category servers
+    if let Err(e) = config.start_file_watcher() {
+        log::error!("Failed to watch config files: {}", e);
+    }
+    reload_on_signal(config.clone());
+}
+
+/// Routers for the config tools, for either transport.
+fn register_tools<S: Send + Sync + 'static>(
+    config: &kodegen_tools_config::ConfigManager,
+) -> (ToolRouter<S>, PromptRouter<S>) {
+    let tool_router = ToolRouter::new();
+    let prompt_router = PromptRouter::new();
+
+    // Register config tools
+    let (tool_router, prompt_router) = register_tool(
+        tool_router,
+        prompt_router,
+        kodegen_tools_config::GetConfigTool::new(config.clone()),
+    );
+
+    let (tool_router, prompt_router) = register_tool(
+        tool_router,
+        prompt_router,
+        kodegen_tools_config::SetConfigValueTool::new(config.clone()),
+    );
+
+    let (tool_router, prompt_router) = register_tool(
+        tool_router,
+        prompt_router,
+        kodegen_tools_config::UnsetConfigTool::new(config.clone()),
+    );
+
+    let (tool_router, prompt_router) = register_tool(
+        tool_router,
+        prompt_router,
+        kodegen_tools_config::SetSecretTool::new(config.clone()),
+    );
+
+    let (tool_router, prompt_router) = register_tool(
+        tool_router,
+        prompt_router,
+        kodegen_tools_config::UndoConfigTool::new(config.clone()),
+    );
+
+    let (tool_router, prompt_router) = register_tool(
+        tool_router,
+        prompt_router,
+        kodegen_tools_config::RedoConfigTool::new(config.clone()),
+    );
+
+    let (tool_router, prompt_router) = register_tool(
+        tool_router,
+        prompt_router,
+        kodegen_tools_config::ListConfigKeysTool::new(config.clone()),
+    );
+
+    let (tool_router, prompt_router) = register_tool(
+        tool_router,
+        prompt_router,
+        kodegen_tools_config::ConfigBackupsTool::new(config.clone()),
+    );
+
+    let (tool_router, prompt_router) = register_tool(
+        tool_router,
+        prompt_router,
+        kodegen_tools_config::ConfigConvertTool::new(config.clone()),
+    );
+
+    let (tool_router, prompt_router) = register_tool(
+        tool_router,
+        prompt_router,
+        kodegen_tools_config::ConfigEffectiveTool::new(config.clone()),
+    );
+
+    let (tool_router, prompt_router) = register_tool(
+        tool_router,
+        prompt_router,
+        kodegen_tools_config::SystemMetricsTool::new(),
+    );
+
+    let (tool_router, prompt_router) = register_tool(
+        tool_router,
+        prompt_router,
+        kodegen_tools_config::SystemDiagnosticsTool::new(config.clone()),
+    );
+
+    (tool_router, prompt_router)
+}
+
+/// Reload the config files whenever the process receives SIGHUP, as
+/// daemons conventionally do after their config is edited.
+#[cfg(unix)]
+fn reload_on_signal(config: kodegen_tools_config::ConfigManager) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            log::error!("Failed to listen for SIGHUP: {}", e);
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            log::info!("Received SIGHUP; reloading config");
+            if let Err(e) = config.reload().await {
+                log::error!("{}", e);
+            }
+        }
+    });
+}
+
+/// Reload the config files whenever the console sends Ctrl+Break, Windows'
+/// closest equivalent to SIGHUP.
+#[cfg(windows)]
+fn reload_on_signal(config: kodegen_tools_config::ConfigManager) {
+    let mut breaks = match tokio::signal::windows::ctrl_break() {
+        Ok(breaks) => breaks,
+        Err(e) => {
+            log::error!("Failed to listen for Ctrl+Break: {}", e);
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while breaks.recv().await.is_some() {
+            log::info!("Received Ctrl+Break; reloading config");
+            if let Err(e) = config.reload().await {
+                log::error!("{}", e);
+            }
+        }
+    });
+}
+
+/// Config manager for the server, sharing config through etcd when
+/// `KODEGEN_ETCD_ENDPOINTS` is set, Consul when `KODEGEN_CONSUL_KEY` is, an
+/// S3/GCS object when `KODEGEN_CONFIG_OBJECT_URL` is, or Redis when
+/// `KODEGEN_REDIS_URL` is; setting more than one is an error. `path`
+/// replaces the default extended config file.
+fn build_config(
+    upstream: kodegen_tools_config::UpstreamConfigManager,
+    path: Option<PathBuf>,
+    read_only: bool,
+) -> Result<kodegen_tools_config::ConfigManager> {
+    let builder = kodegen_tools_config::ConfigManager::builder(upstream).read_only(read_only);
+    let builder = match path {
+        Some(path) => builder.path(path),
+        None => builder,
+    };
+    #[cfg(feature = "etcd")]
+    let etcd = kodegen_tools_config::EtcdStore::from_env();
+    #[cfg(feature = "consul")]
+    let consul = kodegen_tools_config::ConsulStore::from_env();
+    #[cfg(feature = "object-store")]
+    let bucket = kodegen_tools_config::BucketStore::from_env();
+    #[cfg(feature = "redis")]
+    let redis = kodegen_tools_config::RedisStore::from_env();
+    // The manager keeps a single store, so a second one would silently
+    // replace the first
+    let configured: &[Option<&str>] = &[
+        #[cfg(feature = "etcd")]
+        etcd.is_some()
+            .then_some(kodegen_tools_config::ETCD_ENDPOINTS_ENV),
+        #[cfg(feature = "consul")]
+        consul
+            .is_some()
+            .then_some(kodegen_tools_config::CONSUL_KEY_ENV),
+        #[cfg(feature = "object-store")]
+        bucket
+            .is_some()
+            .then_some(kodegen_tools_config::OBJECT_URL_ENV),
+        #[cfg(feature = "redis")]
+        redis
+            .is_some()
+            .then_some(kodegen_tools_config::REDIS_URL_ENV),
+    ];
+    let configured: Vec<&str> = configured.iter().flatten().copied().collect();
+    if configured.len() > 1 {
+        anyhow::bail!(
+            "Only one remote config store can be used, but {} are all set",
+            configured.join(", ")
+        );
+    }
+    #[cfg(feature = "etcd")]
+    let builder = match etcd {
+        Some(store) => builder.store(store),
+        None => builder,
+    };
+    #[cfg(feature = "consul")]
+    let builder = match consul {
+        Some(store) => builder.store(store),
+        None => builder,
+    };
+    #[cfg(feature = "object-store")]
+    let builder = match bucket {
+        Some(Ok(store)) => builder.store(store),
+        Some(Err(e)) => {
+            log::error!("Ignoring {}: {}", kodegen_tools_config::OBJECT_URL_ENV, e);
+            builder
+        }
+        None => builder,
+    };
+    #[cfg(feature = "redis")]
+    let builder = match redis {
+        Some(Ok(store)) => builder.store(store),
+        Some(Err(e)) => {
+            log::error!("Ignoring {}: {}", kodegen_tools_config::REDIS_URL_ENV, e);
+            builder
+        }
+        None => builder,
+    };
+    Ok(builder.build())
 }
@@ -0,0 +1,2940 @@
+//! ConfigManager
+//!
+//! Wraps the infrastructure `kodegen_config_manager::ConfigManager` and layers
+//! this crate's `ExtendedConfig` on top of it. Cloning is cheap; all clones
+//! share the same state.
+//!
+//! Both configs are held in `ArcSwap` cells so hot-path reads (every command
+//! execution checks the blocked list) never take a lock or clone a `Vec`.
+//! Writers are serialized by `write_lock` and publish a fresh snapshot.
+
+use crate::audit::{AUDIT_LOG_PATH_KEY, AuditOperation, AuditRecord, AuditResult, append_record};
+use crate::auth::{AuthError, AuthToken};
+use crate::builder::ConfigManagerBuilder;
+use crate::callbacks::{CallbackId, CallbackRegistry, ChangeCallback};
+use crate::client_history::{
+    self, DEFAULT_CLIENT_HISTORY_MAX_ENTRIES, merge_client, prune_client_history,
+};
+use crate::command_policy::{CommandDecision, evaluate_command};
+use crate::confirmation::PendingConfirmations;
+use crate::diff::{ConfigDelta, diff_configs, diff_values};
+use crate::encryption::{self, EncryptionKey};
+use crate::expand::{self, ALIAS_PREFIX};
+use crate::env_loader::{EnvOverride, EnvOverrides};
+use crate::extended_config::{
+    ConfigView, DEFAULT_COMMAND_TIMEOUT_SECS, DEFAULT_MAX_SEARCH_RESULTS, ExtendedConfig,
+    TELEMETRY_ENABLED_KEY, default_cache_directory, default_temp_directory,
+};
+use crate::format::{self, ConfigFormat};
+use crate::history::{ConfigChange, UndoStack};
+use crate::host_policy::{HostDecision, evaluate_host};
+use crate::integrity::{self, IntegrityReport, IntegrityState, SIGNATURE_FILE, Signer};
+use crate::journal::{JOURNAL_FILE, Journal};
+use crate::keys::{ConfigKey, EXTENSION_PREFIX, ExtensionKey, parse_extension_key, resolve_key};
+use crate::log_level;
+use crate::metadata::{KeyMetadata, all_key_metadata};
+use crate::normalize::normalize;
+use crate::path_policy::{PathDecision, evaluate_path};
+use crate::persistence::{
+    self, BackupFile, BackupInfo, ConflictRecord, Durability, PersistenceMetrics,
+    PersistenceStatus, RetryPolicy, SaveErrorLog, SaveErrorRecord,
+};
+use crate::provenance::{Provenance, ValueSource};
+use crate::rate_limit::{RateDecision, RateLimiter};
+use crate::remote::RemoteSource;
+use crate::saver::{
+    CLIENT_HISTORY_SYNC_INTERVAL, SAVER_SETTINGS_CATEGORY, SaveSignal, SaverHandle,
+    SaverSettings,
+};
+use crate::secrets::{self, REDACTED, SECRETS_FILE, SECRETS_KEY, StoredSecret};
+use crate::shell::{ShellInvocation, platform_shell_args};
+use crate::store::ConfigStore;
+use crate::subprocess_env::{DEFAULT_ENV_PASSTHROUGH, filter_env};
+use crate::validation::validate;
+use crate::watcher;
+use crate::{ConfigValue, ConfigValueExt, ServerConfig};
+use arc_swap::ArcSwap;
+use kodegen_mcp_tool::error::McpError;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// The upstream manager owning `ServerConfig` and `config.json`.
+pub type UpstreamConfigManager = kodegen_config_manager::ConfigManager;
+
+// ============================================================================
+// MANAGER STRUCT
+// ============================================================================
+
+#[derive(Clone)]
+pub struct ConfigManager {
+    upstream: UpstreamConfigManager,
+    server: Arc<ArcSwap<ServerConfig>>,
+    extended: Arc<ArcSwap<ExtendedConfig>>,
+    /// Swapped by `convert()` when the file moves to another format.
+    extended_path: Arc<ArcSwap<PathBuf>>,
+    /// Replaces the extended config file when set.
+    store: Option<Arc<dyn ConfigStore>>,
+    /// Environment values layered over `config.json`.
+    env_overrides: Arc<EnvOverrides>,
+    /// Change journal; unused with a `store`, which keeps its own history.
+    journal: Option<Arc<Journal>>,
+    /// Polled for centrally managed values after `init()`.
+    remote: Option<Arc<RemoteSource>>,
+    write_lock: Arc<Mutex<()>>,
+    read_only: bool,
+    backup_count: usize,
+    durability: Durability,
+    encryption_key: Option<Arc<EncryptionKey>>,
+    retry_policy: RetryPolicy,
+    debounce: Duration,
+    max_save_delay: Duration,
+    saver: Arc<SaveSignal>,
+    saver_handle: Arc<Mutex<Option<SaverHandle>>>,
+    /// Dropped by `shutdown()` to stop following other processes' writes.
+    file_watcher: Arc<Mutex<Option<notify::RecommendedWatcher>>>,
+    /// Clients that have connected; the only persisted copy of the history.
+    clients: Arc<Mutex<Vec<serde_json::Value>>>,
+    /// Hash of the client history as last written by `sync_client_history`.
+    client_history_hash: Arc<Mutex<Option<u64>>>,
+    signer: Arc<Signer>,
+    defaults: Arc<ServerConfig>,
+    extended_defaults: Arc<ExtendedConfig>,
+    fresh_install: bool,
+    persistence_status: Arc<Mutex<PersistenceStatus>>,
+    save_lock: Arc<tokio::sync::Mutex<()>>,
+    /// Content hash of the extended config file as we last read or wrote it.
+    file_hash: Arc<Mutex<Option<u64>>>,
+    /// `persistence::config_hash` of the extended config as last loaded or saved.
+    saved_hash: Arc<Mutex<Option<u64>>>,
+    save_errors: Arc<Mutex<SaveErrorLog>>,
+    metrics: Arc<Mutex<PersistenceMetrics>>,
+    history: Arc<Mutex<UndoStack>>,
+    callbacks: Arc<Mutex<CallbackRegistry>>,
+    /// Keys changed at runtime or by the remote source since startup.
+    sources: Arc<Mutex<HashMap<String, ValueSource>>>,
+    rate_limiter: Arc<RateLimiter>,
+    /// Whether `reveal_secrets` may return plaintext.
+    allow_secret_reveal: bool,
+    /// Required by tools that change the config; `None` leaves them open.
+    auth_token: Option<Arc<AuthToken>>,
+    /// Tokens issued for sensitive changes awaiting confirmation.
+    confirmations: Arc<PendingConfirmations>,
+    /// Contents of `secrets.json`; values are never held in plaintext.
+    secrets: Arc<Mutex<BTreeMap<String, StoredSecret>>>,
+    /// Why `secrets.json` couldn't be loaded; secret changes are refused
+    /// while set, so the stored secrets aren't overwritten.
+    secrets_error: Arc<Mutex<Option<String>>>,
+    /// Set once `init` has finished.
+    initialized: Arc<AtomicBool>,
+}
+
+impl ConfigManager {
+    /// Wrap `upstream` with default options.
+    #[must_use]
+    pub fn new(upstream: UpstreamConfigManager) -> Self {
+        Self::builder(upstream).build()
+    }
+
+    /// Wrap `upstream`, loading the extended config from `path`.
+    #[must_use]
+    pub fn with_extended_path(upstream: UpstreamConfigManager, path: PathBuf) -> Self {
+        Self::builder(upstream).path(path).build()
+    }
+
+    /// Start configuring a manager around `upstream`.
+    pub fn builder(upstream: UpstreamConfigManager) -> ConfigManagerBuilder {
+        ConfigManagerBuilder::new(upstream)
+    }
+
+    pub(crate) fn from_builder(builder: ConfigManagerBuilder) -> Self {
+        let manager = Self::load(builder);
+        // Trust the settings found on first start, so later edits are detected
+        if manager.verify_integrity().state == IntegrityState::Unsigned && !manager.read_only {
+            manager.sign();
+        }
+        log_level::follow(&manager);
+        manager
+    }
+
+    fn load(builder: ConfigManagerBuilder) -> Self {
+        // A store is loaded asynchronously by init()
+        let (extended, load_recovery, schema_error) = match &builder.store {
+            Some(_) => (builder.extended_defaults.clone(), None, None),
+            None => match persistence::load_from_disk(
+                &builder.extended_path,
+                &builder.extended_defaults,
+                builder.encryption_key.as_ref(),
+                builder.backup_count,
+            ) {
+                Ok((extended, load_recovery)) => (extended, load_recovery, None),
+                // Saving over the newer file would drop what this build can't read
+                Err(e) => {
+                    log::error!(
+                        "Refusing to load {}: {}. Running read-only on defaults.",
+                        builder.extended_path.display(),
+                        e
+                    );
+                    (builder.extended_defaults.clone(), None, Some(e))
+                }
+            },
+        };
+        let file_hash = match &builder.store {
+            Some(_) => None,
+            None => persistence::file_hash(&builder.extended_path),
+        };
+        let saved_hash = Some(persistence::config_hash(&extended));
+        let env_overrides = EnvOverrides::from_env(&builder.env_prefix);
+        let server = env_overrides.apply(builder.upstream.get_config());
+        // Only custom defaults need applying; upstream already starts from its own
+        let fresh_install =
+            builder.defaults.is_some() && !persistence::upstream_config_path().exists();
+        let secrets_path = builder.extended_path.with_file_name(SECRETS_FILE);
+        let (secrets, secrets_error) = match secrets::load(&secrets_path) {
+            Ok(secrets) => (secrets, None),
+            Err(e) => {
+                log::error!(
+                    "Failed to read {}: {}; secret changes are refused until it is readable",
+                    secrets_path.display(),
+                    e
+                );
+                (BTreeMap::new(), Some(e.to_string()))
+            }
+        };
+        let clients = initial_client_history(&builder, &server);
+        let journal = (builder.journal && builder.store.is_none()).then(|| {
+            Arc::new(Journal::open(
+                builder.extended_path.with_file_name(JOURNAL_FILE),
+                builder.encryption_key.clone(),
+            ))
+        });
+        Self {
+            upstream: builder.upstream,
+            server: Arc::new(ArcSwap::from_pointee(server)),
+            extended: Arc::new(ArcSwap::from_pointee(extended)),
+            extended_path: Arc::new(ArcSwap::from_pointee(builder.extended_path)),
+            store: builder.store,
+            env_overrides: Arc::new(env_overrides),
+            journal,
+            remote: builder.remote.map(Arc::new),
+            write_lock: Arc::new(Mutex::new(())),
+            read_only: builder.read_only || schema_error.is_some(),
+            backup_count: builder.backup_count,
+            durability: builder.durability,
+            encryption_key: builder.encryption_key.map(Arc::new),
+            retry_policy: builder.retry_policy,
+            debounce: builder.debounce,
+            max_save_delay: builder.max_save_delay,
+            saver: Arc::new(SaveSignal::default()),
+            saver_handle: Arc::new(Mutex::new(None)),
+            file_watcher: Arc::new(Mutex::new(None)),
+            clients: Arc::new(Mutex::new(clients)),
+            client_history_hash: Arc::new(Mutex::new(None)),
+            signer: Arc::new(Signer::from_env()),
+            defaults: Arc::new(builder.defaults.unwrap_or_default()),
+            extended_defaults: Arc::new(builder.extended_defaults),
+            fresh_install,
+            persistence_status: Arc::new(Mutex::new(PersistenceStatus {
+                load_recovery,
+                schema_error,
+                ..PersistenceStatus::default()
+            })),
+            save_lock: Arc::new(tokio::sync::Mutex::new(())),
+            file_hash: Arc::new(Mutex::new(file_hash)),
+            saved_hash: Arc::new(Mutex::new(saved_hash)),
+            save_errors: Arc::new(Mutex::new(SaveErrorLog::default())),
+            metrics: Arc::new(Mutex::new(PersistenceMetrics::default())),
+            history: Arc::new(Mutex::new(UndoStack::new(builder.undo_limit))),
+            callbacks: Arc::new(Mutex::new(CallbackRegistry::default())),
+            sources: Arc::new(Mutex::new(HashMap::new())),
+            rate_limiter: Arc::new(RateLimiter::default()),
+            allow_secret_reveal: builder.allow_secret_reveal,
+            auth_token: builder.auth_token.map(Arc::new),
+            confirmations: Arc::new(PendingConfirmations::default()),
+            secrets: Arc::new(Mutex::new(secrets)),
+            secrets_error: Arc::new(Mutex::new(secrets_error)),
+            initialized: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Keys overridden by environment variables.
+    #[must_use]
+    pub fn env_overrides(&self) -> &[EnvOverride] {
+        self.env_overrides.all()
+    }
+
+    /// The wrapped infrastructure manager.
+    #[must_use]
+    pub fn upstream(&self) -> &UpstreamConfigManager {
+        &self.upstream
+    }
+
+    /// Whether mutations are rejected.
+    #[must_use]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Whether tools that change the config require an auth token.
+    #[must_use]
+    pub fn requires_auth(&self) -> bool {
+        self.auth_token.is_some()
+    }
+
+    /// Check the `auth_token` a config-changing tool was called with.
+    /// Always succeeds when no token is configured.
+    pub fn authorize(&self, token: Option<&str>) -> Result<(), AuthError> {
+        match &self.auth_token {
+            Some(expected) => expected.verify(token),
+            None => Ok(()),
+        }
+    }
+
+    // ========================================================================
+    // READ ACCESS
+    // ========================================================================
+
+    /// Deep copy of the upstream config snapshot.
+    ///
+    /// Kept for compatibility; prefer [`Self::get_config_snapshot`].
+    #[must_use]
+    pub fn get_config(&self) -> ServerConfig {
+        ServerConfig::clone(&self.server.load())
+    }
+
+    /// Shared snapshot of the upstream config as of the last write or refresh.
+    #[must_use]
+    pub fn get_config_snapshot(&self) -> Arc<ServerConfig> {
+        self.server.load_full()
+    }
+
+    /// Re-read the upstream config and publish it as the new snapshot.
+    ///
+    /// Every write and reload calls this; reads only load the snapshot.
+    pub fn refresh_snapshot(&self) -> Arc<ServerConfig> {
+        let snapshot = Arc::new(self.env_overrides.apply(self.upstream.get_config()));
+        self.server.store(Arc::clone(&snapshot));
+        snapshot
+    }
+
+    #[must_use]
+    pub fn get_extended_config(&self) -> ExtendedConfig {
+        ExtendedConfig::clone(&self.extended.load())
+    }
+
+    /// Shared snapshot of the extended config.
+    #[must_use]
+    pub fn get_extended_snapshot(&self) -> Arc<ExtendedConfig> {
+        self.extended.load_full()
+    }
+
+    /// Full configuration (upstream and extended) as a single serializable view.
+    #[must_use]
+    pub fn get_config_view(&self) -> ConfigView {
+        ConfigView {
+            server: self.get_config_snapshot(),
+            extended: self.get_extended_snapshot(),
+        }
+    }
+
+    /// Run `f` against the cached `ServerConfig` snapshot without locking or cloning.
+    pub fn with_config<R>(&self, f: impl FnOnce(&ServerConfig) -> R) -> R {
+        f(&self.server.load())
+    }
+
+    /// Run `f` against the current `ExtendedConfig` without locking or cloning.
+    pub fn with_extended_config<R>(&self, f: impl FnOnce(&ExtendedConfig) -> R) -> R {
+        f(&self.extended.load())
+    }
+
+    /// Current effective value of `key` as JSON, or `None` if the key is unknown.
+    ///
+    /// Nested keys are addressed with dots, e.g. `tool_settings.terminal` or
+    /// `ext.myplugin.max_retries`. Deprecated aliases read their canonical key.
+    #[must_use]
+    pub fn get_value(&self, key: &str) -> Option<serde_json::Value> {
+        let key = resolve_key(key).key;
+        let (config_key, path) = match ConfigKey::parse(key) {
+            Ok(config_key) => (config_key, None),
+            // A path into a value, such as `shell_args.linux`
+            Err(_) => {
+                let (top, path) = key.split_once('.')?;
+                (ConfigKey::parse(top).ok()?, Some(path))
+            }
+        };
+        let value = if config_key.is_server() {
+            self.with_config(|c| server_value(c, config_key))?
+        } else {
+            self.with_extended_config(|c| c.get_key(config_key))?
+        };
+        match path {
+            Some(path) => path
+                .split('.')
+                .try_fold(&value, |current, part| current.get(part))
+                .cloned(),
+            None => Some(value),
+        }
+    }
+
+    /// Description, type, range, and security level of every known key.
+    #[must_use]
+    pub fn key_metadata(&self) -> Vec<KeyMetadata> {
+        all_key_metadata()
+    }
+
+    /// Differences between the current config and `other`, with `old` taken
+    /// from the current config.
+    #[must_use]
+    pub fn diff(&self, other: &ServerConfig) -> Vec<ConfigDelta> {
+        diff_configs(&self.get_config_snapshot(), other)
+    }
+
+    /// Whether `command` appears in `blocked_commands`. Use
+    /// [`Self::check_command`] for full command lines.
+    #[must_use]
+    pub fn is_command_blocked(&self, command: &str) -> bool {
+        self.with_config(|c| c.blocked_commands.iter().any(|b| b == command))
+    }
+
+    /// Check a full command line against `blocked_commands`, matched by the
+    /// name of each chained command, and `blocked_command_patterns`.
+    #[must_use]
+    pub fn check_command(&self, cmdline: &str) -> CommandDecision {
+        let config = self.get_config_snapshot();
+        let patterns = self.with_extended_config(|c| c.blocked_command_patterns.clone());
+        evaluate_command(cmdline, &config.blocked_commands, &patterns)
+    }
+
+    /// How to run a command line: `default_shell` with this platform's
+    /// `shell_args`, or the shell's default arguments while unset.
+    #[must_use]
+    pub fn shell_invocation(&self) -> ShellInvocation {
+        let shell = self.get_config_snapshot().default_shell.clone();
+        let args = self.with_extended_config(|c| platform_shell_args(&c.shell_args).cloned());
+        ShellInvocation::new(&shell, args)
+    }
+
+    /// Timeout for a command run without one of its own: `command_timeout_secs`,
+    /// or `DEFAULT_COMMAND_TIMEOUT_SECS` while unset. A timeout passed to a
+    /// single call takes precedence for that call.
+    #[must_use]
+    pub fn command_timeout(&self) -> Duration {
+        let secs = self.with_extended_config(|c| c.command_timeout_secs);
+        Duration::from_secs(secs.unwrap_or(DEFAULT_COMMAND_TIMEOUT_SECS))
+    }
+
+    /// Most results a search tool may return per call: `max_search_results`,
+    /// or `DEFAULT_MAX_SEARCH_RESULTS` while unset.
+    #[must_use]
+    pub fn max_search_results(&self) -> usize {
+        let max = self.with_extended_config(|c| c.max_search_results);
+        max.unwrap_or(DEFAULT_MAX_SEARCH_RESULTS) as usize
+    }
+
+    /// Directory for scratch files: `temp_directory`, or
+    /// `default_temp_directory()` while unset. Created if missing.
+    #[must_use]
+    pub fn temp_directory(&self) -> PathBuf {
+        let dir = self.with_extended_config(|c| c.temp_directory.clone());
+        ensure_directory(dir.unwrap_or_else(default_temp_directory))
+    }
+
+    /// Directory for cached data: `cache_directory`, or
+    /// `default_cache_directory()` while unset. Created if missing.
+    #[must_use]
+    pub fn cache_directory(&self) -> PathBuf {
+        let dir = self.with_extended_config(|c| c.cache_directory.clone());
+        ensure_directory(dir.unwrap_or_else(default_cache_directory))
+    }
+
+    /// Variables forwarded to spawned commands: `env_passthrough`, or
+    /// `DEFAULT_ENV_PASSTHROUGH` while unset.
+    #[must_use]
+    pub fn env_passthrough(&self) -> Vec<String> {
+        self.with_extended_config(|c| c.env_passthrough.clone())
+            .unwrap_or_else(|| DEFAULT_ENV_PASSTHROUGH.iter().map(|s| s.to_string()).collect())
+    }
+
+    /// The environment to spawn commands with: this process's variables
+    /// allowed by `env_passthrough`, sorted by name. Clear the command's
+    /// environment before applying it.
+    #[must_use]
+    pub fn sanitized_env(&self) -> Vec<(String, String)> {
+        filter_env(&self.env_passthrough())
+    }
+
+    /// Encoding for file tools to use when a call doesn't name one:
+    /// `default_encoding`, or UTF-8 while unset.
+    #[must_use]
+    pub fn default_encoding(&self) -> FileEncoding {
+        self.with_extended_config(|c| c.default_encoding).unwrap_or_default()
+    }
+
+    /// Requests per minute `tool` may serve, or `None` if it is unlimited.
+    #[must_use]
+    pub fn rate_limit_for(&self, tool: &str) -> Option<u32> {
+        self.with_extended_config(|c| c.rate_limits.get(tool).copied())
+    }
+
+    /// Count a call to `tool` against its rate limit, failing once it has
+    /// served its limit within the last minute. Call it at the start of
+    /// every execution of a limited tool.
+    pub fn check_rate_limit(&self, tool: &str) -> Result<(), McpError> {
+        match self.rate_limiter.check(tool, self.rate_limit_for(tool)) {
+            RateDecision::Allowed => Ok(()),
+            limited => Err(McpError::PermissionDenied(format!("{}: {}", tool, limited))),
+        }
+    }
+
+    /// Issue a single-use token that lets `config_set` change the sensitive
+    /// `key` to `value`; it expires after `CONFIRMATION_TTL`.
+    #[must_use]
+    pub fn request_confirmation(&self, key: &str, value: &serde_json::Value) -> String {
+        self.confirmations.issue(key, value)
+    }
+
+    /// Spend a token from [`Self::request_confirmation`], failing unless it
+    /// was issued for setting `key` to `value` and hasn't expired.
+    pub fn confirm_change(
+        &self,
+        token: &str,
+        key: &str,
+        value: &serde_json::Value,
+    ) -> Result<(), McpError> {
+        self.confirmations
+            .redeem(token, key, value)
+            .map_err(|e| McpError::PermissionDenied(format!("{}: {}", key, e)))
+    }
+
+    /// Whether the user opted in to usage reporting with `telemetry_enabled`.
+    /// Collectors should also follow [`Self::on_telemetry_change`].
+    #[must_use]
+    pub fn is_telemetry_enabled(&self) -> bool {
+        self.with_extended_config(|c| c.telemetry_enabled)
+    }
+
+    /// Collector config spans are exported to, from `otlp_endpoint`.
+    #[must_use]
+    pub fn otlp_endpoint(&self) -> Option<String> {
+        self.with_extended_config(|c| c.otlp_endpoint.clone())
+    }
+
+    /// Whether network tools may connect to `host`, which may also be given
+    /// as `host:port` or a URL: `denied_hosts` is checked first, then
+    /// `allowed_hosts`.
+    #[must_use]
+    pub fn is_host_allowed(&self, host: &str) -> HostDecision {
+        self.with_extended_config(|c| evaluate_host(host, &c.allowed_hosts, &c.denied_hosts))
+    }
+
+    /// Whether tools may access `path`: `denied_directories` is checked
+    /// first, then `allowed_directories`, after resolving symlinks. Symlinks
+    /// out of the allowed directories are followed only with `follow_symlinks`.
+    /// A leading `@alias` is expanded first; an unknown alias is left as is.
+    #[must_use]
+    pub fn is_path_allowed(&self, path: &Path) -> PathDecision {
+        let config = self.get_config_snapshot();
+        let follow_symlinks = self.with_extended_config(|c| c.follow_symlinks);
+        let path = self.resolve_alias(path).unwrap_or_else(|_| path.to_path_buf());
+        evaluate_path(
+            &path,
+            &config.allowed_directories,
+            &config.denied_directories,
+            follow_symlinks,
+        )
+    }
+
+    /// `path` with a leading `@alias` replaced by its directory from
+    /// `directory_aliases`; other paths are returned unchanged.
+    pub fn resolve_alias(&self, path: &Path) -> Result<PathBuf, McpError> {
+        let Some(entry) = path.to_str().filter(|p| p.starts_with(ALIAS_PREFIX)) else {
+            return Ok(path.to_path_buf());
+        };
+        self.with_extended_config(|c| expand::expand_alias(entry, &c.directory_aliases))
+            .map(PathBuf::from)
+            .map_err(McpError::InvalidArguments)
+    }
+
+    /// Every directory alias and the directory it stands for.
+    #[must_use]
+    pub fn directory_aliases(&self) -> BTreeMap<String, String> {
+        self.with_extended_config(|c| c.directory_aliases.clone())
+    }
+
+    // ========================================================================
+    // PROVENANCE
+    // ========================================================================
+
+    /// The effective value of every key and where it came from: the server
+    /// keys, then each tool settings category and extension value.
+    #[must_use]
+    pub fn provenance(&self) -> Vec<Provenance> {
+        let current = serde_json::to_value(self.get_config_view()).unwrap_or_default();
+        let defaults = serde_json::to_value(ConfigView {
+            server: Arc::clone(&self.defaults),
+            extended: Arc::clone(&self.extended_defaults),
+        })
+        .unwrap_or_default();
+        let sources = self.sources.lock().clone();
+
+        let mut extended: Vec<String> = self.with_extended_config(|c| {
+            let categories = c
+                .tool_settings
+                .keys()
+                .map(|cat| ConfigKey::ToolCategory(cat));
+            let limits = c.rate_limits.keys().map(|tool| ConfigKey::RateLimit(tool));
+            let aliases = c
+                .directory_aliases
+                .keys()
+                .map(|name| ConfigKey::DirectoryAlias(name));
+            let extensions = c.extensions.iter().flat_map(|(namespace, values)| {
+                values
+                    .keys()
+                    .map(move |name| ConfigKey::Extension(ExtensionKey { namespace, name }))
+            });
+            categories
+                .chain(limits)
+                .chain(aliases)
+                .chain(extensions)
+                .map(|key| key.to_string())
+                .collect()
+        });
+        extended.sort();
+        let keys = ConfigKey::SERVER
+            .iter()
+            .chain(ConfigKey::EXTENDED)
+            .map(|key| key.to_string())
+            .chain(extended);
+
+        keys.map(|key| {
+            let value = view_value(&current, &key);
+            let (source, origin) =
+                self.value_source(&key, &value, &view_value(&defaults, &key), &sources);
+            Provenance {
+                key,
+                value,
+                source,
+                origin,
+            }
+        })
+        .collect()
+    }
+
+    fn value_source(
+        &self,
+        key: &str,
+        value: &serde_json::Value,
+        default: &serde_json::Value,
+        sources: &HashMap<String, ValueSource>,
+    ) -> (ValueSource, Option<String>) {
+        if let Ok(config_key) = ConfigKey::server(key)
+            && let Some(var) = self.env_overrides.var_for(config_key)
+        {
+            return (ValueSource::Env, Some(var));
+        }
+        // Whole sections are replaced by reset() and undo
+        let section = if key.starts_with(EXTENSION_PREFIX) {
+            "extensions"
+        } else {
+            key.split('.').next().unwrap_or(key)
+        };
+        match sources.get(key).or_else(|| sources.get(section)) {
+            Some(ValueSource::Runtime) => (ValueSource::Runtime, None),
+            Some(ValueSource::Remote) => (
+                ValueSource::Remote,
+                self.remote.as_ref().map(|remote| remote.url().to_string()),
+            ),
+            _ if value == default => (ValueSource::Default, None),
+            _ if !is_extended_key(key) => (
+                ValueSource::File,
+                Some(persistence::upstream_config_path().display().to_string()),
+            ),
+            _ => (
+                ValueSource::File,
+                Some(match &self.store {
+                    Some(store) => store.location(),
+                    None => self.extended_path.load().display().to_string(),
+                }),
+            ),
+        }
+    }
+
+    /// Record that `key` was last changed by `source`; `File` forgets the
+    /// key so it is judged against its default again.
+    fn record_source(&self, key: &str, source: ValueSource) {
+        let mut sources = self.sources.lock();
+        match source {
+            ValueSource::File => {
+                sources.remove(key);
+            }
+            _ => {
+                sources.insert(key.to_string(), source);
+            }
+        }
+    }
+
+    // ========================================================================
+    // WRITE ACCESS
+    // ========================================================================
+
+    /// Set `key`, which may be a deprecated alias, to `value`.
+    pub async fn set_value(&self, key: &str, value: ConfigValue) -> Result<(), McpError> {
+        let key = canonical_key(key);
+        self.set_value_as(key, value.to_json(), AuditOperation::Set, None).await
+    }
+
+    /// Set `key` from JSON. Unlike [`Self::set_value`] this also takes
+    /// objects and nested values, for structured keys such as
+    /// `tool_settings.<category>`, `rate_limits`, `directory_aliases`,
+    /// `shell_args`, and `ext.*`.
+    ///
+    /// `null` unsets a `config.json` key as [`Self::unset`] does; for the
+    /// other keys it clears the value or removes the map entry.
+    pub async fn set_json_value(
+        &self,
+        key: &str,
+        value: serde_json::Value,
+    ) -> Result<(), McpError> {
+        self.set_json_value_with_reason(key, value, None).await
+    }
+
+    /// [`Self::set_json_value`], recording `reason` with the change in the
+    /// change history, the journal, and the audit log.
+    pub async fn set_json_value_with_reason(
+        &self,
+        key: &str,
+        value: serde_json::Value,
+        reason: Option<String>,
+    ) -> Result<(), McpError> {
+        let key = canonical_key(key);
+        if value.is_null() && ConfigKey::server(key).is_ok() {
+            return self.unset(key, reason).await;
+        }
+        self.set_value_as(key, value, AuditOperation::Set, reason).await
+    }
+
+    /// Revert `key` to its default, recording `reason` with the change.
+    ///
+    /// Unlike setting an empty array, the key then reports `default` as its
+    /// source. A map entry such as `rate_limits.<tool>` or an `ext.*` value
+    /// is removed unless the defaults define it.
+    pub async fn unset(&self, key: &str, reason: Option<String>) -> Result<(), McpError> {
+        let key = canonical_key(key);
+        self.ensure_writable()?;
+        let default = self.default_json(key)?;
+        self.set_value_as(key, default, AuditOperation::Reset, reason).await?;
+        self.record_source(key, ValueSource::Default);
+        Ok(())
+    }
+
+    /// Default value of `key` as JSON; `null` for a map entry without one.
+    fn default_json(&self, key: &str) -> Result<serde_json::Value, McpError> {
+        let config_key = parse_key(key)?;
+        if !config_key.is_server() {
+            let defaults = serde_json::to_value(&*self.extended_defaults)
+                .map_err(|e| McpError::Other(e.into()))?;
+            return Ok(view_value(&defaults, key));
+        }
+        let defaults =
+            serde_json::to_value(&*self.defaults).map_err(|e| McpError::Other(e.into()))?;
+        let default = defaults
+            .get(config_key.as_str())
+            .cloned()
+            .unwrap_or_default();
+        Ok(config_value_from_json(config_key, &default)?.to_json())
+    }
+
+    /// `set_json_value`, audited as `operation`.
+    #[tracing::instrument(
+        name = "config.set",
+        skip_all,
+        fields(key = key, operation = operation.as_str()),
+        err
+    )]
+    async fn set_value_as(
+        &self,
+        key: &str,
+        value: serde_json::Value,
+        operation: AuditOperation,
+        reason: Option<String>,
+    ) -> Result<(), McpError> {
+        let result = self.try_set_value(key, &value, operation, reason.clone()).await;
+        if let Err(e) = &result {
+            self.audit_failure(operation, key, value, reason, e);
+        }
+        result
+    }
+
+    async fn try_set_value(
+        &self,
+        key: &str,
+        value: &serde_json::Value,
+        operation: AuditOperation,
+        reason: Option<String>,
+    ) -> Result<(), McpError> {
+        self.ensure_writable()?;
+        let old = self.get_value(key).unwrap_or(serde_json::Value::Null);
+        if key == SECRETS_KEY || key.starts_with("secrets.") {
+            return Err(McpError::InvalidArguments(
+                "Secrets can't be set with config_set; use config_set_secret".to_string(),
+            ));
+        }
+        let config_key = parse_key(key)?;
+        if !config_key.is_server() {
+            self.apply_extended(config_key, value).await?;
+        } else {
+            self.ensure_not_overridden(config_key)?;
+            let (value, raw) = self.prepare_server_value(config_key, value)?;
+            self.backup_upstream_config().await;
+            self.upstream.set_value(config_key.as_str(), value).await?;
+            self.save_upstream_config().await;
+            self.refresh_snapshot();
+            self.record_raw_directories(config_key, raw).await?;
+        }
+        let new = self.get_value(key).unwrap_or(serde_json::Value::Null);
+        self.commit_change(ConfigChange::new(key, old, new).with_reason(reason), operation);
+        Ok(())
+    }
+
+    /// Normalize, expand, and validate `value` for a `config.json` key.
+    /// Returns the value to store and, if expansion changed any directory
+    /// entry, the entries as written.
+    fn prepare_server_value(
+        &self,
+        key: ConfigKey<'_>,
+        value: &serde_json::Value,
+    ) -> Result<(ConfigValue, Option<Vec<String>>), McpError> {
+        let aliases = self.directory_aliases();
+        let value = normalize(key.as_str(), value.clone()).map_err(McpError::InvalidArguments)?;
+        let (json, raw) = expand::expand_directories_with(key, value, &aliases)
+            .map_err(McpError::InvalidArguments)?;
+        validate(key, &json).map_err(|e| McpError::InvalidArguments(e.to_string()))?;
+        let value = ConfigValue::from_json(json).map_err(|e| {
+            McpError::InvalidArguments(format!("Invalid value for '{}': {}", key, e))
+        })?;
+        Ok((value, raw))
+    }
+
+    /// Remember the directory entries of `key` as written, or forget them
+    /// when they were stored unchanged.
+    async fn record_raw_directories(
+        &self,
+        key: ConfigKey<'_>,
+        raw: Option<Vec<String>>,
+    ) -> Result<(), McpError> {
+        let current = self.with_extended_config(|c| c.raw_directories.get(key.as_str()).cloned());
+        if current == raw {
+            return Ok(());
+        }
+        self.update_extended(|c| match raw {
+            Some(raw) => {
+                c.raw_directories.insert(key.as_str().to_string(), raw);
+            }
+            None => {
+                c.raw_directories.remove(key.as_str());
+            }
+        });
+        self.persist().await
+    }
+
+    /// Set a key kept in the extended config and persist it.
+    async fn apply_extended(
+        &self,
+        key: ConfigKey<'_>,
+        value: &serde_json::Value,
+    ) -> Result<(), McpError> {
+        let mut result = Ok(());
+        self.update_extended(|c| result = c.set_key(key, value));
+        result.map_err(McpError::InvalidArguments)?;
+        self.persist().await
+    }
+
+    fn ensure_writable(&self) -> Result<(), McpError> {
+        if self.read_only {
+            let reason = match &self.persistence_status.lock().schema_error {
+                Some(e) => format!("Configuration is read-only: {}", e),
+                None => "Configuration is read-only".to_string(),
+            };
+            return Err(McpError::PermissionDenied(reason));
+        }
+        Ok(())
+    }
+
+    /// Refuse to change a key whose value comes from the environment; the
+    /// change would be hidden by the override and lost on restart.
+    fn ensure_not_overridden(&self, key: ConfigKey<'_>) -> Result<(), McpError> {
+        match self.env_overrides.var_for(key) {
+            Some(var) => Err(McpError::PermissionDenied(format!(
+                "{} is overridden by {}; change the environment variable instead",
+                key, var
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Write a JSON value to `key` without recording history.
+    ///
+    /// Used to replay undo/redo entries and restore backups; `null` removes
+    /// `tool_settings.*`, `rate_limits.*`, `directory_aliases.*`, and `ext.*`
+    /// entries. The whole `tool_settings`, `rate_limits`, `directory_aliases`,
+    /// and `extensions` sections can also be replaced.
+    async fn apply_json(&self, key: &str, value: &serde_json::Value) -> Result<(), McpError> {
+        let config_key = parse_key(key)?;
+        if !config_key.is_server() {
+            return self.apply_extended(config_key, value).await;
+        }
+        self.upstream
+            .set_value(config_key.as_str(), config_value_from_json(config_key, value)?)
+            .await?;
+        self.save_upstream_config().await;
+        self.refresh_snapshot();
+        Ok(())
+    }
+
+    /// Apply `f` to a copy of the extended config and publish the result.
+    fn update_extended(&self, f: impl FnOnce(&mut ExtendedConfig)) {
+        let _guard = self.write_lock.lock();
+        let mut next = self.get_extended_config();
+        f(&mut next);
+        self.extended.store(Arc::new(next));
+    }
+
+    // ========================================================================
+    // TOOL SETTINGS
+    // ========================================================================
+
+    /// Deserialize the settings stored for a tool category.
+    ///
+    /// Returns `Ok(None)` when the category has no settings yet.
+    pub fn get_tool_settings<T: DeserializeOwned>(
+        &self,
+        category: &str,
+    ) -> Result<Option<T>, McpError> {
+        let Some(value) = self.with_extended_config(|c| c.tool_settings.get(category).cloned())
+        else {
+            return Ok(None);
+        };
+        serde_json::from_value(value).map(Some).map_err(|e| {
+            McpError::InvalidArguments(format!(
+                "Invalid tool_settings for '{}': {}",
+                category, e
+            ))
+        })
+    }
+
+    /// Replace the settings stored for a tool category and persist them.
+    pub async fn set_tool_settings<T: Serialize>(
+        &self,
+        category: &str,
+        settings: &T,
+    ) -> Result<(), McpError> {
+        self.ensure_writable()?;
+        let value = serde_json::to_value(settings).map_err(|e| {
+            McpError::InvalidArguments(format!(
+                "Cannot serialize tool_settings for '{}': {}",
+                category, e
+            ))
+        })?;
+        let key = ConfigKey::ToolCategory(category).to_string();
+        let old = self.get_value(&key).unwrap_or(serde_json::Value::Null);
+        self.update_extended(|c| {
+            c.tool_settings.insert(category.to_string(), value.clone());
+        });
+        // A change that wasn't saved mustn't be undoable or reach the journal
+        self.persist().await?;
+        self.commit_change(ConfigChange::new(key, old, value), AuditOperation::Set);
+        Ok(())
+    }
+
+    // ========================================================================
+    // CHANGE CALLBACKS
+    // ========================================================================
+
+    /// Call `callback` after every applied change to `key` (including undo/redo).
+    ///
+    /// Registering on a parent key such as `tool_settings` also matches its
+    /// nested keys.
+    pub fn on_change(
+        &self,
+        key: &str,
+        callback: impl Fn(&ConfigChange) + Send + Sync + 'static,
+    ) -> CallbackId {
+        let callback: ChangeCallback = Arc::new(callback);
+        self.callbacks.lock().register(key, callback)
+    }
+
+    /// Call `callback` with the new setting whenever `telemetry_enabled`
+    /// changes, so collectors can start or stop immediately.
+    pub fn on_telemetry_change(
+        &self,
+        callback: impl Fn(bool) + Send + Sync + 'static,
+    ) -> CallbackId {
+        self.on_change(TELEMETRY_ENABLED_KEY, move |change| {
+            let enabled = change.new.as_bool().unwrap_or(false);
+            if change.old.as_bool().unwrap_or(false) != enabled {
+                callback(enabled);
+            }
+        })
+    }
+
+    /// Unregister a callback added with [`Self::on_change`] or
+    /// [`Self::on_telemetry_change`].
+    pub fn remove_on_change(&self, id: CallbackId) -> bool {
+        self.callbacks.lock().unregister(id)
+    }
+
+    /// Apply a JSON value to `key`, then record and announce the change.
+    #[tracing::instrument(
+        name = "config.commit",
+        skip_all,
+        fields(key = key, operation = operation.as_str()),
+        err
+    )]
+    async fn apply_and_commit(
+        &self,
+        key: &str,
+        value: &serde_json::Value,
+        operation: AuditOperation,
+    ) -> Result<(), McpError> {
+        let old = self.get_value(key).unwrap_or(serde_json::Value::Null);
+        if let Err(e) = self.apply_json(key, value).await {
+            self.audit_failure(operation, key, value.clone(), None, &e);
+            return Err(e);
+        }
+        let new = self.get_value(key).unwrap_or(serde_json::Value::Null);
+        if old != new {
+            self.commit_change(ConfigChange::new(key, old, new), operation);
+        }
+        Ok(())
+    }
+
+    /// Record a change for undo and notify its callbacks.
+    fn commit_change(&self, change: ConfigChange, operation: AuditOperation) {
+        self.record_source(&change.key, ValueSource::Runtime);
+        self.history.lock().record(change.clone());
+        self.persist_change(&change, operation);
+        self.notify(&change);
+    }
+
+    /// Append `change` to the journal or the store's change history and the
+    /// audit log, and re-sign the settings.
+    fn persist_change(&self, change: &ConfigChange, operation: AuditOperation) {
+        self.audit(
+            &AuditRecord::new(
+                operation,
+                change.key.clone(),
+                change.old.clone(),
+                change.new.clone(),
+                AuditResult::Success,
+            )
+            .with_reason(change.reason.clone()),
+        );
+        self.sign();
+        if let Some(journal) = &self.journal
+            && let Err(e) = journal.append_change(change)
+        {
+            log::warn!("Failed to journal change to {}: {}", change.key, e);
+        }
+        let Some(store) = self.store.clone() else {
+            return;
+        };
+        let change = change.clone();
+        tokio::spawn(async move {
+            if let Err(e) = store.record_change(&change).await {
+                log::warn!("Failed to record change to {}: {}", change.key, e);
+            }
+        });
+    }
+
+    /// Record a mutation that failed with `error`; `attempted` is the value
+    /// that would have been written.
+    fn audit_failure(
+        &self,
+        operation: AuditOperation,
+        key: &str,
+        attempted: serde_json::Value,
+        reason: Option<String>,
+        error: &McpError,
+    ) {
+        let old = self.get_value(key).unwrap_or(serde_json::Value::Null);
+        self.audit(
+            &AuditRecord::new(
+                operation,
+                key,
+                old,
+                attempted,
+                AuditResult::Failure {
+                    error: error.to_string(),
+                },
+            )
+            .with_reason(reason),
+        );
+    }
+
+    /// Append `record` to the audit log, if `audit_log_path` is set. A change
+    /// of `audit_log_path` is also written to the file it replaced.
+    fn audit(&self, record: &AuditRecord) {
+        let mut paths: Vec<PathBuf> = self
+            .with_extended_config(|c| c.audit_log_path.clone())
+            .into_iter()
+            .collect();
+        if record.key == AUDIT_LOG_PATH_KEY
+            && let Some(old) = record.old.as_str().map(PathBuf::from)
+            && !paths.contains(&old)
+        {
+            paths.push(old);
+        }
+        for path in paths {
+            if let Err(e) = append_record(&path, record) {
+                log::warn!("Failed to write audit log {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    fn notify(&self, change: &ConfigChange) {
+        // Clone out of the lock so callbacks may register further callbacks
+        let callbacks = self.callbacks.lock().matching(&change.key);
+        for callback in callbacks {
+            callback(change);
+        }
+    }
+
+    // ========================================================================
+    // DEFAULTS
+    // ========================================================================
+
+    /// Apply the builder-supplied defaults if no `config.json` existed at startup.
+    ///
+    /// Also loads the extended config from the `ConfigStore`, if one is
+    /// configured, and starts following its updates, and replays extended
+    /// config changes that were journaled but never saved, e.g. because the
+    /// process crashed before the save. Finally merges in the remote config
+    /// source, if any, and starts polling it. Call it once after building.
+    pub async fn init(&self) -> Result<(), McpError> {
+        let result = self.try_init().await;
+        self.initialized.store(true, Ordering::Release);
+        result
+    }
+
+    async fn try_init(&self) -> Result<(), McpError> {
+        if let Some(store) = self.store.clone() {
+            self.load_from_store(&*store).await;
+            self.watch_store(&*store);
+        }
+        if self.read_only {
+            return Ok(());
+        }
+        if self.fresh_install {
+            log::info!("No existing config found; applying embedder defaults");
+            self.reset(None).await?;
+        }
+        self.recover_from_journal().await?;
+        if let Some(remote) = self.remote.clone() {
+            self.sync_remote(&remote).await;
+            self.poll_remote(remote);
+        }
+        Ok(())
+    }
+
+    /// Whether [`Self::init`] has finished, successfully or not.
+    #[must_use]
+    pub fn is_initialized(&self) -> bool {
+        self.initialized.load(Ordering::Acquire)
+    }
+
+    async fn recover_from_journal(&self) -> Result<(), McpError> {
+        let Some(journal) = self.journal.clone() else {
+            return Ok(());
+        };
+        // Only the final value of each key matters; config.json keys are
+        // persisted by the infrastructure crate and aren't replayed
+        let mut latest: Vec<(String, serde_json::Value)> = Vec::new();
+        for change in journal.unsaved_changes() {
+            if !is_extended_key(&change.key) {
+                continue;
+            }
+            match latest.iter_mut().find(|(key, _)| *key == change.key) {
+                Some(entry) => entry.1 = change.new,
+                None => latest.push((change.key, change.new)),
+            }
+        }
+        for (key, value) in latest {
+            if self.get_value(&key).unwrap_or(serde_json::Value::Null) != value {
+                log::warn!(
+                    "Recovering unsaved change to {} from {}",
+                    key,
+                    journal.path().display()
+                );
+                self.apply_json(&key, &value).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Restore `key` to its default, or every key when `key` is `None`.
+    ///
+    /// Each restored key is recorded individually and can be undone.
+    pub async fn reset(&self, key: Option<&str>) -> Result<(), McpError> {
+        self.ensure_writable()?;
+        let keys: Vec<&str> = match key {
+            Some(key) => vec![key],
+            // Keys set from the environment keep their environment values
+            None => ConfigKey::SERVER
+                .iter()
+                .filter(|&&k| self.env_overrides.get(k).is_none())
+                .chain(ConfigKey::EXTENDED)
+                .chain(&[
+                    ConfigKey::RateLimits,
+                    ConfigKey::DirectoryAliases,
+                    ConfigKey::ToolSettings,
+                ])
+                .map(|k| k.as_str())
+                .collect(),
+        };
+        for key in keys {
+            let default = self.default_json(key)?;
+            if is_extended_key(key) {
+                self.apply_and_commit(key, &default, AuditOperation::Reset).await?;
+            } else {
+                self.set_value_as(key, default, AuditOperation::Reset, None).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // ========================================================================
+    // UNDO / REDO
+    // ========================================================================
+
+    /// Revert the most recent mutation. Returns `Ok(None)` when there is nothing to undo.
+    pub async fn undo(&self) -> Result<Option<ConfigChange>, McpError> {
+        self.undo_checked(None).await
+    }
+
+    /// The change `undo` would revert next, if any.
+    #[must_use]
+    pub fn next_undo(&self) -> Option<ConfigChange> {
+        self.history.lock().peek_undo().cloned()
+    }
+
+    /// Revert `expected`, as returned by `next_undo`, for callers that had it
+    /// confirmed first. Fails without changing anything if another change
+    /// became the most recent one in the meantime.
+    pub async fn undo_expected(&self, expected: &ConfigChange) -> Result<ConfigChange, McpError> {
+        self.undo_checked(Some(expected))
+            .await?
+            .ok_or_else(|| history_moved_on("undo"))
+    }
+
+    async fn undo_checked(
+        &self,
+        expected: Option<&ConfigChange>,
+    ) -> Result<Option<ConfigChange>, McpError> {
+        self.ensure_writable()?;
+        let popped = {
+            let mut history = self.history.lock();
+            if expected.is_some_and(|expected| history.peek_undo() != Some(expected)) {
+                return Err(history_moved_on("undo"));
+            }
+            history.pop_undo()
+        };
+        let Some(change) = popped else {
+            return Ok(None);
+        };
+        if let Err(e) = self.apply_json(&change.key, &change.old).await {
+            let attempted = change.old.clone();
+            self.audit_failure(AuditOperation::Undo, &change.key, attempted, None, &e);
+            self.history.lock().push_undo(change);
+            return Err(e);
+        }
+        self.record_source(&change.key, ValueSource::Runtime);
+        self.history.lock().push_redo(change.clone());
+        let inverse = change.inverse();
+        self.persist_change(&inverse, AuditOperation::Undo);
+        self.notify(&inverse);
+        Ok(Some(change))
+    }
+
+    /// Re-apply the most recently undone mutation. Returns `Ok(None)` when there is nothing to redo.
+    pub async fn redo(&self) -> Result<Option<ConfigChange>, McpError> {
+        self.redo_checked(None).await
+    }
+
+    /// The change `redo` would re-apply next, if any.
+    #[must_use]
+    pub fn next_redo(&self) -> Option<ConfigChange> {
+        self.history.lock().peek_redo().cloned()
+    }
+
+    /// Re-apply `expected`, as returned by `next_redo`, for callers that had
+    /// it confirmed first. Fails without changing anything if it is no longer
+    /// the next change to redo.
+    pub async fn redo_expected(&self, expected: &ConfigChange) -> Result<ConfigChange, McpError> {
+        self.redo_checked(Some(expected))
+            .await?
+            .ok_or_else(|| history_moved_on("redo"))
+    }
+
+    async fn redo_checked(
+        &self,
+        expected: Option<&ConfigChange>,
+    ) -> Result<Option<ConfigChange>, McpError> {
+        self.ensure_writable()?;
+        let popped = {
+            let mut history = self.history.lock();
+            if expected.is_some_and(|expected| history.peek_redo() != Some(expected)) {
+                return Err(history_moved_on("redo"));
+            }
+            history.pop_redo()
+        };
+        let Some(change) = popped else {
+            return Ok(None);
+        };
+        if let Err(e) = self.apply_json(&change.key, &change.new).await {
+            let attempted = change.new.clone();
+            self.audit_failure(AuditOperation::Redo, &change.key, attempted, None, &e);
+            self.history.lock().push_redo(change);
+            return Err(e);
+        }
+        self.record_source(&change.key, ValueSource::Runtime);
+        self.history.lock().push_undo(change.clone());
+        self.persist_change(&change, AuditOperation::Redo);
+        self.notify(&change);
+        Ok(Some(change))
+    }
+
+    /// Applied changes, most recent first, optionally only those to `key`.
+    ///
+    /// Served from the `ConfigStore` or the change journal, whichever is in
+    /// use, otherwise from the in-memory undo stack.
+    pub async fn change_history(
+        &self,
+        key: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<ConfigChange>, McpError> {
+        let Some(store) = self.store.clone() else {
+            return Ok(match &self.journal {
+                Some(journal) => journal.history(key, limit),
+                None => self.history.lock().recent(key, limit),
+            });
+        };
+        store
+            .change_history(key, limit)
+            .await
+            .map_err(|e| McpError::Other(e.into()))
+    }
+
+    /// Number of mutations that can currently be undone and redone.
+    #[must_use]
+    pub fn history_depth(&self) -> (usize, usize) {
+        let history = self.history.lock();
+        (history.undo_len(), history.redo_len())
+    }
+
+    // ========================================================================
+    // PERSISTENCE
+    // ========================================================================
+
+    /// Last save time, pending flag, and error details for both config files.
+    #[must_use]
+    pub fn persistence_status(&self) -> PersistenceStatus {
+        let mut status = self.persistence_status.lock().clone();
+        status.error_count = self.save_errors.lock().total();
+        status.upstream_error_count = UpstreamConfigManager::get_save_error_count() as u64;
+        status
+    }
+
+    // ========================================================================
+    // BACKUPS
+    // ========================================================================
+
+    fn backup_source(&self, file: BackupFile) -> PathBuf {
+        match file {
+            BackupFile::Config => persistence::upstream_config_path(),
+            BackupFile::Tools => self.extended_config_path(),
+        }
+    }
+
+    /// Rotate `config.json` backups before the infrastructure crate overwrites it.
+    async fn backup_upstream_config(&self) {
+        let path = persistence::upstream_config_path();
+        if let Err(e) = persistence::rotate_backups(&path, self.backup_count).await {
+            log::warn!("Failed to rotate backups of {}: {}", path.display(), e);
+        }
+    }
+
+    /// Write the infrastructure crate's settings to `config.json` through
+    /// `write_atomic`, so a change is on disk with our durability without
+    /// waiting for the infrastructure crate's debounced save.
+    ///
+    /// That save still runs and overwrites the file in place, so `config.json`
+    /// as a whole is not guaranteed to be replaced atomically. `client_history`
+    /// is written empty: the history is kept in `clients.json`.
+    async fn save_upstream_config(&self) {
+        let path = persistence::upstream_config_path();
+        let saved = serde_json::to_value(self.upstream.get_config()).and_then(|mut config| {
+            if let Some(history) = config.get_mut("client_history") {
+                *history = json!([]);
+            }
+            serde_json::to_vec_pretty(&config)
+        });
+        let saved = match saved {
+            Ok(json) => persistence::write_atomic(&path, &json, self.durability).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = saved {
+            log::warn!("Failed to save {}: {}", path.display(), e);
+        }
+    }
+
+    /// Rotated backups of `file`, most recent first.
+    pub async fn list_backups(&self, file: BackupFile) -> Vec<BackupInfo> {
+        persistence::list_backups(file, &self.backup_source(file), self.backup_count).await
+    }
+
+    /// Restore backup number `index` of `file`, returning the keys that changed.
+    ///
+    /// Each restored key is recorded individually and can be undone.
+    pub async fn restore_backup(
+        &self,
+        file: BackupFile,
+        index: usize,
+    ) -> Result<Vec<ConfigDelta>, McpError> {
+        self.ensure_writable()?;
+        let deltas = self.backup_changes(file, index).await?;
+        self.apply_restore(file, &deltas).await?;
+        Ok(deltas)
+    }
+
+    /// Restore backup `index` of `file` if it still makes exactly the
+    /// `expected` changes, as returned by `backup_changes`, for callers that
+    /// had them confirmed first. Fails without changing anything otherwise,
+    /// e.g. when a save rotated the backups in the meantime.
+    pub async fn restore_backup_expected(
+        &self,
+        file: BackupFile,
+        index: usize,
+        expected: &[ConfigDelta],
+    ) -> Result<Vec<ConfigDelta>, McpError> {
+        self.ensure_writable()?;
+        let deltas = self.backup_changes(file, index).await?;
+        if deltas != expected {
+            return Err(McpError::InvalidArguments(format!(
+                "Backup #{} or the config changed since the restore was confirmed; \
+                 restore it again",
+                index
+            )));
+        }
+        self.apply_restore(file, &deltas).await?;
+        Ok(deltas)
+    }
+
+    /// The keys restoring backup `index` of `file` would change, without
+    /// changing them.
+    pub async fn backup_changes(
+        &self,
+        file: BackupFile,
+        index: usize,
+    ) -> Result<Vec<ConfigDelta>, McpError> {
+        let source = self.backup_source(file);
+        let path = persistence::backup_path(&source, index);
+        let contents = tokio::fs::read(&path)
+            .await
+            .and_then(|bytes| encryption::decode(bytes, self.encryption_key.as_deref()))
+            .map_err(|e| {
+                McpError::InvalidArguments(format!("Cannot read backup {}: {}", path.display(), e))
+            })?;
+        let backup: serde_json::Value = ConfigFormat::detect(&source, &contents)
+            .parse(&contents)
+            .map_err(|e| {
+                McpError::InvalidArguments(format!("Backup {} is invalid: {}", path.display(), e))
+            })?;
+
+        let current = match file {
+            BackupFile::Config => serde_json::to_value(&*self.get_config_snapshot()),
+            BackupFile::Tools => serde_json::to_value(&*self.get_extended_snapshot()),
+        }
+        .map_err(|e| McpError::Other(e.into()))?;
+
+        let restorable = |key: &str| match file {
+            BackupFile::Config => ConfigKey::server(key).is_ok(),
+            BackupFile::Tools => ConfigKey::parse(key).is_ok_and(|key| {
+                ConfigKey::EXTENDED.contains(&key) || ConfigKey::SECTIONS.contains(&key)
+            }),
+        };
+        Ok(diff_values(&current, &backup)
+            .into_iter()
+            .filter(|delta| restorable(&delta.key))
+            .collect())
+    }
+
+    async fn apply_restore(
+        &self,
+        file: BackupFile,
+        deltas: &[ConfigDelta],
+    ) -> Result<(), McpError> {
+        if file == BackupFile::Config && !deltas.is_empty() {
+            self.backup_upstream_config().await;
+        }
+        for delta in deltas {
+            self.apply_and_commit(&delta.key, &delta.new, AuditOperation::Restore)
+                .await?;
+        }
+        Ok(())
+    }
+
+    // ========================================================================
+    // FORMAT CONVERSION
+    // ========================================================================
+
+    /// Current location of the extended config file.
+    #[must_use]
+    pub fn extended_config_path(&self) -> PathBuf {
+        self.extended_path.load().to_path_buf()
+    }
+
+    /// Rewrite the extended config in `format`, returning the new path.
+    ///
+    /// The file is written next to the old one with the matching extension
+    /// (`config.tools.toml`), then the old file is removed and later saves use
+    /// the new location.
+    pub async fn convert(&self, format: ConfigFormat) -> Result<PathBuf, McpError> {
+        self.ensure_writable()?;
+        if let Some(store) = &self.store {
+            return Err(McpError::InvalidArguments(format!(
+                "Config is stored in {}, not a file",
+                store.location()
+            )));
+        }
+        let _save_guard = self.save_lock.lock().await;
+        let old_path = self.extended_path.load_full();
+        let new_path = format::path_for_format(&old_path, format);
+
+        let snapshot = self.extended.load_full();
+        let key = self.encryption_key.as_deref();
+        let saved = persistence::save_to_disk(&new_path, &snapshot, self.durability, key)
+            .await
+            .map_err(|e| match e.kind() {
+                // TOML can't represent every JSON value (e.g. null)
+                std::io::ErrorKind::InvalidData => McpError::InvalidArguments(format!(
+                    "Config cannot be written as {}: {}",
+                    format, e
+                )),
+                _ => McpError::Other(e.into()),
+            })?;
+
+        if *old_path != new_path {
+            match tokio::fs::remove_file(&*old_path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => log::warn!(
+                    "Converted to {} but failed to remove {}: {}",
+                    new_path.display(),
+                    old_path.display(),
+                    e
+                ),
+                _ => {}
+            }
+            self.extended_path.store(Arc::new(new_path.clone()));
+        }
+        *self.file_hash.lock() = Some(saved.hash);
+        self.persistence_status.lock().last_save_at = Some(chrono::Utc::now());
+        Ok(new_path)
+    }
+
+    /// Recent save failures, oldest first, bounded to `SAVE_ERROR_HISTORY` entries.
+    #[must_use]
+    pub fn get_save_errors(&self) -> Vec<SaveErrorRecord> {
+        self.save_errors.lock().records()
+    }
+
+    /// Save counts, latency, and payload size since startup.
+    #[must_use]
+    pub fn persistence_metrics(&self) -> PersistenceMetrics {
+        self.metrics.lock().clone()
+    }
+
+    /// Persist the extended config: hand it to the background saver if one
+    /// is running, otherwise save immediately.
+    async fn persist(&self) -> Result<(), McpError> {
+        if self.saver.mark_dirty() {
+            self.persistence_status.lock().save_pending = true;
+            return Ok(());
+        }
+        self.save().await
+    }
+
+    /// Debounce and max-delay in effect, preferring `tool_settings.persistence`.
+    fn saver_timing(&self) -> (Duration, Duration) {
+        let settings = self
+            .get_tool_settings::<SaverSettings>(SAVER_SETTINGS_CATEGORY)
+            .unwrap_or_else(|e| {
+                log::warn!("{}", e);
+                None
+            })
+            .unwrap_or_default();
+        (
+            settings.debounce_ms.map_or(self.debounce, Duration::from_millis),
+            settings.max_delay_ms.map_or(self.max_save_delay, Duration::from_millis),
+        )
+    }
+
+    /// Start saving in the background with debouncing.
+    ///
+    /// Until this is called every mutation writes to disk before returning.
+    /// Afterwards mutations return immediately and save failures are reported
+    /// through `persistence_status()`. Calling it again returns the existing
+    /// handle.
+    pub fn start_background_saver(&self) -> SaverHandle {
+        let mut slot = self.saver_handle.lock();
+        if let Some(handle) = slot.as_ref() {
+            return handle.clone();
+        }
+        self.saver.start();
+        let manager = self.clone();
+        let task = tokio::spawn(async move {
+            let mut client_sync = tokio::time::interval(CLIENT_HISTORY_SYNC_INTERVAL);
+            'run: loop {
+                tokio::select! {
+                    () = manager.saver.changed() => {}
+                    () = manager.saver.stopped() => break 'run,
+                    _ = client_sync.tick() => {
+                        manager.sync_client_history_logged().await;
+                        continue 'run;
+                    }
+                }
+                loop {
+                    let (debounce, max_delay) = manager.saver_timing();
+                    let Some(deadline) = manager.saver.deadline(debounce, max_delay) else {
+                        break;
+                    };
+                    if tokio::time::Instant::now() >= deadline {
+                        break;
+                    }
+                    tokio::select! {
+                        () = tokio::time::sleep_until(deadline) => {}
+                        () = manager.saver.stopped() => break 'run,
+                    }
+                }
+                manager.saver.take();
+                // Failures are already logged and recorded in the persistence status
+                let _ = manager.save().await;
+            }
+
+            // Final flush of anything still pending
+            manager.sync_client_history_logged().await;
+            if manager.saver.is_dirty() {
+                manager.saver.take();
+                return manager.save().await;
+            }
+            Ok(())
+        });
+        let handle = SaverHandle::new(Arc::clone(&self.saver), task);
+        *slot = Some(handle.clone());
+        handle
+    }
+
+    /// Stop the file watcher and the background saver, and flush pending
+    /// changes, returning any error from the final save.
+    pub async fn shutdown(&self) -> Result<(), McpError> {
+        self.file_watcher.lock().take();
+        let handle = self.saver_handle.lock().clone();
+        match handle {
+            Some(handle) => handle.shutdown().await,
+            None => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "config.save", skip_all, err)]
+    async fn save(&self) -> Result<(), McpError> {
+        let _save_guard = self.save_lock.lock().await;
+        self.persistence_status.lock().save_pending = true;
+        let journaled = self.journal.as_ref().map(|journal| journal.last_seq());
+        let snapshot = self.extended.load_full();
+        let content_hash = persistence::config_hash(&snapshot);
+
+        // Setting a value to what it already was shouldn't touch the disk
+        let path = self.extended_path.load_full();
+        let unchanged = *self.saved_hash.lock() == Some(content_hash);
+        let exists = self.store.is_some() || tokio::fs::try_exists(&*path).await.unwrap_or(false);
+        if unchanged && exists {
+            log::debug!("Config unchanged since last save; skipping write");
+            self.persistence_status.lock().save_pending = false;
+            self.metrics.lock().saves_skipped += 1;
+            self.checkpoint_journal(journaled);
+            return Ok(());
+        }
+
+        if let Some(store) = self.store.clone() {
+            let location = PathBuf::from(store.location());
+            let started = Instant::now();
+            let result = self.retry_save(&location, || store.save(&snapshot)).await;
+            // Stores don't report what they wrote; the JSON form approximates it
+            let size = result.is_ok().then(|| {
+                serde_json::to_vec(&*snapshot).map_or(0, |json| json.len() as u64)
+            });
+            self.metrics.lock().record_save(started.elapsed(), size);
+            self.finish_save(&location, result.map(|()| None))?;
+            *self.saved_hash.lock() = Some(content_hash);
+            return Ok(());
+        }
+
+        // Don't silently clobber a hand edit made since we last touched the file
+        let expected = *self.file_hash.lock();
+        if persistence::modified_externally(&path, expected).await {
+            let backup_path = match persistence::backup_external_edit(&path).await {
+                Ok(backup) => Some(backup),
+                Err(e) => {
+                    log::error!("Failed to back up externally edited config: {}", e);
+                    None
+                }
+            };
+            log::warn!(
+                "{} was modified externally; previous contents saved to {:?}",
+                path.display(),
+                backup_path
+            );
+            let mut status = self.persistence_status.lock();
+            status.conflict_count += 1;
+            status.last_conflict = Some(ConflictRecord {
+                detected_at: chrono::Utc::now(),
+                path: path.to_path_buf(),
+                backup_path,
+            });
+        }
+
+        if let Err(e) = persistence::rotate_backups(&path, self.backup_count).await {
+            log::warn!("Failed to rotate backups of {}: {}", path.display(), e);
+        }
+
+        let key = self.encryption_key.as_deref();
+        let started = Instant::now();
+        let result = self
+            .retry_save(&path, || {
+                persistence::save_to_disk(&path, &snapshot, self.durability, key)
+            })
+            .await;
+        let size = result.as_ref().ok().map(|saved| saved.size);
+        self.metrics.lock().record_save(started.elapsed(), size);
+        self.finish_save(&path, result.map(|saved| Some(saved.hash)))?;
+        *self.saved_hash.lock() = Some(content_hash);
+        self.checkpoint_journal(journaled);
+        Ok(())
+    }
+
+    /// Mark journal entries up to `through` as saved.
+    fn checkpoint_journal(&self, through: Option<u64>) {
+        if let (Some(journal), Some(through)) = (&self.journal, through)
+            && let Err(e) = journal.checkpoint(through)
+        {
+            log::warn!("Failed to checkpoint {}: {}", journal.path().display(), e);
+        }
+    }
+
+    /// Run `write`, retrying transient failures with exponential backoff.
+    ///
+    /// `save_pending` stays set throughout, so status readers see the save
+    /// as outstanding until it succeeds or the retries are exhausted.
+    async fn retry_save<T, F, Fut>(&self, path: &Path, mut write: F) -> std::io::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = std::io::Result<T>>,
+    {
+        let policy = self.retry_policy;
+        let mut attempt = 1;
+        loop {
+            match write().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < policy.max_attempts && persistence::is_retryable(&e) => {
+                    let delay = policy.delay(attempt);
+                    log::warn!(
+                        "Failed to save {} (attempt {}/{}): {}; retrying in {:?}",
+                        path.display(),
+                        attempt,
+                        policy.max_attempts,
+                        e,
+                        delay
+                    );
+                    self.persistence_status.lock().retry_count += 1;
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    // ========================================================================
+    // CONFIG STORE
+    // ========================================================================
+
+    /// Replace the extended config with the one in `store`, keeping the
+    /// current (default) config if the store is empty or unreadable.
+    async fn load_from_store(&self, store: &dyn ConfigStore) {
+        match store.load().await {
+            Ok(Some(config)) => {
+                if self.apply_external_config(config) {
+                    self.sign_external();
+                }
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!(
+                "Failed to load config from {}: {}; using defaults",
+                store.location(),
+                e
+            ),
+        }
+    }
+
+    /// Apply configs saved to `store` by other processes as they arrive.
+    fn watch_store(&self, store: &dyn ConfigStore) {
+        let Some(mut updates) = store.watch() else {
+            return;
+        };
+        let manager = self.clone();
+        tokio::spawn(async move {
+            while let Some(config) = updates.recv().await {
+                if manager.apply_external_config(config) {
+                    manager.sign_external();
+                }
+            }
+        });
+    }
+
+    /// Publish an extended config that is already persisted elsewhere and
+    /// notify callbacks of the keys that changed. Not recorded for undo.
+    /// Returns whether anything changed.
+    fn apply_external_config(&self, config: ExtendedConfig) -> bool {
+        let old = serde_json::to_value(&*self.get_extended_snapshot()).unwrap_or_default();
+        let new = serde_json::to_value(&config).unwrap_or_default();
+        let deltas = diff_values(&old, &new);
+        if deltas.is_empty() {
+            return false;
+        }
+        // Already persisted; don't write it straight back
+        *self.saved_hash.lock() = Some(persistence::config_hash(&config));
+        {
+            let _guard = self.write_lock.lock();
+            self.extended.store(Arc::new(config));
+        }
+        for delta in deltas {
+            self.record_source(&delta.key, ValueSource::File);
+            self.notify(&ConfigChange::new(delta.key, delta.old, delta.new));
+        }
+        true
+    }
+
+    // ========================================================================
+    // FILE WATCHING
+    // ========================================================================
+
+    /// Follow writes made to `config.json` and the extended config file by
+    /// other processes, updating this manager and notifying change callbacks.
+    ///
+    /// Values changed in `config.json` are re-applied through the
+    /// infrastructure crate, which holds its own copy. Does nothing if the
+    /// watcher is already running.
+    pub fn start_file_watcher(&self) -> Result<(), McpError> {
+        let mut slot = self.file_watcher.lock();
+        if slot.is_some() {
+            return Ok(());
+        }
+        let upstream_path = persistence::upstream_config_path();
+        let mut files = vec![upstream_path.clone()];
+        if self.store.is_none() {
+            files.push(self.extended_path.load_full().to_path_buf());
+        }
+        let (watcher, mut changes) =
+            watcher::watch_files(&files).map_err(|e| McpError::Other(e.into()))?;
+        *slot = Some(watcher);
+
+        let manager = self.clone();
+        tokio::spawn(async move {
+            while let Some(paths) = watcher::next_changes(&mut changes).await {
+                for path in paths {
+                    let reloaded = if path == upstream_path {
+                        manager.reload_upstream_file(&path).await
+                    } else if path == **manager.extended_path.load() {
+                        manager.reload_extended_file(&path).await
+                    } else {
+                        Ok(())
+                    };
+                    // Possibly caught mid-edit; the next write triggers another reload
+                    if let Err(e) = reloaded {
+                        log::warn!("Ignoring unreadable {}: {}", path.display(), e);
+                    }
+                }
+                // Another process's tools re-sign what they write; anything
+                // else is an edit outside the config tools and stays a mismatch
+                manager.verify_integrity();
+            }
+        });
+        Ok(())
+    }
+
+    /// Re-read `config.json` and the extended config now, applying and
+    /// announcing whatever changed as the file watcher does. With a config
+    /// store the extended config is loaded from the store instead, and the
+    /// remote source, if any, is fetched again.
+    ///
+    /// A file or store that can't be read or parsed is reported as an error
+    /// and leaves the values already in effect unchanged.
+    pub async fn reload(&self) -> Result<(), McpError> {
+        let mut failures = Vec::new();
+        let upstream_path = persistence::upstream_config_path();
+        if let Err(e) = self.reload_upstream_file(&upstream_path).await {
+            failures.push(format!("{}: {}", upstream_path.display(), e));
+        }
+        match &self.store {
+            Some(store) => match store.load().await {
+                Ok(Some(config)) => {
+                    self.apply_external_config(config);
+                }
+                Ok(None) => {}
+                Err(e) => failures.push(format!("{}: {}", store.location(), e)),
+            },
+            None => {
+                let path = self.extended_path.load_full();
+                if let Err(e) = self.reload_extended_file(&path).await {
+                    failures.push(format!("{}: {}", path.display(), e));
+                }
+            }
+        }
+        if let Some(remote) = &self.remote {
+            self.sync_remote(remote).await;
+        }
+        if failures.is_empty() {
+            // The operator asked for the files as they are now
+            self.sign_external();
+            Ok(())
+        } else {
+            Err(McpError::Other(anyhow::anyhow!("Failed to reload {}", failures.join("; "))))
+        }
+    }
+
+    /// Apply an extended config file written by another process. A missing
+    /// file is left alone.
+    async fn reload_extended_file(&self, path: &Path) -> std::io::Result<()> {
+        let Ok(bytes) = tokio::fs::read(path).await else {
+            return Ok(());
+        };
+        let hash = persistence::content_hash(&bytes);
+        // Our own save
+        if *self.file_hash.lock() == Some(hash) {
+            return Ok(());
+        }
+        let contents = encryption::decode(bytes, self.encryption_key.as_deref())?;
+        let config = persistence::parse_config(ConfigFormat::detect(path, &contents), &contents)?;
+        log::info!("{} changed on disk; reloading", path.display());
+        *self.file_hash.lock() = Some(hash);
+        self.apply_external_config(config);
+        Ok(())
+    }
+
+    /// Re-apply settings changed in `config.json` by another process. A
+    /// missing file is left alone.
+    ///
+    /// Values are normalized, expanded, and validated as `config_set` would;
+    /// a key that fails keeps its current value and is logged.
+    async fn reload_upstream_file(&self, path: &Path) -> std::io::Result<()> {
+        let Ok(contents) = tokio::fs::read_to_string(path).await else {
+            return Ok(());
+        };
+        let on_disk: serde_json::Value = serde_json::from_str(&contents)?;
+        let current = serde_json::to_value(&*self.get_config_snapshot()).unwrap_or_default();
+        for &key in ConfigKey::SERVER {
+            let Some(new) = on_disk.get(key.as_str()) else {
+                continue;
+            };
+            let old = current.get(key.as_str()).cloned().unwrap_or_default();
+            // The environment wins over config.json
+            if *new == old || self.env_overrides.var_for(key).is_some() {
+                continue;
+            }
+            if self.read_only {
+                log::warn!("{} changed in {} but config is read-only", key, path.display());
+                continue;
+            }
+            let (value, raw) = match self.prepare_server_value(key, new) {
+                Ok(prepared) => prepared,
+                Err(e) => {
+                    log::warn!("Ignoring {} from {}: {}", key, path.display(), e);
+                    continue;
+                }
+            };
+            if let Err(e) = self.upstream.set_value(key.as_str(), value).await {
+                log::warn!("Failed to reload {} from {}: {}", key, path.display(), e);
+                continue;
+            }
+            log::info!("{} changed in {}; reloaded", key, path.display());
+            self.refresh_snapshot();
+            if let Err(e) = self.record_raw_directories(key, raw).await {
+                log::warn!("Failed to record the entries of {} as written: {}", key, e);
+            }
+            self.record_source(key.as_str(), ValueSource::File);
+            let stored = self.get_value(key.as_str()).unwrap_or_default();
+            self.notify(&ConfigChange::new(key.as_str(), old, stored));
+        }
+        Ok(())
+    }
+
+    // ========================================================================
+    // REMOTE SOURCE
+    // ========================================================================
+
+    /// Fetch the remote config and merge in any values that changed.
+    async fn sync_remote(&self, remote: &RemoteSource) {
+        let values = match remote.fetch().await {
+            Ok(Some(values)) => values,
+            Ok(None) => return,
+            Err(e) => {
+                log::warn!("Failed to fetch remote config from {}: {}", remote.url(), e);
+                return;
+            }
+        };
+        for (key, value) in values {
+            let key = resolve_key(&key).key;
+            if let Err(e) = self.apply_remote_value(key, &value).await {
+                self.audit_failure(AuditOperation::Remote, key, value, None, &e);
+                log::warn!("Ignoring remote value for {}: {}", key, e);
+            }
+        }
+    }
+
+    /// Apply one remote value unless an environment variable overrides `key`.
+    ///
+    /// Recorded in the journal and reported to callbacks, but not undoable.
+    async fn apply_remote_value(
+        &self,
+        key: &str,
+        value: &serde_json::Value,
+    ) -> Result<(), McpError> {
+        if !is_extended_key(key) {
+            let config_key =
+                ConfigKey::server(key).map_err(|e| McpError::InvalidArguments(e.to_string()))?;
+            if let Some(var) = self.env_overrides.var_for(config_key) {
+                log::debug!("{} is overridden by {}; ignoring remote value", key, var);
+                return Ok(());
+            }
+            validate(config_key, value).map_err(|e| McpError::InvalidArguments(e.to_string()))?;
+        }
+        let old = self.get_value(key).unwrap_or(serde_json::Value::Null);
+        if old == *value {
+            return Ok(());
+        }
+        self.apply_json(key, value).await?;
+        let new = self.get_value(key).unwrap_or(serde_json::Value::Null);
+        log::info!("Applied remote value for {}", key);
+        self.record_source(key, ValueSource::Remote);
+        let change = ConfigChange::new(key, old, new);
+        self.persist_change(&change, AuditOperation::Remote);
+        self.notify(&change);
+        Ok(())
+    }
+
+    /// Re-fetch the remote config every poll interval.
+    fn poll_remote(&self, remote: Arc<RemoteSource>) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(remote.poll_interval());
+            // The first tick completes immediately; init() has just fetched
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                manager.sync_remote(&remote).await;
+            }
+        });
+    }
+
+    // ========================================================================
+    // INTEGRITY
+    // ========================================================================
+
+    fn signature_path(&self) -> PathBuf {
+        self.extended_path.load().with_file_name(SIGNATURE_FILE)
+    }
+
+    /// Record the current settings as the trusted state in `config.sig`.
+    fn sign(&self) {
+        // Environment overrides aren't persisted, so they aren't signed
+        let signature = self
+            .signer
+            .sign(&self.upstream.get_config(), &self.get_extended_snapshot());
+        let path = self.signature_path();
+        if let Err(e) = integrity::write_signature(&path, &signature) {
+            log::warn!("Failed to write {}: {}", path.display(), e);
+            return;
+        }
+        self.persistence_status.lock().integrity = Some(IntegrityReport {
+            state: IntegrityState::Verified,
+            algorithm: signature.algorithm,
+            checked_at: signature.signed_at,
+            signed_at: Some(signature.signed_at),
+        });
+    }
+
+    /// Sign settings that arrived through a trusted channel: the config
+    /// store, which peers write through their own config tools, or a reload
+    /// the operator asked for.
+    fn sign_external(&self) {
+        if !self.read_only {
+            self.sign();
+        }
+    }
+
+    /// Check the current settings against `config.sig` and record the result
+    /// in `persistence_status()`. Never writes to disk, so read-only tools and
+    /// read-only configs can call it.
+    ///
+    /// A mismatch means settings were edited outside the config tools; it is
+    /// logged and reported until the next successful check. A missing
+    /// signature is reported as `Unsigned`.
+    pub fn verify_integrity(&self) -> IntegrityReport {
+        let signature = integrity::read_signature(&self.signature_path());
+        let report = self.signer.verify(
+            &self.upstream.get_config(),
+            &self.get_extended_snapshot(),
+            signature.as_ref(),
+        );
+        match report.state {
+            IntegrityState::Verified => {}
+            IntegrityState::Unsigned => log::info!("Config settings are not signed yet"),
+            state => log::warn!("Config integrity check failed: {:?}", state),
+        }
+        self.persistence_status.lock().integrity = Some(report.clone());
+        report
+    }
+
+    // ========================================================================
+    // SECRETS
+    // ========================================================================
+
+    /// Names of the stored secrets, sorted.
+    #[must_use]
+    pub fn secret_names(&self) -> Vec<String> {
+        self.secrets.lock().keys().cloned().collect()
+    }
+
+    /// How the secret `name` is held (`encrypted` or `keyring`), if it exists.
+    #[must_use]
+    pub fn secret_backend(&self, name: &str) -> Option<&'static str> {
+        self.secrets.lock().get(name).map(StoredSecret::backend)
+    }
+
+    /// The plaintext of the secret `name`, for tools that need it. Never
+    /// include the result in tool output.
+    pub fn secret(&self, name: &str) -> Result<Option<String>, McpError> {
+        let Some(stored) = self.secrets.lock().get(name).cloned() else {
+            return Ok(None);
+        };
+        secrets::unseal(name, &stored, self.encryption_key.as_deref())
+            .map(Some)
+            .map_err(|e| McpError::Other(anyhow::anyhow!(e)))
+    }
+
+    /// Every secret and its plaintext, for `config_get` with
+    /// `include_secrets`. Refused unless the embedder or operator allowed it
+    /// with `allow_secret_reveal`, always when the config is read-only, and
+    /// when `auth_token` fails `authorize`.
+    pub fn reveal_secrets(
+        &self,
+        auth_token: Option<&str>,
+    ) -> Result<BTreeMap<String, String>, McpError> {
+        self.authorize(auth_token)?;
+        if !self.allow_secret_reveal || self.read_only {
+            return Err(McpError::PermissionDenied(format!(
+                "Secret values are redacted; set {}=1 on a writable config to reveal them",
+                secrets::SECRET_REVEAL_ENV
+            )));
+        }
+        log::warn!("Revealing secret values to a config_get caller");
+        self.secret_names()
+            .into_iter()
+            .map(|name| {
+                let value = self.secret(&name)?.unwrap_or_default();
+                Ok((name, value))
+            })
+            .collect()
+    }
+
+    /// Store the secret `name`, or remove it for `None`, and save
+    /// `secrets.json`. Changes are audited and reported to `on_change`
+    /// callbacks under `secrets.<name>` with the values redacted; they are
+    /// not journaled and can't be undone.
+    pub async fn set_secret(&self, name: &str, value: Option<&str>) -> Result<(), McpError> {
+        let key = format!("{}.{}", SECRETS_KEY, name);
+        let redacted = |present: bool| {
+            if present {
+                json!(REDACTED)
+            } else {
+                serde_json::Value::Null
+            }
+        };
+        let existed = self.secrets.lock().contains_key(name);
+        let result = self.try_set_secret(name, value).await;
+        let old = redacted(existed);
+        let new = redacted(value.is_some());
+        match &result {
+            Ok(()) => {
+                self.audit(&AuditRecord::new(
+                    AuditOperation::Set,
+                    key.clone(),
+                    old.clone(),
+                    new.clone(),
+                    AuditResult::Success,
+                ));
+                self.record_source(&key, ValueSource::Runtime);
+                self.notify(&ConfigChange::new(key, old, new));
+            }
+            Err(e) => self.audit(&AuditRecord::new(
+                AuditOperation::Set,
+                key,
+                old,
+                new,
+                AuditResult::Failure {
+                    error: e.to_string(),
+                },
+            )),
+        }
+        result
+    }
+
+    async fn try_set_secret(&self, name: &str, value: Option<&str>) -> Result<(), McpError> {
+        self.ensure_writable()?;
+        secrets::validate_name(name).map_err(McpError::InvalidArguments)?;
+        self.ensure_secrets_loaded()?;
+        let mut updated = self.secrets.lock().clone();
+        let previous = match value {
+            Some(value) => {
+                let stored = secrets::seal(name, value, self.encryption_key.as_deref())
+                    .map_err(|e| McpError::Other(anyhow::anyhow!(e)))?;
+                updated.insert(name.to_string(), stored)
+            }
+            None => updated.remove(name),
+        };
+        let json = serde_json::to_string_pretty(&updated).map_err(|e| McpError::Other(e.into()))?;
+        let path = self.extended_path.load().with_file_name(SECRETS_FILE);
+        persistence::write_atomic(&path, json.as_bytes(), self.durability)
+            .await
+            .map_err(|e| McpError::Other(e.into()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600));
+        }
+        *self.secrets.lock() = updated;
+        // A keyring secret replaced by an encrypted one, or removed
+        if let Some(previous) = previous
+            && self.secret_backend(name) != Some(previous.backend())
+            && let Err(e) = secrets::discard(name, &previous)
+        {
+            log::warn!("{}", e);
+        }
+        Ok(())
+    }
+
+    /// Retry loading `secrets.json` if it couldn't be read before, and refuse
+    /// while it still can't be.
+    fn ensure_secrets_loaded(&self) -> Result<(), McpError> {
+        let mut error = self.secrets_error.lock();
+        if error.is_none() {
+            return Ok(());
+        }
+        let path = self.extended_path.load().with_file_name(SECRETS_FILE);
+        match secrets::load(&path) {
+            Ok(loaded) => {
+                log::info!("Loaded {}", path.display());
+                *self.secrets.lock() = loaded;
+                *error = None;
+                Ok(())
+            }
+            Err(e) => {
+                *error = Some(e.to_string());
+                Err(McpError::Other(anyhow::anyhow!(
+                    "{} can't be read ({}); repair or remove it before changing secrets",
+                    path.display(),
+                    e
+                )))
+            }
+        }
+    }
+
+    // ========================================================================
+    // CLIENT HISTORY
+    // ========================================================================
+
+    /// Clients that have connected, pruned to `client_history_max_entries`
+    /// and `client_history_max_age_days`.
+    #[must_use]
+    pub fn client_history(&self) -> serde_json::Value {
+        let mut clients = self.clients.lock().clone();
+        self.prune_clients(&mut clients);
+        serde_json::Value::Array(clients)
+    }
+
+    /// Record that client `name` at `version` connected, then prune the
+    /// history and persist it, so the saved history never holds records the
+    /// retention settings drop.
+    pub async fn set_client_info(&self, name: &str, version: &str) -> Result<(), McpError> {
+        let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        {
+            let mut clients = self.clients.lock();
+            merge_client(
+                &mut clients,
+                json!({
+                    "client_info": { "name": name, "version": version },
+                    "connected_at": now,
+                    "last_seen": now,
+                }),
+            );
+            self.prune_clients(&mut clients);
+        }
+        self.sync_client_history().await.map(|_| ())
+    }
+
+    /// Apply the client history retention settings to `clients`.
+    fn prune_clients(&self, clients: &mut Vec<serde_json::Value>) {
+        let (max_entries, max_age_days) = self.with_extended_config(|c| {
+            (c.client_history_max_entries, c.client_history_max_age_days)
+        });
+        prune_client_history(
+            clients,
+            max_entries.unwrap_or(DEFAULT_CLIENT_HISTORY_MAX_ENTRIES) as usize,
+            max_age_days.map(|days| chrono::Duration::days(i64::from(days))),
+            chrono::Utc::now(),
+        );
+    }
+
+    /// Location of the client history file (`clients.json` next to the extended config).
+    #[must_use]
+    pub fn client_history_path(&self) -> PathBuf {
+        self.extended_path
+            .load()
+            .with_file_name(persistence::CLIENT_HISTORY_FILE)
+    }
+
+    /// Merge the infrastructure crate's client records into the history and
+    /// persist it to its own file (or the store) if it changed since the last
+    /// sync. Returns whether anything was written.
+    ///
+    /// Keeps connection bookkeeping out of the settings files, so a client
+    /// connecting never rewrites them.
+    pub async fn sync_client_history(&self) -> Result<bool, McpError> {
+        let upstream = upstream_client_history(&self.upstream.get_config());
+        let clients = {
+            let mut clients = self.clients.lock();
+            for record in upstream {
+                merge_client(&mut clients, record);
+            }
+            self.prune_clients(&mut clients);
+            serde_json::Value::Array(clients.clone())
+        };
+        let json =
+            serde_json::to_string_pretty(&clients).map_err(|e| McpError::Other(e.into()))?;
+        let hash = persistence::content_hash(json.as_bytes());
+        if *self.client_history_hash.lock() == Some(hash) {
+            return Ok(false);
+        }
+
+        let result = match self.store.clone() {
+            Some(store) => store.save_client_history(&clients).await,
+            None => {
+                let path = self.client_history_path();
+                let bytes = match self.encryption_key.as_deref() {
+                    Some(key) => key.encrypt(json.as_bytes()),
+                    None => Ok(json.into_bytes()),
+                };
+                match bytes {
+                    Ok(bytes) => persistence::write_atomic(&path, &bytes, self.durability).await,
+                    Err(e) => Err(e),
+                }
+            }
+        };
+        result.map_err(|e| McpError::Other(e.into()))?;
+        *self.client_history_hash.lock() = Some(hash);
+        Ok(true)
+    }
+
+    async fn sync_client_history_logged(&self) {
+        if let Err(e) = self.sync_client_history().await {
+            log::warn!("Failed to save client history: {}", e);
+        }
+    }
+
+    /// Record the outcome of a save; `hash` is the new file content hash, if any.
+    fn finish_save(
+        &self,
+        path: &Path,
+        result: std::io::Result<Option<u64>>,
+    ) -> Result<(), McpError> {
+        let mut status = self.persistence_status.lock();
+        status.save_pending = false;
+        match result {
+            Ok(hash) => {
+                *self.file_hash.lock() = hash;
+                status.last_save_at = Some(chrono::Utc::now());
+                status.last_error = None;
+                Ok(())
+            }
+            Err(e) => {
+                log::error!("Failed to save {}: {}", path.display(), e);
+                let record = SaveErrorRecord::new(path, &e);
+                status.last_error = Some(record.clone());
+                self.save_errors.lock().push(record);
+                Err(McpError::Other(e.into()))
+            }
+        }
+    }
+}
+
+// ============================================================================
+// HELPERS
+// ============================================================================
+
+/// `key` with a deprecated alias replaced by its canonical name, logging a
+/// warning for the alias.
+fn canonical_key(key: &str) -> &str {
+    let resolved = resolve_key(key);
+    if let Some(warning) = resolved.deprecation_warning() {
+        log::warn!("{}", warning);
+    }
+    resolved.key
+}
+
+/// Refusal of `undo_expected` and `redo_expected` when the history changed.
+fn history_moved_on(action: &str) -> McpError {
+    McpError::InvalidArguments(format!(
+        "The change to {} is no longer the one that was confirmed; {} again",
+        action, action
+    ))
+}
+
+/// Create `dir` if it was removed since it was configured, logging failures
+/// so callers get the path either way and report their own write errors.
+fn ensure_directory(dir: PathBuf) -> PathBuf {
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        log::warn!("Cannot create {}: {}", dir.display(), e);
+    }
+    dir
+}
+
+/// Value of `key` in a serialized `ConfigView`, or `null` if absent.
+fn view_value(view: &serde_json::Value, key: &str) -> serde_json::Value {
+    let path = match key.strip_prefix(EXTENSION_PREFIX) {
+        Some(rest) => match rest.split_once('.') {
+            Some((namespace, name)) => vec!["extensions", namespace, name],
+            None => vec!["extensions", rest],
+        },
+        None => key.split('.').collect(),
+    };
+    path.iter()
+        .try_fold(view, |current, part| current.get(part))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// `key` of `config` as `ServerConfig` serializes it, without serializing
+/// the rest; `None` for keys kept in the extended config.
+fn server_value(config: &ServerConfig, key: ConfigKey<'_>) -> Option<serde_json::Value> {
+    let value = match key {
+        ConfigKey::BlockedCommands => json!(config.blocked_commands),
+        ConfigKey::DefaultShell => json!(config.default_shell),
+        ConfigKey::AllowedDirectories => json!(config.allowed_directories),
+        ConfigKey::DeniedDirectories => json!(config.denied_directories),
+        ConfigKey::FileReadLineLimit => json!(config.file_read_line_limit),
+        ConfigKey::FileWriteLineLimit => json!(config.file_write_line_limit),
+        ConfigKey::FuzzySearchThreshold => json!(config.fuzzy_search_threshold),
+        ConfigKey::HttpConnectionTimeoutSecs => json!(config.http_connection_timeout_secs),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Whether `key` lives in the extended config rather than `config.json`.
+fn is_extended_key(key: &str) -> bool {
+    // Malformed `ext.*` keys too, so that they fail with the extension error
+    key.starts_with(EXTENSION_PREFIX) || ConfigKey::parse(key).is_ok_and(|k| !k.is_server())
+}
+
+/// Parse `key`, explaining what is wrong with a malformed `ext.*` key.
+fn parse_key(key: &str) -> Result<ConfigKey<'_>, McpError> {
+    parse_extension_key(key).map_err(McpError::InvalidArguments)?;
+    ConfigKey::parse(key).map_err(|e| McpError::InvalidArguments(e.to_string()))
+}
+
+/// Convert a JSON value read back from the config into the `ConfigValue` that
+/// `set_value` expects for `key`.
+fn config_value_from_json(
+    key: ConfigKey<'_>,
+    value: &serde_json::Value,
+) -> Result<ConfigValue, McpError> {
+    // The threshold is stored as a 0.0-1.0 fraction but set as a percentage
+    let value = normalize(key.as_str(), value.clone()).map_err(McpError::InvalidArguments)?;
+    ConfigValue::from_json(value).map_err(|e| {
+        McpError::InvalidArguments(format!("Cannot restore value for '{}': {}", key, e))
+    })
+}
+
+/// Client history to start from: `clients.json` when it can be read,
+/// otherwise the records `config.json` still carries from older versions.
+/// With a store, the records in `config.json`.
+fn initial_client_history(
+    builder: &ConfigManagerBuilder,
+    server: &ServerConfig,
+) -> Vec<serde_json::Value> {
+    if builder.store.is_none() {
+        let path = builder
+            .extended_path
+            .with_file_name(persistence::CLIENT_HISTORY_FILE);
+        match client_history::load(&path, builder.encryption_key.as_ref()) {
+            Ok(clients) => return clients,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log::warn!(
+                "Failed to read {}: {}; starting from the history in config.json",
+                path.display(),
+                e
+            ),
+        }
+    }
+    upstream_client_history(server)
+}
+
+/// Client records the infrastructure crate holds in `client_history`.
+fn upstream_client_history(config: &ServerConfig) -> Vec<serde_json::Value> {
+    match serde_json::to_value(config) {
+        Ok(serde_json::Value::Object(mut fields)) => match fields.remove("client_history") {
+            Some(serde_json::Value::Array(clients)) => clients,
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+impl From<UpstreamConfigManager> for ConfigManager {
+    fn from(upstream: UpstreamConfigManager) -> Self {
+        Self::new(upstream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extended_config::MAX_SEARCH_RESULTS_KEY;
+
+    /// Manager keeping its extended config, journal, and side files in `dir`.
+    /// Tests only change extended keys, so `config.json` is never written.
+    fn manager_in(dir: &Path) -> ConfigManagerBuilder {
+        ConfigManager::builder(UpstreamConfigManager::new())
+            .path(dir.join("config.tools.json"))
+            .encryption_key(None)
+    }
+
+    #[tokio::test]
+    async fn set_client_info_prunes_the_history_before_saving_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = manager_in(dir.path()).build();
+        config
+            .set_json_value("client_history_max_entries", json!(1))
+            .await
+            .unwrap();
+
+        config.set_client_info("first", "1.0.0").await.unwrap();
+        config.set_client_info("second", "2.0.0").await.unwrap();
+
+        let saved = client_history::load(&config.client_history_path(), None).unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(config.clients.lock().len(), 1);
+        assert_eq!(config.client_history(), json!(saved));
+    }
+
+    #[tokio::test]
+    async fn undo_and_redo_walk_the_change_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = manager_in(dir.path()).build();
+        config
+            .set_json_value(MAX_SEARCH_RESULTS_KEY, json!(10))
+            .await
+            .unwrap();
+        config
+            .set_json_value(MAX_SEARCH_RESULTS_KEY, json!(20))
+            .await
+            .unwrap();
+        assert_eq!(config.history_depth(), (2, 0));
+
+        let undone = config.undo().await.unwrap().unwrap();
+        assert_eq!((undone.old, undone.new), (json!(10), json!(20)));
+        assert_eq!(config.max_search_results(), 10);
+        config.undo().await.unwrap();
+        assert_eq!(
+            config.max_search_results(),
+            DEFAULT_MAX_SEARCH_RESULTS as usize
+        );
+        assert!(config.undo().await.unwrap().is_none());
+
+        config.redo().await.unwrap();
+        assert_eq!(config.max_search_results(), 10);
+        assert_eq!(config.history_depth(), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn a_new_change_clears_redo_and_stale_undos_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = manager_in(dir.path()).build();
+        config
+            .set_json_value(MAX_SEARCH_RESULTS_KEY, json!(10))
+            .await
+            .unwrap();
+        let confirmed = config.next_undo().unwrap();
+        config
+            .set_json_value(MAX_SEARCH_RESULTS_KEY, json!(20))
+            .await
+            .unwrap();
+        assert!(config.undo_expected(&confirmed).await.is_err());
+        assert_eq!(config.max_search_results(), 20);
+
+        config.undo().await.unwrap();
+        config
+            .set_json_value(MAX_SEARCH_RESULTS_KEY, json!(30))
+            .await
+            .unwrap();
+        assert!(config.redo().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn restoring_a_backup_reverts_the_extended_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = manager_in(dir.path()).backup_count(2).build();
+        for value in [10, 20, 30, 40] {
+            config
+                .set_json_value(MAX_SEARCH_RESULTS_KEY, json!(value))
+                .await
+                .unwrap();
+        }
+
+        let backups = config.list_backups(BackupFile::Tools).await;
+        assert_eq!(backups.iter().map(|b| b.index).collect::<Vec<_>>(), [1, 2]);
+
+        let deltas = config.restore_backup(BackupFile::Tools, 2).await.unwrap();
+        assert_eq!(
+            deltas,
+            [ConfigDelta {
+                key: MAX_SEARCH_RESULTS_KEY.to_string(),
+                old: json!(40),
+                new: json!(20),
+            }]
+        );
+        assert_eq!(config.max_search_results(), 20);
+        config.undo().await.unwrap();
+        assert_eq!(config.max_search_results(), 40);
+    }
+
+    #[tokio::test]
+    async fn a_restore_confirmed_against_other_changes_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = manager_in(dir.path()).build();
+        config
+            .set_json_value(MAX_SEARCH_RESULTS_KEY, json!(10))
+            .await
+            .unwrap();
+        config
+            .set_json_value(MAX_SEARCH_RESULTS_KEY, json!(20))
+            .await
+            .unwrap();
+        let confirmed = config.backup_changes(BackupFile::Tools, 1).await.unwrap();
+        config
+            .set_json_value(MAX_SEARCH_RESULTS_KEY, json!(30))
+            .await
+            .unwrap();
+
+        let restored = config
+            .restore_backup_expected(BackupFile::Tools, 1, &confirmed)
+            .await;
+        assert!(restored.is_err());
+        assert_eq!(config.max_search_results(), 30);
+    }
+
+    #[tokio::test]
+    async fn confirmation_tokens_are_single_use_and_bound_to_the_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = manager_in(dir.path()).build();
+        let dirs = json!(["/"]);
+        let token = config.request_confirmation("allowed_directories", &dirs);
+
+        assert!(
+            config
+                .confirm_change(&token, "allowed_directories", &json!(["/tmp"]))
+                .is_err()
+        );
+        // Spent by the mismatch above
+        assert!(
+            config
+                .confirm_change(&token, "allowed_directories", &dirs)
+                .is_err()
+        );
+
+        let token = config.request_confirmation("allowed_directories", &dirs);
+        assert!(
+            config
+                .confirm_change(&token, "denied_directories", &dirs)
+                .is_err()
+        );
+        let token = config.request_confirmation("allowed_directories", &dirs);
+        config
+            .confirm_change(&token, "allowed_directories", &dirs)
+            .unwrap();
+        assert!(
+            config
+                .confirm_change(&token, "allowed_directories", &dirs)
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn authorize_checks_the_configured_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let open = manager_in(dir.path()).build();
+        assert!(!open.requires_auth());
+        assert_eq!(open.authorize(None), Ok(()));
+
+        let token = AuthToken::new("s3cret").unwrap();
+        let config = manager_in(dir.path()).auth_token(Some(token)).build();
+        assert!(config.requires_auth());
+        assert_eq!(config.authorize(None), Err(AuthError::Missing));
+        assert_eq!(config.authorize(Some("guess")), Err(AuthError::Invalid));
+        assert_eq!(config.authorize(Some("s3cret")), Ok(()));
+        assert_eq!(config.authorize(Some("Bearer s3cret")), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn secrets_are_revealed_only_when_allowed_and_authorized() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = || Some(EncryptionKey::new([7; 32]));
+        let config = manager_in(dir.path()).encryption_key(key()).build();
+        config
+            .set_secret("api_token", Some("hunter2"))
+            .await
+            .unwrap();
+        assert_eq!(config.secret_names(), ["api_token"]);
+        assert!(matches!(
+            config.reveal_secrets(None),
+            Err(McpError::PermissionDenied(_))
+        ));
+
+        let token = AuthToken::new("s3cret").unwrap();
+        let config = manager_in(dir.path())
+            .encryption_key(key())
+            .allow_secret_reveal(true)
+            .auth_token(Some(token))
+            .build();
+        assert!(config.reveal_secrets(None).is_err());
+        let revealed = config.reveal_secrets(Some("s3cret")).unwrap();
+        assert_eq!(
+            revealed.get("api_token").map(String::as_str),
+            Some("hunter2")
+        );
+    }
+
+    /// `key` as saved in the extended config file at `path`, if it was saved.
+    fn saved_value(path: &Path, key: &str) -> Option<serde_json::Value> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        let saved: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        saved.get(key).filter(|value| !value.is_null()).cloned()
+    }
+
+    #[tokio::test]
+    async fn the_background_saver_defers_saves_until_shutdown_flushes_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = manager_in(dir.path())
+            .debounce(Duration::from_secs(60))
+            .max_save_delay(Duration::from_secs(60))
+            .build();
+        let path = config.extended_config_path();
+        config
+            .set_json_value(MAX_SEARCH_RESULTS_KEY, json!(10))
+            .await
+            .unwrap();
+        assert_eq!(saved_value(&path, MAX_SEARCH_RESULTS_KEY), Some(json!(10)));
+
+        config.start_background_saver();
+        config
+            .set_json_value(MAX_SEARCH_RESULTS_KEY, json!(20))
+            .await
+            .unwrap();
+        assert_eq!(saved_value(&path, MAX_SEARCH_RESULTS_KEY), Some(json!(10)));
+
+        config.shutdown().await.unwrap();
+        assert_eq!(saved_value(&path, MAX_SEARCH_RESULTS_KEY), Some(json!(20)));
+    }
+
+    #[tokio::test]
+    async fn provenance_reports_runtime_changes_and_untouched_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = manager_in(dir.path()).build();
+        config
+            .set_json_value(MAX_SEARCH_RESULTS_KEY, json!(10))
+            .await
+            .unwrap();
+        config
+            .set_json_value("rate_limits.config_set", json!(5))
+            .await
+            .unwrap();
+
+        let provenance = config.provenance();
+        let source = |key: &str| {
+            provenance
+                .iter()
+                .find(|p| p.key == key)
+                .map(|p| (p.value.clone(), p.source))
+        };
+        assert_eq!(
+            source(MAX_SEARCH_RESULTS_KEY),
+            Some((json!(10), ValueSource::Runtime))
+        );
+        assert_eq!(
+            source("rate_limits.config_set"),
+            Some((json!(5), ValueSource::Runtime))
+        );
+        assert_eq!(
+            source("command_timeout_secs"),
+            Some((serde_json::Value::Null, ValueSource::Default))
+        );
+    }
+
+    #[tokio::test]
+    async fn reset_restores_the_default_and_can_be_undone() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = manager_in(dir.path()).build();
+        config
+            .set_json_value(MAX_SEARCH_RESULTS_KEY, json!(10))
+            .await
+            .unwrap();
+        config
+            .set_json_value("rate_limits.config_set", json!(5))
+            .await
+            .unwrap();
+
+        config.reset(Some(MAX_SEARCH_RESULTS_KEY)).await.unwrap();
+        assert_eq!(
+            config.max_search_results(),
+            DEFAULT_MAX_SEARCH_RESULTS as usize
+        );
+        assert_eq!(config.rate_limit_for("config_set"), Some(5));
+        assert_eq!(
+            saved_value(&config.extended_config_path(), MAX_SEARCH_RESULTS_KEY),
+            None
+        );
+
+        config.undo().await.unwrap();
+        assert_eq!(config.max_search_results(), 10);
+    }
+
+    #[tokio::test]
+    async fn reset_refuses_unknown_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = manager_in(dir.path()).build();
+        assert!(matches!(
+            config.reset(Some("no_such_key")).await,
+            Err(McpError::InvalidArguments(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn entry_keys_are_set_and_read_through_their_section() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = manager_in(dir.path()).build();
+        config
+            .set_json_value("tool_settings.search", json!({"max_depth": 3}))
+            .await
+            .unwrap();
+        config
+            .set_json_value("rate_limits.fs_read_file", json!(5))
+            .await
+            .unwrap();
+        config
+            .set_json_value("ext.plugin.retries", json!(2))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            config.get_value("tool_settings.search.max_depth"),
+            Some(json!(3))
+        );
+        assert_eq!(config.get_value("rate_limits.fs_read_file"), Some(json!(5)));
+        assert_eq!(
+            config.get_value("rate_limits"),
+            Some(json!({"fs_read_file": 5}))
+        );
+        assert_eq!(config.get_value("ext.plugin.retries"), Some(json!(2)));
+
+        config
+            .set_json_value("rate_limits.fs_read_file", serde_json::Value::Null)
+            .await
+            .unwrap();
+        assert_eq!(config.get_value("rate_limits.fs_read_file"), None);
+        assert!(
+            config
+                .set_json_value("rate_limits.", json!(5))
+                .await
+                .is_err()
+        );
+        assert!(config.set_json_value("ext.plugin", json!(1)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn get_value_reads_single_settings_and_paths_into_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = manager_in(dir.path()).build();
+        assert_eq!(config.get_value(MAX_SEARCH_RESULTS_KEY), None);
+        assert_eq!(config.get_value("telemetry_enabled"), Some(json!(false)));
+        assert_eq!(config.get_value("shell_args"), None);
+
+        config
+            .set_json_value("shell_args", json!({"linux": ["-lc"]}))
+            .await
+            .unwrap();
+        assert_eq!(config.get_value("shell_args.linux"), Some(json!(["-lc"])));
+        assert_eq!(config.get_value("shell_args.windows"), None);
+        assert_eq!(config.get_value("no_such_key"), None);
+        assert_eq!(config.get_value("no_such.key"), None);
+    }
+
+    #[tokio::test]
+    async fn tool_settings_that_fail_to_save_are_not_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = manager_in(dir.path())
+            .retry_policy(RetryPolicy {
+                max_attempts: 1,
+                ..RetryPolicy::default()
+            })
+            .build();
+        // A directory where the config file should be makes every save fail
+        std::fs::create_dir_all(dir.path().join("config.tools.json/blocker")).unwrap();
+
+        let result = config
+            .set_tool_settings("search", &json!({"max_depth": 3}))
+            .await;
+        assert!(result.is_err());
+        assert_eq!(config.history_depth(), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn reloading_config_json_skips_values_that_fail_validation() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = manager_in(dir.path()).build();
+        let before = serde_json::to_value(&*config.get_config_snapshot()).unwrap();
+        let path = dir.path().join("config.json");
+        let edited = json!({
+            "file_read_line_limit": 0,
+            "allowed_directories": [dir.path().join("missing")],
+        });
+        std::fs::write(&path, edited.to_string()).unwrap();
+
+        config.reload_upstream_file(&path).await.unwrap();
+        let after = serde_json::to_value(&*config.get_config_snapshot()).unwrap();
+        assert_eq!(after, before);
+    }
+}
@@ -0,0 +1,413 @@
+//! Per-key metadata
+//!
+//! Describes each fixed `ConfigKey` for `config_list_keys`, schema generation, and
+//! UI clients that render config editors.
+
+use crate::audit::AUDIT_LOG_PATH_KEY;
+use crate::client_history::{CLIENT_HISTORY_MAX_AGE_DAYS_KEY, CLIENT_HISTORY_MAX_ENTRIES_KEY};
+use crate::extended_config::{
+    BLOCKED_COMMAND_PATTERNS_KEY, CACHE_DIRECTORY_KEY, COMMAND_TIMEOUT_SECS_KEY,
+    MAX_SEARCH_RESULTS_KEY, MAX_SEARCH_RESULTS_LIMIT, TELEMETRY_ENABLED_KEY, TEMP_DIRECTORY_KEY,
+};
+use crate::file_encoding::DEFAULT_ENCODING_KEY;
+use crate::host_policy::{ALLOWED_HOSTS_KEY, DENIED_HOSTS_KEY};
+use crate::keys::ConfigKey;
+use crate::log_level::LOG_LEVEL_KEY;
+use crate::otlp::OTLP_ENDPOINT_KEY;
+use crate::path_policy::FOLLOW_SYMLINKS_KEY;
+use crate::shell::SHELL_ARGS_KEY;
+use crate::subprocess_env::ENV_PASSTHROUGH_KEY;
+use serde::Serialize;
+
+// ============================================================================
+// METADATA TYPES
+// ============================================================================
+
+/// JSON shape a key accepts in `config_set`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueType {
+    String,
+    Number,
+    /// A number that may have a fractional part.
+    Float,
+    Boolean,
+    StringArray,
+}
+
+/// Inclusive numeric bounds for a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ValueRange {
+    pub min: i64,
+    pub max: Option<i64>,
+}
+
+/// Whether changing a key can weaken the server's sandbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityLevel {
+    Normal,
+    /// Controls what the server may execute or access.
+    Sensitive,
+}
+
+/// How much harm a bad value for a key can do, for clients deciding which
+/// changes need a human's approval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskLevel {
+    /// Limits and formatting; a bad value only degrades tool output.
+    Safe,
+    /// Where the server writes, what it reports, or how long commands run.
+    Caution,
+    /// Can widen what the server may execute or access; approval recommended.
+    Dangerous,
+}
+
+impl RiskLevel {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Safe => "safe",
+            Self::Caution => "caution",
+            Self::Dangerous => "dangerous",
+        }
+    }
+
+    /// Risk of `key` given its security level. Sensitive keys are dangerous
+    /// unless they only affect the audit trail.
+    fn classify(key: &str, security: SecurityLevel) -> Self {
+        match (key, security) {
+            (AUDIT_LOG_PATH_KEY, _) => Self::Caution,
+            (_, SecurityLevel::Sensitive) => Self::Dangerous,
+            (
+                TEMP_DIRECTORY_KEY | CACHE_DIRECTORY_KEY | TELEMETRY_ENABLED_KEY
+                | OTLP_ENDPOINT_KEY | COMMAND_TIMEOUT_SECS_KEY,
+                _,
+            ) => Self::Caution,
+            _ => Self::Safe,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyMetadata {
+    pub key: &'static str,
+    pub description: &'static str,
+    pub value_type: ValueType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub range: Option<ValueRange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<&'static str>,
+    pub security: SecurityLevel,
+    pub risk: RiskLevel,
+    /// How the value interacts with per-call arguments, if it is only a default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<&'static str>,
+}
+
+// ============================================================================
+// KEY METADATA
+// ============================================================================
+
+impl ConfigKey<'_> {
+    /// Description, type, range, and security level of the key; `None` for
+    /// whole sections and their entries, such as `tool_settings.<category>`.
+    #[must_use]
+    pub fn metadata(self) -> Option<KeyMetadata> {
+        let (description, value_type, range, unit, security) = match self {
+            ConfigKey::BlockedCommands => (
+                "Commands that the terminal tool refuses to execute",
+                ValueType::StringArray,
+                None,
+                None,
+                SecurityLevel::Sensitive,
+            ),
+            ConfigKey::DefaultShell => (
+                "Shell used for all command executions",
+                ValueType::String,
+                None,
+                None,
+                SecurityLevel::Sensitive,
+            ),
+            ConfigKey::AllowedDirectories => (
+                "Directories the server may access; empty allows the entire file system",
+                ValueType::StringArray,
+                None,
+                None,
+                SecurityLevel::Sensitive,
+            ),
+            ConfigKey::DeniedDirectories => (
+                "Directories the server may never access, checked before allowed_directories",
+                ValueType::StringArray,
+                None,
+                None,
+                SecurityLevel::Sensitive,
+            ),
+            ConfigKey::FileReadLineLimit => (
+                "Maximum lines returned by a single fs_read_file call",
+                ValueType::Number,
+                Some(ValueRange { min: 1, max: None }),
+                Some("lines"),
+                SecurityLevel::Normal,
+            ),
+            ConfigKey::FileWriteLineLimit => (
+                "Maximum lines accepted by a single fs_write_file call",
+                ValueType::Number,
+                Some(ValueRange { min: 1, max: None }),
+                Some("lines"),
+                SecurityLevel::Normal,
+            ),
+            ConfigKey::FuzzySearchThreshold => (
+                "Minimum similarity for fuzzy search matches",
+                ValueType::Float,
+                Some(ValueRange { min: 0, max: Some(100) }),
+                Some("percent"),
+                SecurityLevel::Normal,
+            ),
+            ConfigKey::HttpConnectionTimeoutSecs => (
+                "Timeout for outbound HTTP connections",
+                ValueType::Number,
+                Some(ValueRange { min: 1, max: None }),
+                Some("seconds"),
+                SecurityLevel::Normal,
+            ),
+            key if ConfigKey::EXTENDED.contains(&key) => {
+                return extended_key_metadata(key.as_str());
+            }
+            _ => return None,
+        };
+        Some(KeyMetadata {
+            key: self.as_str(),
+            description,
+            value_type,
+            range,
+            unit,
+            security,
+            risk: RiskLevel::classify(self.as_str(), security),
+            notes: match self {
+                ConfigKey::FuzzySearchThreshold => Some(
+                    "Accepts a fraction (0.85) or a whole percentage (85); reported as a fraction",
+                ),
+                ConfigKey::HttpConnectionTimeoutSecs => {
+                    Some("Also accepts a duration string such as 30s or 1m")
+                }
+                _ => None,
+            },
+        })
+    }
+}
+
+/// Metadata for one of `EXTENDED_KEYS`, or `None` for any other key.
+#[must_use]
+pub fn extended_key_metadata(key: &str) -> Option<KeyMetadata> {
+    let (key, description, value_type, range, unit, security, notes) = match key {
+        LOG_LEVEL_KEY => (
+            LOG_LEVEL_KEY,
+            "Most verbose level logged: off, error, warn, info, debug, or trace",
+            ValueType::String,
+            None,
+            None,
+            SecurityLevel::Normal,
+            Some("Applied immediately; null restores the host's level"),
+        ),
+        TELEMETRY_ENABLED_KEY => (
+            TELEMETRY_ENABLED_KEY,
+            "Whether downstream crates may report usage",
+            ValueType::Boolean,
+            None,
+            None,
+            SecurityLevel::Normal,
+            None,
+        ),
+        BLOCKED_COMMAND_PATTERNS_KEY => (
+            BLOCKED_COMMAND_PATTERNS_KEY,
+            "Regexes, or globs prefixed with glob:, of command lines the terminal tool refuses",
+            ValueType::StringArray,
+            None,
+            None,
+            SecurityLevel::Sensitive,
+            None,
+        ),
+        COMMAND_TIMEOUT_SECS_KEY => (
+            COMMAND_TIMEOUT_SECS_KEY,
+            "Default timeout for command execution",
+            ValueType::Number,
+            Some(ValueRange { min: 1, max: None }),
+            Some("seconds"),
+            SecurityLevel::Normal,
+            Some(
+                "Default only: a timeout passed to a single command call overrides it for \
+                 that call; unset means 120 seconds. Also accepts a duration string such as 5m",
+            ),
+        ),
+        TEMP_DIRECTORY_KEY => (
+            TEMP_DIRECTORY_KEY,
+            "Directory all tools put scratch files in",
+            ValueType::String,
+            None,
+            None,
+            SecurityLevel::Normal,
+            Some("Created if missing and must be writable; unset means <system temp>/kodegen"),
+        ),
+        CACHE_DIRECTORY_KEY => (
+            CACHE_DIRECTORY_KEY,
+            "Directory all tools cache data in",
+            ValueType::String,
+            None,
+            None,
+            SecurityLevel::Normal,
+            Some("Created if missing and must be writable; unset means <user cache>/kodegen"),
+        ),
+        ENV_PASSTHROUGH_KEY => (
+            ENV_PASSTHROUGH_KEY,
+            "Environment variables forwarded to spawned commands; LC_* style prefixes allowed",
+            ValueType::StringArray,
+            None,
+            None,
+            SecurityLevel::Sensitive,
+            Some("Unset means a safe default set (PATH, HOME, LANG, ...); [] forwards nothing"),
+        ),
+        DEFAULT_ENCODING_KEY => (
+            DEFAULT_ENCODING_KEY,
+            "Encoding file tools read and write with: utf-8, utf-16le, utf-16be, latin-1, \
+             windows-1252, or auto",
+            ValueType::String,
+            None,
+            None,
+            SecurityLevel::Normal,
+            Some(
+                "A per-call encoding overrides it; auto detects on read and writes new files \
+                 as UTF-8",
+            ),
+        ),
+        FOLLOW_SYMLINKS_KEY => (
+            FOLLOW_SYMLINKS_KEY,
+            "Whether symlinks inside allowed_directories may lead outside them",
+            ValueType::Boolean,
+            None,
+            None,
+            SecurityLevel::Sensitive,
+            Some("Off by default; denied_directories apply to symlink targets either way"),
+        ),
+        MAX_SEARCH_RESULTS_KEY => (
+            MAX_SEARCH_RESULTS_KEY,
+            "Most results search tools return per call",
+            ValueType::Number,
+            Some(ValueRange { min: 1, max: Some(i64::from(MAX_SEARCH_RESULTS_LIMIT)) }),
+            Some("results"),
+            SecurityLevel::Normal,
+            Some("Unset means 1000"),
+        ),
+        SHELL_ARGS_KEY => (
+            SHELL_ARGS_KEY,
+            "Arguments passed to default_shell before the command line, per platform",
+            ValueType::StringArray,
+            None,
+            None,
+            SecurityLevel::Sensitive,
+            Some(
+                "An array sets this platform's entry; unset means -c, or -NoProfile -Command \
+                 for PowerShell and /C for cmd",
+            ),
+        ),
+        AUDIT_LOG_PATH_KEY => (
+            AUDIT_LOG_PATH_KEY,
+            "JSONL file every config mutation is appended to, with its user, values, and result",
+            ValueType::String,
+            None,
+            None,
+            SecurityLevel::Sensitive,
+            Some("Created if missing; unset disables auditing. The file is never rotated"),
+        ),
+        OTLP_ENDPOINT_KEY => (
+            OTLP_ENDPOINT_KEY,
+            "OTLP/HTTP collector config operation spans are exported to",
+            ValueType::String,
+            None,
+            None,
+            SecurityLevel::Normal,
+            Some(
+                "Needs a build with the otlp feature; takes effect on restart. /v1/traces is \
+                 appended to a URL without a path; unset disables export",
+            ),
+        ),
+        CLIENT_HISTORY_MAX_ENTRIES_KEY => (
+            CLIENT_HISTORY_MAX_ENTRIES_KEY,
+            "Most client connection records kept; the least recently seen are dropped",
+            ValueType::Number,
+            Some(ValueRange { min: 1, max: None }),
+            Some("records"),
+            SecurityLevel::Normal,
+            Some("Unset means 100"),
+        ),
+        CLIENT_HISTORY_MAX_AGE_DAYS_KEY => (
+            CLIENT_HISTORY_MAX_AGE_DAYS_KEY,
+            "Age after which a client connection record is dropped",
+            ValueType::Number,
+            Some(ValueRange { min: 1, max: None }),
+            Some("days"),
+            SecurityLevel::Normal,
+            Some("Measured from when the client was last seen; unset keeps records of any age"),
+        ),
+        ALLOWED_HOSTS_KEY => (
+            ALLOWED_HOSTS_KEY,
+            "Hosts fetch and browser tools may reach; *.example.com matches subdomains",
+            ValueType::StringArray,
+            None,
+            None,
+            SecurityLevel::Sensitive,
+            Some("Empty allows every host not in denied_hosts"),
+        ),
+        DENIED_HOSTS_KEY => (
+            DENIED_HOSTS_KEY,
+            "Hosts fetch and browser tools must not reach; checked before allowed_hosts",
+            ValueType::StringArray,
+            None,
+            None,
+            SecurityLevel::Sensitive,
+            None,
+        ),
+        _ => return None,
+    };
+    Some(KeyMetadata {
+        key,
+        description,
+        value_type,
+        range,
+        unit,
+        security,
+        risk: RiskLevel::classify(key, security),
+        notes,
+    })
+}
+
+/// Metadata for a `ConfigKey`, by canonical name.
+#[must_use]
+pub fn metadata_for(key: &str) -> Option<KeyMetadata> {
+    ConfigKey::parse(key).ok()?.metadata()
+}
+
+/// Risk level of `key`, or `None` for a key without metadata such as
+/// `ext.*` and `tool_settings.*`.
+#[must_use]
+pub fn risk_level(key: &str) -> Option<RiskLevel> {
+    metadata_for(key).map(|m| m.risk)
+}
+
+/// Whether `key` is marked `SecurityLevel::Sensitive`; unknown keys are not.
+#[must_use]
+pub fn is_sensitive(key: &str) -> bool {
+    metadata_for(key).is_some_and(|m| m.security == SecurityLevel::Sensitive)
+}
+
+/// Metadata for every known key, in `ConfigKey::SERVER` order followed by
+/// `ConfigKey::EXTENDED`.
+#[must_use]
+pub fn all_key_metadata() -> Vec<KeyMetadata> {
+    ConfigKey::SERVER
+        .iter()
+        .chain(ConfigKey::EXTENDED)
+        .filter_map(|key| key.metadata())
+        .collect()
+}
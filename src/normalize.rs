@@ -0,0 +1,149 @@
+//! Alternative input forms
+//!
+//! Some keys accept more than one spelling of a value. `normalize` rewrites
+//! them into the single form the config stores before validation, so the
+//! rules and the saved files only ever see that form.
+
+use crate::keys::ConfigKey;
+use crate::metadata::{ValueType, metadata_for};
+use serde_json::json;
+
+/// Rewrite `value` for `key` into the form validation expects. Anything else
+/// is returned unchanged for validation to judge.
+pub(crate) fn normalize(key: &str, value: serde_json::Value) -> Result<serde_json::Value, String> {
+    if key == ConfigKey::FuzzySearchThreshold.as_str() {
+        return Ok(threshold_percent(value));
+    }
+    if !value.is_null() && metadata_for(key).is_some_and(|m| m.value_type == ValueType::Boolean) {
+        return coerce_bool(&value).map(serde_json::Value::Bool).ok_or_else(|| {
+            format!("Invalid value for '{}': expected true or false, got {}", key, value)
+        });
+    }
+    match (&value, duration_unit(key)) {
+        (serde_json::Value::String(s), Some(unit)) => duration_in(key, s, unit).map(|n| json!(n)),
+        _ => Ok(value),
+    }
+}
+
+/// `fuzzy_search_threshold` given as a fraction (`0.85`) or a percentage
+/// (`85`), as the whole percentage `ConfigValue::Number` holds.
+///
+/// Integers are always percentages, so `1` is 1% while `1.0` is 100%.
+fn threshold_percent(value: serde_json::Value) -> serde_json::Value {
+    if !value.is_f64() {
+        return value;
+    }
+    match value.as_f64() {
+        Some(fraction) if (0.0..=1.0).contains(&fraction) => {
+            json!((fraction * 100.0).round() as i64)
+        }
+        Some(percent) => json!(percent.round() as i64),
+        None => value,
+    }
+}
+
+// ============================================================================
+// BOOLEANS
+// ============================================================================
+
+/// `value` as a boolean: `true`/`false`, `1`/`0`, or one of the strings
+/// `true`, `false`, `yes`, `no`, `on`, `off`, `1`, and `0` in any case.
+pub(crate) fn coerce_bool(value: &serde_json::Value) -> Option<bool> {
+    match value {
+        serde_json::Value::Bool(b) => Some(*b),
+        serde_json::Value::Number(n) => match n.as_u64()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        },
+        serde_json::Value::String(s) => match s.trim().to_ascii_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => Some(true),
+            "false" | "no" | "off" | "0" => Some(false),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// ============================================================================
+// DURATIONS
+// ============================================================================
+
+/// Unit a timeout key is stored in, if `key` is a number of seconds or
+/// milliseconds and so also accepts duration strings.
+pub(crate) fn duration_unit(key: &str) -> Option<DurationUnit> {
+    let metadata = metadata_for(key).filter(|m| m.value_type == ValueType::Number)?;
+    match metadata.unit? {
+        "seconds" => Some(DurationUnit::Seconds),
+        "milliseconds" => Some(DurationUnit::Milliseconds),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DurationUnit {
+    Seconds,
+    Milliseconds,
+}
+
+/// `text` as a whole number of `unit`s.
+fn duration_in(key: &str, text: &str, unit: DurationUnit) -> Result<u64, String> {
+    let millis = parse_duration_ms(text)
+        .map_err(|e| format!("Invalid value for '{}': {}", key, e))?;
+    match unit {
+        DurationUnit::Milliseconds => Ok(millis),
+        DurationUnit::Seconds if millis % 1000 == 0 => Ok(millis / 1000),
+        DurationUnit::Seconds => Err(format!(
+            "Invalid value for '{}': '{}' is not a whole number of seconds",
+            key, text
+        )),
+    }
+}
+
+/// Parse a duration such as `500ms`, `30s`, `5m`, `1h`, or `1m30s` into
+/// milliseconds. A number without a unit is taken as seconds.
+pub fn parse_duration_ms(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("empty duration".to_string());
+    }
+    if let Ok(secs) = text.parse::<u64>() {
+        return secs
+            .checked_mul(1000)
+            .ok_or_else(|| format!("duration '{}' is too long", text));
+    }
+    let mut total: f64 = 0.0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let (number, tail) = rest.split_at(number_len);
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let number: f64 = number
+            .parse()
+            .map_err(|_| format!("'{}' is not a duration such as 30s, 5m, or 500ms", text))?;
+        let scale = match unit.trim() {
+            "ms" => 1.0,
+            "s" | "sec" | "secs" => 1_000.0,
+            "m" | "min" | "mins" => 60_000.0,
+            "h" | "hr" | "hrs" => 3_600_000.0,
+            "" => return Err(format!("'{}' is missing a unit after {}", text, number)),
+            other => {
+                return Err(format!(
+                    "unknown unit '{}' in '{}'; use ms, s, m, or h",
+                    other, text
+                ));
+            }
+        };
+        total += number * scale;
+        rest = tail.trim_start();
+    }
+    if total > u64::MAX as f64 {
+        return Err(format!("duration '{}' is too long", text));
+    }
+    Ok(total.round() as u64)
+}
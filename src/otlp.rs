@@ -0,0 +1,100 @@
+//! Trace export
+//!
+//! Config operations are recorded as `tracing` spans: `config.set` for each
+//! key set and each `config.json` key reset, `config.commit` for each extended
+//! config key reset and each key restored from a backup, `config.save` for
+//! each save of the extended config, and `config.tool` for each tool call,
+//! which for `config_get` covers the whole read and for `config_undo` the
+//! change undone.
+//! Spans carry the key or tool name, and a failed operation records its
+//! error, which marks the span as failed.
+//!
+//! With the `otlp` feature, `OtlpExporter::install` sends the spans to the
+//! collector at `otlp_endpoint`. The exporter is installed once at startup,
+//! so a new endpoint takes effect when the server restarts. Headers, timeouts,
+//! and compression come from the standard `OTEL_EXPORTER_OTLP_*` variables.
+
+/// Key of the OTLP/HTTP collector traces are exported to.
+pub const OTLP_ENDPOINT_KEY: &str = "otlp_endpoint";
+
+/// `entry` checked as an `http` or `https` collector URL.
+pub(crate) fn validate_endpoint(entry: &str) -> Result<String, String> {
+    let entry = entry.trim();
+    let url = reqwest::Url::parse(entry).map_err(|e| format!("'{}': {}", entry, e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("'{}': expected an http or https URL", entry));
+    }
+    if url.host_str().is_none() {
+        return Err(format!("'{}': missing host", entry));
+    }
+    Ok(entry.to_string())
+}
+
+// ============================================================================
+// EXPORTER
+// ============================================================================
+
+/// Name spans are exported under.
+#[cfg(feature = "otlp")]
+const SERVICE_NAME: &str = "kodegen-config";
+
+/// Path the OTLP/HTTP trace receiver listens on.
+#[cfg(feature = "otlp")]
+const TRACES_PATH: &str = "/v1/traces";
+
+/// Exports this process's spans over OTLP/HTTP until shut down.
+#[cfg(feature = "otlp")]
+pub struct OtlpExporter {
+    provider: opentelemetry_sdk::trace::SdkTracerProvider,
+}
+
+#[cfg(feature = "otlp")]
+impl OtlpExporter {
+    /// Install a global `tracing` subscriber exporting spans to `endpoint`.
+    ///
+    /// Fails if the exporter can't be built or the process already has a
+    /// global subscriber.
+    pub fn install(endpoint: &str) -> anyhow::Result<Self> {
+        use opentelemetry::trace::TracerProvider as _;
+        use opentelemetry_otlp::WithExportConfig;
+        use tracing_subscriber::layer::SubscriberExt;
+        use tracing_subscriber::util::SubscriberInitExt;
+
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(traces_url(endpoint))
+            .build()?;
+        let resource = opentelemetry_sdk::Resource::builder()
+            .with_service_name(SERVICE_NAME)
+            .build();
+        let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(resource)
+            .build();
+        let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+        tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer))
+            .try_init()?;
+        log::info!("Exporting traces to {}", traces_url(endpoint));
+        Ok(Self { provider })
+    }
+
+    /// Send any spans still buffered and stop exporting.
+    pub fn shutdown(&self) {
+        if let Err(e) = self.provider.shutdown() {
+            log::warn!("Failed to flush exported traces: {}", e);
+        }
+    }
+}
+
+/// The traces URL for `endpoint`: `/v1/traces` is appended unless the
+/// endpoint already has a path.
+#[cfg(feature = "otlp")]
+fn traces_url(endpoint: &str) -> String {
+    match reqwest::Url::parse(endpoint) {
+        Ok(url) if url.path() == "/" => {
+            format!("{}{}", endpoint.trim_end_matches('/'), TRACES_PATH)
+        }
+        _ => endpoint.to_string(),
+    }
+}
@@ -0,0 +1,334 @@
+//! Path access policy
+//!
+//! The single implementation of the `allowed_directories` /
+//! `denied_directories` check, so tool crates don't each reimplement it
+//! against the raw string lists. Paths and directory entries are
+//! canonicalized before comparison, so a symlink is judged by its target;
+//! a path that doesn't exist yet is judged by its nearest existing ancestor.
+//! A symlink inside an allowed directory whose target lies outside all of
+//! them is refused unless `follow_symlinks` is on.
+
+use serde::Serialize;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+/// Key of the switch that lets symlinks lead out of `allowed_directories`.
+pub const FOLLOW_SYMLINKS_KEY: &str = "follow_symlinks";
+
+/// Outcome of checking a path against the directory lists.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum PathDecision {
+    /// Inside `dir`, an entry of `allowed_directories`.
+    Allowed { path: PathBuf, dir: PathBuf },
+    /// `allowed_directories` is empty, so every path not denied is allowed.
+    Unrestricted { path: PathBuf },
+    /// Inside `dir`, an entry of `denied_directories`.
+    Denied { path: PathBuf, dir: PathBuf },
+    /// Outside every entry of `allowed_directories`.
+    NotAllowed { path: PathBuf },
+    /// `link` lies inside the allowed `dir` but resolves to `path` outside
+    /// it, and `follow_symlinks` is on.
+    FollowedSymlink {
+        path: PathBuf,
+        link: PathBuf,
+        dir: PathBuf,
+    },
+    /// `link` lies inside the allowed `dir` but resolves to `path` outside
+    /// it, and `follow_symlinks` is off.
+    SymlinkEscape {
+        path: PathBuf,
+        link: PathBuf,
+        dir: PathBuf,
+    },
+}
+
+impl PathDecision {
+    #[must_use]
+    pub fn is_allowed(&self) -> bool {
+        matches!(
+            self,
+            Self::Allowed { .. } | Self::Unrestricted { .. } | Self::FollowedSymlink { .. }
+        )
+    }
+
+    /// The path as resolved for the check.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Allowed { path, .. }
+            | Self::Unrestricted { path }
+            | Self::Denied { path, .. }
+            | Self::NotAllowed { path }
+            | Self::FollowedSymlink { path, .. }
+            | Self::SymlinkEscape { path, .. } => path,
+        }
+    }
+
+    /// Why the path was allowed or refused, for error messages.
+    #[must_use]
+    pub fn reason(&self) -> String {
+        match self {
+            Self::Allowed { dir, .. } => format!("inside allowed directory {}", dir.display()),
+            Self::Unrestricted { .. } => "no allowed directories configured".to_string(),
+            Self::Denied { dir, .. } => format!("inside denied directory {}", dir.display()),
+            Self::NotAllowed { .. } => "outside every allowed directory".to_string(),
+            Self::FollowedSymlink { link, dir, .. } => format!(
+                "symlink {} in allowed directory {} followed",
+                link.display(),
+                dir.display()
+            ),
+            Self::SymlinkEscape { link, dir, .. } => format!(
+                "symlink {} escapes allowed directory {} and follow_symlinks is off",
+                link.display(),
+                dir.display()
+            ),
+        }
+    }
+}
+
+impl fmt::Display for PathDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.is_allowed() { "allowed" } else { "denied" };
+        write!(f, "{} {}: {}", self.path().display(), verdict, self.reason())
+    }
+}
+
+/// Check `path` against `denied` first, then `allowed`.
+///
+/// A path is denied if either its resolved form or its literal form lies in
+/// a denied directory, so neither a symlink out of nor a symlink into a
+/// denied directory escapes the check. Allowed directories are matched
+/// against the resolved form, and against the literal form only when
+/// `follow_symlinks` is set.
+#[must_use]
+pub fn evaluate_path(
+    path: &Path,
+    allowed: &[String],
+    denied: &[String],
+    follow_symlinks: bool,
+) -> PathDecision {
+    let literal = normalize(&absolute(path));
+    let resolved = resolve(path);
+    for entry in denied.iter().filter(|dir| !dir.is_empty()) {
+        let dir = resolve(Path::new(entry));
+        let literal_dir = normalize(&absolute(Path::new(entry)));
+        if is_within(&resolved, &dir) || is_within(&literal, &literal_dir) {
+            return PathDecision::Denied {
+                path: resolved,
+                dir,
+            };
+        }
+    }
+    if allowed.is_empty() {
+        return PathDecision::Unrestricted { path: resolved };
+    }
+    if let Some(dir) = entries(allowed).find(|dir| is_within(&resolved, dir)) {
+        return PathDecision::Allowed {
+            path: resolved,
+            dir,
+        };
+    }
+    let escaped_from = allowed
+        .iter()
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| normalize(&absolute(Path::new(dir))))
+        .find(|dir| is_within(&literal, dir));
+    match escaped_from {
+        Some(dir) if follow_symlinks => PathDecision::FollowedSymlink {
+            path: resolved,
+            link: literal,
+            dir,
+        },
+        Some(dir) => PathDecision::SymlinkEscape {
+            path: resolved,
+            link: literal,
+            dir,
+        },
+        None => PathDecision::NotAllowed { path: resolved },
+    }
+}
+
+/// Whether `path` is `dir` or lies inside it. Case-insensitive on Windows.
+fn is_within(path: &Path, dir: &Path) -> bool {
+    #[cfg(windows)]
+    {
+        let lower = |p: &Path| PathBuf::from(p.to_string_lossy().to_lowercase());
+        lower(path).starts_with(lower(dir))
+    }
+    #[cfg(not(windows))]
+    path.starts_with(dir)
+}
+
+/// Resolved form of every non-empty allowed directory entry.
+fn entries(dirs: &[String]) -> impl Iterator<Item = PathBuf> + '_ {
+    dirs.iter()
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| resolve(Path::new(dir)))
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Canonicalize the longest existing ancestor of `path` and append the rest.
+fn resolve(path: &Path) -> PathBuf {
+    let absolute = absolute(path);
+    let mut existing = absolute.as_path();
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return normalize(&rest.iter().rev().fold(canonical, |dir, name| dir.join(name)));
+        }
+        let mut components = existing.components();
+        match components.next_back() {
+            Some(last @ (Component::Normal(_) | Component::CurDir | Component::ParentDir)) => {
+                rest.push(last);
+                existing = components.as_path();
+            }
+            _ => return normalize(&absolute),
+        }
+    }
+}
+
+/// Remove `.` and `..` components without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normal.pop();
+            }
+            other => normal.push(other),
+        }
+    }
+    normal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dirs(paths: &[&Path]) -> Vec<String> {
+        paths.iter().map(|p| p.display().to_string()).collect()
+    }
+
+    #[test]
+    fn allows_paths_inside_an_allowed_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let file = root.path().join("src/main.rs");
+        let decision = evaluate_path(&file, &dirs(&[root.path()]), &[], false);
+        assert!(
+            matches!(decision, PathDecision::Allowed { .. }),
+            "{}",
+            decision
+        );
+    }
+
+    #[test]
+    fn allows_everything_without_allowed_directories() {
+        let root = tempfile::tempdir().unwrap();
+        let decision = evaluate_path(root.path(), &[], &[], false);
+        assert!(
+            matches!(decision, PathDecision::Unrestricted { .. }),
+            "{}",
+            decision
+        );
+    }
+
+    #[test]
+    fn denied_directories_win_over_allowed_ones() {
+        let root = tempfile::tempdir().unwrap();
+        let secrets = root.path().join("secrets");
+        std::fs::create_dir(&secrets).unwrap();
+        let decision = evaluate_path(
+            &secrets.join("key.pem"),
+            &dirs(&[root.path()]),
+            &dirs(&[&secrets]),
+            false,
+        );
+        assert!(
+            matches!(decision, PathDecision::Denied { .. }),
+            "{}",
+            decision
+        );
+        assert!(!decision.is_allowed());
+    }
+
+    #[test]
+    fn refuses_parent_dir_traversal_out_of_an_allowed_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        let escape = project.join("../outside/file.txt");
+        let decision = evaluate_path(&escape, &dirs(&[&project]), &[], false);
+        assert!(
+            matches!(decision, PathDecision::NotAllowed { .. }),
+            "{}",
+            decision
+        );
+    }
+
+    #[test]
+    fn ignores_empty_entries() {
+        let root = tempfile::tempdir().unwrap();
+        let allowed = vec![String::new(), root.path().display().to_string()];
+        let decision = evaluate_path(root.path(), &allowed, &[String::new()], false);
+        assert!(
+            matches!(decision, PathDecision::Allowed { .. }),
+            "{}",
+            decision
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_out_of_an_allowed_directory_needs_follow_symlinks() {
+        let root = tempfile::tempdir().unwrap();
+        let project = root.path().join("project");
+        let outside = root.path().join("outside");
+        std::fs::create_dir(&project).unwrap();
+        std::fs::create_dir(&outside).unwrap();
+        let link = project.join("link");
+        std::os::unix::fs::symlink(&outside, &link).unwrap();
+        let allowed = dirs(&[&project]);
+
+        let decision = evaluate_path(&link.join("file"), &allowed, &[], false);
+        assert!(
+            matches!(decision, PathDecision::SymlinkEscape { .. }),
+            "{}",
+            decision
+        );
+        assert!(!decision.is_allowed());
+
+        let decision = evaluate_path(&link.join("file"), &allowed, &[], true);
+        assert!(
+            matches!(decision, PathDecision::FollowedSymlink { .. }),
+            "{}",
+            decision
+        );
+        assert!(decision.is_allowed());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_into_a_denied_directory_is_denied() {
+        let root = tempfile::tempdir().unwrap();
+        let secrets = root.path().join("secrets");
+        std::fs::create_dir(&secrets).unwrap();
+        let link = root.path().join("innocent");
+        std::os::unix::fs::symlink(&secrets, &link).unwrap();
+        let decision = evaluate_path(
+            &link.join("key.pem"),
+            &dirs(&[root.path()]),
+            &dirs(&[&secrets]),
+            true,
+        );
+        assert!(
+            matches!(decision, PathDecision::Denied { .. }),
+            "{}",
+            decision
+        );
+    }
+}
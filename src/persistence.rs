@@ -0,0 +1,722 @@
+//! Disk persistence for `ExtendedConfig`
+
+use crate::encryption::{self, EncryptionKey};
+use crate::extended_config::ExtendedConfig;
+use crate::format::{self, ConfigFormat};
+use crate::integrity::IntegrityReport;
+use crate::schema_version::{self, SchemaTooNew};
+use aes_gcm::aead::OsRng;
+use aes_gcm::aead::rand_core::RngCore;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+/// File name of the infrastructure crate's config.
+pub const CONFIG_FILE: &str = "config.json";
+
+/// File name of the extended config, stored alongside `config.json`.
+pub const EXTENDED_CONFIG_FILE: &str = "config.tools.json";
+
+/// File name of the client history, kept apart from the settings files.
+pub const CLIENT_HISTORY_FILE: &str = "clients.json";
+
+/// Environment variable relocating the extended config file, e.g. to a
+/// job-scoped path in CI. Its journal, backups, and signature follow it.
+pub const CONFIG_PATH_ENV: &str = "KODEGEN_CONFIG_PATH";
+
+/// Directory under `~/.kodegen` holding one directory per named profile.
+pub const PROFILES_DIR: &str = "profiles";
+
+/// Directory holding all kodegen configuration files (`~/.kodegen`).
+#[must_use]
+pub fn config_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".kodegen")
+}
+
+/// Location of `config.json` as written by the infrastructure crate.
+#[must_use]
+pub fn upstream_config_path() -> PathBuf {
+    config_dir().join(CONFIG_FILE)
+}
+
+/// Default location of the extended config file: `KODEGEN_CONFIG_PATH` if
+/// set, else an existing `config.tools.{json,toml,yaml}`, or
+/// `config.tools.json` if none exists.
+#[must_use]
+pub fn default_extended_config_path() -> PathBuf {
+    match std::env::var_os(CONFIG_PATH_ENV).filter(|path| !path.is_empty()) {
+        Some(path) => PathBuf::from(path),
+        None => format::find_existing(&config_dir().join(EXTENDED_CONFIG_FILE)),
+    }
+}
+
+/// Location of the extended config file of profile `name`:
+/// `~/.kodegen/profiles/<name>/config.tools.json`, or an existing TOML or
+/// YAML variant. Each profile keeps its own journal, backups, and history;
+/// `config.json` is shared.
+pub fn profile_config_path(name: &str) -> Result<PathBuf, String> {
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!("invalid profile name '{}': use letters, digits, '-', and '_'", name));
+    }
+    let dir = config_dir().join(PROFILES_DIR).join(name);
+    Ok(format::find_existing(&dir.join(EXTENDED_CONFIG_FILE)))
+}
+
+/// How hard a save works to get bytes onto stable storage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Durability {
+    /// Leave the write in the OS page cache.
+    None,
+    /// Flush userspace buffers to the OS before renaming.
+    Flush,
+    /// `sync_all()` before renaming, surviving power loss.
+    #[default]
+    Fsync,
+}
+
+/// Load the extended config, decrypting it with `key` if it was saved encrypted.
+///
+/// Falls back to `defaults` if the file is missing. A file that exists but
+/// can't be parsed is moved aside to `<file>.corrupt-<timestamp>` and the
+/// newest of its `backup_count` rotated backups that parses is used instead;
+/// the returned `RecoveryRecord` describes what happened. A file written with
+/// a newer schema is left untouched and its `SchemaTooNew` returned.
+pub fn load_from_disk(
+    path: &Path,
+    defaults: &ExtendedConfig,
+    key: Option<&EncryptionKey>,
+    backup_count: usize,
+) -> Result<(ExtendedConfig, Option<RecoveryRecord>), SchemaTooNew> {
+    let error = match read_config(path, key) {
+        Ok(config) => return Ok((config, None)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((defaults.clone(), None)),
+        // Missing key rather than a damaged file; leave it untouched
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+            log::warn!("Failed to read {}: {}; using defaults", path.display(), e);
+            return Ok((defaults.clone(), None));
+        }
+        Err(e) => match schema_too_new(&e) {
+            Some(too_new) => return Err(too_new),
+            None => e,
+        },
+    };
+
+    log::error!("{} is corrupt: {}", path.display(), error);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".corrupt-{}", Utc::now().format("%Y%m%dT%H%M%S")));
+    let corrupt_path = path.with_file_name(name);
+    let corrupt_path = match std::fs::rename(path, &corrupt_path) {
+        Ok(()) => Some(corrupt_path),
+        Err(e) => {
+            log::error!("Failed to move corrupt {} aside: {}", path.display(), e);
+            None
+        }
+    };
+
+    let recovered = (1..=backup_count).find_map(|index| {
+        let backup = backup_path(path, index);
+        read_config(&backup, key).ok().map(|config| (backup, config))
+    });
+    let (config, recovered_from) = match recovered {
+        Some((backup, config)) => {
+            log::warn!("Recovered config from {}", backup.display());
+            (config, Some(backup))
+        }
+        None => {
+            log::warn!("No usable backup of {}; using defaults", path.display());
+            (defaults.clone(), None)
+        }
+    };
+    let record = RecoveryRecord {
+        detected_at: Utc::now(),
+        path: path.to_path_buf(),
+        error: error.to_string(),
+        corrupt_path,
+        recovered_from,
+    };
+    Ok((config, Some(record)))
+}
+
+/// Read, decrypt, and parse one config file.
+fn read_config(path: &Path, key: Option<&EncryptionKey>) -> std::io::Result<ExtendedConfig> {
+    let contents = encryption::decode(std::fs::read(path)?, key)?;
+    // Backups (`config.tools.json.1`) carry the original extension before the index
+    let format_path = match ConfigFormat::from_path(path) {
+        Some(_) => path,
+        None => path.file_stem().map_or(path, Path::new),
+    };
+    parse_config(ConfigFormat::detect(format_path, &contents), &contents)
+}
+
+/// Parse the contents of an extended config file, migrating it from an
+/// older schema version. A newer schema fails with `ErrorKind::Unsupported`
+/// wrapping `SchemaTooNew`.
+pub(crate) fn parse_config(
+    format: ConfigFormat,
+    contents: &str,
+) -> std::io::Result<ExtendedConfig> {
+    let invalid = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+    let mut value: serde_json::Value = format.parse(contents).map_err(invalid)?;
+    schema_version::migrate(&mut value)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Unsupported, e))?;
+    serde_json::from_value(value).map_err(|e| invalid(e.to_string()))
+}
+
+/// The `SchemaTooNew` behind an error from `parse_config`, if any.
+#[must_use]
+pub(crate) fn schema_too_new(error: &std::io::Error) -> Option<SchemaTooNew> {
+    error.get_ref()?.downcast_ref::<SchemaTooNew>().cloned()
+}
+
+/// A corrupt config file found at startup.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveryRecord {
+    pub detected_at: DateTime<Utc>,
+    pub path: PathBuf,
+    pub error: String,
+    /// Where the corrupt file was moved, if that succeeded.
+    pub corrupt_path: Option<PathBuf>,
+    /// Backup the config was restored from; `None` means defaults were used.
+    pub recovered_from: Option<PathBuf>,
+}
+
+/// Write the extended config to `path` in the format implied by its
+/// extension, creating the parent directory if needed.
+///
+/// The file is replaced atomically: contents go to a `temp_path` file, are synced
+/// according to `durability`, then renamed over `path`, so a crash mid-write
+/// never leaves a truncated file. The contents are encrypted when `key` is
+/// given.
+pub async fn save_to_disk(
+    path: &Path,
+    config: &ExtendedConfig,
+    durability: Durability,
+    key: Option<&EncryptionKey>,
+) -> std::io::Result<SavedFile> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let contents = ConfigFormat::from_path(path)
+        .unwrap_or_default()
+        .serialize(config)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let bytes = match key {
+        Some(key) => key.encrypt(contents.as_bytes())?,
+        None => contents.into_bytes(),
+    };
+    let saved = SavedFile {
+        hash: content_hash(&bytes),
+        size: bytes.len() as u64,
+    };
+    write_atomic(path, &bytes, durability).await?;
+    Ok(saved)
+}
+
+/// What `save_to_disk` wrote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SavedFile {
+    /// `content_hash` of the bytes on disk.
+    pub hash: u64,
+    /// Bytes written, after encryption.
+    pub size: u64,
+}
+
+/// Path of a temporary file used while replacing `path`.
+///
+/// The name carries the process id and a random suffix, so concurrent
+/// writers in this or another process never share a temp file.
+#[must_use]
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{:016x}.tmp", std::process::id(), OsRng.next_u64()));
+    path.with_file_name(name)
+}
+
+/// Flush the directory entry of a freshly renamed `path` to disk.
+///
+/// Directories can't be opened as files on Windows, where this is a no-op.
+pub fn sync_parent_dir(path: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        std::fs::File::open(dir)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Replace `path` with `contents` via a temp file and rename.
+///
+/// With `Durability::Fsync` the parent directory is synced after the rename
+/// as well, so the new directory entry survives power loss.
+pub async fn write_atomic(
+    path: &Path,
+    contents: &[u8],
+    durability: Durability,
+) -> std::io::Result<()> {
+    let tmp = temp_path(path);
+    let result = async {
+        let mut file = tokio::fs::File::create(&tmp).await?;
+        file.write_all(contents).await?;
+        match durability {
+            Durability::None => {}
+            Durability::Flush => file.flush().await?,
+            Durability::Fsync => file.sync_all().await?,
+        }
+        drop(file);
+        tokio::fs::rename(&tmp, path).await
+    }
+    .await;
+    if result.is_ok() && durability == Durability::Fsync {
+        // The rename is done; a failed directory sync leaves no temp file
+        let path = path.to_path_buf();
+        return tokio::task::spawn_blocking(move || sync_parent_dir(&path))
+            .await
+            .map_err(std::io::Error::other)?;
+    }
+
+    if result.is_err() {
+        // Best effort: don't leave a half-written temp file behind
+        let _ = tokio::fs::remove_file(&tmp).await;
+    }
+    result
+}
+
+// ============================================================================
+// EXTERNAL EDIT DETECTION
+// ============================================================================
+
+/// Hash of file contents, used to notice edits made outside this process.
+#[must_use]
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash of `config`'s contents, independent of file format and encryption.
+///
+/// Goes through `serde_json::Value` so map keys are sorted and equal configs
+/// always hash the same.
+#[must_use]
+pub fn config_hash(config: &ExtendedConfig) -> u64 {
+    let value = serde_json::to_value(config).unwrap_or_default();
+    content_hash(value.to_string().as_bytes())
+}
+
+/// Content hash of the file at `path`, or `None` if it doesn't exist or can't be read.
+#[must_use]
+pub fn file_hash(path: &Path) -> Option<u64> {
+    std::fs::read(path).ok().map(|bytes| content_hash(&bytes))
+}
+
+/// Whether the file at `path` no longer matches `expected`, the hash recorded
+/// at load or after our last save.
+pub async fn modified_externally(path: &Path, expected: Option<u64>) -> bool {
+    let current = tokio::fs::read(path)
+        .await
+        .ok()
+        .map(|bytes| content_hash(&bytes));
+    current.is_some() && current != expected
+}
+
+/// Copy an externally edited file aside before it is overwritten.
+///
+/// The copy is named `<file>.external-<timestamp>` in the same directory.
+pub async fn backup_external_edit(path: &Path) -> std::io::Result<PathBuf> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".external-{}", Utc::now().format("%Y%m%dT%H%M%S")));
+    let backup = path.with_file_name(name);
+    tokio::fs::copy(path, &backup).await?;
+    Ok(backup)
+}
+
+/// An external edit detected before a save.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictRecord {
+    pub detected_at: DateTime<Utc>,
+    pub path: PathBuf,
+    /// Where the external version was preserved, if the backup succeeded.
+    pub backup_path: Option<PathBuf>,
+}
+
+// ============================================================================
+// BACKUP ROTATION
+// ============================================================================
+
+/// Number of rotated backups kept per file unless configured otherwise.
+pub const DEFAULT_BACKUP_COUNT: usize = 5;
+
+/// Which config file a backup belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupFile {
+    /// `config.json`, owned by the infrastructure crate.
+    #[default]
+    Config,
+    /// `config.tools.json`, this crate's extended config.
+    Tools,
+}
+
+/// A rotated backup on disk.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupInfo {
+    pub file: BackupFile,
+    /// 1 is the most recent backup.
+    pub index: usize,
+    pub path: PathBuf,
+    pub modified: Option<DateTime<Utc>>,
+    pub size_bytes: u64,
+}
+
+/// Path of backup number `index` of `path`, e.g. `config.json.2`.
+#[must_use]
+pub fn backup_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", index));
+    path.with_file_name(name)
+}
+
+/// Shift `path.1..path.{keep-1}` up by one (dropping the oldest) and copy the
+/// current file to `path.1`. Does nothing if `path` doesn't exist yet.
+pub async fn rotate_backups(path: &Path, keep: usize) -> std::io::Result<()> {
+    if keep == 0 || !tokio::fs::try_exists(path).await? {
+        return Ok(());
+    }
+    match tokio::fs::remove_file(backup_path(path, keep)).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    for index in (1..keep).rev() {
+        let from = backup_path(path, index);
+        if tokio::fs::try_exists(&from).await? {
+            tokio::fs::rename(&from, backup_path(path, index + 1)).await?;
+        }
+    }
+    tokio::fs::copy(path, backup_path(path, 1)).await?;
+    Ok(())
+}
+
+/// Existing backups of `path`, most recent first.
+pub async fn list_backups(file: BackupFile, path: &Path, keep: usize) -> Vec<BackupInfo> {
+    let mut backups = Vec::new();
+    for index in 1..=keep {
+        let backup = backup_path(path, index);
+        let Ok(metadata) = tokio::fs::metadata(&backup).await else {
+            continue;
+        };
+        backups.push(BackupInfo {
+            file,
+            index,
+            path: backup,
+            modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+            size_bytes: metadata.len(),
+        });
+    }
+    backups
+}
+
+// ============================================================================
+// RETRY
+// ============================================================================
+
+/// Bounded exponential backoff for failed saves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first; `1` disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each further retry.
+    pub base_delay: Duration,
+    /// Upper bound on a single delay.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay after failed attempt number `attempt` (1-based).
+    #[must_use]
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay)
+    }
+}
+
+/// Whether a failed save may succeed if tried again. Serialization,
+/// encryption and permission failures won't; disk-full and network filesystem
+/// errors might.
+#[must_use]
+pub fn is_retryable(error: &std::io::Error) -> bool {
+    !matches!(
+        error.kind(),
+        std::io::ErrorKind::InvalidData
+            | std::io::ErrorKind::InvalidInput
+            | std::io::ErrorKind::PermissionDenied
+    )
+}
+
+// ============================================================================
+// PERSISTENCE STATUS
+// ============================================================================
+
+/// Health of config persistence, reported by `ConfigManager::persistence_status()`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PersistenceStatus {
+    /// When the extended config was last written successfully.
+    pub last_save_at: Option<DateTime<Utc>>,
+    /// Whether a save is in progress, including while waiting to retry.
+    pub save_pending: bool,
+    /// Most recent failed save, cleared by the next success.
+    pub last_error: Option<SaveErrorRecord>,
+    /// Failed saves of the extended config since startup, after all retries.
+    pub error_count: u64,
+    /// Failed write attempts that were retried since startup.
+    pub retry_count: u64,
+    /// Failed saves of `config.json` reported by the infrastructure crate.
+    pub upstream_error_count: u64,
+    /// Most recent external edit that was overwritten by a save.
+    pub last_conflict: Option<ConflictRecord>,
+    /// External edits detected since startup.
+    pub conflict_count: u64,
+    /// Set if the extended config was corrupt at startup.
+    pub load_recovery: Option<RecoveryRecord>,
+    /// Latest check of the settings against `config.sig`.
+    pub integrity: Option<IntegrityReport>,
+    /// Set if the extended config was written with a newer schema; the
+    /// config is then read-only and runs on defaults.
+    pub schema_error: Option<SchemaTooNew>,
+}
+
+// ============================================================================
+// PERSISTENCE METRICS
+// ============================================================================
+
+/// Upper bounds of the save latency buckets, in milliseconds.
+pub const LATENCY_BUCKETS_MS: &[u64] = &[1, 5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000];
+
+/// Upper bounds of the payload size buckets, in bytes.
+pub const SIZE_BUCKETS_BYTES: &[u64] = &[1 << 10, 4 << 10, 16 << 10, 64 << 10, 256 << 10, 1 << 20];
+
+/// Bucketed distribution: `counts[i]` holds values in
+/// `(bounds[i - 1], bounds[i]]`, and the extra last count holds values above
+/// every bound.
+#[derive(Debug, Clone, Serialize)]
+pub struct Histogram {
+    pub bounds: Vec<u64>,
+    pub counts: Vec<u64>,
+    pub count: u64,
+    pub sum: u64,
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+}
+
+impl Histogram {
+    #[must_use]
+    pub fn new(bounds: &[u64]) -> Self {
+        Self {
+            bounds: bounds.to_vec(),
+            counts: vec![0; bounds.len() + 1],
+            count: 0,
+            sum: 0,
+            min: None,
+            max: None,
+        }
+    }
+
+    pub fn record(&mut self, value: u64) {
+        let bucket = self
+            .bounds
+            .iter()
+            .position(|&bound| value <= bound)
+            .unwrap_or(self.bounds.len());
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum = self.sum.saturating_add(value);
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+    }
+
+    #[must_use]
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum as f64 / self.count as f64)
+    }
+}
+
+/// Save statistics since startup, returned by `ConfigManager::persistence_metrics()`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PersistenceMetrics {
+    pub saves_succeeded: u64,
+    /// Saves that failed after all retries.
+    pub saves_failed: u64,
+    /// Saves skipped because the config hadn't changed.
+    pub saves_skipped: u64,
+    /// Wall time of each attempted save, including retries.
+    pub latency_ms: Histogram,
+    /// Bytes written by each successful save.
+    pub size_bytes: Histogram,
+}
+
+impl Default for PersistenceMetrics {
+    fn default() -> Self {
+        Self {
+            saves_succeeded: 0,
+            saves_failed: 0,
+            saves_skipped: 0,
+            latency_ms: Histogram::new(LATENCY_BUCKETS_MS),
+            size_bytes: Histogram::new(SIZE_BUCKETS_BYTES),
+        }
+    }
+}
+
+impl PersistenceMetrics {
+    /// Record a save that took `latency`; `size` is set if it succeeded.
+    pub fn record_save(&mut self, latency: Duration, size: Option<u64>) {
+        self.latency_ms
+            .record(u64::try_from(latency.as_millis()).unwrap_or(u64::MAX));
+        match size {
+            Some(size) => {
+                self.saves_succeeded += 1;
+                self.size_bytes.record(size);
+            }
+            None => self.saves_failed += 1,
+        }
+    }
+}
+
+// ============================================================================
+// SAVE ERROR LOG
+// ============================================================================
+
+/// Number of save failures retained by `SaveErrorLog`.
+pub const SAVE_ERROR_HISTORY: usize = 32;
+
+/// A single failed save.
+#[derive(Debug, Clone, Serialize)]
+pub struct SaveErrorRecord {
+    pub timestamp: DateTime<Utc>,
+    /// `std::io::ErrorKind` of the failure, e.g. `StorageFull` or `PermissionDenied`.
+    pub kind: String,
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl SaveErrorRecord {
+    #[must_use]
+    pub fn new(path: &Path, error: &std::io::Error) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            kind: format!("{:?}", error.kind()),
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Bounded ring buffer of recent save failures; the oldest record is dropped when full.
+#[derive(Debug, Clone, Default)]
+pub struct SaveErrorLog {
+    records: VecDeque<SaveErrorRecord>,
+    total: u64,
+}
+
+impl SaveErrorLog {
+    pub fn push(&mut self, record: SaveErrorRecord) {
+        if self.records.len() == SAVE_ERROR_HISTORY {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+        self.total += 1;
+    }
+
+    /// Retained records, oldest first.
+    #[must_use]
+    pub fn records(&self) -> Vec<SaveErrorRecord> {
+        self.records.iter().cloned().collect()
+    }
+
+    #[must_use]
+    pub fn latest(&self) -> Option<&SaveErrorRecord> {
+        self.records.back()
+    }
+
+    /// Failures since startup, including ones already evicted from the buffer.
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+        };
+        let delays: Vec<Duration> = (1..=6).map(|n| policy.delay(n)).collect();
+        let expected = [100, 200, 400, 800, 1000, 1000].map(Duration::from_millis);
+        assert_eq!(delays, expected);
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn only_transient_errors_are_retried() {
+        use std::io::{Error, ErrorKind};
+        assert!(is_retryable(&Error::from(ErrorKind::StorageFull)));
+        assert!(!is_retryable(&Error::from(ErrorKind::PermissionDenied)));
+        assert!(!is_retryable(&Error::from(ErrorKind::InvalidData)));
+        assert!(!is_retryable(&Error::from(ErrorKind::InvalidInput)));
+    }
+
+    #[test]
+    fn temp_paths_are_unique_siblings() {
+        let path = Path::new("/tmp/config.tools.json");
+        let (a, b) = (temp_path(path), temp_path(path));
+        assert_ne!(a, b);
+        assert_eq!(a.parent(), path.parent());
+        let name = a.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.starts_with(&format!("config.tools.json.{}.", std::process::id())));
+        assert!(name.ends_with(".tmp"));
+    }
+
+    #[tokio::test]
+    async fn write_atomic_replaces_the_file_and_leaves_no_temp_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, "old").unwrap();
+        write_atomic(&path, b"new", Durability::Fsync)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,47 @@
+//! Where effective config values come from
+//!
+//! Environment overrides are known from startup; values set at runtime or
+//! pulled from the remote source are recorded as they are applied. Anything
+//! else either still equals its default or was read from disk or the store.
+
+use serde::Serialize;
+use std::fmt;
+
+/// Origin of a key's effective value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueSource {
+    /// The built-in or product default.
+    Default,
+    /// `config.json`, the extended config file, or the configured store.
+    File,
+    /// An environment variable.
+    Env,
+    /// Set through `config_set` or the API since startup.
+    Runtime,
+    /// The remote config source.
+    Remote,
+}
+
+impl fmt::Display for ValueSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Default => "default",
+            Self::File => "file",
+            Self::Env => "env",
+            Self::Runtime => "runtime",
+            Self::Remote => "remote",
+        })
+    }
+}
+
+/// The effective value of one key and where it came from.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Provenance {
+    pub key: String,
+    pub value: serde_json::Value,
+    pub source: ValueSource,
+    /// Variable, file, store, or URL the value was read from, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+}
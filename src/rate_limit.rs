@@ -0,0 +1,88 @@
+//! Per-tool rate limiting
+//!
+//! `rate_limits` maps tool names to the requests per minute each may serve;
+//! tools without an entry are unlimited. Calls are counted over a sliding
+//! one-minute window, so after a burst further calls are refused until the
+//! oldest ones age out.
+
+use parking_lot::Mutex;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Key of the per-tool limits, set one tool at a time as `rate_limits.<tool>`.
+pub const RATE_LIMITS_KEY: &str = "rate_limits";
+
+/// Window the per-minute limits are counted over.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Parse a per-minute limit for `key`; `null` removes it and `0` is refused.
+pub(crate) fn parse_limit(key: &str, value: &serde_json::Value) -> Result<Option<u32>, String> {
+    let limit: Option<u32> = serde_json::from_value(value.clone())
+        .map_err(|e| format!("Invalid value for '{}': {}", key, e))?;
+    if limit == Some(0) {
+        return Err(format!("Invalid value for '{}': value must be positive (got 0)", key));
+    }
+    Ok(limit)
+}
+
+/// Outcome of counting one call against a tool's limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateDecision {
+    Allowed,
+    /// The tool already served `limit` calls in the last minute.
+    Limited { limit: u32, retry_after: Duration },
+}
+
+impl RateDecision {
+    #[must_use]
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, Self::Allowed)
+    }
+}
+
+impl fmt::Display for RateDecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Allowed => f.write_str("allowed"),
+            Self::Limited { limit, retry_after } => write!(
+                f,
+                "rate limit of {} requests per minute exceeded; retry in {}s",
+                limit,
+                retry_after.as_secs().max(1)
+            ),
+        }
+    }
+}
+
+/// Recent call times per tool.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    calls: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    /// Count a call to `tool` against `limit` calls per minute, unless that
+    /// would exceed it. `None` allows every call without counting it.
+    pub fn check(&self, tool: &str, limit: Option<u32>) -> RateDecision {
+        let Some(limit) = limit else {
+            return RateDecision::Allowed;
+        };
+        let now = Instant::now();
+        let mut calls = self.calls.lock();
+        let window = calls.entry(tool.to_string()).or_default();
+        while window.front().is_some_and(|&at| now.duration_since(at) >= WINDOW) {
+            window.pop_front();
+        }
+        if let Some(&oldest) = window.front()
+            && window.len() >= limit as usize
+        {
+            return RateDecision::Limited {
+                limit,
+                retry_after: WINDOW.saturating_sub(now.duration_since(oldest)),
+            };
+        }
+        window.push_back(now);
+        RateDecision::Allowed
+    }
+}
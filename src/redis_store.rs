@@ -0,0 +1,267 @@
+//! Redis persistence backend
+//!
+//! The extended config is stored as a Redis hash with one JSON field per
+//! tool category (`tool_settings.<category>`), extension namespace
+//! (`extensions.<namespace>`), and unexpanded directory list
+//! (`raw_directories.<key>`). Every save is a single MULTI/EXEC that rewrites
+//! the hash and publishes on `<key>:changes`, so other servers sharing the
+//! hash reload it as soon as a change is saved. With an encryption key each
+//! field is encrypted before it is written.
+
+use crate::encryption::{self, EncryptionKey};
+use crate::extended_config::ExtendedConfig;
+use crate::store::{ConfigStore, StoreFuture};
+use futures::StreamExt;
+use redis::AsyncCommands;
+use redis::aio::MultiplexedConnection;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OnceCell, mpsc};
+
+/// Environment variable holding the Redis URL; the store is used only when set.
+pub const REDIS_URL_ENV: &str = "KODEGEN_REDIS_URL";
+
+/// Environment variable overriding the hash key.
+pub const REDIS_KEY_ENV: &str = "KODEGEN_REDIS_KEY";
+
+/// Hash key used unless configured otherwise.
+pub const DEFAULT_REDIS_KEY: &str = "kodegen:config";
+
+/// Wait before resubscribing after the pub/sub connection drops.
+const WATCH_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Clone)]
+pub struct RedisStore {
+    client: redis::Client,
+    key: String,
+    /// Published with each save so a server ignores its own notifications.
+    origin: String,
+    encryption: Option<EncryptionKey>,
+    conn: Arc<OnceCell<MultiplexedConnection>>,
+}
+
+impl RedisStore {
+    /// Store the config in the hash `key` on the server at `url`.
+    /// Connects lazily.
+    pub fn new(url: &str, key: impl Into<String>) -> std::io::Result<Self> {
+        let client = redis::Client::open(url).map_err(to_io)?;
+        let nanos = chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        Ok(Self {
+            client,
+            key: key.into(),
+            origin: format!("{}-{:x}", std::process::id(), nanos),
+            encryption: None,
+            conn: Arc::new(OnceCell::new()),
+        })
+    }
+
+    /// Encrypt each stored field with `key`.
+    #[must_use]
+    pub fn encryption_key(mut self, key: Option<EncryptionKey>) -> Self {
+        self.encryption = key;
+        self
+    }
+
+    /// Store configured from `KODEGEN_REDIS_URL` and `KODEGEN_REDIS_KEY`, or
+    /// `None` if no URL is set. Fields are encrypted if `KODEGEN_CONFIG_KEY`
+    /// is set.
+    pub fn from_env() -> Option<std::io::Result<Self>> {
+        let url = std::env::var(REDIS_URL_ENV).ok().filter(|url| !url.is_empty())?;
+        let key = std::env::var(REDIS_KEY_ENV)
+            .ok()
+            .filter(|key| !key.is_empty())
+            .unwrap_or_else(|| DEFAULT_REDIS_KEY.to_string());
+        Some(Self::new(&url, key).map(|store| store.encryption_key(EncryptionKey::from_env())))
+    }
+
+    #[must_use]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Pub/sub channel announcing saves.
+    #[must_use]
+    pub fn channel(&self) -> String {
+        format!("{}:changes", self.key)
+    }
+
+    async fn connection(&self) -> std::io::Result<MultiplexedConnection> {
+        self.conn
+            .get_or_try_init(|| self.client.get_multiplexed_async_connection())
+            .await
+            .cloned()
+            .map_err(to_io)
+    }
+
+    async fn read(&self) -> std::io::Result<Option<ExtendedConfig>> {
+        let fields: HashMap<String, Vec<u8>> =
+            self.connection().await?.hgetall(&self.key).await.map_err(to_io)?;
+        if fields.is_empty() {
+            return Ok(None);
+        }
+        self.decode(fields).map(Some)
+    }
+
+    /// `config` as hash fields, each encrypted if a key is set.
+    fn encode(&self, config: &ExtendedConfig) -> std::io::Result<Vec<(String, Vec<u8>)>> {
+        let key = self.encryption.as_ref();
+        let mut fields = Vec::new();
+        for (category, value) in &config.tool_settings {
+            fields.push((
+                format!("tool_settings.{}", category),
+                encryption::seal_json(value, key)?,
+            ));
+        }
+        for (namespace, values) in &config.extensions {
+            fields.push((
+                format!("extensions.{}", namespace),
+                encryption::seal_json(values, key)?,
+            ));
+        }
+        for (name, entries) in &config.raw_directories {
+            fields.push((
+                format!("raw_directories.{}", name),
+                encryption::seal_json(entries, key)?,
+            ));
+        }
+        Ok(fields)
+    }
+
+    /// Config from hash fields written by `encode`.
+    fn decode(&self, fields: HashMap<String, Vec<u8>>) -> std::io::Result<ExtendedConfig> {
+        let key = self.encryption.as_ref();
+        let mut config = ExtendedConfig::default();
+        for (field, data) in fields {
+            match field.split_once('.') {
+                Some(("tool_settings", category)) => {
+                    config
+                        .tool_settings
+                        .insert(category.to_string(), encryption::open_json(&data, key)?);
+                }
+                Some(("extensions", namespace)) => {
+                    config
+                        .extensions
+                        .insert(namespace.to_string(), encryption::open_json(&data, key)?);
+                }
+                Some(("raw_directories", name)) => {
+                    config
+                        .raw_directories
+                        .insert(name.to_string(), encryption::open_json(&data, key)?);
+                }
+                _ => log::warn!("Ignoring unknown field {} in {}", field, self.key),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Forward configs saved by other servers until the subscription drops
+    /// or `updates` closes.
+    async fn follow(&self, updates: &mpsc::Sender<ExtendedConfig>) -> std::io::Result<()> {
+        let mut pubsub = self.client.get_async_pubsub().await.map_err(to_io)?;
+        pubsub.subscribe(self.channel()).await.map_err(to_io)?;
+        // Catch up on saves missed while unsubscribed
+        if let Some(config) = self.read().await?
+            && updates.send(config).await.is_err()
+        {
+            return Ok(());
+        }
+        let mut messages = pubsub.on_message();
+        while let Some(message) = messages.next().await {
+            let origin: String = message.get_payload().map_err(to_io)?;
+            if origin == self.origin {
+                continue;
+            }
+            if let Some(config) = self.read().await?
+                && updates.send(config).await.is_err()
+            {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ConfigStore for RedisStore {
+    fn location(&self) -> String {
+        format!("redis://{}/{}", self.client.get_connection_info().addr, self.key)
+    }
+
+    fn load(&self) -> StoreFuture<'_, Option<ExtendedConfig>> {
+        Box::pin(self.read())
+    }
+
+    fn save<'a>(&'a self, config: &'a ExtendedConfig) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            let fields = self.encode(config)?;
+            let mut pipe = redis::pipe();
+            pipe.atomic().del(&self.key).ignore();
+            if !fields.is_empty() {
+                pipe.hset_multiple(&self.key, &fields).ignore();
+            }
+            pipe.publish(self.channel(), &self.origin).ignore();
+            pipe.query_async::<()>(&mut self.connection().await?)
+                .await
+                .map_err(to_io)
+        })
+    }
+
+    fn watch(&self) -> Option<mpsc::Receiver<ExtendedConfig>> {
+        let (tx, rx) = mpsc::channel(16);
+        let store = self.clone();
+        tokio::spawn(async move {
+            while !tx.is_closed() {
+                if let Err(e) = store.follow(&tx).await {
+                    log::warn!("Redis subscription to {} failed: {}", store.channel(), e);
+                }
+                tokio::time::sleep(WATCH_RETRY_DELAY).await;
+            }
+        });
+        Some(rx)
+    }
+}
+
+fn to_io(e: redis::RedisError) -> std::io::Error {
+    std::io::Error::other(e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(key: Option<EncryptionKey>) -> RedisStore {
+        RedisStore::new("redis://127.0.0.1/", DEFAULT_REDIS_KEY)
+            .unwrap()
+            .encryption_key(key)
+    }
+
+    fn config() -> ExtendedConfig {
+        let mut config = ExtendedConfig::default();
+        config.tool_settings.insert(
+            "search".to_string(),
+            serde_json::json!({ "max_results": 10 }),
+        );
+        config
+    }
+
+    #[test]
+    fn every_field_is_encrypted_with_the_key() {
+        let encrypted = store(Some(EncryptionKey::new([3; 32])));
+        let fields = encrypted.encode(&config()).unwrap();
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].0, "tool_settings.search");
+        assert!(encryption::is_encrypted(&fields[0].1));
+
+        let fields: HashMap<String, Vec<u8>> = fields.into_iter().collect();
+        assert!(store(None).decode(fields.clone()).is_err());
+        let decoded = encrypted.decode(fields).unwrap();
+        assert_eq!(decoded.tool_settings, config().tool_settings);
+    }
+
+    #[test]
+    fn fields_are_plain_json_without_a_key() {
+        let fields = store(None).encode(&config()).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&fields[0].1).unwrap();
+        assert_eq!(json["max_results"], 10);
+    }
+}
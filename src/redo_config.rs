@@ -0,0 +1,136 @@
+use crate::ConfigManager;
+use crate::metadata::is_sensitive;
+use crate::schema::{CONFIG_REDO, ConfigRedoArgs, ConfigRedoPromptArgs};
+use crate::set_config_value::confirmation_required;
+use kodegen_mcp_tool::Tool;
+use kodegen_mcp_tool::error::McpError;
+use rmcp::model::{Content, PromptArgument, PromptMessage, PromptMessageContent, PromptMessageRole};
+use serde_json::json;
+
+// ============================================================================
+// TOOL STRUCT
+// ============================================================================
+
+#[derive(Clone)]
+pub struct RedoConfigTool {
+    config_manager: ConfigManager,
+}
+
+impl RedoConfigTool {
+    #[must_use]
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self { config_manager }
+    }
+}
+
+// ============================================================================
+// TOOL IMPLEMENTATION
+// ============================================================================
+
+impl Tool for RedoConfigTool {
+    type Args = ConfigRedoArgs;
+    type PromptArgs = ConfigRedoPromptArgs;
+
+    fn name() -> &'static str {
+        CONFIG_REDO
+    }
+
+    fn description() -> &'static str {
+        "Re-apply the most recent configuration change reverted by config_undo. \
+         The redo history is cleared by any new config_set. Re-applying a change to a \
+         sensitive key needs confirmation as with config_set: the first call returns a \
+         confirmation_token, and repeating the call with it applies the change."
+    }
+
+    fn read_only() -> bool {
+        false
+    }
+
+    fn destructive() -> bool {
+        false
+    }
+
+    fn idempotent() -> bool {
+        false
+    }
+
+    fn prompt_arguments() -> Vec<PromptArgument> {
+        vec![] // No prompt arguments needed
+    }
+
+    #[tracing::instrument(name = "config.tool", skip_all, fields(tool = Self::name()), err)]
+    async fn execute(&self, args: Self::Args) -> Result<Vec<Content>, McpError> {
+        self.config_manager.authorize(args.auth_token.as_deref())?;
+        let change = match self.config_manager.next_redo() {
+            Some(next) => {
+                // Sensitive keys need a second, confirming call, as with config_set
+                if is_sensitive(&next.key) {
+                    match args.confirmation_token.as_deref() {
+                        Some(token) => {
+                            self.config_manager.confirm_change(token, &next.key, &next.new)?;
+                        }
+                        None => {
+                            let token =
+                                self.config_manager.request_confirmation(&next.key, &next.new);
+                            return Ok(confirmation_required(&next.key, &next.new, &token));
+                        }
+                    }
+                }
+                Some(self.config_manager.redo_expected(&next).await?)
+            }
+            None => None,
+        };
+        let (undo_depth, redo_depth) = self.config_manager.history_depth();
+
+        let mut contents = Vec::new();
+
+        // ========================================
+        // Content[0]: Human-Readable Summary
+        // ========================================
+        let summary = match &change {
+            Some(change) => format!(
+                "↪️  Configuration Change Redone\n\
+                 \n\
+                 Setting: {}\n\
+                 Re-applied value: {}\n\
+                 Previous value: {}\n\
+                 \n\
+                 {} change(s) can be undone, {} more can be redone.",
+                change.key, change.new, change.old, undo_depth, redo_depth
+            ),
+            None => "ℹ️  Nothing to redo".to_string(),
+        };
+        contents.push(Content::text(summary));
+
+        // ========================================
+        // Content[1]: Machine-Parseable JSON
+        // ========================================
+        let metadata = json!({
+            "success": true,
+            "redone": change,
+            "undo_depth": undo_depth,
+            "redo_depth": redo_depth
+        });
+        let json_str = serde_json::to_string_pretty(&metadata)
+            .unwrap_or_else(|_| "{}".to_string());
+        contents.push(Content::text(json_str));
+
+        Ok(contents)
+    }
+
+    async fn prompt(&self, _args: Self::PromptArgs) -> Result<Vec<PromptMessage>, McpError> {
+        Ok(vec![
+            PromptMessage {
+                role: PromptMessageRole::User,
+                content: PromptMessageContent::text("How do I re-apply a change I undid?"),
+            },
+            PromptMessage {
+                role: PromptMessageRole::Assistant,
+                content: PromptMessageContent::text(
+                    "Use config_redo right after config_undo to re-apply the undone change. \
+                     Making a new config_set clears the redo history.",
+                ),
+            },
+        ])
+    }
+}
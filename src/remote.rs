@@ -0,0 +1,107 @@
+//! Remote config source
+//!
+//! With `KODEGEN_CONFIG_URL` set, a JSON object of config keys and values,
+//! e.g. `{"blocked_commands": ["rm -rf /"]}`, is fetched from that HTTPS
+//! endpoint by `ConfigManager::init()` and polled afterwards, so policies can
+//! be pushed centrally to every machine. Keys overridden by environment
+//! variables (`KODEGEN_ALLOWED_DIRS`, `KODEGEN_BLOCKED_COMMANDS`, ...) keep
+//! their environment values.
+
+use parking_lot::Mutex;
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use reqwest::{StatusCode, Url};
+use std::time::Duration;
+
+/// Environment variable holding the remote config URL.
+pub const REMOTE_CONFIG_URL_ENV: &str = "KODEGEN_CONFIG_URL";
+
+/// Environment variable overriding the poll interval, in seconds.
+pub const REMOTE_POLL_INTERVAL_ENV: &str = "KODEGEN_CONFIG_POLL_SECS";
+
+/// How often the remote config is polled unless configured otherwise.
+pub const DEFAULT_REMOTE_POLL_INTERVAL: Duration = Duration::from_secs(300);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct RemoteSource {
+    url: Url,
+    poll_interval: Duration,
+    http: reqwest::Client,
+    /// ETag of the last response, so unchanged configs aren't re-downloaded.
+    etag: Mutex<Option<String>>,
+}
+
+impl RemoteSource {
+    /// Source fetching `url` every `poll_interval`.
+    ///
+    /// `url` must be HTTPS; plain HTTP is only accepted for loopback hosts.
+    pub fn new(url: &str, poll_interval: Duration) -> Result<Self, String> {
+        let url = Url::parse(url).map_err(|e| format!("invalid URL '{}': {}", url, e))?;
+        let loopback = matches!(url.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+        if url.scheme() != "https" && !(url.scheme() == "http" && loopback) {
+            return Err(format!("remote config URL must use https: {}", url));
+        }
+        Ok(Self {
+            url,
+            poll_interval,
+            http: reqwest::Client::new(),
+            etag: Mutex::new(None),
+        })
+    }
+
+    /// Source configured from `KODEGEN_CONFIG_URL` and
+    /// `KODEGEN_CONFIG_POLL_SECS`, or `None` if unset or invalid.
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var(REMOTE_CONFIG_URL_ENV)
+            .ok()
+            .filter(|url| !url.is_empty())?;
+        let poll_interval = std::env::var(REMOTE_POLL_INTERVAL_ENV)
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .filter(|&secs| secs > 0)
+            .map_or(DEFAULT_REMOTE_POLL_INTERVAL, Duration::from_secs);
+        match Self::new(&url, poll_interval) {
+            Ok(source) => Some(source),
+            Err(e) => {
+                log::error!("Ignoring {}: {}", REMOTE_CONFIG_URL_ENV, e);
+                None
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn url(&self) -> &str {
+        self.url.as_str()
+    }
+
+    #[must_use]
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Fetch the remote values, or `None` if they haven't changed since the
+    /// last fetch.
+    pub async fn fetch(
+        &self,
+    ) -> std::io::Result<Option<serde_json::Map<String, serde_json::Value>>> {
+        let mut request = self.http.get(self.url.clone()).timeout(REQUEST_TIMEOUT);
+        if let Some(etag) = self.etag.lock().clone() {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        let response = request.send().await.map_err(std::io::Error::other)?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        let response = response.error_for_status().map_err(std::io::Error::other)?;
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let body = response.bytes().await.map_err(std::io::Error::other)?;
+        let values = serde_json::from_slice(&body)?;
+        *self.etag.lock() = etag;
+        Ok(Some(values))
+    }
+}
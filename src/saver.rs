@@ -0,0 +1,142 @@
+//! Debounced background saving
+//!
+//! Once `ConfigManager::start_background_saver` is called, mutations only mark
+//! the extended config dirty. The saver writes it after `debounce` of quiet,
+//! but never later than `max_delay` after the first unsaved change, so a
+//! steady stream of changes can't postpone persistence indefinitely.
+//! `SaverHandle::shutdown` stops the task after a final flush.
+
+use kodegen_mcp_tool::error::McpError;
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Quiet period before a save unless configured otherwise.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Longest a change may wait to be saved unless configured otherwise.
+pub const DEFAULT_MAX_SAVE_DELAY: Duration = Duration::from_secs(5);
+
+/// How often the saver persists client history if it changed.
+pub const CLIENT_HISTORY_SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+/// `tool_settings` category holding `SaverSettings`.
+pub const SAVER_SETTINGS_CATEGORY: &str = "persistence";
+
+/// Saver timing stored in `tool_settings.persistence`, overriding the builder.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SaverSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debounce_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_delay_ms: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct DirtyState {
+    running: bool,
+    first_change: Option<Instant>,
+    last_change: Option<Instant>,
+}
+
+/// Dirty tracking shared between mutations and the saver task.
+#[derive(Debug, Default)]
+pub(crate) struct SaveSignal {
+    state: Mutex<DirtyState>,
+    notify: Notify,
+    stop: Notify,
+}
+
+impl SaveSignal {
+    /// Mark the saver as running; returns `false` if it already was.
+    pub fn start(&self) -> bool {
+        !std::mem::replace(&mut self.state.lock().running, true)
+    }
+
+    /// Record an unsaved change. Returns `false` if no saver is running, in
+    /// which case the caller must save itself.
+    pub fn mark_dirty(&self) -> bool {
+        let mut state = self.state.lock();
+        if !state.running {
+            return false;
+        }
+        let now = Instant::now();
+        state.first_change.get_or_insert(now);
+        state.last_change = Some(now);
+        drop(state);
+        self.notify.notify_one();
+        true
+    }
+
+    /// Wait until there is an unsaved change.
+    pub async fn changed(&self) {
+        self.notify.notified().await;
+    }
+
+    /// Ask the saver to flush and exit. Later mutations save synchronously.
+    pub fn request_stop(&self) {
+        self.state.lock().running = false;
+        self.stop.notify_one();
+    }
+
+    /// Wait until `request_stop` is called.
+    pub async fn stopped(&self) {
+        self.stop.notified().await;
+    }
+
+    #[must_use]
+    pub fn is_dirty(&self) -> bool {
+        self.state.lock().first_change.is_some()
+    }
+
+    /// When the pending changes should be written, or `None` if there are none.
+    pub fn deadline(&self, debounce: Duration, max_delay: Duration) -> Option<Instant> {
+        let state = self.state.lock();
+        let (first, last) = (state.first_change?, state.last_change?);
+        Some((last + debounce).min(first + max_delay))
+    }
+
+    /// Clear the dirty state just before a save snapshots the config.
+    pub fn take(&self) {
+        let mut state = self.state.lock();
+        state.first_change = None;
+        state.last_change = None;
+    }
+}
+
+// ============================================================================
+// SAVER HANDLE
+// ============================================================================
+
+/// Handle to the background saver task, returned by
+/// `ConfigManager::start_background_saver`.
+#[derive(Clone)]
+pub struct SaverHandle {
+    signal: Arc<SaveSignal>,
+    task: Arc<tokio::sync::Mutex<Option<JoinHandle<Result<(), McpError>>>>>,
+}
+
+impl SaverHandle {
+    pub(crate) fn new(signal: Arc<SaveSignal>, task: JoinHandle<Result<(), McpError>>) -> Self {
+        Self {
+            signal,
+            task: Arc::new(tokio::sync::Mutex::new(Some(task))),
+        }
+    }
+
+    /// Stop the saver, wait for its final flush, and return that flush's result.
+    ///
+    /// Returns `Ok(())` if the saver was already shut down.
+    pub async fn shutdown(&self) -> Result<(), McpError> {
+        self.signal.request_stop();
+        let Some(task) = self.task.lock().await.take() else {
+            return Ok(());
+        };
+        task.await.map_err(|e| McpError::Other(e.into()))?
+    }
+}
@@ -0,0 +1,447 @@
+//! Tool names and argument types for tools defined in this crate
+//!
+//! Mirrors `kodegen_mcp_schema::config` for the tools that are not part of the
+//! shared schema crate.
+
+use crate::format::ConfigFormat;
+use crate::persistence::BackupFile;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// ============================================================================
+// TOOL NAMES
+// ============================================================================
+
+pub const CONFIG_UNDO: &str = "config_undo";
+pub const CONFIG_REDO: &str = "config_redo";
+pub const CONFIG_LIST_KEYS: &str = "config_list_keys";
+pub const CONFIG_BACKUPS: &str = "config_backups";
+pub const CONFIG_CONVERT: &str = "config_convert";
+pub const CONFIG_EFFECTIVE: &str = "config_effective";
+pub const CONFIG_SET_SECRET: &str = "config_set_secret";
+pub const CONFIG_UNSET: &str = "config_unset";
+pub const SYSTEM_METRICS: &str = "system_metrics";
+pub const SYSTEM_DIAGNOSTICS: &str = "system_diagnostics";
+
+// ============================================================================
+// GET
+// ============================================================================
+
+/// A group of settings and status `config_get` reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSection {
+    /// What commands may run and which paths and hosts tools may reach.
+    Security,
+    /// How commands are run.
+    Shell,
+    /// Per-call caps and timeouts.
+    Limits,
+    /// Tool settings, extension values, scratch directories, and encoding.
+    Tools,
+    /// Log level, telemetry, and the audit log.
+    Logging,
+    /// Connected clients and their retention.
+    Clients,
+    /// Names of the stored secrets.
+    Secrets,
+    /// Host details; also needs `include_system_info`.
+    System,
+    /// Save status, errors, and integrity.
+    Persistence,
+}
+
+impl ConfigSection {
+    pub const ALL: [ConfigSection; 9] = [
+        ConfigSection::Security,
+        ConfigSection::Shell,
+        ConfigSection::Limits,
+        ConfigSection::Tools,
+        ConfigSection::Logging,
+        ConfigSection::Clients,
+        ConfigSection::Secrets,
+        ConfigSection::System,
+        ConfigSection::Persistence,
+    ];
+
+    /// Top-level keys of the `config` object that belong to this section.
+    #[must_use]
+    pub const fn keys(self) -> &'static [&'static str] {
+        match self {
+            Self::Security => &[
+                "blocked_commands",
+                crate::BLOCKED_COMMAND_PATTERNS_KEY,
+                "allowed_directories",
+                "denied_directories",
+                "raw_directories",
+                crate::DIRECTORY_ALIASES_KEY,
+                crate::FOLLOW_SYMLINKS_KEY,
+                crate::ALLOWED_HOSTS_KEY,
+                crate::DENIED_HOSTS_KEY,
+                crate::ENV_PASSTHROUGH_KEY,
+                crate::RATE_LIMITS_KEY,
+            ],
+            Self::Shell => &[
+                "default_shell",
+                crate::SHELL_ARGS_KEY,
+                crate::COMMAND_TIMEOUT_SECS_KEY,
+            ],
+            Self::Limits => &[
+                "file_read_line_limit",
+                "file_write_line_limit",
+                "fuzzy_search_threshold",
+                "http_connection_timeout_secs",
+                crate::MAX_SEARCH_RESULTS_KEY,
+            ],
+            Self::Tools => &[
+                "tool_settings",
+                "extensions",
+                crate::TEMP_DIRECTORY_KEY,
+                crate::CACHE_DIRECTORY_KEY,
+                crate::DEFAULT_ENCODING_KEY,
+            ],
+            Self::Logging => &[
+                crate::LOG_LEVEL_KEY,
+                crate::TELEMETRY_ENABLED_KEY,
+                crate::AUDIT_LOG_PATH_KEY,
+                crate::OTLP_ENDPOINT_KEY,
+            ],
+            Self::Clients => &[
+                "client_history",
+                crate::CLIENT_HISTORY_MAX_ENTRIES_KEY,
+                crate::CLIENT_HISTORY_MAX_AGE_DAYS_KEY,
+            ],
+            Self::Secrets => &[crate::SECRETS_KEY],
+            Self::System => &["system_info"],
+            Self::Persistence => &[crate::SCHEMA_VERSION_KEY, "save_error_count"],
+        }
+    }
+}
+
+/// How `config_get` renders its human-readable summary.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryFormat {
+    /// Grouped overview with status notes.
+    #[default]
+    Text,
+    /// The reported config as pretty-printed JSON.
+    Json,
+    /// The reported config as TOML.
+    Toml,
+    /// One Markdown table row per reported key.
+    Table,
+}
+
+/// Arguments of `config_get`; extends the shared schema's empty arguments.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigGetArgs {
+    /// Sections to report (default: all of them). Naming only the ones
+    /// needed keeps the response small.
+    #[serde(default)]
+    pub sections: Vec<ConfigSection>,
+
+    /// How the first content item is rendered (default `text`); the second
+    /// is always the full JSON.
+    #[serde(default)]
+    pub format: SummaryFormat,
+
+    /// Re-collect cached system details (host, CPU, memory, toolchains).
+    #[serde(default)]
+    pub refresh: bool,
+
+    /// Include `system_info` (default `true`). Skip it for a fast response.
+    #[serde(default = "default_true")]
+    pub include_system_info: bool,
+
+    /// Include `client_history` in the config (default `true`).
+    #[serde(default = "default_true")]
+    pub include_client_history: bool,
+
+    /// Include the most recent config changes (default `false`).
+    #[serde(default)]
+    pub include_history: bool,
+
+    /// Show secret values instead of `***` (default `false`). Refused unless
+    /// the server allows it.
+    #[serde(default)]
+    pub include_secrets: bool,
+
+    /// The server's auth token, required with `include_secrets` when one is
+    /// configured; see `ConfigSetArgs::auth_token`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+}
+
+impl Default for ConfigGetArgs {
+    fn default() -> Self {
+        Self {
+            sections: Vec::new(),
+            format: SummaryFormat::Text,
+            refresh: false,
+            include_system_info: true,
+            include_client_history: true,
+            include_history: false,
+            include_secrets: false,
+            auth_token: None,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// ============================================================================
+// SET
+// ============================================================================
+
+/// Arguments of `config_set`; extends the shared schema's key and value.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigSetArgs {
+    /// Key to set.
+    pub key: String,
+
+    /// New value: a string, number, boolean, or array, or an object for
+    /// structured keys such as `tool_settings.<category>` and `rate_limits`.
+    pub value: serde_json::Value,
+
+    /// Token returned by a previous call, confirming a change to a sensitive
+    /// key such as `allowed_directories`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmation_token: Option<String>,
+
+    /// Why the change is made; recorded in the change history and audit log.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// The server's auth token, required to change the config when one is
+    /// configured; `Bearer <token>` is also accepted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+}
+
+/// Prompt arguments of `config_set`; extends the shared schema with a key.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigSetPromptArgs {
+    /// Key to get guidance for: its valid values, security notes, and an
+    /// example. Omit for general guidance.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+// ============================================================================
+// UNSET
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigUnsetArgs {
+    /// Key to revert to its default.
+    pub key: String,
+
+    /// Token returned by a previous call, confirming the change to a
+    /// sensitive key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmation_token: Option<String>,
+
+    /// Why the change is made; recorded in the change history and audit log.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// The server's auth token; see `ConfigSetArgs::auth_token`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigUnsetPromptArgs {}
+
+// ============================================================================
+// UNDO / REDO
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigUndoArgs {
+    /// Token returned by a previous call, confirming that a sensitive key
+    /// may be reverted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmation_token: Option<String>,
+
+    /// The server's auth token; see `ConfigSetArgs::auth_token`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigUndoPromptArgs {}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigRedoArgs {
+    /// Token returned by a previous call, confirming that a sensitive key
+    /// may be changed again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmation_token: Option<String>,
+
+    /// The server's auth token; see `ConfigSetArgs::auth_token`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigRedoPromptArgs {}
+
+// ============================================================================
+// SECRETS
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigSetSecretArgs {
+    /// Secret name: letters, digits, `_`, `-`, and `.`.
+    pub name: String,
+
+    /// Secret value; omit or pass `null` to remove the secret.
+    #[serde(default)]
+    pub value: Option<String>,
+
+    /// The server's auth token; see `ConfigSetArgs::auth_token`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigSetSecretPromptArgs {}
+
+// ============================================================================
+// LIST KEYS
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigListKeysArgs {}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigListKeysPromptArgs {}
+
+// ============================================================================
+// BACKUPS
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupAction {
+    /// List available backups.
+    #[default]
+    List,
+    /// Restore the backup given by `index`.
+    Restore,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigBackupsArgs {
+    /// `list` (default) or `restore`.
+    #[serde(default)]
+    pub action: BackupAction,
+
+    /// `config` (config.json, default) or `tools` (config.tools.json).
+    #[serde(default)]
+    pub file: BackupFile,
+
+    /// Backup to restore; 1 is the most recent. Required for `restore`.
+    #[serde(default)]
+    pub index: Option<usize>,
+
+    /// Token returned by a previous `restore` of the same backup, confirming
+    /// the changes it makes to sensitive keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmation_token: Option<String>,
+
+    /// The server's auth token; see `ConfigSetArgs::auth_token`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigBackupsPromptArgs {}
+
+// ============================================================================
+// CONVERT
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigConvertArgs {
+    /// Target format: `json`, `toml`, or `yaml`.
+    pub format: ConfigFormat,
+
+    /// The server's auth token; see `ConfigSetArgs::auth_token`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigConvertPromptArgs {}
+
+// ============================================================================
+// EFFECTIVE
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigEffectiveArgs {
+    /// Only report this key, e.g. `allowed_directories` or `tool_settings.terminal`.
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct ConfigEffectivePromptArgs {}
+
+// ============================================================================
+// SYSTEM METRICS
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct SystemMetricsArgs {
+    /// Milliseconds to sample CPU usage over (default 250, at most 5000).
+    #[serde(default)]
+    pub sample_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct SystemMetricsPromptArgs {}
+
+// ============================================================================
+// SYSTEM DIAGNOSTICS
+// ============================================================================
+
+/// A group of host details `system_diagnostics` collects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticsSection {
+    Cpu,
+    Memory,
+    Disks,
+    Network,
+    Toolchains,
+}
+
+impl DiagnosticsSection {
+    pub const ALL: [DiagnosticsSection; 5] = [
+        DiagnosticsSection::Cpu,
+        DiagnosticsSection::Memory,
+        DiagnosticsSection::Disks,
+        DiagnosticsSection::Network,
+        DiagnosticsSection::Toolchains,
+    ];
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct SystemDiagnosticsArgs {
+    /// Sections to collect (default: all of them).
+    #[serde(default)]
+    pub sections: Vec<DiagnosticsSection>,
+
+    /// Re-collect cached details (CPU model, memory, toolchains).
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct SystemDiagnosticsPromptArgs {}
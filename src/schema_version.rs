@@ -0,0 +1,74 @@
+//! Schema version of the extended config
+//!
+//! `config.tools.json` records the layout it was written with in
+//! `schema_version`; files written before the field existed are version 0.
+//! Older files are migrated one version at a time when they are read. A file
+//! from a newer build is refused instead of being parsed without the fields
+//! this build doesn't know and then saved back without them.
+
+use serde::{Deserialize, Serialize};
+
+/// Key of the schema version in the extended config file.
+pub const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+/// Schema version written by this build.
+pub const SCHEMA_VERSION: u32 = 1;
+
+type Migration = fn(&mut serde_json::Map<String, serde_json::Value>);
+
+/// Upgrades from each version to the next; `MIGRATIONS[n]` upgrades version `n`.
+const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [
+    // 0 -> 1: the version is recorded; the layout is unchanged
+    |_| {},
+];
+
+/// Schema version of a loaded extended config; defaults to `SCHEMA_VERSION`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SchemaVersion(pub u32);
+
+impl Default for SchemaVersion {
+    fn default() -> Self {
+        Self(SCHEMA_VERSION)
+    }
+}
+
+/// A config file written by a build with a newer schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, thiserror::Error)]
+#[error(
+    "config was written with schema version {found}, but this build only supports up to \
+     {supported}; upgrade kodegen, or move the file aside to start over"
+)]
+pub struct SchemaTooNew {
+    pub found: u32,
+    pub supported: u32,
+}
+
+/// Upgrade a parsed extended config to `SCHEMA_VERSION` in place, returning
+/// the version it was written with.
+pub fn migrate(config: &mut serde_json::Value) -> Result<u32, SchemaTooNew> {
+    let Some(fields) = config.as_object_mut() else {
+        // Not a config object; left for deserialization to reject
+        return Ok(SCHEMA_VERSION);
+    };
+    let found = match fields.get(SCHEMA_VERSION_KEY) {
+        None => 0,
+        Some(version) => match version.as_u64() {
+            Some(version) => u32::try_from(version).unwrap_or(u32::MAX),
+            // Left for deserialization to reject
+            None => return Ok(SCHEMA_VERSION),
+        },
+    };
+    if found > SCHEMA_VERSION {
+        return Err(SchemaTooNew {
+            found,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    for (version, upgrade) in MIGRATIONS.iter().enumerate().skip(found as usize) {
+        log::info!("Migrating config from schema version {} to {}", version, version + 1);
+        upgrade(fields);
+    }
+    fields.insert(SCHEMA_VERSION_KEY.to_string(), SCHEMA_VERSION.into());
+    Ok(found)
+}
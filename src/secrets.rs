@@ -0,0 +1,187 @@
+//! Secrets for tools
+//!
+//! API tokens and passwords that tools need are kept out of the config
+//! files. They live in `secrets.json` next to the extended config, encrypted
+//! with the config encryption key, or in the OS keyring when no key is set
+//! and the `keyring` feature is enabled. Config output only ever shows their
+//! names, with the values rendered as `***`.
+
+use crate::encryption::EncryptionKey;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// File name of the secrets, stored alongside the extended config.
+pub const SECRETS_FILE: &str = "secrets.json";
+
+/// Key under which secrets appear in `config_get`, and the prefix of the
+/// keys their changes are reported under (`secrets.<name>`).
+pub const SECRETS_KEY: &str = "secrets";
+
+/// Shown in place of every secret value.
+pub const REDACTED: &str = "***";
+
+/// Environment variable that, set to `1` or `true`, lets `config_get` reveal
+/// secret values with `include_secrets`.
+pub const SECRET_REVEAL_ENV: &str = "KODEGEN_ALLOW_SECRET_REVEAL";
+
+/// Keyring service the `keyring` backend stores entries under.
+#[cfg(feature = "keyring")]
+pub const KEYRING_SERVICE: &str = "kodegen";
+
+/// How a secret is held.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum StoredSecret {
+    /// Base64 ciphertext under the config encryption key.
+    Encrypted { ciphertext: String },
+    /// Held by the OS keyring under `KEYRING_SERVICE` and the secret's name.
+    Keyring,
+}
+
+impl StoredSecret {
+    #[must_use]
+    pub const fn backend(&self) -> &'static str {
+        match self {
+            Self::Encrypted { .. } => "encrypted",
+            Self::Keyring => "keyring",
+        }
+    }
+}
+
+/// Check that `name` is non-empty and uses only ASCII letters, digits,
+/// `_`, `-`, and `.`.
+pub(crate) fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if !valid {
+        return Err(format!(
+            "'{}' is not a valid secret name; use letters, digits, '_', '-', and '.'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Store `value` under `name`: encrypted with `key` when given, otherwise
+/// in the OS keyring.
+pub(crate) fn seal(
+    name: &str,
+    value: &str,
+    key: Option<&EncryptionKey>,
+) -> Result<StoredSecret, String> {
+    if let Some(key) = key {
+        let ciphertext = key
+            .encrypt(value.as_bytes())
+            .map_err(|e| format!("cannot encrypt secret '{}': {}", name, e))?;
+        return Ok(StoredSecret::Encrypted {
+            ciphertext: STANDARD.encode(ciphertext),
+        });
+    }
+    #[cfg(feature = "keyring")]
+    {
+        keyring_entry(name)?
+            .set_password(value)
+            .map_err(|e| format!("cannot store secret '{}' in the keyring: {}", name, e))?;
+        Ok(StoredSecret::Keyring)
+    }
+    #[cfg(not(feature = "keyring"))]
+    Err(format!(
+        "cannot store secret '{}': set {} or build with the keyring feature",
+        name,
+        crate::encryption::ENCRYPTION_KEY_ENV
+    ))
+}
+
+/// The plaintext of a stored secret.
+pub(crate) fn unseal(
+    name: &str,
+    stored: &StoredSecret,
+    key: Option<&EncryptionKey>,
+) -> Result<String, String> {
+    match stored {
+        StoredSecret::Encrypted { ciphertext } => {
+            let key = key.ok_or_else(|| {
+                format!(
+                    "secret '{}' is encrypted and {} is not set",
+                    name,
+                    crate::encryption::ENCRYPTION_KEY_ENV
+                )
+            })?;
+            let bytes = STANDARD
+                .decode(ciphertext)
+                .map_err(|e| format!("secret '{}' is corrupted: {}", name, e))?;
+            let plaintext = key
+                .decrypt(&bytes)
+                .map_err(|e| format!("cannot decrypt secret '{}': {}", name, e))?;
+            String::from_utf8(plaintext)
+                .map_err(|_| format!("secret '{}' is not valid UTF-8", name))
+        }
+        #[cfg(feature = "keyring")]
+        StoredSecret::Keyring => keyring_entry(name)?
+            .get_password()
+            .map_err(|e| format!("cannot read secret '{}' from the keyring: {}", name, e)),
+        #[cfg(not(feature = "keyring"))]
+        StoredSecret::Keyring => Err(format!(
+            "secret '{}' is in the OS keyring; build with the keyring feature to read it",
+            name
+        )),
+    }
+}
+
+/// Release whatever holds a secret outside `secrets.json`.
+pub(crate) fn discard(name: &str, stored: &StoredSecret) -> Result<(), String> {
+    match stored {
+        StoredSecret::Encrypted { .. } => Ok(()),
+        #[cfg(feature = "keyring")]
+        StoredSecret::Keyring => match keyring_entry(name)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!("cannot remove secret '{}' from the keyring: {}", name, e)),
+        },
+        #[cfg(not(feature = "keyring"))]
+        StoredSecret::Keyring => {
+            log::warn!("Secret '{}' stays in the OS keyring; remove it there", name);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_entry(name: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, name)
+        .map_err(|e| format!("cannot open keyring entry for '{}': {}", name, e))
+}
+
+/// Secrets stored at `path`; empty if the file is missing. A file that
+/// can't be read or parsed is an error, so it is never replaced by an empty
+/// set on the next write.
+pub(crate) fn load(path: &Path) -> std::io::Result<BTreeMap<String, StoredSecret>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(e),
+    };
+    serde_json::from_str(&contents)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// Whether `SECRET_REVEAL_ENV` allows revealing secrets.
+#[must_use]
+pub fn reveal_allowed_by_env() -> bool {
+    std::env::var(SECRET_REVEAL_ENV)
+        .is_ok_and(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true"))
+}
+
+/// Every name mapped to `REDACTED`, as shown by `config_get`.
+#[must_use]
+pub fn redact<'a>(names: impl IntoIterator<Item = &'a String>) -> serde_json::Value {
+    names
+        .into_iter()
+        .map(|name| (name.clone(), serde_json::Value::from(REDACTED)))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
@@ -0,0 +1,198 @@
+//! MCP transports
+//!
+//! Serves the registered tools and prompts over streamable HTTP, optionally
+//! with TLS, or over stdin/stdout for editors that launch MCP servers as
+//! child processes.
+
+use kodegen_tools_config::ConfigManager;
+use rmcp::handler::server::prompt::PromptContext;
+use rmcp::handler::server::router::{prompt::PromptRouter, tool::ToolRouter};
+use rmcp::handler::server::tool::ToolCallContext;
+use rmcp::model::{
+    CallToolRequestParam, CallToolResult, GetPromptRequestParam, GetPromptResult, Implementation,
+    InitializeRequestParam, InitializeResult, ListPromptsResult, ListToolsResult,
+    PaginatedRequestParam, ServerCapabilities, ServerInfo,
+};
+use rmcp::service::RequestContext;
+use rmcp::transport::streamable_http_server::{
+    StreamableHttpServerConfig, StreamableHttpService, session::local::LocalSessionManager,
+};
+use rmcp::{ErrorData, RoleServer, ServerHandler, ServiceExt};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Path the MCP endpoint is served on.
+pub const MCP_PATH: &str = "/mcp";
+
+/// How long open HTTP connections may finish after Ctrl+C or SIGTERM.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// PEM certificate and private key for serving HTTPS.
+#[derive(Debug, Clone)]
+pub struct TlsFiles {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+/// MCP handler dispatching to the registered tools and prompts.
+#[derive(Clone)]
+pub struct ConfigServer {
+    tool_router: ToolRouter<Self>,
+    prompt_router: PromptRouter<Self>,
+    /// Records each connecting client in the client history.
+    config: ConfigManager,
+}
+
+impl ConfigServer {
+    pub fn new(
+        tool_router: ToolRouter<Self>,
+        prompt_router: PromptRouter<Self>,
+        config: ConfigManager,
+    ) -> Self {
+        Self {
+            tool_router,
+            prompt_router,
+            config,
+        }
+    }
+
+    /// Serve MCP on stdin/stdout until the client closes the connection.
+    pub async fn serve_stdio(self) -> anyhow::Result<()> {
+        let service = self.serve(rmcp::transport::stdio()).await?;
+        service.waiting().await?;
+        Ok(())
+    }
+
+    /// Serve MCP at `MCP_PATH` on `addr` until Ctrl+C or SIGTERM, over HTTPS
+    /// when `tls` is given.
+    pub async fn serve_http(self, addr: SocketAddr, tls: Option<TlsFiles>) -> anyhow::Result<()> {
+        let service = StreamableHttpService::new(
+            move || Ok(self.clone()),
+            LocalSessionManager::default().into(),
+            StreamableHttpServerConfig::default(),
+        );
+        let app = axum::Router::new().nest_service(MCP_PATH, service);
+
+        let handle = axum_server::Handle::new();
+        let stop = handle.clone();
+        tokio::spawn(async move {
+            if shutdown_signal().await.is_ok() {
+                log::info!("Shutting down");
+                stop.graceful_shutdown(Some(SHUTDOWN_GRACE));
+            }
+        });
+
+        match tls {
+            Some(tls) => {
+                let config =
+                    axum_server::tls_rustls::RustlsConfig::from_pem_file(&tls.cert, &tls.key)
+                        .await?;
+                log::info!("Serving config tools on https://{}{}", addr, MCP_PATH);
+                axum_server::bind_rustls(addr, config)
+                    .handle(handle)
+                    .serve(app.into_make_service())
+                    .await?;
+            }
+            None => {
+                log::info!("Serving config tools on http://{}{}", addr, MCP_PATH);
+                axum_server::bind(addr)
+                    .handle(handle)
+                    .serve(app.into_make_service())
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Wait for Ctrl+C or, on unix, SIGTERM, which service managers and
+/// container runtimes send to stop the server.
+async fn shutdown_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                return tokio::select! {
+                    result = tokio::signal::ctrl_c() => result,
+                    _ = terminate.recv() => Ok(()),
+                };
+            }
+            Err(e) => log::error!("Failed to listen for SIGTERM: {}", e),
+        }
+    }
+    tokio::signal::ctrl_c().await
+}
+
+impl ServerHandler for ConfigServer {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_prompts()
+                .build(),
+            server_info: Implementation {
+                name: "kodegen-config".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    async fn initialize(
+        &self,
+        request: InitializeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<InitializeResult, ErrorData> {
+        let client = &request.client_info;
+        if let Err(e) = self
+            .config
+            .set_client_info(&client.name, &client.version)
+            .await
+        {
+            log::warn!("Failed to record client {}: {}", client.name, e);
+        }
+        if context.peer.peer_info().is_none() {
+            context.peer.set_peer_info(request);
+        }
+        Ok(self.get_info())
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, ErrorData> {
+        Ok(ListToolsResult::with_all_items(self.tool_router.list_all()))
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        self.tool_router
+            .call(ToolCallContext::new(self, request, context))
+            .await
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, ErrorData> {
+        Ok(ListPromptsResult::with_all_items(self.prompt_router.list_all()))
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, ErrorData> {
+        let context = PromptContext::new(self, request.name, request.arguments, context);
+        self.prompt_router.get_prompt(context).await
+    }
+}
@@ -1,7 +1,13 @@
 use crate::ConfigManager;
+use crate::confirmation::CONFIRMATION_TTL;
+use crate::keys::{ConfigKey, resolve_key};
+use crate::metadata::{
+    KeyMetadata, RiskLevel, SecurityLevel, ValueType, is_sensitive, metadata_for, risk_level,
+};
+use crate::schema::{ConfigSetArgs, ConfigSetPromptArgs};
 use kodegen_mcp_tool::Tool;
 use kodegen_mcp_tool::error::McpError;
-use kodegen_mcp_schema::config::{SetConfigValueArgs, SetConfigValuePromptArgs, CONFIG_SET};
+use kodegen_mcp_schema::config::CONFIG_SET;
 use rmcp::model::{Content, PromptArgument, PromptMessage, PromptMessageContent, PromptMessageRole};
 use serde_json::json;
 
@@ -26,8 +32,8 @@ impl SetConfigValueTool {
 // ============================================================================
 
 impl Tool for SetConfigValueTool {
-    type Args = SetConfigValueArgs;
-    type PromptArgs = SetConfigValuePromptArgs;
+    type Args = ConfigSetArgs;
+    type PromptArgs = ConfigSetPromptArgs;
 
     fn name() -> &'static str {
         CONFIG_SET
@@ -39,12 +45,48 @@ impl Tool for SetConfigValueTool {
          command execution to prevent security issues.\n\n\
          Config keys include:\n\
          - blocked_commands (array)\n\
+         - blocked_command_patterns (array of regexes, or globs prefixed with glob:, \
+         matched against whole command lines)\n\
          - default_shell (string)\n\
+         - shell_args (array of arguments before the command line, for this platform, or \
+         object of platform -> array to replace all of them)\n\
          - allowed_directories (array of paths)\n\
+         - allowed_hosts, denied_hosts (array of hosts or *.domain wildcards for network tools)\n\
          - file_read_line_limit (number, max lines for fs_read_file)\n\
-         - file_write_line_limit (number, max lines per fs_write_file call)\n\n\
+         - file_write_line_limit (number, max lines per fs_write_file call)\n\
+         - fuzzy_search_threshold (number, fraction 0.0-1.0 such as 0.85, or percent 0-100)\n\
+         - max_search_results (number 1-100000, cap on results per search call)\n\
+         - command_timeout_secs (number, default timeout for commands without their own)\n\
+         - http_connection_timeout_secs (number of seconds)\n\
+         Timeout keys also take duration strings such as \"30s\", \"5m\", or \"1m30s\".\n\
+         Boolean keys also take \"true\"/\"false\", \"yes\"/\"no\", \"on\"/\"off\", and 1/0.\n\
+         - temp_directory, cache_directory (path, created if missing; shared by all tools)\n\
+         - env_passthrough (array of variable names or PREFIX_* entries forwarded to commands)\n\
+         - follow_symlinks (boolean, allow symlinks out of allowed_directories; off by default)\n\
+         - default_encoding (string: utf-8, utf-16le, utf-16be, latin-1, windows-1252, auto)\n\
+         - rate_limits.<tool> (number, requests per minute the tool may serve; null removes)\n\
+         - rate_limits (object of tool -> number, replaces every limit)\n\
+         - tool_settings.<category> (object, settings for one tool category)\n\
+         - directory_aliases.<name> (absolute path that @name stands for in directory entries; \
+         null removes)\n\
+         - client_history_max_entries, client_history_max_age_days (number, client record retention)\n\
+         - audit_log_path (path of a JSONL file recording every config change; null disables)\n\
+         - log_level (string: off, error, warn, info, debug, trace; applied immediately)\n\
+         - telemetry_enabled (boolean, allow usage reporting; off by default)\n\
+         - otlp_endpoint (http(s) URL of an OTLP collector for traces; applied on restart)\n\
+         - ext.<namespace>.<name> (any value, settings for third-party tool crates)\n\n\
          IMPORTANT: Setting allowed_directories to an empty array ([]) allows full access \n\
-         to the entire file system."
+         to the entire file system.\n\n\
+         Sensitive keys (blocked_commands, default_shell, allowed/denied directories and \n\
+         hosts, and similar) are not changed by the first call: it returns a \n\
+         confirmation_token, and the change is applied by repeating the call with that \n\
+         token. The same applies to config_unset, config_undo, config_redo, and \n\
+         config_backups restore.\n\n\
+         An optional reason (string) is recorded with the change in the change history \n\
+         and the audit log. A null value reverts a key to its default, as config_unset \n\
+         does.\n\n\
+         If the server requires authentication, pass its token as auth_token; calls \n\
+         without a valid token fail with an authentication error."
     }
 
     fn read_only() -> bool {
@@ -56,21 +98,60 @@ impl Tool for SetConfigValueTool {
     }
 
     fn idempotent() -> bool {
-        true
+        false
     }
 
     fn prompt_arguments() -> Vec<PromptArgument> {
-        vec![] // No prompt arguments needed
+        vec![PromptArgument {
+            name: "key".to_string(),
+            title: None,
+            description: Some(
+                "Key to get guidance for (valid values, security notes, and an example); \
+                 omit for general guidance"
+                    .to_string(),
+            ),
+            required: Some(false),
+        }]
     }
 
+    #[tracing::instrument(
+        name = "config.tool",
+        skip_all,
+        fields(tool = Self::name(), key = %args.key),
+        err
+    )]
     async fn execute(&self, args: Self::Args) -> Result<Vec<Content>, McpError> {
-        // Set the value
+        self.config_manager.authorize(args.auth_token.as_deref())?;
+
+        // Resolve deprecated aliases to the canonical key
+        let resolved = resolve_key(&args.key);
+        let key = resolved.key;
+        let deprecation_warning = resolved.deprecation_warning();
+
+        // Sensitive keys need a second, confirming call
+        if is_sensitive(key) {
+            match args.confirmation_token.as_deref() {
+                Some(token) => self.config_manager.confirm_change(token, key, &args.value)?,
+                None => {
+                    let token = self.config_manager.request_confirmation(key, &args.value);
+                    return Ok(confirmation_required(key, &args.value, &token));
+                }
+            }
+        }
+
+        // Set the value, keeping the caller's reason with the change
+        let reason = args
+            .reason
+            .as_deref()
+            .map(str::trim)
+            .filter(|reason| !reason.is_empty())
+            .map(str::to_string);
         self.config_manager
-            .set_value(&args.key, args.value.clone())
+            .set_json_value_with_reason(key, args.value.clone(), reason.clone())
             .await?;
         
         // Get updated config
-        let updated_config = self.config_manager.get_config();
+        let updated_config = self.config_manager.get_config_view();
         
         let mut contents = Vec::new();
         
@@ -80,31 +161,56 @@ impl Tool for SetConfigValueTool {
         
         // Format the value for display
         let value_display = match &args.value {
-            crate::ConfigValue::String(s) => format!("\"{}\"", s),
-            crate::ConfigValue::Number(n) => n.to_string(),
-            crate::ConfigValue::Boolean(b) => b.to_string(),
-            crate::ConfigValue::Array(arr) => {
-                if arr.is_empty() {
+            serde_json::Value::Array(arr) => {
+                let items: Vec<String> = arr
+                    .iter()
+                    .map(|v| v.as_str().map_or_else(|| v.to_string(), str::to_string))
+                    .collect();
+                if items.is_empty() {
                     "[] (empty)".to_string()
-                } else if arr.len() <= 3 {
-                    format!("[{}]", arr.join(", "))
+                } else if items.len() <= 3 {
+                    format!("[{}]", items.join(", "))
                 } else {
-                    format!("[{}, ... {} total]", arr[0], arr.len())
+                    format!("[{}, ... {} total]", items[0], items.len())
                 }
             }
+            serde_json::Value::Object(map) if map.is_empty() => "{} (empty)".to_string(),
+            serde_json::Value::Object(map) if map.len() > 3 => {
+                let keys: Vec<&str> = map.keys().take(3).map(String::as_str).collect();
+                format!("{{{}, ... {} entries}}", keys.join(", "), map.len())
+            }
+            other => other.to_string(),
         };
         
         // Contextual messages based on what changed
-        let context_info = match args.key.as_str() {
-            "blocked_commands" => "Commands in this list will be rejected by the terminal tool.",
-            "allowed_directories" => "Only paths within these directories can be accessed (empty = unrestricted).",
-            "default_shell" => "This shell will be used for all command executions.",
-            "file_read_line_limit" => "Maximum lines that can be read from a file in a single operation.",
-            "file_write_line_limit" => "Maximum lines that can be written to a file in a single operation.",
+        let context_info = match ConfigKey::parse(key) {
+            Ok(ConfigKey::BlockedCommands) => "Commands in this list will be rejected by the terminal tool.",
+            Ok(ConfigKey::AllowedDirectories) => "Only paths within these directories can be accessed (empty = unrestricted).",
+            Ok(ConfigKey::DefaultShell) => "This shell will be used for all command executions.",
+            Ok(ConfigKey::FileReadLineLimit) => "Maximum lines that can be read from a file in a single operation.",
+            Ok(ConfigKey::FileWriteLineLimit) => "Maximum lines that can be written to a file in a single operation.",
+            Ok(ConfigKey::BlockedCommandPatterns) => "Command lines matching these patterns will be rejected by the terminal tool.",
+            Ok(ConfigKey::MaxSearchResults) => "Search tools return at most this many results per call.",
+            Ok(ConfigKey::CommandTimeoutSecs) => "Commands run without their own timeout are stopped after this many seconds.",
+            Ok(ConfigKey::TempDirectory | ConfigKey::CacheDirectory) => "Tools put their scratch or cached data in this directory from now on.",
+            Ok(ConfigKey::EnvPassthrough) => "Only these environment variables will be forwarded to spawned commands.",
+            Ok(ConfigKey::AllowedHosts) => "Network tools may only reach these hosts (empty = unrestricted).",
+            Ok(ConfigKey::DeniedHosts) => "Network tools will refuse to reach these hosts.",
+            Ok(ConfigKey::ShellArgs) => "Commands on this platform will be run as default_shell followed by these arguments.",
+            Ok(ConfigKey::FollowSymlinks) => "Symlinks inside allowed directories are followed or refused based on this setting.",
+            Ok(ConfigKey::DefaultEncoding) => "File tools read and write with this encoding unless a call names another.",
+            Ok(ConfigKey::RateLimit(_)) => "Calls beyond this many per minute will be refused until older ones age out.",
+            Ok(ConfigKey::DirectoryAlias(_)) => "Directory entries and paths starting with this @alias now expand to this directory.",
+            Ok(ConfigKey::ClientHistoryMaxEntries | ConfigKey::ClientHistoryMaxAgeDays) => "Older client connection records are pruned before the history is next saved.",
+            Ok(ConfigKey::AuditLogPath) => "Every config change from now on, including failed attempts, is appended to this file.",
+            Ok(ConfigKey::LogLevel) => "The server logs at this level from now on; no restart needed.",
+            Ok(ConfigKey::TelemetryEnabled) => "Usage reporting starts or stops immediately.",
+            Ok(ConfigKey::OtlpEndpoint) => "Traces are exported to this collector once the server restarts.",
+            Ok(ConfigKey::Extension(_)) => "Extension setting stored for its plugin namespace.",
             _ => "Configuration value updated successfully."
         };
         
-        let summary = format!(
+        let mut summary = format!(
             "✅ Configuration Updated\n\
              \n\
              Setting: {}\n\
@@ -113,10 +219,20 @@ impl Tool for SetConfigValueTool {
              {}\n\
              \n\
              To view full configuration, use config_get.",
-            args.key,
+            key,
             value_display,
             context_info
         );
+        if let Some(reason) = &reason {
+            summary.push_str(&format!("\nReason: {}", reason));
+        }
+        let risk = risk_level(key);
+        if let Some(risk @ (RiskLevel::Caution | RiskLevel::Dangerous)) = risk {
+            summary.push_str(&format!("\nRisk: {}", risk.as_str()));
+        }
+        if let Some(warning) = &deprecation_warning {
+            summary.push_str(&format!("\n\n⚠️  {}", warning));
+        }
         contents.push(Content::text(summary));
         
         // ========================================
@@ -124,8 +240,11 @@ impl Tool for SetConfigValueTool {
         // ========================================
         let metadata = json!({
             "success": true,
-            "key": args.key,
+            "key": key,
             "value": args.value,
+            "reason": reason,
+            "risk": risk,
+            "deprecation_warning": deprecation_warning,
             "updated_config": updated_config
         });
         let json_str = serde_json::to_string_pretty(&metadata)
@@ -135,7 +254,34 @@ impl Tool for SetConfigValueTool {
         Ok(contents)
     }
 
-    async fn prompt(&self, _args: Self::PromptArgs) -> Result<Vec<PromptMessage>, McpError> {
+    async fn prompt(&self, args: Self::PromptArgs) -> Result<Vec<PromptMessage>, McpError> {
+        if let Some(key) = args.key.as_deref().map(str::trim).filter(|k| !k.is_empty()) {
+            let resolved = resolve_key(key);
+            let guidance = match metadata_for(resolved.key) {
+                Some(meta) => {
+                    let current = self.config_manager.get_value(meta.key);
+                    key_guidance(&meta, current, resolved.deprecation_warning())
+                }
+                None => format!(
+                    "'{}' has no registered metadata. The structured keys \
+                     tool_settings.<category>, rate_limits.<tool>, directory_aliases.<name>, \
+                     and ext.<namespace>.<name> are described in the config_set tool \
+                     description; use config_list_keys to see every registered key.",
+                    key
+                ),
+            };
+            return Ok(vec![
+                PromptMessage {
+                    role: PromptMessageRole::User,
+                    content: PromptMessageContent::text(format!("How do I set {}?", key)),
+                },
+                PromptMessage {
+                    role: PromptMessageRole::Assistant,
+                    content: PromptMessageContent::text(guidance),
+                },
+            ]);
+        }
+
         Ok(vec![
             PromptMessage {
                 role: PromptMessageRole::User,
@@ -152,9 +298,113 @@ impl Tool for SetConfigValueTool {
                      Restrict directories:\n\
                      {\"key\": \"allowed_directories\", \"value\": [\"/home/user/projects\"]}\n\n\
                      Adjust line limits:\n\
-                     {\"key\": \"file_read_line_limit\", \"value\": 2000}",
+                     {\"key\": \"file_read_line_limit\", \"value\": 2000}\n\n\
+                     Shell, command, and directory changes return a confirmation_token \
+                     first; repeat the call with it to apply the change.",
                 ),
             },
         ])
     }
 }
+
+/// Prompt guidance for one key: what it accepts, its security implications,
+/// and an example call.
+fn key_guidance(
+    meta: &KeyMetadata,
+    current: Option<serde_json::Value>,
+    deprecation_warning: Option<String>,
+) -> String {
+    let mut guidance = format!("{}: {}\n", meta.key, meta.description);
+    if let Some(warning) = deprecation_warning {
+        guidance.push_str(&format!("\n⚠️  {}\n", warning));
+    }
+
+    let value_type = match meta.value_type {
+        ValueType::String => "string",
+        ValueType::Number => "number",
+        ValueType::Float => "number, fractions allowed",
+        ValueType::Boolean => "boolean (true or false)",
+        ValueType::StringArray => "array of strings",
+    };
+    guidance.push_str(&format!("\nType: {}", value_type));
+    if let Some(range) = meta.range {
+        let unit = meta.unit.map_or_else(String::new, |u| format!(" {}", u));
+        let bounds = match range.max {
+            Some(max) => format!("{}-{}", range.min, max),
+            None => format!("at least {}", range.min),
+        };
+        guidance.push_str(&format!("\nValid range: {}{}", bounds, unit));
+    } else if let Some(unit) = meta.unit {
+        guidance.push_str(&format!("\nUnit: {}", unit));
+    }
+    if let Some(notes) = meta.notes {
+        guidance.push_str(&format!("\nNotes: {}", notes));
+    }
+    guidance.push_str(&format!("\nRisk: {}", meta.risk.as_str()));
+    let current = current.filter(|v| !v.is_null());
+    match &current {
+        Some(value) => guidance.push_str(&format!("\nCurrent value: {}", value)),
+        None => guidance.push_str("\nCurrent value: unset"),
+    }
+
+    if meta.risk == RiskLevel::Dangerous {
+        guidance.push_str(
+            "\n\nThis key is classified as dangerous: confirm the change with the user \
+             before making it.",
+        );
+    }
+    if meta.security == SecurityLevel::Sensitive {
+        guidance.push_str(&format!(
+            "\n\n🔒 Security: {} controls what the server may execute or access. Only \
+             change it when the user asked for it. The first config_set call returns a \
+             confirmation_token instead of applying the change; repeat the call with that \
+             token within {} minutes to apply it.",
+            meta.key,
+            CONFIRMATION_TTL.as_secs() / 60
+        ));
+    }
+
+    let example = current.unwrap_or_else(|| match meta.value_type {
+        ValueType::String => json!("<value>"),
+        ValueType::Number => json!(meta.range.map_or(1, |r| r.min)),
+        ValueType::Float => json!(0.5),
+        ValueType::Boolean => json!(true),
+        ValueType::StringArray => json!(["<value>"]),
+    });
+    guidance.push_str(&format!(
+        "\n\nExample:\n{}",
+        json!({ "key": meta.key, "value": example })
+    ));
+    guidance
+}
+
+/// Response to the first `config_set` call on a sensitive key.
+pub(crate) fn confirmation_required(key: &str, value: &serde_json::Value, token: &str) -> Vec<Content> {
+    let summary = format!(
+        "⚠️  Confirmation Required\n\
+         \n\
+         Setting: {}\n\
+         Requested value: {}\n\
+         \n\
+         {} controls what the server may execute or access, so the change has not \
+         been applied. Repeat the call with \"confirmation_token\": \"{}\" within {} \
+         minutes to apply it.",
+        key,
+        value,
+        key,
+        token,
+        CONFIRMATION_TTL.as_secs() / 60
+    );
+    let metadata = json!({
+        "success": false,
+        "confirmation_required": true,
+        "key": key,
+        "value": value,
+        "confirmation_token": token,
+        "risk": risk_level(key),
+        "expires_in_secs": CONFIRMATION_TTL.as_secs()
+    });
+    let json_str = serde_json::to_string_pretty(&metadata)
+        .unwrap_or_else(|_| "{}".to_string());
+    vec![Content::text(summary), Content::text(json_str)]
+}
@@ -0,0 +1,135 @@
+use crate::ConfigManager;
+use crate::schema::{CONFIG_SET_SECRET, ConfigSetSecretArgs, ConfigSetSecretPromptArgs};
+use crate::secrets::REDACTED;
+use kodegen_mcp_tool::Tool;
+use kodegen_mcp_tool::error::McpError;
+use rmcp::model::{Content, PromptArgument, PromptMessage, PromptMessageContent, PromptMessageRole};
+use serde_json::json;
+
+// ============================================================================
+// TOOL STRUCT
+// ============================================================================
+
+#[derive(Clone)]
+pub struct SetSecretTool {
+    config_manager: ConfigManager,
+}
+
+impl SetSecretTool {
+    #[must_use]
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self { config_manager }
+    }
+}
+
+// ============================================================================
+// TOOL IMPLEMENTATION
+// ============================================================================
+
+impl Tool for SetSecretTool {
+    type Args = ConfigSetSecretArgs;
+    type PromptArgs = ConfigSetSecretPromptArgs;
+
+    fn name() -> &'static str {
+        CONFIG_SET_SECRET
+    }
+
+    fn description() -> &'static str {
+        "Store a secret (API token, password) that tools need, or remove it by omitting \
+         the value. Secrets are encrypted with the config encryption key or kept in the \
+         OS keyring, never written to the config files, and always shown as *** by \
+         config_get. The value is never echoed back."
+    }
+
+    fn read_only() -> bool {
+        false
+    }
+
+    fn destructive() -> bool {
+        true
+    }
+
+    fn idempotent() -> bool {
+        true
+    }
+
+    fn prompt_arguments() -> Vec<PromptArgument> {
+        vec![] // No prompt arguments needed
+    }
+
+    #[tracing::instrument(name = "config.tool", skip_all, fields(tool = Self::name()), err)]
+    async fn execute(&self, args: Self::Args) -> Result<Vec<Content>, McpError> {
+        self.config_manager.authorize(args.auth_token.as_deref())?;
+        let name = args.name.trim();
+        let existed = self.config_manager.secret_backend(name).is_some();
+        self.config_manager
+            .set_secret(name, args.value.as_deref())
+            .await?;
+        let backend = self.config_manager.secret_backend(name);
+        let action = match (&args.value, existed) {
+            (Some(_), false) => "stored",
+            (Some(_), true) => "replaced",
+            (None, true) => "removed",
+            (None, false) => "unchanged",
+        };
+
+        let mut contents = Vec::new();
+
+        // ========================================
+        // Content[0]: Human-Readable Summary
+        // ========================================
+        let summary = match backend {
+            Some(backend) => format!(
+                "🔐 Secret {}\n\
+                 \n\
+                 Name: {}\n\
+                 Value: {}\n\
+                 Storage: {}\n\
+                 \n\
+                 {} secret(s) stored.",
+                action,
+                name,
+                REDACTED,
+                backend,
+                self.config_manager.secret_names().len()
+            ),
+            None if existed => format!("🗑️  Secret '{}' removed", name),
+            None => format!("ℹ️  No secret named '{}'", name),
+        };
+        contents.push(Content::text(summary));
+
+        // ========================================
+        // Content[1]: Machine-Parseable JSON
+        // ========================================
+        let metadata = json!({
+            "success": true,
+            "name": name,
+            "action": action,
+            "backend": backend,
+            "secrets": self.config_manager.secret_names()
+        });
+        let json_str = serde_json::to_string_pretty(&metadata)
+            .unwrap_or_else(|_| "{}".to_string());
+        contents.push(Content::text(json_str));
+
+        Ok(contents)
+    }
+
+    async fn prompt(&self, _args: Self::PromptArgs) -> Result<Vec<PromptMessage>, McpError> {
+        Ok(vec![
+            PromptMessage {
+                role: PromptMessageRole::User,
+                content: PromptMessageContent::text("How do I give tools an API token?"),
+            },
+            PromptMessage {
+                role: PromptMessageRole::Assistant,
+                content: PromptMessageContent::text(
+                    "Use config_set_secret rather than config_set, so the token is stored \
+                     encrypted and never appears in config output:\n\n\
+                     {\"name\": \"github_token\", \"value\": \"ghp_...\"}\n\n\
+                     Call it with only the name to remove the secret.",
+                ),
+            },
+        ])
+    }
+}
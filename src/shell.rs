@@ -0,0 +1,122 @@
+//! Shell invocation
+//!
+//! `shell_args` holds the arguments passed to `default_shell` ahead of the
+//! command line, keyed by platform so one config file can serve hosts running
+//! different systems. Setting the key to an array sets the current platform's
+//! entry; an object replaces every entry. Platforms without an entry use
+//! arguments derived from the shell's name.
+
+use crate::system_info::resolve_shell;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Key of the per-platform shell arguments.
+pub const SHELL_ARGS_KEY: &str = "shell_args";
+
+/// Accepted keys of `shell_args`; `unix` applies to every Unix platform
+/// without an entry of its own.
+pub const SHELL_ARGS_PLATFORMS: &[&str] =
+    &["linux", "macos", "windows", "freebsd", "netbsd", "openbsd", "unix"];
+
+/// Platform key the running host reads from `shell_args`.
+#[must_use]
+pub fn current_platform() -> &'static str {
+    std::env::consts::OS
+}
+
+/// Arguments that make `shell` run the command line that follows them.
+#[must_use]
+pub fn default_shell_args(shell: &str) -> Vec<String> {
+    let name = Path::new(shell)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(shell)
+        .to_ascii_lowercase();
+    let args: &[&str] = match name.as_str() {
+        "powershell" | "pwsh" => &["-NoProfile", "-Command"],
+        "cmd" => &["/C"],
+        _ => &["-c"],
+    };
+    args.iter().map(|s| s.to_string()).collect()
+}
+
+/// The configured arguments for this platform, falling back to `unix`.
+#[must_use]
+pub fn platform_shell_args(entries: &HashMap<String, Vec<String>>) -> Option<&Vec<String>> {
+    entries
+        .get(current_platform())
+        .or_else(|| if cfg!(unix) { entries.get("unix") } else { None })
+}
+
+/// Parse `shell_args` into `entries`: an array replaces the current
+/// platform's entry, `null` removes it, and an object replaces every entry.
+pub(crate) fn apply_shell_args(
+    entries: &mut HashMap<String, Vec<String>>,
+    value: &serde_json::Value,
+) -> Result<(), String> {
+    let parsed = match value {
+        serde_json::Value::Null => {
+            entries.remove(current_platform());
+            return Ok(());
+        }
+        serde_json::Value::Object(_) => serde_json::from_value(value.clone()),
+        _ => serde_json::from_value(value.clone())
+            .map(|args| HashMap::from([(current_platform().to_string(), args)])),
+    };
+    let new: HashMap<String, Vec<String>> =
+        parsed.map_err(|e| format!("Invalid value for '{}': {}", SHELL_ARGS_KEY, e))?;
+    for (platform, args) in &new {
+        validate_entry(platform, args)
+            .map_err(|e| format!("Invalid value for '{}': {}", SHELL_ARGS_KEY, e))?;
+    }
+    if value.is_object() {
+        *entries = new;
+    } else {
+        entries.extend(new);
+    }
+    Ok(())
+}
+
+fn validate_entry(platform: &str, args: &[String]) -> Result<(), String> {
+    if platform != current_platform() && !SHELL_ARGS_PLATFORMS.contains(&platform) {
+        return Err(format!(
+            "unknown platform '{}', expected one of {}",
+            platform,
+            SHELL_ARGS_PLATFORMS.join(", ")
+        ));
+    }
+    if let Some(arg) = args.iter().find(|arg| arg.contains('\0')) {
+        return Err(format!("argument '{}' contains a NUL byte", arg.escape_debug()));
+    }
+    Ok(())
+}
+
+/// Program and leading arguments for running a command line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShellInvocation {
+    /// `default_shell`, resolved on the `PATH` when it names a command.
+    pub program: PathBuf,
+    /// `shell_args` for this platform, or the shell's defaults.
+    pub args: Vec<String>,
+}
+
+impl ShellInvocation {
+    /// Invocation of `shell` with `args`, or its default arguments for `None`.
+    #[must_use]
+    pub fn new(shell: &str, args: Option<Vec<String>>) -> Self {
+        Self {
+            program: resolve_shell(shell).unwrap_or_else(|| PathBuf::from(shell)),
+            args: args.unwrap_or_else(|| default_shell_args(shell)),
+        }
+    }
+
+    /// A `Command` that runs `cmdline` through the shell.
+    #[must_use]
+    pub fn command(&self, cmdline: &str) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args).arg(cmdline);
+        command
+    }
+}
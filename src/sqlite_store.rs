@@ -0,0 +1,212 @@
+//! SQLite persistence backend
+//!
+//! Each save is a single transaction touching one row, so there is no
+//! whole-file rewrite and a crash can never leave a partial config behind.
+
+use crate::extended_config::ExtendedConfig;
+use crate::history::ConfigChange;
+use crate::store::{ConfigStore, StoreFuture};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use rusqlite::{Connection, OptionalExtension, params};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// File name of the database, stored alongside `config.json`.
+pub const SQLITE_CONFIG_FILE: &str = "config.db";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS extended_config (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        data TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS changes (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        key TEXT NOT NULL,
+        old TEXT NOT NULL,
+        new TEXT NOT NULL,
+        timestamp TEXT NOT NULL,
+        reason TEXT
+    );
+    CREATE INDEX IF NOT EXISTS changes_key ON changes (key, id);
+    CREATE TABLE IF NOT EXISTS client_history (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT,
+        data TEXT NOT NULL
+    );
+";
+
+pub struct SqliteStore {
+    path: PathBuf,
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    /// Open (creating if needed) the database at `path`.
+    pub fn open(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(&path).map_err(to_io)?;
+        conn.execute_batch(SCHEMA).map_err(to_io)?;
+        add_reason_column(&conn).map_err(to_io)?;
+        Ok(Self {
+            path,
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Open the database at `~/.kodegen/config.db`.
+    pub fn open_default() -> std::io::Result<Self> {
+        Self::open(crate::persistence::config_dir().join(SQLITE_CONFIG_FILE))
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Run `f` against the connection on the blocking thread pool.
+    fn blocking<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    ) -> StoreFuture<'_, T> {
+        let conn = Arc::clone(&self.conn);
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || f(&mut conn.lock()).map_err(to_io))
+                .await
+                .unwrap_or_else(|e| Err(std::io::Error::other(e)))
+        })
+    }
+}
+
+impl ConfigStore for SqliteStore {
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn load(&self) -> StoreFuture<'_, Option<ExtendedConfig>> {
+        let query = self.blocking(|conn| {
+            conn.query_row("SELECT data FROM extended_config WHERE id = 1", [], |row| {
+                row.get::<_, String>(0)
+            })
+            .optional()
+        });
+        Box::pin(async move {
+            query
+                .await?
+                .map(|data| serde_json::from_str(&data).map_err(std::io::Error::from))
+                .transpose()
+        })
+    }
+
+    fn save<'a>(&'a self, config: &'a ExtendedConfig) -> StoreFuture<'a, ()> {
+        let data = match serde_json::to_string(config) {
+            Ok(data) => data,
+            Err(e) => return Box::pin(async move { Err(e.into()) }),
+        };
+        self.blocking(move |conn| {
+            conn.execute(
+                "INSERT INTO extended_config (id, data, updated_at) VALUES (1, ?1, ?2)
+                 ON CONFLICT (id) DO UPDATE SET data = excluded.data, updated_at = excluded.updated_at",
+                params![data, Utc::now().to_rfc3339()],
+            )
+            .map(|_| ())
+        })
+    }
+
+    fn record_change<'a>(&'a self, change: &'a ConfigChange) -> StoreFuture<'a, ()> {
+        let change = change.clone();
+        self.blocking(move |conn| {
+            conn.execute(
+                "INSERT INTO changes (key, old, new, timestamp, reason)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    change.key,
+                    change.old.to_string(),
+                    change.new.to_string(),
+                    change.timestamp.to_rfc3339(),
+                    change.reason
+                ],
+            )
+            .map(|_| ())
+        })
+    }
+
+    fn change_history<'a>(
+        &'a self,
+        key: Option<&'a str>,
+        limit: usize,
+    ) -> StoreFuture<'a, Vec<ConfigChange>> {
+        let key = key.map(str::to_string);
+        let query = self.blocking(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT key, old, new, timestamp, reason FROM changes
+                 WHERE ?1 IS NULL OR key = ?1
+                 ORDER BY id DESC LIMIT ?2",
+            )?;
+            let rows = stmt.query_map(params![key, limit as i64], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            })?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()
+        });
+        Box::pin(async move {
+            let mut changes = Vec::new();
+            for (key, old, new, timestamp, reason) in query.await? {
+                changes.push(ConfigChange {
+                    key,
+                    old: serde_json::from_str(&old)?,
+                    new: serde_json::from_str(&new)?,
+                    timestamp: DateTime::parse_from_rfc3339(&timestamp)
+                        .map(|t| t.with_timezone(&Utc))
+                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?,
+                    reason,
+                });
+            }
+            Ok(changes)
+        })
+    }
+
+    fn save_client_history<'a>(&'a self, clients: &'a serde_json::Value) -> StoreFuture<'a, ()> {
+        let clients: Vec<serde_json::Value> = clients.as_array().cloned().unwrap_or_default();
+        self.blocking(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM client_history", [])?;
+            for client in &clients {
+                let name = client
+                    .pointer("/client_info/name")
+                    .and_then(serde_json::Value::as_str);
+                tx.execute(
+                    "INSERT INTO client_history (name, data) VALUES (?1, ?2)",
+                    params![name, client.to_string()],
+                )?;
+            }
+            tx.commit()
+        })
+    }
+}
+
+fn to_io(e: rusqlite::Error) -> std::io::Error {
+    std::io::Error::other(e)
+}
+
+/// Add `changes.reason` to databases created before it existed.
+fn add_reason_column(conn: &Connection) -> rusqlite::Result<()> {
+    let exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('changes') WHERE name = 'reason'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        conn.execute("ALTER TABLE changes ADD COLUMN reason TEXT", [])?;
+    }
+    Ok(())
+}
@@ -0,0 +1,129 @@
+//! Pluggable persistence backends
+//!
+//! By default the extended config lives in `config.tools.*` and history is
+//! kept in the change journal. A `ConfigStore` set via
+//! `ConfigManagerBuilder::store` replaces the file, is loaded by
+//! `ConfigManager::init()`, and may also persist change and client history
+//! and push updates made by other processes through `watch`.
+
+use crate::encryption::{self, EncryptionKey};
+use crate::extended_config::ExtendedConfig;
+use crate::format::ConfigFormat;
+use crate::history::ConfigChange;
+use crate::persistence::{self, Durability};
+use futures::future::BoxFuture;
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+/// Future returned by `ConfigStore` methods.
+pub type StoreFuture<'a, T> = BoxFuture<'a, std::io::Result<T>>;
+
+/// Storage for the extended config and its history.
+pub trait ConfigStore: Send + Sync {
+    /// Where the data lives (a path or URL), for logs and error reports.
+    fn location(&self) -> String;
+
+    /// The stored config, or `None` if nothing has been saved yet.
+    fn load(&self) -> StoreFuture<'_, Option<ExtendedConfig>>;
+
+    /// Replace the stored config.
+    fn save<'a>(&'a self, config: &'a ExtendedConfig) -> StoreFuture<'a, ()>;
+
+    /// Configs saved by other processes, delivered as they happen.
+    ///
+    /// Returns `None` if the backend can't watch for changes (the default).
+    fn watch(&self) -> Option<mpsc::Receiver<ExtendedConfig>> {
+        None
+    }
+
+    /// Append an applied change to the change history. Ignored by default.
+    fn record_change<'a>(&'a self, _change: &'a ConfigChange) -> StoreFuture<'a, ()> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Most recent changes first, optionally only those to `key`. Empty by default.
+    fn change_history<'a>(
+        &'a self,
+        _key: Option<&'a str>,
+        _limit: usize,
+    ) -> StoreFuture<'a, Vec<ConfigChange>> {
+        Box::pin(async { Ok(Vec::new()) })
+    }
+
+    /// Replace the stored client history with the entries in `clients`, a
+    /// JSON array. Called only when the history changed. Ignored by default.
+    fn save_client_history<'a>(&'a self, _clients: &'a serde_json::Value) -> StoreFuture<'a, ()> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+// ============================================================================
+// FILE STORE
+// ============================================================================
+
+/// A single config file in any supported format, optionally encrypted.
+///
+/// `ConfigManager` has its own richer file handling (backups, conflict
+/// detection) when no store is configured; this store is for embedders and
+/// for remote backends that keep a local copy.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    path: PathBuf,
+    durability: Durability,
+    key: Option<EncryptionKey>,
+}
+
+impl FileStore {
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            durability: Durability::default(),
+            key: None,
+        }
+    }
+
+    #[must_use]
+    pub fn durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    #[must_use]
+    pub fn encryption_key(mut self, key: Option<EncryptionKey>) -> Self {
+        self.key = key;
+        self
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+}
+
+impl ConfigStore for FileStore {
+    fn location(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn load(&self) -> StoreFuture<'_, Option<ExtendedConfig>> {
+        Box::pin(async move {
+            let bytes = match tokio::fs::read(&self.path).await {
+                Ok(bytes) => bytes,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e),
+            };
+            let contents = encryption::decode(bytes, self.key.as_ref())?;
+            persistence::parse_config(ConfigFormat::detect(&self.path, &contents), &contents)
+                .map(Some)
+        })
+    }
+
+    fn save<'a>(&'a self, config: &'a ExtendedConfig) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            persistence::save_to_disk(&self.path, config, self.durability, self.key.as_ref())
+                .await
+                .map(|_| ())
+        })
+    }
+}
@@ -0,0 +1,93 @@
+//! Environment of spawned commands
+//!
+//! Terminal tools start commands with only the variables `env_passthrough`
+//! allows, so API keys and tokens in the server's environment don't leak
+//! into subprocesses. Entries are variable names, or prefixes ending in `*`
+//! such as `LC_*`; names are case-insensitive on Windows.
+
+/// Key of the variables forwarded to spawned commands.
+pub const ENV_PASSTHROUGH_KEY: &str = "env_passthrough";
+
+/// Forwarded while `env_passthrough` is unset: what shells and common tools
+/// need to run, and nothing that typically holds a secret.
+pub const DEFAULT_ENV_PASSTHROUGH: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "TERM",
+    "COLORTERM",
+    "LANG",
+    "LANGUAGE",
+    "LC_*",
+    "TZ",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    // Windows
+    "SYSTEMROOT",
+    "SYSTEMDRIVE",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+    "USERNAME",
+    "USERPROFILE",
+    "HOMEDRIVE",
+    "HOMEPATH",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "PROGRAMDATA",
+    "PROGRAMFILES",
+    "PROGRAMFILES(X86)",
+    "NUMBER_OF_PROCESSORS",
+    "PROCESSOR_ARCHITECTURE",
+];
+
+/// Check that `entry` is a variable name, or a prefix followed by one `*`.
+pub(crate) fn validate_entry(entry: &str) -> Result<(), String> {
+    let name = entry.strip_suffix('*').unwrap_or(entry);
+    let invalid = name.is_empty()
+        || name.contains(['=', '*', '\0'])
+        || name.chars().any(char::is_whitespace);
+    if invalid {
+        return Err(format!("'{}' is not a variable name or a prefix ending in '*'", entry));
+    }
+    Ok(())
+}
+
+/// Whether `name` is allowed by any of `entries`.
+#[must_use]
+pub fn is_passed_through(name: &str, entries: &[impl AsRef<str>]) -> bool {
+    entries.iter().any(|entry| {
+        let entry = entry.as_ref();
+        match entry.strip_suffix('*') {
+            Some(prefix) => starts_with(name, prefix),
+            None => equals(name, entry),
+        }
+    })
+}
+
+/// The variables of this process allowed by `entries`, sorted by name.
+/// Variables that aren't valid Unicode are dropped.
+#[must_use]
+pub fn filter_env(entries: &[impl AsRef<str>]) -> Vec<(String, String)> {
+    let mut env: Vec<(String, String)> = std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(name, _)| is_passed_through(name, entries))
+        .collect();
+    env.sort();
+    env
+}
+
+fn equals(name: &str, entry: &str) -> bool {
+    if cfg!(windows) {
+        name.eq_ignore_ascii_case(entry)
+    } else {
+        name == entry
+    }
+}
+
+fn starts_with(name: &str, prefix: &str) -> bool {
+    name.get(..prefix.len()).is_some_and(|start| equals(start, prefix))
+}
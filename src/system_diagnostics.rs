@@ -0,0 +1,187 @@
+use crate::ConfigManager;
+use crate::schema::{
+    DiagnosticsSection, SYSTEM_DIAGNOSTICS, SystemDiagnosticsArgs, SystemDiagnosticsPromptArgs,
+};
+use crate::system_info::{
+    SYSTEM_INFO_SETTINGS_CATEGORY, SystemInfoSettings, cached_cpu_info, cached_memory_bytes, disks,
+    network_interfaces, toolchains,
+};
+use kodegen_mcp_tool::Tool;
+use kodegen_mcp_tool::error::McpError;
+use rmcp::model::{Content, PromptArgument, PromptMessage, PromptMessageContent, PromptMessageRole};
+use serde_json::json;
+
+const MB: u64 = 1024 * 1024;
+const GB: u64 = 1024 * MB;
+
+// ============================================================================
+// TOOL STRUCT
+// ============================================================================
+
+#[derive(Clone)]
+pub struct SystemDiagnosticsTool {
+    config_manager: ConfigManager,
+}
+
+impl SystemDiagnosticsTool {
+    #[must_use]
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self { config_manager }
+    }
+}
+
+// ============================================================================
+// TOOL IMPLEMENTATION
+// ============================================================================
+
+impl Tool for SystemDiagnosticsTool {
+    type Args = SystemDiagnosticsArgs;
+    type PromptArgs = SystemDiagnosticsPromptArgs;
+
+    fn name() -> &'static str {
+        SYSTEM_DIAGNOSTICS
+    }
+
+    fn description() -> &'static str {
+        "Collect selected host details: cpu (model and cores), memory, disks (mount points \
+         and free space), network (interfaces and addresses), and toolchains (rustc, cargo, \
+         node, python, git versions). Only the requested sections are collected, so asking \
+         for disks alone skips the slow toolchain probes. Omit sections to collect all."
+    }
+
+    fn read_only() -> bool {
+        true
+    }
+
+    fn prompt_arguments() -> Vec<PromptArgument> {
+        vec![] // No prompt arguments needed
+    }
+
+    #[tracing::instrument(name = "config.tool", skip_all, fields(tool = Self::name()), err)]
+    async fn execute(&self, args: Self::Args) -> Result<Vec<Content>, McpError> {
+        // Collected in a fixed order, each at most once
+        let sections: Vec<DiagnosticsSection> = DiagnosticsSection::ALL
+            .into_iter()
+            .filter(|s| args.sections.is_empty() || args.sections.contains(s))
+            .collect();
+
+        let mut lines = Vec::new();
+        let mut metadata = json!({
+            "success": true,
+            "sections": sections
+        });
+
+        for section in &sections {
+            match section {
+                DiagnosticsSection::Cpu => {
+                    let cpu = cached_cpu_info(args.refresh);
+                    lines.push(format!(
+                        "• CPU: {} ({} physical / {} logical cores, {} MHz)",
+                        if cpu.brand.is_empty() { "unknown" } else { cpu.brand.as_str() },
+                        cpu.physical_cores.map_or_else(|| "?".to_string(), |n| n.to_string()),
+                        cpu.logical_cores,
+                        cpu.frequency_mhz
+                    ));
+                    metadata["cpu"] = json!(cpu);
+                }
+                DiagnosticsSection::Memory => {
+                    let memory = cached_memory_bytes(args.refresh);
+                    lines.push(format!(
+                        "• Memory: {} MB used, {} MB available of {} MB",
+                        memory.used_bytes / MB,
+                        memory.available_bytes / MB,
+                        memory.total_bytes / MB
+                    ));
+                    metadata["memory"] = json!(memory);
+                }
+                DiagnosticsSection::Disks => {
+                    let disks = disks();
+                    let list: Vec<String> = disks
+                        .iter()
+                        .map(|d| {
+                            format!(
+                                "{} {} of {} GB free",
+                                d.mount_point.display(),
+                                d.available_bytes / GB,
+                                d.total_bytes / GB
+                            )
+                        })
+                        .collect();
+                    lines.push(format!("• Disks: {}", join_or(&list, "none found")));
+                    metadata["disks"] = json!(disks);
+                }
+                DiagnosticsSection::Network => {
+                    let settings: SystemInfoSettings = self
+                        .config_manager
+                        .get_tool_settings(SYSTEM_INFO_SETTINGS_CATEGORY)
+                        .ok()
+                        .flatten()
+                        .unwrap_or_default();
+                    let network = network_interfaces(settings.include_mac);
+                    let list: Vec<String> = network
+                        .iter()
+                        .filter_map(|interface| {
+                            let addr = interface.ipv4.first().or(interface.ipv6.first())?;
+                            Some(format!("{} ({})", interface.name, addr))
+                        })
+                        .collect();
+                    lines.push(format!("• Network: {}", join_or(&list, "no addresses")));
+                    metadata["network"] = json!(network);
+                }
+                DiagnosticsSection::Toolchains => {
+                    let toolchains = toolchains(args.refresh).await;
+                    let found: Vec<String> = toolchains
+                        .iter()
+                        .filter_map(|t| Some(format!("{} {}", t.name, t.version.as_ref()?)))
+                        .collect();
+                    lines.push(format!("• Toolchains: {}", join_or(&found, "none found")));
+                    metadata["toolchains"] = json!(toolchains);
+                }
+            }
+        }
+
+        let mut contents = Vec::new();
+
+        // ========================================
+        // Content[0]: Human-Readable Summary
+        // ========================================
+        let summary = format!("🩺 System Diagnostics\n\n{}", lines.join("\n"));
+        contents.push(Content::text(summary));
+
+        // ========================================
+        // Content[1]: Machine-Parseable JSON
+        // ========================================
+        let json_str = serde_json::to_string_pretty(&metadata)
+            .unwrap_or_else(|_| "{}".to_string());
+        contents.push(Content::text(json_str));
+
+        Ok(contents)
+    }
+
+    async fn prompt(&self, _args: Self::PromptArgs) -> Result<Vec<PromptMessage>, McpError> {
+        Ok(vec![
+            PromptMessage {
+                role: PromptMessageRole::User,
+                content: PromptMessageContent::text("Is there enough disk space for a build?"),
+            },
+            PromptMessage {
+                role: PromptMessageRole::Assistant,
+                content: PromptMessageContent::text(
+                    "Use system_diagnostics with only the sections you need:\n\n\
+                     {\"sections\": [\"disks\"]}\n\n\
+                     Each disk reports its mount point with total and available bytes; \
+                     check the one holding the target directory.",
+                ),
+            },
+        ])
+    }
+}
+
+/// `items` separated by commas, or `empty` when there are none.
+fn join_or(items: &[String], empty: &str) -> String {
+    if items.is_empty() {
+        empty.to_string()
+    } else {
+        items.join(", ")
+    }
+}
@@ -0,0 +1,777 @@
+//! Host details beyond the infrastructure crate's `SystemInfo`
+//!
+//! `config_get` adds these sections to its `system_info` output. Collecting
+//! them is slow enough to matter on every call, so the `cached_*` accessors
+//! keep static details for the life of the process and memory figures for
+//! `SYSTEM_INFO_TTL`.
+
+use crate::SystemInfo;
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+/// Interval CPU usage is sampled over unless requested otherwise.
+pub const DEFAULT_CPU_SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Longest sampling interval `system_metrics` accepts.
+pub const MAX_CPU_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Longest a toolchain's `--version` may take before it is reported missing.
+const TOOLCHAIN_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Toolchains probed by `toolchains()`, each with the commands tried in order.
+pub const TOOLCHAIN_PROBES: &[(&str, &[&str])] = &[
+    ("rustc", &["rustc"]),
+    ("cargo", &["cargo"]),
+    ("node", &["node"]),
+    ("python", &["python3", "python"]),
+    ("git", &["git"]),
+];
+
+/// Resource limits reported by `resource_limits()`; Unix only.
+#[cfg(unix)]
+const RESOURCE_LIMITS: &[(&str, rlimit::Resource)] = &[
+    ("open_files", rlimit::Resource::NOFILE),
+    ("processes", rlimit::Resource::NPROC),
+    ("core_file_bytes", rlimit::Resource::CORE),
+];
+
+/// Shells probed on the `PATH` where `/etc/shells` is missing, and on Windows.
+#[cfg(not(windows))]
+const SHELL_PROBES: &[&str] = &["sh", "bash", "zsh", "fish", "dash", "ksh", "tcsh", "nu"];
+#[cfg(windows)]
+const SHELL_PROBES: &[&str] = &["pwsh", "powershell", "cmd", "bash", "nu"];
+
+/// How long memory figures are reused by the cached accessors.
+pub const SYSTEM_INFO_TTL: Duration = Duration::from_secs(2);
+
+/// `tool_settings` category holding `SystemInfoSettings`.
+pub const SYSTEM_INFO_SETTINGS_CATEGORY: &str = "system_info";
+
+/// What `config_get` reports about the host, stored in
+/// `tool_settings.system_info`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SystemInfoSettings {
+    /// Include interface MAC addresses, which identify the machine.
+    #[serde(default)]
+    pub include_mac: bool,
+}
+
+/// Operating system identity, for choosing package managers and install
+/// instructions rather than for display.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OsRelease {
+    /// Lowercase distribution id, e.g. `ubuntu`, `fedora`, `macos`, or `windows`.
+    pub id: String,
+    /// Distributions this one derives from, e.g. `["debian"]` for Ubuntu.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub id_like: Vec<String>,
+    /// e.g. `24.04`, `15.3`, or `11`.
+    pub version_id: Option<String>,
+    /// e.g. `noble` or `sequoia`.
+    pub codename: Option<String>,
+    pub pretty_name: Option<String>,
+}
+
+/// Parsed from `/etc/os-release` on Linux and the BSDs; derived from the OS
+/// version on macOS and Windows.
+#[must_use]
+pub fn os_release() -> OsRelease {
+    let file = ["/etc/os-release", "/usr/lib/os-release"]
+        .iter()
+        .find_map(|path| std::fs::read_to_string(path).ok());
+    if let Some(file) = file {
+        return parse_os_release(&file);
+    }
+    let version_id = sysinfo::System::os_version();
+    let id = sysinfo::System::distribution_id();
+    OsRelease {
+        codename: match id.as_str() {
+            "macos" => version_id.as_deref().and_then(macos_codename).map(str::to_string),
+            _ => None,
+        },
+        id,
+        id_like: Vec::new(),
+        version_id,
+        pretty_name: sysinfo::System::long_os_version(),
+    }
+}
+
+/// Fields of an os-release file; see os-release(5).
+fn parse_os_release(file: &str) -> OsRelease {
+    let fields: std::collections::HashMap<&str, String> = file
+        .lines()
+        .filter_map(|line| line.trim().split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim().trim_matches(['"', '\'']).to_string()))
+        .filter(|(_, value)| !value.is_empty())
+        .collect();
+    OsRelease {
+        id: fields.get("ID").map_or_else(|| "linux".to_string(), |id| id.to_lowercase()),
+        id_like: fields
+            .get("ID_LIKE")
+            .map(|ids| ids.split_whitespace().map(str::to_lowercase).collect())
+            .unwrap_or_default(),
+        version_id: fields.get("VERSION_ID").cloned(),
+        codename: fields
+            .get("VERSION_CODENAME")
+            .or_else(|| fields.get("UBUNTU_CODENAME"))
+            .cloned(),
+        pretty_name: fields.get("PRETTY_NAME").or_else(|| fields.get("NAME")).cloned(),
+    }
+}
+
+/// Marketing name of a macOS release, from its major version.
+fn macos_codename(version: &str) -> Option<&'static str> {
+    let major = version.split('.').next()?;
+    Some(match major {
+        "11" => "big_sur",
+        "12" => "monterey",
+        "13" => "ventura",
+        "14" => "sonoma",
+        "15" => "sequoia",
+        "26" => "tahoe",
+        _ => return None,
+    })
+}
+
+/// Processor model and core counts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CpuInfo {
+    /// Brand string, e.g. `Apple M2 Pro`.
+    pub brand: String,
+    pub vendor: String,
+    /// Frequency reported by the OS, usually the base frequency.
+    pub frequency_mhz: u64,
+    /// `None` where the OS doesn't report it.
+    pub physical_cores: Option<usize>,
+    pub logical_cores: usize,
+}
+
+/// Processor details of the first CPU; all cores are assumed identical.
+#[must_use]
+pub fn cpu_info() -> CpuInfo {
+    let system = sysinfo::System::new_with_specifics(
+        sysinfo::RefreshKind::nothing()
+            .with_cpu(sysinfo::CpuRefreshKind::nothing().with_frequency()),
+    );
+    let cpus = system.cpus();
+    let first = cpus.first();
+    CpuInfo {
+        brand: first.map_or_else(String::new, |cpu| cpu.brand().trim().to_string()),
+        vendor: first.map_or_else(String::new, |cpu| cpu.vendor_id().to_string()),
+        frequency_mhz: first.map_or(0, sysinfo::Cpu::frequency),
+        physical_cores: sysinfo::System::physical_core_count(),
+        logical_cores: cpus.len(),
+    }
+}
+
+/// CPU utilization over one sampling interval, in percent.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CpuUsage {
+    pub overall_percent: f32,
+    pub per_core_percent: Vec<f32>,
+    pub sample_ms: u64,
+}
+
+/// Measure CPU utilization over `interval`, raised to the minimum the OS
+/// needs between two readings.
+pub async fn sample_cpu_usage(interval: Duration) -> CpuUsage {
+    let interval = interval.max(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+    let mut system = sysinfo::System::new_with_specifics(
+        sysinfo::RefreshKind::nothing()
+            .with_cpu(sysinfo::CpuRefreshKind::nothing().with_cpu_usage()),
+    );
+    tokio::time::sleep(interval).await;
+    system.refresh_cpu_usage();
+    CpuUsage {
+        overall_percent: system.global_cpu_usage(),
+        per_core_percent: system.cpus().iter().map(sysinfo::Cpu::cpu_usage).collect(),
+        sample_ms: u64::try_from(interval.as_millis()).unwrap_or(u64::MAX),
+    }
+}
+
+/// A developer toolchain probed on the host.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Toolchain {
+    pub name: &'static str,
+    /// Version number, e.g. `1.85.0`; `None` if the toolchain wasn't found.
+    pub version: Option<String>,
+    /// First line of `--version`, e.g. `rustc 1.85.0 (4d91de4e4 2025-02-17)`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+}
+
+static TOOLCHAINS: tokio::sync::Mutex<Option<Vec<Toolchain>>> =
+    tokio::sync::Mutex::const_new(None);
+
+/// Versions of every toolchain in `TOOLCHAIN_PROBES`, probed concurrently
+/// on first use and cached until `refresh` is set.
+pub async fn toolchains(refresh: bool) -> Vec<Toolchain> {
+    let mut cached = TOOLCHAINS.lock().await;
+    if !refresh && let Some(toolchains) = cached.as_ref() {
+        return toolchains.clone();
+    }
+    let probes = TOOLCHAIN_PROBES.iter().map(|&(name, commands)| async move {
+        let details = probe_version(commands).await;
+        Toolchain {
+            name,
+            version: details.as_deref().map(parse_version),
+            details,
+        }
+    });
+    let toolchains = futures::future::join_all(probes).await;
+    *cached = Some(toolchains.clone());
+    toolchains
+}
+
+/// First line of `<command> --version` for the first command that runs.
+async fn probe_version(commands: &[&str]) -> Option<String> {
+    for command in commands {
+        let run = tokio::process::Command::new(command)
+            .arg("--version")
+            .kill_on_drop(true)
+            .output();
+        let Ok(Ok(output)) = tokio::time::timeout(TOOLCHAIN_PROBE_TIMEOUT, run).await else {
+            continue;
+        };
+        if !output.status.success() {
+            continue;
+        }
+        // Python 2 prints its version to stderr
+        let text = if output.stdout.is_empty() {
+            output.stderr
+        } else {
+            output.stdout
+        };
+        if let Some(line) = String::from_utf8_lossy(&text)
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+        {
+            return Some(line.to_string());
+        }
+    }
+    None
+}
+
+/// The first word of `line` that looks like a version number, without a
+/// leading `v`; the whole line if there is none.
+fn parse_version(line: &str) -> String {
+    line.split_whitespace()
+        .map(|word| word.strip_prefix('v').unwrap_or(word))
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+        .unwrap_or(line)
+        .to_string()
+}
+
+/// Resource usage of the server process itself.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// Resident set size.
+    pub rss_bytes: u64,
+    pub virtual_bytes: u64,
+    /// Average since the previous report; `0` on the first.
+    pub cpu_percent: f32,
+    /// `None` where the OS doesn't report it.
+    pub threads: Option<usize>,
+    pub open_files: Option<usize>,
+    pub open_files_limit: Option<usize>,
+    pub uptime_secs: u64,
+}
+
+/// Kept between reports so process CPU usage can be measured across them.
+static PROCESS_SYSTEM: LazyLock<Mutex<sysinfo::System>> =
+    LazyLock::new(|| Mutex::new(sysinfo::System::new()));
+
+/// Current resource usage of this process, or `None` if it can't be read.
+#[must_use]
+pub fn process_info() -> Option<ProcessInfo> {
+    let pid = sysinfo::get_current_pid().ok()?;
+    let mut system = PROCESS_SYSTEM.lock();
+    system.refresh_processes_specifics(
+        sysinfo::ProcessesToUpdate::Some(&[pid]),
+        true,
+        sysinfo::ProcessRefreshKind::nothing()
+            .with_memory()
+            .with_cpu()
+            .with_tasks(),
+    );
+    let process = system.process(pid)?;
+    Some(ProcessInfo {
+        pid: pid.as_u32(),
+        rss_bytes: process.memory(),
+        virtual_bytes: process.virtual_memory(),
+        cpu_percent: process.cpu_usage(),
+        threads: process.tasks().map(|tasks| tasks.len()),
+        open_files: process.open_files(),
+        open_files_limit: process.open_files_limit(),
+        uptime_secs: process.run_time(),
+    })
+}
+
+/// Soft and hard value of one resource limit; `None` means unlimited.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResourceLimit {
+    /// `open_files`, `processes`, or `core_file_bytes`.
+    pub name: &'static str,
+    /// The limit in force; the process may raise it up to `hard`.
+    pub soft: Option<u64>,
+    pub hard: Option<u64>,
+}
+
+/// Limits of this process that commonly explain failures such as "too many
+/// open files". Empty off Unix, and without limits the OS doesn't support.
+#[must_use]
+pub fn resource_limits() -> Vec<ResourceLimit> {
+    #[cfg(unix)]
+    {
+        let finite = |value: u64| (value != rlimit::INFINITY).then_some(value);
+        RESOURCE_LIMITS
+            .iter()
+            .filter(|(_, resource)| resource.is_supported())
+            .filter_map(|&(name, resource)| {
+                let (soft, hard) = resource.get().ok()?;
+                Some(ResourceLimit {
+                    name,
+                    soft: finite(soft),
+                    hard: finite(hard),
+                })
+            })
+            .collect()
+    }
+    #[cfg(not(unix))]
+    Vec::new()
+}
+
+/// A shell installed on the host.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Shell {
+    /// File name without extension, e.g. `bash` or `pwsh`.
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Every executable shell listed in `/etc/shells`, or found on the `PATH`
+/// where that file is missing and on Windows.
+#[must_use]
+pub fn installed_shells() -> Vec<Shell> {
+    #[cfg(unix)]
+    let listed: Vec<PathBuf> = std::fs::read_to_string("/etc/shells")
+        .map(|shells| {
+            shells
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default();
+    #[cfg(not(unix))]
+    let listed: Vec<PathBuf> = Vec::new();
+    let candidates = if listed.is_empty() {
+        SHELL_PROBES.iter().filter_map(|name| find_executable(name)).collect()
+    } else {
+        listed
+    };
+
+    let mut shells: Vec<Shell> = Vec::new();
+    for path in candidates {
+        if !is_executable(&path) || shells.iter().any(|shell| shell.path == path) {
+            continue;
+        }
+        let Some(name) = path.file_stem() else {
+            continue;
+        };
+        shells.push(Shell {
+            name: name.to_string_lossy().into_owned(),
+            path,
+        });
+    }
+    shells
+}
+
+/// Path of the executable `shell` names: either a path, or a command looked
+/// up on the `PATH`. `None` if it doesn't exist or can't be executed.
+#[must_use]
+pub fn resolve_shell(shell: &str) -> Option<PathBuf> {
+    let path = Path::new(shell);
+    if path.components().count() > 1 {
+        is_executable(path).then(|| path.to_path_buf())
+    } else {
+        find_executable(shell)
+    }
+}
+
+/// First executable named `command` on the `PATH`, trying each `PATHEXT`
+/// extension on Windows.
+fn find_executable(command: &str) -> Option<PathBuf> {
+    #[cfg(windows)]
+    let extensions: Vec<String> = std::env::var("PATHEXT")
+        .unwrap_or_else(|_| ".EXE;.CMD;.BAT;.COM".to_string())
+        .split(';')
+        .map(str::to_string)
+        .chain(std::iter::once(String::new()))
+        .collect();
+    #[cfg(not(windows))]
+    let extensions = [String::new()];
+    std::env::split_paths(&std::env::var_os("PATH")?).find_map(|dir| {
+        extensions
+            .iter()
+            .map(|ext| dir.join(format!("{}{}", command, ext)))
+            .find(|candidate| is_executable(candidate))
+    })
+}
+
+/// Whether `path` is a file this process may execute.
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    metadata.is_file()
+}
+
+/// Account the server runs as, which decides what it may read and write.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserInfo {
+    /// `None` if neither the user database nor the environment names it.
+    pub username: Option<String>,
+    /// Effective user and group ids; Unix only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    /// Running as root on Unix, or elevated on Windows.
+    pub privileged: bool,
+    pub home_dir: Option<PathBuf>,
+}
+
+/// The user this process runs as.
+#[must_use]
+pub fn user_info() -> UserInfo {
+    let (uid, gid, name) = effective_user().unwrap_or_default();
+    let username = name
+        .or_else(|| std::env::var("USER").ok())
+        .or_else(|| std::env::var("USERNAME").ok());
+    #[cfg(windows)]
+    let privileged = is_elevated::is_elevated();
+    #[cfg(not(windows))]
+    let privileged = uid == Some(0);
+    UserInfo {
+        username,
+        uid,
+        gid,
+        privileged,
+        home_dir: dirs::home_dir(),
+    }
+}
+
+/// Effective uid, gid, and user name of this process; `None` off Unix.
+#[cfg(unix)]
+fn effective_user() -> Option<(Option<u32>, Option<u32>, Option<String>)> {
+    let pid = sysinfo::get_current_pid().ok()?;
+    let mut system = PROCESS_SYSTEM.lock();
+    system.refresh_processes_specifics(
+        sysinfo::ProcessesToUpdate::Some(&[pid]),
+        false,
+        sysinfo::ProcessRefreshKind::nothing().with_user(sysinfo::UpdateKind::Always),
+    );
+    let process = system.process(pid)?;
+    let name = process.effective_user_id().and_then(|uid| {
+        sysinfo::Users::new_with_refreshed_list()
+            .get_user_by_id(uid)
+            .map(|user| user.name().to_string())
+    });
+    Some((
+        process.effective_user_id().map(|uid| **uid),
+        process.effective_group_id().map(|gid| *gid),
+        name,
+    ))
+}
+
+#[cfg(not(unix))]
+fn effective_user() -> Option<(Option<u32>, Option<u32>, Option<String>)> {
+    None
+}
+
+/// One thermal sensor reading, in degrees Celsius.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TemperatureSensor {
+    /// Sensor name as reported by the OS, e.g. `coretemp Package id 0`.
+    pub label: String,
+    pub celsius: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_celsius: Option<f32>,
+    /// Temperature at which the hardware throttles or shuts down.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub critical_celsius: Option<f32>,
+}
+
+/// Every sensor with a current reading, ordered by label. Empty where the
+/// OS exposes none (common in VMs and containers).
+#[must_use]
+pub fn temperature_sensors() -> Vec<TemperatureSensor> {
+    let components = sysinfo::Components::new_with_refreshed_list();
+    let mut sensors: Vec<TemperatureSensor> = components
+        .iter()
+        .filter_map(|component| {
+            Some(TemperatureSensor {
+                label: component.label().to_string(),
+                celsius: component.temperature().filter(|t| t.is_finite())?,
+                max_celsius: component.max().filter(|t| t.is_finite()),
+                critical_celsius: component.critical().filter(|t| t.is_finite()),
+            })
+        })
+        .collect();
+    sensors.sort_by(|a, b| a.label.cmp(&b.label));
+    sensors
+}
+
+/// Time zone and locale, for generating cron entries, timestamps, and
+/// localized output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LocaleInfo {
+    /// IANA name, e.g. `Europe/Berlin`; `None` if it can't be determined.
+    pub timezone: Option<String>,
+    /// Current offset from UTC, e.g. `+02:00`, including daylight saving.
+    pub utc_offset: String,
+    pub utc_offset_secs: i32,
+    /// BCP 47 tag, e.g. `de-DE`.
+    pub locale: Option<String>,
+}
+
+/// Current time zone, UTC offset, and locale.
+#[must_use]
+pub fn locale_info() -> LocaleInfo {
+    let offset = *chrono::Local::now().offset();
+    LocaleInfo {
+        timezone: iana_time_zone::get_timezone().ok(),
+        utc_offset: offset.to_string(),
+        utc_offset_secs: offset.local_minus_utc(),
+        locale: sys_locale::get_locale(),
+    }
+}
+
+/// Battery state; `present` is `false` on machines without one.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatteryInfo {
+    pub present: bool,
+    /// Combined charge of every battery.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charge_percent: Option<f32>,
+    /// `None` when neither charging nor discharging, e.g. idle at a threshold.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_ac: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_to_empty_secs: Option<u64>,
+}
+
+/// Current battery state, treating an unreadable battery as absent.
+#[must_use]
+pub fn battery_info() -> BatteryInfo {
+    use starship_battery::State;
+    use starship_battery::units::energy::watt_hour;
+    use starship_battery::units::time::second;
+
+    let batteries: Vec<starship_battery::Battery> = starship_battery::Manager::new()
+        .and_then(|manager| manager.batteries()?.collect())
+        .unwrap_or_else(|e| {
+            log::debug!("Failed to read battery state: {}", e);
+            Vec::new()
+        });
+    if batteries.is_empty() {
+        return BatteryInfo {
+            present: false,
+            charge_percent: None,
+            on_ac: None,
+            time_to_empty_secs: None,
+        };
+    }
+    let energy: f32 = batteries.iter().map(|b| b.energy().get::<watt_hour>()).sum();
+    let full: f32 = batteries.iter().map(|b| b.energy_full().get::<watt_hour>()).sum();
+    let on_ac = if batteries.iter().any(|b| matches!(b.state(), State::Charging | State::Full)) {
+        Some(true)
+    } else if batteries.iter().all(|b| b.state() == State::Discharging) {
+        Some(false)
+    } else {
+        None
+    };
+    BatteryInfo {
+        present: true,
+        charge_percent: (full > 0.0).then(|| energy * 100.0 / full),
+        on_ac,
+        time_to_empty_secs: batteries
+            .iter()
+            .filter_map(|b| b.time_to_empty())
+            .map(|t| t.get::<second>() as u64)
+            .max(),
+    }
+}
+
+/// Memory in bytes, next to the infrastructure crate's formatted strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MemoryBytes {
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+    pub used_bytes: u64,
+}
+
+/// Current memory usage.
+#[must_use]
+pub fn memory_bytes() -> MemoryBytes {
+    let mut system = sysinfo::System::new();
+    system.refresh_memory();
+    MemoryBytes {
+        total_bytes: system.total_memory(),
+        available_bytes: system.available_memory(),
+        used_bytes: system.used_memory(),
+    }
+}
+
+/// One network interface and its addresses, in CIDR notation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NetworkInterface {
+    pub name: String,
+    pub ipv4: Vec<String>,
+    pub ipv6: Vec<String>,
+    /// Only reported with `SystemInfoSettings::include_mac`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mac: Option<String>,
+}
+
+/// Every network interface, ordered by name.
+#[must_use]
+pub fn network_interfaces(include_mac: bool) -> Vec<NetworkInterface> {
+    let networks = sysinfo::Networks::new_with_refreshed_list();
+    let mut interfaces: Vec<NetworkInterface> = networks
+        .iter()
+        .map(|(name, data)| {
+            let (mut ipv4, mut ipv6) = (Vec::new(), Vec::new());
+            for network in data.ip_networks() {
+                let cidr = format!("{}/{}", network.addr, network.prefix);
+                match network.addr {
+                    IpAddr::V4(_) => ipv4.push(cidr),
+                    IpAddr::V6(_) => ipv6.push(cidr),
+                }
+            }
+            let mac = data.mac_address();
+            NetworkInterface {
+                name: name.clone(),
+                ipv4,
+                ipv6,
+                mac: (include_mac && !mac.is_unspecified()).then(|| mac.to_string()),
+            }
+        })
+        .collect();
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+    interfaces
+}
+
+/// One mounted file system and its space.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiskInfo {
+    /// Device name, e.g. `/dev/disk3s1`; may be empty for virtual file systems.
+    pub name: String,
+    pub mount_point: PathBuf,
+    pub file_system: String,
+    pub total_bytes: u64,
+    /// Space usable by this process, which may exclude reserved blocks.
+    pub available_bytes: u64,
+    pub removable: bool,
+}
+
+/// Every mounted disk, ordered by mount point.
+#[must_use]
+pub fn disks() -> Vec<DiskInfo> {
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    let mut disks: Vec<DiskInfo> = disks
+        .iter()
+        .map(|disk| DiskInfo {
+            name: disk.name().to_string_lossy().into_owned(),
+            mount_point: disk.mount_point().to_path_buf(),
+            file_system: disk.file_system().to_string_lossy().into_owned(),
+            total_bytes: disk.total_space(),
+            available_bytes: disk.available_space(),
+            removable: disk.is_removable(),
+        })
+        .collect();
+    disks.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+    disks
+}
+
+// ============================================================================
+// CACHING
+// ============================================================================
+
+/// A value reused until `ttl` passes, or forever without one.
+struct Cached<T> {
+    slot: Mutex<Option<(T, Instant)>>,
+    ttl: Option<Duration>,
+}
+
+impl<T: Clone> Cached<T> {
+    const fn new(ttl: Option<Duration>) -> Self {
+        Self {
+            slot: parking_lot::const_mutex(None),
+            ttl,
+        }
+    }
+
+    fn get(&self, refresh: bool, load: impl FnOnce() -> T) -> T {
+        let mut slot = self.slot.lock();
+        if !refresh
+            && let Some((value, loaded_at)) = slot.as_ref()
+            && self.ttl.is_none_or(|ttl| loaded_at.elapsed() < ttl)
+        {
+            return value.clone();
+        }
+        let value = load();
+        *slot = Some((value.clone(), Instant::now()));
+        value
+    }
+}
+
+static HOST: Cached<SystemInfo> = Cached::new(None);
+static CPU: Cached<CpuInfo> = Cached::new(None);
+static OS_RELEASE: Cached<OsRelease> = Cached::new(None);
+static MEMORY: Cached<MemoryBytes> = Cached::new(Some(SYSTEM_INFO_TTL));
+
+/// `get_system_info()`, collected once and then only refreshing memory
+/// every `SYSTEM_INFO_TTL`, unless `refresh` forces a full re-collection.
+#[must_use]
+pub fn cached_system_info(refresh: bool) -> SystemInfo {
+    let mut info = HOST.get(refresh, crate::get_system_info);
+    let memory = cached_memory_bytes(refresh);
+    info.memory.total_mb = format!("{} MB", memory.total_bytes / (1024 * 1024));
+    info.memory.available_mb = format!("{} MB", memory.available_bytes / (1024 * 1024));
+    info.memory.used_mb = format!("{} MB", memory.used_bytes / (1024 * 1024));
+    info
+}
+
+/// `cpu_info()`, collected once unless `refresh` is set.
+#[must_use]
+pub fn cached_cpu_info(refresh: bool) -> CpuInfo {
+    CPU.get(refresh, cpu_info)
+}
+
+/// `os_release()`, collected once unless `refresh` is set.
+#[must_use]
+pub fn cached_os_release(refresh: bool) -> OsRelease {
+    OS_RELEASE.get(refresh, os_release)
+}
+
+/// `memory_bytes()`, reused for `SYSTEM_INFO_TTL` unless `refresh` is set.
+#[must_use]
+pub fn cached_memory_bytes(refresh: bool) -> MemoryBytes {
+    MEMORY.get(refresh, memory_bytes)
+}
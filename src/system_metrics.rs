@@ -0,0 +1,142 @@
+use crate::schema::{SYSTEM_METRICS, SystemMetricsArgs, SystemMetricsPromptArgs};
+use crate::system_info::{
+    DEFAULT_CPU_SAMPLE_INTERVAL, MAX_CPU_SAMPLE_INTERVAL, memory_bytes, sample_cpu_usage,
+    temperature_sensors,
+};
+use kodegen_mcp_tool::Tool;
+use kodegen_mcp_tool::error::McpError;
+use rmcp::model::{Content, PromptArgument, PromptMessage, PromptMessageContent, PromptMessageRole};
+use serde_json::json;
+use std::time::Duration;
+
+// ============================================================================
+// TOOL STRUCT
+// ============================================================================
+
+#[derive(Clone, Default)]
+pub struct SystemMetricsTool;
+
+impl SystemMetricsTool {
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+// ============================================================================
+// TOOL IMPLEMENTATION
+// ============================================================================
+
+impl Tool for SystemMetricsTool {
+    type Args = SystemMetricsArgs;
+    type PromptArgs = SystemMetricsPromptArgs;
+
+    fn name() -> &'static str {
+        SYSTEM_METRICS
+    }
+
+    fn description() -> &'static str {
+        "Sample current CPU utilization (overall and per core), load average, memory \
+         usage, and temperature sensor readings. Use it to defer heavy work such as large \
+         builds while the machine is busy, or to diagnose thermal throttling."
+    }
+
+    fn read_only() -> bool {
+        true
+    }
+
+    fn prompt_arguments() -> Vec<PromptArgument> {
+        vec![] // No prompt arguments needed
+    }
+
+    #[tracing::instrument(name = "config.tool", skip_all, fields(tool = Self::name()), err)]
+    async fn execute(&self, args: Self::Args) -> Result<Vec<Content>, McpError> {
+        let interval = args
+            .sample_ms
+            .map_or(DEFAULT_CPU_SAMPLE_INTERVAL, Duration::from_millis)
+            .min(MAX_CPU_SAMPLE_INTERVAL);
+        let cpu = sample_cpu_usage(interval).await;
+        let memory = memory_bytes();
+        let load = sysinfo::System::load_average();
+        let temperatures = temperature_sensors();
+
+        let mut contents = Vec::new();
+
+        // ========================================
+        // Content[0]: Human-Readable Summary
+        // ========================================
+        let busiest = cpu.per_core_percent.iter().copied().fold(0.0_f32, f32::max);
+        let hottest = temperatures.iter().max_by(|a, b| a.celsius.total_cmp(&b.celsius));
+        let mut summary = format!(
+            "📈 System Metrics (sampled over {}ms)\n\
+             \n\
+             • CPU: {:.1}% overall, busiest core {:.1}% ({} cores)\n\
+             • Load average: {:.2} {:.2} {:.2}\n\
+             • Memory: {:.1}% used ({} of {} MB)",
+            cpu.sample_ms,
+            cpu.overall_percent,
+            busiest,
+            cpu.per_core_percent.len(),
+            load.one,
+            load.five,
+            load.fifteen,
+            if memory.total_bytes == 0 {
+                0.0
+            } else {
+                memory.used_bytes as f64 * 100.0 / memory.total_bytes as f64
+            },
+            memory.used_bytes / (1024 * 1024),
+            memory.total_bytes / (1024 * 1024)
+        );
+        match hottest {
+            Some(sensor) => summary.push_str(&format!(
+                "\n• Hottest sensor: {} at {:.0}°C{}",
+                sensor.label,
+                sensor.celsius,
+                sensor
+                    .critical_celsius
+                    .map_or_else(String::new, |c| format!(" (critical {:.0}°C)", c))
+            )),
+            None => summary.push_str("\n• Temperature sensors: none available"),
+        }
+        contents.push(Content::text(summary));
+
+        // ========================================
+        // Content[1]: Machine-Parseable JSON
+        // ========================================
+        let metadata = json!({
+            "success": true,
+            "cpu": cpu,
+            "load_average": {
+                "one": load.one,
+                "five": load.five,
+                "fifteen": load.fifteen
+            },
+            "memory": memory,
+            "temperatures": temperatures
+        });
+        let json_str = serde_json::to_string_pretty(&metadata)
+            .unwrap_or_else(|_| "{}".to_string());
+        contents.push(Content::text(json_str));
+
+        Ok(contents)
+    }
+
+    async fn prompt(&self, _args: Self::PromptArgs) -> Result<Vec<PromptMessage>, McpError> {
+        Ok(vec![
+            PromptMessage {
+                role: PromptMessageRole::User,
+                content: PromptMessageContent::text("Is the machine busy right now?"),
+            },
+            PromptMessage {
+                role: PromptMessageRole::Assistant,
+                content: PromptMessageContent::text(
+                    "Use system_metrics to sample CPU usage over a short interval:\n\n\
+                     {\"sample_ms\": 500}\n\n\
+                     High overall usage or a load average above the core count means heavy \
+                     work such as a full build is better deferred.",
+                ),
+            },
+        ])
+    }
+}
@@ -0,0 +1,137 @@
+use crate::ConfigManager;
+use crate::metadata::is_sensitive;
+use crate::schema::{CONFIG_UNDO, ConfigUndoArgs, ConfigUndoPromptArgs};
+use crate::set_config_value::confirmation_required;
+use kodegen_mcp_tool::Tool;
+use kodegen_mcp_tool::error::McpError;
+use rmcp::model::{Content, PromptArgument, PromptMessage, PromptMessageContent, PromptMessageRole};
+use serde_json::json;
+
+// ============================================================================
+// TOOL STRUCT
+// ============================================================================
+
+#[derive(Clone)]
+pub struct UndoConfigTool {
+    config_manager: ConfigManager,
+}
+
+impl UndoConfigTool {
+    #[must_use]
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self { config_manager }
+    }
+}
+
+// ============================================================================
+// TOOL IMPLEMENTATION
+// ============================================================================
+
+impl Tool for UndoConfigTool {
+    type Args = ConfigUndoArgs;
+    type PromptArgs = ConfigUndoPromptArgs;
+
+    fn name() -> &'static str {
+        CONFIG_UNDO
+    }
+
+    fn description() -> &'static str {
+        "Revert the most recent configuration change made via config_set. \
+         Repeated calls step further back through recent changes; use config_redo \
+         to re-apply an undone change. Reverting a sensitive key needs confirmation as \
+         with config_set: the first call returns a confirmation_token, and repeating the \
+         call with it applies the change."
+    }
+
+    fn read_only() -> bool {
+        false
+    }
+
+    fn destructive() -> bool {
+        false
+    }
+
+    fn idempotent() -> bool {
+        false
+    }
+
+    fn prompt_arguments() -> Vec<PromptArgument> {
+        vec![] // No prompt arguments needed
+    }
+
+    #[tracing::instrument(name = "config.tool", skip_all, fields(tool = Self::name()), err)]
+    async fn execute(&self, args: Self::Args) -> Result<Vec<Content>, McpError> {
+        self.config_manager.authorize(args.auth_token.as_deref())?;
+        let change = match self.config_manager.next_undo() {
+            Some(next) => {
+                // Sensitive keys need a second, confirming call, as with config_set
+                if is_sensitive(&next.key) {
+                    match args.confirmation_token.as_deref() {
+                        Some(token) => {
+                            self.config_manager.confirm_change(token, &next.key, &next.old)?;
+                        }
+                        None => {
+                            let token =
+                                self.config_manager.request_confirmation(&next.key, &next.old);
+                            return Ok(confirmation_required(&next.key, &next.old, &token));
+                        }
+                    }
+                }
+                Some(self.config_manager.undo_expected(&next).await?)
+            }
+            None => None,
+        };
+        let (undo_depth, redo_depth) = self.config_manager.history_depth();
+
+        let mut contents = Vec::new();
+
+        // ========================================
+        // Content[0]: Human-Readable Summary
+        // ========================================
+        let summary = match &change {
+            Some(change) => format!(
+                "↩️  Configuration Change Undone\n\
+                 \n\
+                 Setting: {}\n\
+                 Restored value: {}\n\
+                 Reverted value: {}\n\
+                 \n\
+                 {} more change(s) can be undone, {} can be redone.",
+                change.key, change.old, change.new, undo_depth, redo_depth
+            ),
+            None => "ℹ️  Nothing to undo".to_string(),
+        };
+        contents.push(Content::text(summary));
+
+        // ========================================
+        // Content[1]: Machine-Parseable JSON
+        // ========================================
+        let metadata = json!({
+            "success": true,
+            "undone": change,
+            "undo_depth": undo_depth,
+            "redo_depth": redo_depth
+        });
+        let json_str = serde_json::to_string_pretty(&metadata)
+            .unwrap_or_else(|_| "{}".to_string());
+        contents.push(Content::text(json_str));
+
+        Ok(contents)
+    }
+
+    async fn prompt(&self, _args: Self::PromptArgs) -> Result<Vec<PromptMessage>, McpError> {
+        Ok(vec![
+            PromptMessage {
+                role: PromptMessageRole::User,
+                content: PromptMessageContent::text("How do I revert a configuration change?"),
+            },
+            PromptMessage {
+                role: PromptMessageRole::Assistant,
+                content: PromptMessageContent::text(
+                    "Use config_undo to revert the most recent config_set. Call it again to \
+                     step further back, and use config_redo to re-apply a change you undid.",
+                ),
+            },
+        ])
+    }
+}
@@ -0,0 +1,172 @@
+use crate::ConfigManager;
+use crate::keys::resolve_key;
+use crate::metadata::{is_sensitive, risk_level};
+use crate::provenance::ValueSource;
+use crate::schema::{CONFIG_UNSET, ConfigUnsetArgs, ConfigUnsetPromptArgs};
+use crate::set_config_value::confirmation_required;
+use kodegen_mcp_tool::Tool;
+use kodegen_mcp_tool::error::McpError;
+use rmcp::model::{Content, PromptArgument, PromptMessage, PromptMessageContent, PromptMessageRole};
+use serde_json::json;
+
+// ============================================================================
+// TOOL STRUCT
+// ============================================================================
+
+#[derive(Clone)]
+pub struct UnsetConfigTool {
+    config_manager: ConfigManager,
+}
+
+impl UnsetConfigTool {
+    #[must_use]
+    pub fn new(config_manager: ConfigManager) -> Self {
+        Self { config_manager }
+    }
+}
+
+// ============================================================================
+// TOOL IMPLEMENTATION
+// ============================================================================
+
+impl Tool for UnsetConfigTool {
+    type Args = ConfigUnsetArgs;
+    type PromptArgs = ConfigUnsetPromptArgs;
+
+    fn name() -> &'static str {
+        CONFIG_UNSET
+    }
+
+    fn description() -> &'static str {
+        "Revert a configuration key to its default, so it no longer overrides the \
+         built-in value. This is different from setting an empty value: unsetting \
+         allowed_directories restores the default list rather than storing []. Entries \
+         such as rate_limits.<tool>, directory_aliases.<name>, and ext.<namespace>.<name> \
+         are removed.\n\n\
+         Sensitive keys need confirmation as with config_set: the first call returns a \
+         confirmation_token, and the change is applied by repeating the call with it. \
+         An optional reason is recorded with the change. Servers that require \
+         authentication also need their token as auth_token."
+    }
+
+    fn read_only() -> bool {
+        false
+    }
+
+    fn destructive() -> bool {
+        false
+    }
+
+    fn idempotent() -> bool {
+        true
+    }
+
+    fn prompt_arguments() -> Vec<PromptArgument> {
+        vec![] // No prompt arguments needed
+    }
+
+    #[tracing::instrument(
+        name = "config.tool",
+        skip_all,
+        fields(tool = Self::name(), key = %args.key),
+        err
+    )]
+    async fn execute(&self, args: Self::Args) -> Result<Vec<Content>, McpError> {
+        self.config_manager.authorize(args.auth_token.as_deref())?;
+
+        // Resolve deprecated aliases to the canonical key
+        let resolved = resolve_key(&args.key);
+        let key = resolved.key;
+        let deprecation_warning = resolved.deprecation_warning();
+
+        // Sensitive keys need a second, confirming call, bound to the unset
+        let unset = serde_json::Value::Null;
+        if is_sensitive(key) {
+            match args.confirmation_token.as_deref() {
+                Some(token) => self.config_manager.confirm_change(token, key, &unset)?,
+                None => {
+                    let token = self.config_manager.request_confirmation(key, &unset);
+                    return Ok(confirmation_required(key, &unset, &token));
+                }
+            }
+        }
+
+        let reason = args
+            .reason
+            .as_deref()
+            .map(str::trim)
+            .filter(|reason| !reason.is_empty())
+            .map(str::to_string);
+        let previous = self.config_manager.get_value(key);
+        self.config_manager.unset(key, reason.clone()).await?;
+        let value = self.config_manager.get_value(key);
+
+        let mut contents = Vec::new();
+
+        // ========================================
+        // Content[0]: Human-Readable Summary
+        // ========================================
+        let display = |value: &Option<serde_json::Value>| match value {
+            Some(value) if !value.is_null() => value.to_string(),
+            _ => "(unset)".to_string(),
+        };
+        let mut summary = format!(
+            "↩️  Configuration Reverted to Default\n\
+             \n\
+             Setting: {}\n\
+             Previous value: {}\n\
+             Default value: {}",
+            key,
+            display(&previous),
+            display(&value)
+        );
+        if let Some(reason) = &reason {
+            summary.push_str(&format!("\nReason: {}", reason));
+        }
+        if let Some(warning) = &deprecation_warning {
+            summary.push_str(&format!("\n\n⚠️  {}", warning));
+        }
+        contents.push(Content::text(summary));
+
+        // ========================================
+        // Content[1]: Machine-Parseable JSON
+        // ========================================
+        let metadata = json!({
+            "success": true,
+            "key": key,
+            "previous": previous,
+            "value": value,
+            "source": ValueSource::Default,
+            "reason": reason,
+            "risk": risk_level(key),
+            "deprecation_warning": deprecation_warning
+        });
+        let json_str = serde_json::to_string_pretty(&metadata)
+            .unwrap_or_else(|_| "{}".to_string());
+        contents.push(Content::text(json_str));
+
+        Ok(contents)
+    }
+
+    async fn prompt(&self, _args: Self::PromptArgs) -> Result<Vec<PromptMessage>, McpError> {
+        Ok(vec![
+            PromptMessage {
+                role: PromptMessageRole::User,
+                content: PromptMessageContent::text(
+                    "How do I go back to the default for a configuration key?",
+                ),
+            },
+            PromptMessage {
+                role: PromptMessageRole::Assistant,
+                content: PromptMessageContent::text(
+                    "Use config_unset with the key, for example:\n\
+                     {\"key\": \"file_read_line_limit\"}\n\n\
+                     The key returns to its default and config_effective reports its source \
+                     as default. Setting an empty array is not the same: an empty \
+                     allowed_directories allows the whole file system. Sensitive keys return \
+                     a confirmation_token first; repeat the call with it to apply the change.",
+                ),
+            },
+        ])
+    }
+}
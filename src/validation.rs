@@ -0,0 +1,183 @@
+//! Declarative validation rules
+//!
+//! Each `config.json` key declares its rules; `validate` checks every rule and
+//! reports all violations together rather than stopping at the first one.
+
+use crate::keys::ConfigKey;
+use crate::metadata::ValueType;
+use crate::system_info::{installed_shells, resolve_shell};
+use std::fmt;
+use std::path::Path;
+
+// ============================================================================
+// RULES
+// ============================================================================
+
+/// A single constraint on a config value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// Numbers must be at least this value.
+    Min(i64),
+    /// Numbers must be at most this value.
+    Max(i64),
+    /// Strings and arrays must not be empty.
+    NonEmpty,
+    /// Strings, and every array item, must fully match this pattern.
+    Regex(&'static str),
+    /// Strings, and every array item, must name an existing path.
+    PathExists,
+    /// Strings must name an executable shell, by path or on the `PATH`.
+    Shell,
+}
+
+impl ConfigKey<'_> {
+    /// Validation rules applied by `set_value`, in addition to the type check.
+    /// The extended config checks its own keys in `ExtendedConfig::set_key`.
+    #[must_use]
+    pub fn rules(self) -> &'static [Rule] {
+        match self {
+            ConfigKey::BlockedCommands => &[Rule::Regex(r"^\S+$")],
+            ConfigKey::DefaultShell => &[Rule::NonEmpty, Rule::Regex(r"^\S.*$"), Rule::Shell],
+            ConfigKey::AllowedDirectories => &[Rule::PathExists],
+            ConfigKey::DeniedDirectories => &[],
+            ConfigKey::FileReadLineLimit => &[Rule::Min(1)],
+            ConfigKey::FileWriteLineLimit => &[Rule::Min(1)],
+            ConfigKey::FuzzySearchThreshold => &[Rule::Min(0), Rule::Max(100)],
+            ConfigKey::HttpConnectionTimeoutSecs => &[Rule::Min(1)],
+            _ => &[],
+        }
+    }
+}
+
+// ============================================================================
+// VALIDATION ERRORS
+// ============================================================================
+
+/// Every rule violation found for one key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationErrors {
+    pub key: &'static str,
+    pub violations: Vec<String>,
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid value for '{}': {}",
+            self.key,
+            self.violations.join("; ")
+        )
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+// ============================================================================
+// VALIDATION
+// ============================================================================
+
+/// Check `value` against the type and rules declared for `key`.
+pub fn validate(key: ConfigKey<'_>, value: &serde_json::Value) -> Result<(), ValidationErrors> {
+    let mut violations = Vec::new();
+
+    match key.metadata().map(|m| m.value_type) {
+        Some(value_type) if !has_type(value, value_type) => {
+            violations.push(format!("expected {:?}, got {}", value_type, value));
+        }
+        _ => {
+            for rule in key.rules() {
+                check_rule(*rule, value, &mut violations);
+            }
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationErrors {
+            key: key.as_str(),
+            violations,
+        })
+    }
+}
+
+fn has_type(value: &serde_json::Value, value_type: ValueType) -> bool {
+    match value_type {
+        ValueType::String => value.is_string(),
+        ValueType::Number => value.is_i64() || value.is_u64(),
+        ValueType::Float => value.is_number(),
+        ValueType::Boolean => value.is_boolean(),
+        ValueType::StringArray => value
+            .as_array()
+            .is_some_and(|items| items.iter().all(serde_json::Value::is_string)),
+    }
+}
+
+fn check_rule(rule: Rule, value: &serde_json::Value, violations: &mut Vec<String>) {
+    let strings: Vec<&str> = match value {
+        serde_json::Value::String(s) => vec![s.as_str()],
+        serde_json::Value::Array(items) => items.iter().filter_map(|v| v.as_str()).collect(),
+        _ => Vec::new(),
+    };
+
+    match rule {
+        Rule::Min(min) => {
+            if let Some(n) = value.as_i64().filter(|n| *n < min) {
+                if min == 1 {
+                    violations.push(format!("value must be positive (got {})", n));
+                } else {
+                    violations.push(format!("value must be at least {} (got {})", min, n));
+                }
+            }
+        }
+        Rule::Max(max) => {
+            if let Some(n) = value.as_i64().filter(|n| *n > max) {
+                violations.push(format!("value must be at most {} (got {})", max, n));
+            }
+        }
+        Rule::NonEmpty => {
+            let empty = match value {
+                serde_json::Value::String(s) => s.trim().is_empty(),
+                serde_json::Value::Array(items) => items.is_empty(),
+                _ => false,
+            };
+            if empty {
+                violations.push("value must not be empty".to_string());
+            }
+        }
+        Rule::Regex(pattern) => match regex::Regex::new(pattern) {
+            Ok(re) => {
+                for s in strings.iter().filter(|s| !re.is_match(s)) {
+                    violations.push(format!("'{}' does not match {}", s, pattern));
+                }
+            }
+            Err(e) => log::error!("Invalid validation pattern {}: {}", pattern, e),
+        },
+        Rule::PathExists => {
+            for s in strings.iter().filter(|s| !Path::new(s).exists()) {
+                violations.push(format!("path '{}' does not exist", s));
+            }
+        }
+        Rule::Shell => {
+            for s in strings.iter().filter(|s| !s.trim().is_empty()) {
+                if resolve_shell(s).is_some() {
+                    continue;
+                }
+                let detected: Vec<String> = installed_shells()
+                    .iter()
+                    .map(|shell| shell.path.display().to_string())
+                    .collect();
+                violations.push(format!(
+                    "shell '{}' was not found or is not executable (detected: {})",
+                    s,
+                    if detected.is_empty() {
+                        "none".to_string()
+                    } else {
+                        detected.join(", ")
+                    }
+                ));
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Cross-process change detection
+//!
+//! Watches the directories holding `config.json` and the extended config so
+//! that writes by other kodegen processes reach this one without a restart.
+//! Directories rather than files are watched because saves replace files by
+//! renaming over them.
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Quiet period that coalesces the burst of events a single save produces.
+pub const FILE_WATCH_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Report writes to any of `files` on the returned channel until the
+/// watcher is dropped.
+pub(crate) fn watch_files(
+    files: &[PathBuf],
+) -> notify::Result<(RecommendedWatcher, mpsc::UnboundedReceiver<PathBuf>)> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let wanted: HashSet<PathBuf> = files.iter().cloned().collect();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                log::warn!("Config file watch error: {}", e);
+                return;
+            }
+        };
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            return;
+        }
+        for path in event.paths {
+            if wanted.contains(&path) {
+                let _ = tx.send(path);
+            }
+        }
+    })?;
+    let dirs: HashSet<&Path> = files.iter().filter_map(|file| file.parent()).collect();
+    for dir in dirs {
+        std::fs::create_dir_all(dir)?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+    Ok((watcher, rx))
+}
+
+/// Wait for the next changed file, then collect every other file changed
+/// within `FILE_WATCH_DEBOUNCE`. Returns `None` once the watcher is dropped.
+pub(crate) async fn next_changes(
+    rx: &mut mpsc::UnboundedReceiver<PathBuf>,
+) -> Option<Vec<PathBuf>> {
+    let mut changed = vec![rx.recv().await?];
+    tokio::time::sleep(FILE_WATCH_DEBOUNCE).await;
+    while let Ok(path) = rx.try_recv() {
+        if !changed.contains(&path) {
+            changed.push(path);
+        }
+    }
+    Some(changed)
+}